@@ -0,0 +1,281 @@
+use std::collections::BTreeMap;
+use std::ops::Range;
+
+use tui::style::Style;
+
+use crate::decode::{decode_float, decode_integer, Endianness};
+use crate::display::Highlight;
+use crate::terminal::{color, color_hsl};
+
+// How the bytes covered by an annotation should be interpreted when showing
+// its value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    None,
+    Unsigned,
+    Signed,
+    Hex,
+    Float,
+    Text,
+    // An integer with symbolic names for known values.
+    Enum(&'static [(u64, &'static str)]),
+    // A bitfield, `width` bits wide, starting `shift` bits into the integer
+    // formed by the annotated bytes.
+    Bits {
+        shift: u32,
+        width: u32,
+        signed: bool,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Annotation {
+    pub range: Range<u64>,
+    pub label: String,
+    pub format: Format,
+    // Overrides the endianness selected by the user, e.g. for file formats
+    // that specify their own.
+    pub endianness: Option<Endianness>,
+    // Nesting depth; deeper annotations are drawn on top of shallower ones.
+    pub depth: usize,
+    // Set when the annotated bytes are known to be wrong, e.g. a checksum
+    // that doesn't match.
+    pub error: Option<String>,
+}
+
+impl Annotation {
+    pub fn new(range: Range<u64>, label: impl Into<String>, format: Format) -> Self {
+        Annotation {
+            range,
+            label: label.into(),
+            format,
+            endianness: None,
+            depth: 0,
+            error: None,
+        }
+    }
+
+    pub fn endianness(mut self, endianness: Endianness) -> Self {
+        self.endianness = Some(endianness);
+        self
+    }
+
+    pub fn depth(mut self, depth: usize) -> Self {
+        self.depth = depth;
+        self
+    }
+
+    pub fn error(mut self, error: impl Into<String>) -> Self {
+        self.error = Some(error.into());
+        self
+    }
+
+    pub fn contains(&self, location: u64) -> bool {
+        self.range.start <= location && location < self.range.end
+    }
+
+    // Renders the value of `data`, which should be the bytes covered by this
+    // annotation.
+    pub fn value(&self, data: &[u8], endianness: Endianness) -> Option<String> {
+        let endianness = self.endianness.unwrap_or(endianness);
+        let (signed, unsigned) = decode_integer(data, endianness);
+
+        match self.format {
+            Format::None => None,
+            Format::Unsigned => Some(format!("{}", unsigned)),
+            Format::Signed => Some(format!("{}", signed)),
+            Format::Hex => Some(format!("{:#x}", unsigned)),
+            Format::Float => decode_float(data, endianness)
+                .map(|value| format!("{}", value))
+                .or_else(|| Some(format!("{:#x}", unsigned))),
+            Format::Enum(names) => Some(
+                match names.iter().find(|(value, _)| *value as u128 == unsigned) {
+                    Some((_, name)) => format!("{} ({})", unsigned, name),
+                    None => format!("{}", unsigned),
+                },
+            ),
+            Format::Text => {
+                let text: String = data
+                    .iter()
+                    .take_while(|&&b| b != 0)
+                    .flat_map(|&b| std::ascii::escape_default(b))
+                    .map(|b| b as char)
+                    .collect();
+                Some(format!("\"{}\"", text))
+            }
+            Format::Bits {
+                shift,
+                width,
+                signed,
+            } => {
+                let bits = (unsigned >> shift) & ((1u128 << width) - 1);
+                if signed && width > 0 && bits & (1u128 << (width - 1)) != 0 {
+                    Some(format!("{}", bits as i128 - (1i128 << width)))
+                } else {
+                    Some(format!("{}", bits))
+                }
+            }
+        }
+    }
+}
+
+// Annotations are kept in named groups so that, e.g., re-applying a struct
+// overlay replaces the previous one without touching anything else.
+#[derive(Debug, Default)]
+pub struct Annotations {
+    groups: BTreeMap<&'static str, Vec<Annotation>>,
+}
+
+impl Annotations {
+    pub fn set_group(&mut self, group: &'static str, annotations: Vec<Annotation>) {
+        if annotations.is_empty() {
+            self.groups.remove(group);
+        } else {
+            self.groups.insert(group, annotations);
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Annotation> {
+        self.groups.values().flatten()
+    }
+
+    // Returns the innermost annotation containing `location`.
+    pub fn at(&self, location: u64) -> Option<&Annotation> {
+        self.iter()
+            .filter(|annotation| annotation.contains(location))
+            .fold(None, |best: Option<&Annotation>, annotation| match best {
+                Some(best) if best.depth > annotation.depth => Some(best),
+                _ => Some(annotation),
+            })
+    }
+
+    // Returns highlights for all annotations overlapping `range`, ordered such
+    // that deeper annotations come last.
+    pub fn highlights(&self, range: Range<u64>) -> Vec<Highlight> {
+        let mut visible: Vec<(usize, &Annotation)> = self
+            .iter()
+            .enumerate()
+            .filter(|(_, a)| a.range.start < range.end && range.start < a.range.end)
+            .collect();
+        visible.sort_by_key(|(_, a)| a.depth);
+
+        visible
+            .into_iter()
+            .map(|(index, annotation)| Highlight {
+                range: annotation.range.clone(),
+                style: match annotation.error {
+                    Some(_) => error_style(),
+                    None => annotation_style(index, annotation.depth),
+                },
+            })
+            .collect()
+    }
+}
+
+fn annotation_style(index: usize, depth: usize) -> Style {
+    // Alternate hues so that neighbouring fields are easy to tell apart.
+    let hue = (index * 67 + depth * 23) as f64 % 360.0;
+    Style::default()
+        .bg(color_hsl(hue, 0.5, 0.18))
+        .fg(color(224, 224, 224))
+}
+
+fn error_style() -> Style {
+    Style::default()
+        .bg(color(160, 0, 0))
+        .fg(color(255, 255, 255))
+}
+
+#[cfg(test)]
+mod annotation_tests {
+    use super::*;
+
+    #[test]
+    fn test_value() {
+        let a = Annotation::new(0..2, "a", Format::Unsigned);
+        assert_eq!(a.value(&[0x01, 0x02], Endianness::Little).unwrap(), "513");
+        assert_eq!(a.value(&[0x01, 0x02], Endianness::Big).unwrap(), "258");
+
+        let a = a.endianness(Endianness::Big);
+        assert_eq!(a.value(&[0x01, 0x02], Endianness::Little).unwrap(), "258");
+
+        let a = Annotation::new(0..1, "a", Format::Signed);
+        assert_eq!(a.value(&[0xfe], Endianness::Little).unwrap(), "-2");
+
+        let a = Annotation::new(0..4, "a", Format::Text);
+        assert_eq!(a.value(b"hi\n\0", Endianness::Little).unwrap(), "\"hi\\n\"");
+
+        let a = Annotation::new(0..1, "a", Format::Enum(&[(1, "ONE"), (2, "TWO")]));
+        assert_eq!(a.value(&[2], Endianness::Little).unwrap(), "2 (TWO)");
+        assert_eq!(a.value(&[3], Endianness::Little).unwrap(), "3");
+
+        let a = Annotation::new(0..1, "a", Format::None);
+        assert_eq!(a.value(&[0], Endianness::Little), None);
+    }
+
+    #[test]
+    fn test_bits_value() {
+        let bits = |shift, width, signed| {
+            Annotation::new(
+                0..2,
+                "bits",
+                Format::Bits {
+                    shift,
+                    width,
+                    signed,
+                },
+            )
+        };
+        let data = [0b1011_0100, 0b0000_0001];
+        assert_eq!(
+            bits(2, 3, false).value(&data, Endianness::Little).unwrap(),
+            "5"
+        );
+        assert_eq!(
+            bits(2, 3, true).value(&data, Endianness::Little).unwrap(),
+            "-3"
+        );
+        assert_eq!(
+            bits(7, 2, false).value(&data, Endianness::Little).unwrap(),
+            "3"
+        );
+    }
+
+    #[test]
+    fn test_at() {
+        let mut annotations = Annotations::default();
+        annotations.set_group(
+            "test",
+            vec![
+                Annotation::new(0..8, "outer", Format::None),
+                Annotation::new(2..4, "inner", Format::Unsigned).depth(1),
+            ],
+        );
+        assert_eq!(annotations.at(0).unwrap().label, "outer");
+        assert_eq!(annotations.at(2).unwrap().label, "inner");
+        assert_eq!(annotations.at(4).unwrap().label, "outer");
+        assert!(annotations.at(8).is_none());
+
+        annotations.set_group("test", vec![]);
+        assert!(annotations.at(0).is_none());
+    }
+
+    #[test]
+    fn test_error_highlight() {
+        let mut annotations = Annotations::default();
+        annotations.set_group(
+            "test",
+            vec![
+                Annotation::new(0..8, "chunk", Format::None),
+                Annotation::new(4..8, "crc", Format::Hex)
+                    .depth(1)
+                    .error("CRC mismatch"),
+            ],
+        );
+        let highlights = annotations.highlights(0..16);
+        assert_eq!(highlights.len(), 2);
+        assert_ne!(highlights[0].style, error_style());
+        assert_eq!(highlights[1].range, 4..8);
+        assert_eq!(highlights[1].style, error_style());
+    }
+}
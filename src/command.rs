@@ -0,0 +1,1144 @@
+// The commands the `:` command line takes and the arguments they expect,
+// used to check command lines, complete them and list them with `:help`, and
+// what each of them does. Key bindings run commands too; see `keymap`.
+
+use crossterm::event::KeyEvent;
+use std::fs;
+
+use crate::copy::CopyFormat;
+use crate::cursor::{Cursor, SelectionMode};
+use crate::decode::Endianness;
+use crate::disasm::Architecture;
+use crate::encoding::Encoding;
+use crate::image::PixelFormat;
+use crate::pointer::PointerMode;
+use crate::table::Element;
+use crate::{parse_address, App, PromptKind};
+
+#[derive(Clone, Copy)]
+pub enum Arg {
+    // The rest of the line, as an offset expression; see `expr`.
+    Expression(&'static str),
+    // The rest of the line, as two offset expressions separated by a comma.
+    Range(&'static str),
+    Number(&'static str),
+    // A number, or the given word instead, e.g. `auto`.
+    NumberOr(&'static str, &'static str),
+    // How many times over to do something, which for a key binding is the
+    // count typed in front of the key.
+    Count,
+    Choice(&'static str, fn() -> Vec<&'static str>),
+    Mark(&'static str),
+    Path(&'static str),
+    Word(&'static str),
+    // Any number of `name=value` pairs, for `:set`.
+    Settings,
+}
+
+// An argument as read for its `Arg`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Value {
+    Offset(u64),
+    Range(u64, u64),
+    Number(u64),
+    // One of the names of a `Choice`, or the word of a `NumberOr`.
+    Choice(&'static str),
+    Mark(char),
+    // A `Path` or a `Word`.
+    Text(String),
+    Setting(&'static str, String),
+}
+
+impl Arg {
+    fn label(self) -> &'static str {
+        match self {
+            Arg::Expression(label)
+            | Arg::Range(label)
+            | Arg::Number(label)
+            | Arg::NumberOr(label, _)
+            | Arg::Choice(label, _)
+            | Arg::Mark(label)
+            | Arg::Path(label)
+            | Arg::Word(label) => label,
+            Arg::Count => "count",
+            Arg::Settings => "name=value...",
+        }
+    }
+
+    // Reads `text` as this argument. Offsets are evaluated where the cursor
+    // is in `app`.
+    fn value(self, app: &mut App, text: &str) -> Result<Value, String> {
+        match self {
+            Arg::Expression(_) => app.evaluate(text).map(Value::Offset),
+            Arg::Range(_) => {
+                let (start, end) = text
+                    .split_once(',')
+                    .ok_or(format!("expected start, end, not {}", text))?;
+                Ok(Value::Range(app.evaluate(start)?, app.evaluate(end)?))
+            }
+            Arg::NumberOr(_, word) if text == word => Ok(Value::Choice(word)),
+            Arg::Number(label) | Arg::NumberOr(label, _) => parse_address(text)
+                .map(Value::Number)
+                .ok_or(format!("invalid {}: {}", label, text)),
+            Arg::Count => parse_address(text)
+                .filter(|&count| count > 0)
+                .map(Value::Number)
+                .ok_or(format!("invalid count: {}", text)),
+            Arg::Choice(_, names) => {
+                let names = names();
+                let name = names.iter().find(|&&name| name == text);
+                name.map(|&name| Value::Choice(name)).ok_or(format!(
+                    "{} isn't one of {}",
+                    text,
+                    names.join(", ")
+                ))
+            }
+            Arg::Mark(_) => {
+                let mut chars = text.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(name), None) => Ok(Value::Mark(name)),
+                    _ => Err(format!("marks are single letters, not {}", text)),
+                }
+            }
+            Arg::Path(_) | Arg::Word(_) => Ok(Value::Text(text.to_string())),
+            Arg::Settings => {
+                let setting = text.split_once('=').and_then(|(name, value)| {
+                    let name = SETTINGS.iter().find(|&&setting| setting == name)?;
+                    Some(Value::Setting(name, value.to_string()))
+                });
+                setting.ok_or(format!("not a setting: {}", text))
+            }
+        }
+    }
+
+    // Ways to finish `prefix` as this argument.
+    fn complete(self, prefix: &str) -> Vec<String> {
+        match self {
+            Arg::Choice(_, names) => names()
+                .into_iter()
+                .filter(|name| name.starts_with(prefix))
+                .map(String::from)
+                .collect(),
+            Arg::NumberOr(_, word) if word.starts_with(prefix) => vec![word.to_string()],
+            Arg::Path(_) => complete_path(prefix),
+            Arg::Settings => match prefix.split_once('=') {
+                Some((name, value)) => SETTINGS
+                    .iter()
+                    .filter(|&&setting| setting == name)
+                    .filter_map(|&setting| find(setting)?.args.first())
+                    .flat_map(|arg| arg.complete(value))
+                    .map(|value| format!("{}={}", name, value))
+                    .collect(),
+                None => SETTINGS
+                    .iter()
+                    .filter(|name| name.starts_with(prefix))
+                    .map(|name| format!("{}=", name))
+                    .collect(),
+            },
+            _ => vec![],
+        }
+    }
+}
+
+// A command with its arguments read, to run. `key` is the key that runs it
+// when it's bound to one.
+pub struct Call {
+    pub command: &'static Command,
+    pub args: Vec<Value>,
+    pub key: Option<KeyEvent>,
+}
+
+impl Call {
+    // The count given to a command that takes one, 1 if there's none.
+    pub fn count(&self) -> u64 {
+        let count = self
+            .command
+            .args
+            .iter()
+            .zip(&self.args)
+            .find_map(|pair| match pair {
+                (Arg::Count, Value::Number(count)) => Some(*count),
+                _ => None,
+            });
+        count.unwrap_or(1)
+    }
+
+    pub fn usage(&self) -> String {
+        self.command.usage()
+    }
+}
+
+pub struct Command {
+    pub name: &'static str,
+    pub args: &'static [Arg],
+    // How many of `args` have to be given; the rest are optional.
+    pub required: usize,
+    pub help: &'static str,
+    pub run: fn(&mut App, &Call) -> Result<(), String>,
+}
+
+impl Command {
+    pub fn usage(&self) -> String {
+        let args = self.args.iter().enumerate().map(|(index, arg)| {
+            if index < self.required {
+                format!(" <{}>", arg.label())
+            } else {
+                format!(" [{}]", arg.label())
+            }
+        });
+        format!("usage: {}{}", self.name, args.collect::<String>())
+    }
+
+    // Reads the arguments `parse` split a command line into. The last `Arg`
+    // takes any more there are, for `:set`.
+    pub fn values(&self, app: &mut App, args: &[String]) -> Result<Vec<Value>, String> {
+        args.iter()
+            .enumerate()
+            .map(|(index, text)| {
+                let arg = self.args.get(index).or(self.args.last());
+                arg.ok_or(self.usage())?.value(app, text)
+            })
+            .collect()
+    }
+}
+
+fn with_off(mut names: Vec<&'static str>) -> Vec<&'static str> {
+    names.push("off");
+    names
+}
+
+fn elements() -> Vec<&'static str> {
+    with_off(Element::names())
+}
+
+fn pixel_formats() -> Vec<&'static str> {
+    with_off(PixelFormat::names())
+}
+
+fn architectures() -> Vec<&'static str> {
+    with_off(Architecture::names())
+}
+
+fn endiannesses() -> Vec<&'static str> {
+    vec!["little", "big"]
+}
+
+fn command_names() -> Vec<&'static str> {
+    COMMANDS.iter().map(|command| command.name).collect()
+}
+
+pub const COMMANDS: &[Command] = &[
+    Command {
+        name: "goto",
+        args: &[Arg::Expression("offset")],
+        required: 0,
+        help: "jump to an offset, e.g. 'a + 4K, or ask for one",
+        run: |app, call| {
+            match call.args[..] {
+                [Value::Offset(offset)] => app.jump_to(offset),
+                _ => app.open_prompt(PromptKind::Goto),
+            }
+            Ok(())
+        },
+    },
+    Command {
+        name: "select",
+        args: &[Arg::Range("start, end")],
+        required: 1,
+        help: "put the cursor on the bytes from start up to end",
+        run: |app, call| match call.args[..] {
+            [Value::Range(start, end)] => app.select(start, end),
+            _ => Err(call.usage()),
+        },
+    },
+    Command {
+        name: "mark",
+        args: &[Arg::Mark("letter"), Arg::Expression("offset")],
+        required: 1,
+        help: "set a mark at the cursor or an offset",
+        run: |app, call| {
+            match call.args[..] {
+                [Value::Mark(name)] => app.set_mark(name, app.cursor().start()),
+                [Value::Mark(name), Value::Offset(offset)] => app.set_mark(name, offset),
+                _ => return Err(call.usage()),
+            }
+            Ok(())
+        },
+    },
+    Command {
+        name: "relative",
+        args: &[Arg::Mark("mark")],
+        required: 0,
+        help: "show offsets relative to a mark, or not",
+        run: |app, call| match call.args[..] {
+            [Value::Mark(name)] => app.relative_to_mark(name),
+            _ => {
+                app.relative_mark = None;
+                Ok(())
+            }
+        },
+    },
+    Command {
+        name: "rva",
+        args: &[Arg::Number("address")],
+        required: 1,
+        help: "jump to a PE relative virtual address",
+        run: |app, call| match call.args[..] {
+            [Value::Number(rva)] => app.goto_rva(rva),
+            _ => Err(call.usage()),
+        },
+    },
+    Command {
+        name: "set",
+        args: &[Arg::Settings],
+        required: 1,
+        help: "change settings, e.g. columns=32 encoding=utf-8",
+        run: |app, call| {
+            call.args.iter().try_for_each(|setting| match setting {
+                Value::Setting(name, value) => {
+                    app.execute(&format!("{} {}", name, shell_words::quote(value)))
+                }
+                _ => Err(call.usage()),
+            })
+        },
+    },
+    Command {
+        name: "columns",
+        args: &[Arg::NumberOr("count|auto", "auto")],
+        required: 1,
+        help: "show this many bytes per row",
+        run: |app, call| match call.args[..] {
+            [Value::Choice(_)] => app.set_columns(None),
+            [Value::Number(columns)] => app.set_columns(Some(columns)),
+            _ => Err(call.usage()),
+        },
+    },
+    Command {
+        name: "group",
+        args: &[Arg::Number("size")],
+        required: 1,
+        help: "space out bytes in groups of this size",
+        run: |app, call| match call.args[..] {
+            [Value::Number(size)] => app.set_group(size),
+            _ => Err(call.usage()),
+        },
+    },
+    Command {
+        name: "encoding",
+        args: &[Arg::Choice("name", Encoding::names)],
+        required: 1,
+        help: "decode text in the text pane as this encoding",
+        run: |app, call| {
+            let [Value::Choice(name)] = call.args[..] else {
+                return Err(call.usage());
+            };
+            app.unicode_display.encoding = Encoding::from_name(name).ok_or(call.usage())?;
+            Ok(())
+        },
+    },
+    Command {
+        name: "endianness",
+        args: &[Arg::Choice("little|big", endiannesses)],
+        required: 1,
+        help: "read values as little or big endian",
+        run: |app, call| {
+            app.endianness = match call.args[..] {
+                [Value::Choice("little")] => Endianness::Little,
+                [Value::Choice("big")] => Endianness::Big,
+                _ => return Err(call.usage()),
+            };
+            Ok(())
+        },
+    },
+    Command {
+        name: "base",
+        args: &[Arg::Number("address")],
+        required: 0,
+        help: "show offsets as addresses from this base",
+        run: |app, call| {
+            app.gutter.base = match call.args[..] {
+                [Value::Number(base)] => base,
+                _ => 0,
+            };
+            Ok(())
+        },
+    },
+    Command {
+        name: "pointer",
+        args: &[Arg::Choice("mode", PointerMode::names)],
+        required: 1,
+        help: "what pointers followed with enter are relative to",
+        run: |app, call| {
+            let [Value::Choice(mode)] = call.args[..] else {
+                return Err(call.usage());
+            };
+            app.pointer_mode = PointerMode::from_name(mode).ok_or(call.usage())?;
+            Ok(())
+        },
+    },
+    Command {
+        name: "struct",
+        args: &[Arg::Path("header"), Arg::Word("name")],
+        required: 0,
+        help: "overlay a C struct from a header at the cursor, or clear it",
+        run: |app, call| match &call.args[..] {
+            [] => {
+                app.annotations.set_group("struct", vec![]);
+                Ok(())
+            }
+            [Value::Text(path), Value::Text(name)] => app.overlay_struct(path, name),
+            _ => Err(call.usage()),
+        },
+    },
+    Command {
+        name: "magic",
+        args: &[],
+        required: 0,
+        help: "identify the file type at the cursor",
+        run: |app, _| {
+            app.identify_at_cursor();
+            Ok(())
+        },
+    },
+    Command {
+        name: "table",
+        args: &[Arg::Choice("type|off", elements), Arg::Number("stride")],
+        required: 0,
+        help: "show an array at the cursor as a table, or stop",
+        run: |app, call| match call.args[..] {
+            [] => {
+                app.toggle_table();
+                Ok(())
+            }
+            [Value::Choice("off")] => {
+                app.table = None;
+                Ok(())
+            }
+            [Value::Choice(element)] => app.show_table(element, None),
+            [Value::Choice(element), Value::Number(stride)] => {
+                app.show_table(element, Some(stride))
+            }
+            _ => Err(call.usage()),
+        },
+    },
+    Command {
+        name: "image",
+        args: &[
+            Arg::Choice("format|off", pixel_formats),
+            Arg::Number("width"),
+            Arg::Number("stride"),
+        ],
+        required: 0,
+        help: "show bytes as pixels, or stop",
+        run: |app, call| match call.args[..] {
+            [] => {
+                app.toggle_image();
+                Ok(())
+            }
+            [Value::Choice("off")] => {
+                app.image = None;
+                Ok(())
+            }
+            [Value::Choice(format)] => app.show_image(format, None, None),
+            [Value::Choice(format), Value::Number(width)] => {
+                app.show_image(format, Some(width), None)
+            }
+            [Value::Choice(format), Value::Number(width), Value::Number(stride)] => {
+                app.show_image(format, Some(width), Some(stride))
+            }
+            _ => Err(call.usage()),
+        },
+    },
+    Command {
+        name: "disasm",
+        args: &[Arg::Choice("architecture|off", architectures)],
+        required: 0,
+        help: "disassemble the code at the cursor, or stop",
+        run: |app, call| {
+            match call.args[..] {
+                [] => app.toggle_disassembly(),
+                [Value::Choice("off")] => app.disassembly = None,
+                [Value::Choice(architecture)] => {
+                    let architecture = Architecture::from_name(architecture);
+                    app.open_disassembly(Some(architecture.ok_or(call.usage())?))
+                }
+                _ => return Err(call.usage()),
+            }
+            Ok(())
+        },
+    },
+    Command {
+        name: "scan",
+        args: &[],
+        required: 0,
+        help: "scan for embedded files, or list those found",
+        run: |app, _| app.scan(),
+    },
+    Command {
+        name: "carve",
+        args: &[Arg::Path("file")],
+        required: 0,
+        help: "save the embedded file at the cursor",
+        run: |app, call| match &call.args[..] {
+            [] => app.carve(None),
+            [Value::Text(path)] => app.carve(Some(path)),
+            _ => Err(call.usage()),
+        },
+    },
+    Command {
+        name: "copy",
+        args: &[Arg::Choice("format", CopyFormat::names)],
+        required: 0,
+        help: "copy the bytes under the cursor, in this format from now on",
+        run: |app, call| {
+            if let [Value::Choice(format)] = call.args[..] {
+                app.copy_format = CopyFormat::from_name(format).ok_or(call.usage())?;
+            }
+            app.copy()
+        },
+    },
+    Command {
+        name: "export",
+        args: &[Arg::Path("file")],
+        required: 1,
+        help: "save the bytes under the cursor",
+        run: |app, call| match &call.args[..] {
+            [Value::Text(path)] => app.export(path),
+            _ => Err(call.usage()),
+        },
+    },
+    Command {
+        name: "help",
+        args: &[Arg::Choice("command", command_names)],
+        required: 0,
+        help: "list commands, or describe one",
+        run: |app, call| {
+            app.help(match call.args[..] {
+                [Value::Choice(name)] => find(name),
+                _ => None,
+            });
+            Ok(())
+        },
+    },
+    // The rest are mostly for key bindings, which pass the count typed in
+    // front of the key on to commands that take one.
+    Command {
+        name: "left",
+        args: &[Arg::Count],
+        required: 0,
+        help: "move the cursor left",
+        run: |app, call| {
+            app.move_left(call.count());
+            Ok(())
+        },
+    },
+    Command {
+        name: "right",
+        args: &[Arg::Count],
+        required: 0,
+        help: "move the cursor right",
+        run: |app, call| {
+            app.move_right(call.count());
+            Ok(())
+        },
+    },
+    Command {
+        name: "up",
+        args: &[Arg::Count],
+        required: 0,
+        help: "move the cursor up a row, or an instruction",
+        run: |app, call| {
+            app.move_up(call.count());
+            Ok(())
+        },
+    },
+    Command {
+        name: "down",
+        args: &[Arg::Count],
+        required: 0,
+        help: "move the cursor down a row, or an instruction",
+        run: |app, call| {
+            app.move_down(call.count());
+            Ok(())
+        },
+    },
+    Command {
+        name: "grow",
+        args: &[Arg::Count],
+        required: 0,
+        help: "make the cursor wider",
+        run: |app, call| {
+            app.repeat_on_cursor(call.count(), Cursor::grow);
+            Ok(())
+        },
+    },
+    Command {
+        name: "shrink",
+        args: &[Arg::Count],
+        required: 0,
+        help: "make the cursor narrower",
+        run: |app, call| {
+            app.repeat_on_cursor(call.count(), Cursor::shrink);
+            Ok(())
+        },
+    },
+    Command {
+        name: "skip-left",
+        args: &[Arg::Count],
+        required: 0,
+        help: "move the cursor left by its width",
+        run: |app, call| {
+            app.repeat_on_cursor(call.count(), Cursor::skip_left);
+            Ok(())
+        },
+    },
+    Command {
+        name: "skip-right",
+        args: &[Arg::Count],
+        required: 0,
+        help: "move the cursor right by its width",
+        run: |app, call| {
+            app.repeat_on_cursor(call.count(), Cursor::skip_right);
+            Ok(())
+        },
+    },
+    Command {
+        name: "page-up",
+        args: &[Arg::Count],
+        required: 0,
+        help: "move the cursor up half a screen",
+        run: |app, call| {
+            app.page_up(call.key, call.count());
+            Ok(())
+        },
+    },
+    Command {
+        name: "page-down",
+        args: &[Arg::Count],
+        required: 0,
+        help: "move the cursor down half a screen",
+        run: |app, call| {
+            app.page_down(call.key, call.count());
+            Ok(())
+        },
+    },
+    Command {
+        name: "start",
+        args: &[],
+        required: 0,
+        help: "move the cursor to the start of the file",
+        run: |app, _| {
+            app.move_to_start();
+            Ok(())
+        },
+    },
+    Command {
+        name: "end",
+        args: &[],
+        required: 0,
+        help: "move the cursor to the end of the file",
+        run: |app, _| {
+            app.move_to_end();
+            Ok(())
+        },
+    },
+    Command {
+        name: "follow",
+        args: &[],
+        required: 0,
+        help: "jump to where the pointer or branch under the cursor goes",
+        run: |app, _| {
+            app.follow();
+            Ok(())
+        },
+    },
+    Command {
+        name: "command-line",
+        args: &[],
+        required: 0,
+        help: "type a command",
+        run: |app, _| {
+            app.open_prompt(PromptKind::Command);
+            Ok(())
+        },
+    },
+    Command {
+        name: "set-mark",
+        args: &[],
+        required: 0,
+        help: "set the mark named by the next key at the cursor",
+        run: |app, _| {
+            app.pending = Some('m');
+            Ok(())
+        },
+    },
+    Command {
+        name: "jump-to-mark",
+        args: &[],
+        required: 0,
+        help: "jump to the mark named by the next key",
+        run: |app, _| {
+            app.pending = Some('\'');
+            Ok(())
+        },
+    },
+    Command {
+        name: "visual",
+        args: &[],
+        required: 0,
+        help: "start or stop selecting",
+        run: |app, _| {
+            app.toggle_selection(SelectionMode::Linear);
+            Ok(())
+        },
+    },
+    Command {
+        name: "visual-block",
+        args: &[],
+        required: 0,
+        help: "start or stop selecting the same columns of several rows",
+        run: |app, _| {
+            app.toggle_selection(SelectionMode::Block);
+            Ok(())
+        },
+    },
+    Command {
+        name: "undo",
+        args: &[Arg::Count],
+        required: 0,
+        help: "go back to where the cursor was",
+        run: |app, call| {
+            (0..call.count()).for_each(|_| app.cursor_stack.undo());
+            Ok(())
+        },
+    },
+    Command {
+        name: "redo",
+        args: &[Arg::Count],
+        required: 0,
+        help: "go forward again after undo",
+        run: |app, call| {
+            (0..call.count()).for_each(|_| app.cursor_stack.redo());
+            Ok(())
+        },
+    },
+    Command {
+        name: "cycle-copy-format",
+        args: &[],
+        required: 0,
+        help: "switch to the next format to copy in",
+        run: |app, _| {
+            app.cycle_copy_format();
+            Ok(())
+        },
+    },
+    Command {
+        name: "toggle-endianness",
+        args: &[],
+        required: 0,
+        help: "switch between little and big endian",
+        run: |app, _| {
+            app.endianness = app.endianness.toggle();
+            Ok(())
+        },
+    },
+    Command {
+        name: "toggle-address-mode",
+        args: &[],
+        required: 0,
+        help: "switch between file offsets and virtual addresses",
+        run: |app, _| {
+            app.toggle_address_mode();
+            Ok(())
+        },
+    },
+    Command {
+        name: "cycle-pointer-mode",
+        args: &[],
+        required: 0,
+        help: "switch to the next way of following pointers",
+        run: |app, _| {
+            app.cycle_pointer_mode();
+            Ok(())
+        },
+    },
+    Command {
+        name: "navigator",
+        args: &[],
+        required: 0,
+        help: "list the sections and symbols of the file",
+        run: |app, _| {
+            app.open_navigator();
+            Ok(())
+        },
+    },
+    Command {
+        name: "cycle-footer",
+        args: &[],
+        required: 0,
+        help: "switch to the next footer",
+        run: |app, _| {
+            app.cycle_footer();
+            Ok(())
+        },
+    },
+    Command {
+        name: "cycle-coloring",
+        args: &[],
+        required: 0,
+        help: "switch to the next way of coloring bytes",
+        run: |app, _| {
+            app.cycle_coloring();
+            Ok(())
+        },
+    },
+    Command {
+        name: "entropy",
+        args: &[],
+        required: 0,
+        help: "show the entropy of the whole file",
+        run: |app, _| {
+            app.open_entropy_view();
+            Ok(())
+        },
+    },
+    Command {
+        name: "bit-view",
+        args: &[],
+        required: 0,
+        help: "show bytes as bits, or not",
+        run: |app, _| {
+            app.toggle_bit_view();
+            Ok(())
+        },
+    },
+    Command {
+        name: "toggle-bit-order",
+        args: &[],
+        required: 0,
+        help: "switch between most and least significant bit first",
+        run: |app, _| {
+            app.toggle_bit_order();
+            Ok(())
+        },
+    },
+    Command {
+        name: "fine-left",
+        args: &[Arg::Count],
+        required: 0,
+        help: "make the image narrower, or move the cursor a bit left",
+        run: |app, call| {
+            app.fine_move(call.count(), false);
+            Ok(())
+        },
+    },
+    Command {
+        name: "fine-right",
+        args: &[Arg::Count],
+        required: 0,
+        help: "make the image wider, or move the cursor a bit right",
+        run: |app, call| {
+            app.fine_move(call.count(), true);
+            Ok(())
+        },
+    },
+    Command {
+        name: "fine-shrink",
+        args: &[Arg::Count],
+        required: 0,
+        help: "make the image 8 pixels narrower, or the cursor a bit narrower",
+        run: |app, call| {
+            app.fine_resize(call.count(), false);
+            Ok(())
+        },
+    },
+    Command {
+        name: "fine-grow",
+        args: &[Arg::Count],
+        required: 0,
+        help: "make the image 8 pixels wider, or the cursor a bit wider",
+        run: |app, call| {
+            app.fine_resize(call.count(), true);
+            Ok(())
+        },
+    },
+    Command {
+        name: "cycle-format",
+        args: &[],
+        required: 0,
+        help: "switch to the next cell, table, pixel or instruction format",
+        run: |app, _| {
+            app.cycle_format();
+            Ok(())
+        },
+    },
+    Command {
+        name: "cycle-word",
+        args: &[],
+        required: 0,
+        help: "switch to the next number of bytes per cell",
+        run: |app, _| {
+            app.cycle_word();
+            Ok(())
+        },
+    },
+    Command {
+        name: "cycle-encoding",
+        args: &[],
+        required: 0,
+        help: "switch to the next text encoding",
+        run: |app, _| {
+            let encoding = &mut app.unicode_display.encoding;
+            *encoding = encoding.next();
+            Ok(())
+        },
+    },
+    Command {
+        name: "cycle-columns",
+        args: &[],
+        required: 0,
+        help: "switch to the next number of bytes per row",
+        run: |app, _| {
+            app.cycle_columns();
+            Ok(())
+        },
+    },
+    Command {
+        name: "cycle-group",
+        args: &[],
+        required: 0,
+        help: "switch to the next group size",
+        run: |app, _| {
+            app.cycle_group();
+            Ok(())
+        },
+    },
+    Command {
+        name: "cycle-radix",
+        args: &[],
+        required: 0,
+        help: "switch to the next radix for offsets",
+        run: |app, _| {
+            app.cycle_radix();
+            Ok(())
+        },
+    },
+    Command {
+        name: "suspend",
+        args: &[],
+        required: 0,
+        help: "put heks in the background",
+        run: |app, _| {
+            app.suspend();
+            Ok(())
+        },
+    },
+    Command {
+        name: "quit",
+        args: &[],
+        required: 0,
+        help: "quit heks",
+        run: |app, _| {
+            app.quitting = true;
+            Ok(())
+        },
+    },
+];
+
+// The commands that move the cursor, which move the head of the selection
+// instead in visual mode.
+pub const MOTIONS: &[&str] = &[
+    "left",
+    "right",
+    "up",
+    "down",
+    "skip-left",
+    "skip-right",
+    "page-up",
+    "page-down",
+    "start",
+    "end",
+];
+
+// What `:set` can change, each of which is also a command of its own.
+const SETTINGS: &[&str] = &[
+    "columns",
+    "group",
+    "encoding",
+    "endianness",
+    "base",
+    "pointer",
+];
+
+pub fn find(name: &str) -> Option<&'static Command> {
+    COMMANDS.iter().find(|command| command.name == name)
+}
+
+// Splits a command line into the command and its arguments, checking that
+// it's given as many as it takes. Expressions run to the end of the line so
+// that they don't need quoting; other arguments are split like a shell would.
+pub fn parse(line: &str) -> Result<Option<(&'static Command, Vec<String>)>, String> {
+    let line = line.trim();
+    let (name, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+    if name.is_empty() {
+        return Ok(None);
+    }
+    let command = find(name).ok_or(format!("unknown command: {}", name))?;
+
+    let args = match command.args.last() {
+        Some(Arg::Expression(_) | Arg::Range(_)) => {
+            let mut args = vec![];
+            let mut rest = rest.trim_start();
+            while args.len() + 1 < command.args.len() && !rest.is_empty() {
+                let (arg, remainder) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+                args.push(arg.to_string());
+                rest = remainder.trim_start();
+            }
+            if !rest.is_empty() {
+                args.push(rest.to_string());
+            }
+            args
+        }
+        _ => shell_words::split(rest).map_err(|error| error.to_string())?,
+    };
+
+    let variadic = matches!(command.args.last(), Some(Arg::Settings));
+    if args.len() < command.required || (args.len() > command.args.len() && !variadic) {
+        return Err(command.usage());
+    }
+    Ok(Some((command, args)))
+}
+
+// Ways to finish the word `line` ends with, and where in `line` that word
+// starts.
+pub fn complete(line: &str) -> (usize, Vec<String>) {
+    let start = line.rfind(char::is_whitespace).map_or(0, |index| index + 1);
+    let word = &line[start..];
+    if start == 0 {
+        return (start, Arg::Choice("command", command_names).complete(word));
+    }
+
+    let mut words = line[..start].split_whitespace();
+    let Some(command) = words.next().and_then(find) else {
+        return (start, vec![]);
+    };
+    let index = words.count();
+    let arg = match command.args.last() {
+        Some(Arg::Settings) => Some(Arg::Settings),
+        _ => command.args.get(index).copied(),
+    };
+    (start, arg.map_or(vec![], |arg| arg.complete(word)))
+}
+
+// Files and directories whose path starts with `prefix`, directories with a
+// trailing slash.
+fn complete_path(prefix: &str) -> Vec<String> {
+    let (directory, name) = match prefix.rfind('/') {
+        Some(index) => prefix.split_at(index + 1),
+        None => ("", prefix),
+    };
+    let Ok(entries) = fs::read_dir(if directory.is_empty() { "." } else { directory }) else {
+        return vec![];
+    };
+    let mut paths: Vec<String> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let file_name = entry.file_name().into_string().ok()?;
+            if !file_name.starts_with(name)
+                || (file_name.starts_with('.') && !name.starts_with('.'))
+            {
+                return None;
+            }
+            let slash = if entry.path().is_dir() { "/" } else { "" };
+            Some(format!("{}{}{}", directory, file_name, slash))
+        })
+        .collect();
+    paths.sort();
+    paths
+}
+
+#[cfg(test)]
+mod command_tests {
+    use super::*;
+
+    fn parsed(line: &str) -> Result<(&'static str, Vec<String>), String> {
+        parse(line).map(|parsed| {
+            let (command, args) = parsed.unwrap();
+            (command.name, args)
+        })
+    }
+
+    #[test]
+    fn test_parse() {
+        assert!(parse("  ").unwrap().is_none());
+        assert_eq!(parsed("scan"), Ok(("scan", vec![])));
+        assert_eq!(
+            parsed("table f32 12"),
+            Ok(("table", vec!["f32".to_string(), "12".to_string()]))
+        );
+        assert_eq!(
+            parsed("carve 'a file.png'"),
+            Ok(("carve", vec!["a file.png".to_string()]))
+        );
+        assert_eq!(
+            parsed("goto 'a + 0x10"),
+            Ok(("goto", vec!["'a + 0x10".to_string()]))
+        );
+        assert_eq!(
+            parsed("mark b  $ - 4"),
+            Ok(("mark", vec!["b".to_string(), "$ - 4".to_string()]))
+        );
+        assert_eq!(parsed("mark b"), Ok(("mark", vec!["b".to_string()])));
+        assert_eq!(
+            parsed("set columns=32 group=4 encoding=utf-8").map(|(_, args)| args.len()),
+            Ok(3)
+        );
+
+        assert_eq!(
+            parsed("frobnicate"),
+            Err("unknown command: frobnicate".to_string())
+        );
+        assert_eq!(parsed("goto"), Ok(("goto", vec![])));
+        assert_eq!(
+            parsed("select"),
+            Err("usage: select <start, end>".to_string())
+        );
+        assert_eq!(
+            parsed("image rgb565 1 2 3"),
+            Err("usage: image [format|off] [width] [stride]".to_string())
+        );
+        assert_eq!(parsed("down 3"), Ok(("down", vec!["3".to_string()])));
+        assert_eq!(parsed("quit now"), Err("usage: quit".to_string()));
+        assert!(parsed("struct 'a.h").is_err());
+    }
+
+    #[test]
+    fn test_complete() {
+        assert_eq!(complete("ta"), (0, vec!["table".to_string()]));
+        assert_eq!(complete("disasm aa"), (7, vec!["aarch64".to_string()]));
+        assert_eq!(complete("table u32 "), (10, vec![]));
+        assert_eq!(complete("frobnicate "), (11, vec![]));
+        assert_eq!(
+            complete("set columns=4 en"),
+            (14, vec!["encoding=".to_string(), "endianness=".to_string()])
+        );
+        assert_eq!(
+            complete("set endianness=b"),
+            (4, vec!["endianness=big".to_string()])
+        );
+        assert_eq!(complete("help disa"), (5, vec!["disasm".to_string()]));
+        assert_eq!(complete("columns a"), (8, vec!["auto".to_string()]));
+        assert_eq!(
+            complete("set columns="),
+            (4, vec!["columns=auto".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_settings_are_commands() {
+        for name in SETTINGS {
+            let command = find(name).unwrap();
+            assert_eq!(command.args.len(), 1);
+        }
+    }
+}
@@ -0,0 +1,167 @@
+// Settings from the user's config.toml, e.g.
+//
+//   columns = 32
+//   encoding = "utf-8"
+//   endianness = "big"
+//   theme = "light"
+//
+//   [keys]
+//   gg = ":goto 0"
+//   G = ":goto $"
+//   "<C-d>" = "page-down"
+
+use serde::{de::Error as _, Deserialize, Deserializer};
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::terminal::Theme;
+
+#[derive(Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    #[serde(default, deserialize_with = "theme")]
+    pub theme: Option<Theme>,
+    // The rest are checked as `:set` would check them once there's an app to
+    // apply them to.
+    columns: Option<Setting>,
+    group: Option<Setting>,
+    encoding: Option<Setting>,
+    endianness: Option<Setting>,
+    base: Option<Setting>,
+    pointer: Option<Setting>,
+    // Key bindings, as `Keymap::bind` takes them.
+    #[serde(default)]
+    pub keys: BTreeMap<String, String>,
+}
+
+// A setting's value, which can be a number or a string, e.g. `columns = 32`
+// or `columns = "auto"`.
+#[derive(Debug, PartialEq, Eq, Deserialize)]
+#[serde(untagged)]
+enum Setting {
+    Number(u64),
+    Text(String),
+}
+
+impl fmt::Display for Setting {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Setting::Number(number) => write!(f, "{}", number),
+            Setting::Text(text) => write!(f, "{}", text),
+        }
+    }
+}
+
+fn theme<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Theme>, D::Error> {
+    let name = String::deserialize(deserializer)?;
+    Theme::from_name(&name).map(Some).ok_or_else(|| {
+        D::Error::custom(format!(
+            "theme must be one of {}",
+            Theme::names().join(", ")
+        ))
+    })
+}
+
+impl Config {
+    pub fn parse(source: &str) -> Result<Config, String> {
+        toml::from_str(source).map_err(|error| {
+            let message = error.message().trim_end();
+            match error.span() {
+                Some(span) => {
+                    let (line, column) = position(source, span.start);
+                    format!("line {}, column {}: {}", line, column, message)
+                }
+                None => message.to_string(),
+            }
+        })
+    }
+
+    // The settings given, as `:set` takes them.
+    pub fn settings(&self) -> Vec<(&'static str, String)> {
+        [
+            ("columns", &self.columns),
+            ("group", &self.group),
+            ("encoding", &self.encoding),
+            ("endianness", &self.endianness),
+            ("base", &self.base),
+            ("pointer", &self.pointer),
+        ]
+        .into_iter()
+        .filter_map(|(name, value)| Some((name, value.as_ref()?.to_string())))
+        .collect()
+    }
+}
+
+// The line and column, counting from 1, of the byte at `offset`.
+fn position(source: &str, offset: usize) -> (usize, usize) {
+    let before = &source[..offset.min(source.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before.rsplit('\n').next().unwrap_or("").chars().count() + 1;
+    (line, column)
+}
+
+#[cfg(test)]
+mod config_tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let source = r#"
+# Wider rows.
+columns = 32
+encoding = "utf-8"  # for logs
+endianness = 'big'
+base = 0x8000_0000
+theme = "light"
+
+[keys]
+gg = ":goto 0"
+"<C-d>" = "page-down"
+'\' = "quit"
+"#;
+        let config = Config::parse(source).unwrap();
+        assert_eq!(config.theme, Some(Theme::Light));
+        let pair = |(name, value): (&'static str, &str)| (name, value.to_string());
+        assert_eq!(
+            config.settings(),
+            vec![
+                pair(("columns", "32")),
+                pair(("encoding", "utf-8")),
+                pair(("endianness", "big")),
+                pair(("base", "2147483648")),
+            ]
+        );
+        let pair = |(keys, action): (&str, &str)| (keys.to_string(), action.to_string());
+        assert_eq!(
+            config.keys.into_iter().collect::<Vec<_>>(),
+            vec![
+                pair(("<C-d>", "page-down")),
+                pair(("\\", "quit")),
+                pair(("gg", ":goto 0")),
+            ]
+        );
+        assert_eq!(
+            Config::parse("columns = \"auto\"").unwrap().settings(),
+            vec![("columns", "auto".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        let error = |source| Config::parse(source).unwrap_err();
+        assert_eq!(error("columns 32"), "line 1, column 9: expected `.`, `=`");
+        assert!(error("\n[colors]").starts_with("line 2, column 2: unknown field `colors`"));
+        assert_eq!(
+            error("\ntheme = \"neon\""),
+            "line 2, column 9: theme must be one of dark, light"
+        );
+        assert_eq!(
+            error("encoding = \"utf-8"),
+            "line 1, column 18: invalid basic string"
+        );
+        assert_eq!(
+            error("[keys]\ngg = 1"),
+            "line 2, column 6: invalid type: integer `1`, expected a string"
+        );
+    }
+}
@@ -0,0 +1,242 @@
+// Bytes written out as text to paste elsewhere, in the notation of a few
+// languages and tools, and the escape sequence that puts that text on the
+// system clipboard.
+
+use itertools::Itertools;
+use std::collections::hash_map::RandomState;
+use std::fmt::Write as _;
+use std::fs::OpenOptions;
+use std::hash::{BuildHasher, Hasher};
+use std::io::{self, Write};
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::PathBuf;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CopyFormat {
+    Hex,       // 7f454c46
+    SpacedHex, // 7f 45 4c 46
+    C,         // unsigned char data[] = { 0x7f, ... };
+    Rust,      // &[0x7f, ...]
+    Python,    // b"\x7fELF"
+    Base64,
+    Text,
+    Xxd, // as `xxd` dumps it, with offsets and text
+}
+
+const FORMATS: &[CopyFormat] = &[
+    CopyFormat::Hex,
+    CopyFormat::SpacedHex,
+    CopyFormat::C,
+    CopyFormat::Rust,
+    CopyFormat::Python,
+    CopyFormat::Base64,
+    CopyFormat::Text,
+    CopyFormat::Xxd,
+];
+
+// Terminals cap how long an OSC 52 sequence they take, at around this much.
+pub const MAX_OSC52: usize = 100_000;
+
+// How many bytes the C and Rust arrays have per line.
+const ARRAY_ROW: usize = 12;
+
+impl CopyFormat {
+    pub fn next(self) -> CopyFormat {
+        let index = FORMATS.iter().position(|&f| f == self).unwrap_or(0);
+        FORMATS[(index + 1) % FORMATS.len()]
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            CopyFormat::Hex => "hex",
+            CopyFormat::SpacedHex => "spaced-hex",
+            CopyFormat::C => "c",
+            CopyFormat::Rust => "rust",
+            CopyFormat::Python => "python",
+            CopyFormat::Base64 => "base64",
+            CopyFormat::Text => "text",
+            CopyFormat::Xxd => "xxd",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<CopyFormat> {
+        FORMATS.iter().copied().find(|f| f.name() == name)
+    }
+
+    pub fn names() -> Vec<&'static str> {
+        FORMATS.iter().map(|f| f.name()).collect()
+    }
+}
+
+// `data` written out as `format`. `offset` is where it's from, for xxd.
+pub fn format(format: CopyFormat, data: &[u8], offset: u64) -> String {
+    match format {
+        CopyFormat::Hex => data.iter().map(|b| format!("{:02x}", b)).collect(),
+        CopyFormat::SpacedHex => data.iter().map(|b| format!("{:02x}", b)).join(" "),
+        CopyFormat::C => format!("unsigned char data[] = {{\n{}}};", array_rows(data)),
+        CopyFormat::Rust => format!("&[\n{}]", array_rows(data)),
+        CopyFormat::Python => {
+            let mut text = String::from("b\"");
+            for &b in data {
+                match b {
+                    b'"' | b'\\' => write!(text, "\\{}", b as char),
+                    b'\n' => write!(text, "\\n"),
+                    b'\r' => write!(text, "\\r"),
+                    b'\t' => write!(text, "\\t"),
+                    0x20..=0x7e => write!(text, "{}", b as char),
+                    _ => write!(text, "\\x{:02x}", b),
+                }
+                .ok();
+            }
+            text.push('"');
+            text
+        }
+        CopyFormat::Base64 => base64(data),
+        CopyFormat::Text => String::from_utf8_lossy(data).into_owned(),
+        CopyFormat::Xxd => data
+            .chunks(16)
+            .enumerate()
+            .map(|(row, bytes)| {
+                let hex = bytes
+                    .chunks(2)
+                    .map(|pair| {
+                        pair.iter()
+                            .map(|b| format!("{:02x}", b))
+                            .collect::<String>()
+                    })
+                    .join(" ");
+                let text: String = bytes
+                    .iter()
+                    .map(|&b| match b {
+                        0x20..=0x7e => b as char,
+                        _ => '.',
+                    })
+                    .collect();
+                let at = offset.saturating_add(row as u64 * 16);
+                format!("{:08x}: {:<39}  {}\n", at, hex, text)
+            })
+            .collect(),
+    }
+}
+
+// The lines of a C or Rust array, each indented and ending in a comma.
+fn array_rows(data: &[u8]) -> String {
+    data.chunks(ARRAY_ROW)
+        .map(|row| {
+            let bytes = row.iter().map(|b| format!("0x{:02x},", b)).join(" ");
+            format!("    {}\n", bytes)
+        })
+        .collect()
+}
+
+pub fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut text = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let bits = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |bits, (i, &b)| bits | ((b as u32) << (16 - 8 * i)));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                text.push(ALPHABET[((bits >> (18 - 6 * i)) & 0x3f) as usize] as char);
+            } else {
+                text.push('=');
+            }
+        }
+    }
+    text
+}
+
+// Asks the terminal to put `text` on the clipboard, which works over SSH too.
+pub fn osc52(text: &str) -> String {
+    format!("\x1b]52;c;{}\x07", base64(text.as_bytes()))
+}
+
+// Writes `text` to a new file in the temporary directory, for when it can't
+// go through the terminal. The file gets a random name and is created rather
+// than opened, so that nobody else can have put something in its place.
+pub fn write_temp(text: &str) -> io::Result<PathBuf> {
+    loop {
+        let random = RandomState::new().build_hasher().finish();
+        let path = std::env::temp_dir().join(format!("heks-{:016x}.txt", random));
+        let file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .mode(0o600)
+            .open(&path);
+        match file {
+            Ok(mut file) => return file.write_all(text.as_bytes()).map(|_| path),
+            Err(error) if error.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(error) => return Err(error),
+        }
+    }
+}
+
+#[cfg(test)]
+mod copy_tests {
+    use super::*;
+
+    const DATA: &[u8] = b"\x7fELF\"\\\n\x00";
+
+    #[test]
+    fn test_format() {
+        let format = |f| super::format(f, DATA, 0x40);
+        assert_eq!(format(CopyFormat::Hex), "7f454c46225c0a00");
+        assert_eq!(format(CopyFormat::SpacedHex), "7f 45 4c 46 22 5c 0a 00");
+        assert_eq!(
+            format(CopyFormat::C),
+            "unsigned char data[] = {\n    0x7f, 0x45, 0x4c, 0x46, 0x22, 0x5c, 0x0a, 0x00,\n};"
+        );
+        assert_eq!(
+            format(CopyFormat::Rust),
+            "&[\n    0x7f, 0x45, 0x4c, 0x46, 0x22, 0x5c, 0x0a, 0x00,\n]"
+        );
+        assert_eq!(format(CopyFormat::Python), r#"b"\x7fELF\"\\\n\x00""#);
+        assert_eq!(format(CopyFormat::Base64), "f0VMRiJcCgA=");
+        assert_eq!(format(CopyFormat::Text), "\u{7f}ELF\"\\\n\0");
+        assert_eq!(
+            format(CopyFormat::Xxd),
+            "00000040: 7f45 4c46 225c 0a00                      .ELF\"\\..\n"
+        );
+    }
+
+    #[test]
+    fn test_long_rows() {
+        let data: Vec<u8> = (0..20).collect();
+        let rust = format(CopyFormat::Rust, &data, 0);
+        assert_eq!(rust.lines().count(), 4);
+        let xxd = format(CopyFormat::Xxd, &data, 0x10);
+        let lines: Vec<&str> = xxd.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[1].starts_with("00000020: 1011 1213  "));
+    }
+
+    #[test]
+    fn test_base64() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+        assert_eq!(base64(b"foobar"), "Zm9vYmFy");
+        assert_eq!(osc52("hi"), "\x1b]52;c;aGk=\x07");
+    }
+
+    #[test]
+    fn test_names() {
+        for &format in FORMATS {
+            assert_eq!(CopyFormat::from_name(format.name()), Some(format));
+        }
+        assert_eq!(CopyFormat::Xxd.next(), CopyFormat::Hex);
+    }
+
+    #[test]
+    fn test_write_temp() {
+        let first = write_temp("hi").unwrap();
+        let second = write_temp("there").unwrap();
+        assert_ne!(first, second);
+        assert_eq!(std::fs::read_to_string(&first).unwrap(), "hi");
+        std::fs::remove_file(first).ok();
+        std::fs::remove_file(second).ok();
+    }
+}
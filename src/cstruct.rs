@@ -0,0 +1,1176 @@
+// Parses C struct and union declarations from header files and computes their
+// layout according to the x86_64 System V ABI.
+//
+// This is not a C compiler: it understands enough of the declaration syntax to
+// pick the structs out of a typical header (primitive types, <stdint.h> types,
+// pointers, fixed size arrays, nested and anonymous structs/unions, enums,
+// typedefs, bitfields, `#pragma pack` and `__attribute__((packed))`) and
+// skips over anything else it comes across.
+
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::annotation::{Annotation, Format};
+use crate::decode::Endianness;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScalarKind {
+    Signed,
+    Unsigned,
+    Char,
+    Bool,
+    Float,
+    Pointer,
+}
+
+#[derive(Debug, Clone)]
+pub enum Type {
+    // On x86_64, every scalar type is aligned to its size.
+    Scalar(ScalarKind, u64),
+    Array(Box<Type>, u64),
+    Record(Rc<Record>),
+    // A type we know the name of, but not the layout (e.g. `void` or a struct
+    // that has only been forward declared). Only usable behind a pointer.
+    Opaque(String),
+}
+
+impl Type {
+    pub fn size_align(&self) -> Result<(u64, u64), String> {
+        match self {
+            Type::Scalar(_, size) => Ok((*size, *size)),
+            Type::Array(element, count) => {
+                let (size, align) = element.size_align()?;
+                // Sizes are also counted in bits, so leave room for that.
+                let size = size
+                    .checked_mul(*count)
+                    .filter(|&size| size <= u64::MAX / 8)
+                    .ok_or("array is too large")?;
+                Ok((size, align))
+            }
+            Type::Record(record) => Ok((record.size, record.align)),
+            Type::Opaque(name) => Err(format!("incomplete type {}", name)),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Member {
+    pub name: Option<String>,
+    pub ty: Type,
+    pub bit_offset: u64,
+    pub bit_width: Option<u32>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Record {
+    pub name: Option<String>,
+    pub is_union: bool,
+    pub members: Vec<Member>,
+    pub size: u64,
+    pub align: u64,
+}
+
+fn align_up(value: u64, align: u64) -> u64 {
+    value.div_ceil(align) * align
+}
+
+struct MemberDecl {
+    name: Option<String>,
+    ty: Type,
+    bit_width: Option<u32>,
+}
+
+fn layout_record(
+    name: Option<String>,
+    is_union: bool,
+    decls: Vec<MemberDecl>,
+    pack: Option<u64>,
+) -> Result<Record, String> {
+    let mut members = vec![];
+    let mut bit_offset = 0u64; // next free bit
+    let mut size_bits = 0u64;
+    let mut max_align = 1u64;
+
+    for decl in decls {
+        let (size, natural_align) = decl.ty.size_align()?;
+        let align = pack.map_or(natural_align, |pack| natural_align.min(pack));
+        if is_union {
+            bit_offset = 0;
+        }
+
+        let offset = match decl.bit_width {
+            None => {
+                max_align = max_align.max(align);
+                align_up(bit_offset, align * 8)
+            }
+            Some(0) => {
+                // A zero-width bitfield pads to the next unit of its type, but
+                // does not affect the alignment of the record.
+                bit_offset = align_up(bit_offset, natural_align * 8);
+                continue;
+            }
+            Some(width) => {
+                if width as u64 > size * 8 {
+                    return Err(format!(
+                        "bitfield {} is wider than its type",
+                        decl.name.as_deref().unwrap_or("<anonymous>")
+                    ));
+                }
+                // Unless packing reduced the alignment, a bitfield may not
+                // straddle a boundary of a storage unit of its type.
+                let unit = size * 8;
+                let offset = if align == natural_align && (bit_offset % unit) + width as u64 > unit
+                {
+                    align_up(bit_offset, unit)
+                } else {
+                    bit_offset
+                };
+                // Unnamed bitfields don't affect the alignment of the record.
+                if decl.name.is_some() {
+                    max_align = max_align.max(align);
+                }
+                offset
+            }
+        };
+
+        let width = decl.bit_width.map_or(size * 8, |width| width as u64);
+        // Leave room to align the offset up to the next member.
+        bit_offset = offset
+            .checked_add(width)
+            .filter(|&end| end <= u64::MAX / 8)
+            .ok_or("struct is too large")?;
+        size_bits = size_bits.max(bit_offset);
+
+        members.push(Member {
+            name: decl.name,
+            ty: decl.ty,
+            bit_offset: offset,
+            bit_width: decl.bit_width,
+        });
+    }
+
+    Ok(Record {
+        name,
+        is_union,
+        members,
+        size: align_up(size_bits.div_ceil(8), max_align),
+        align: max_align,
+    })
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Number(u64),
+    Str,
+    Punct(char),
+    // The packing in effect after a `#pragma pack` directive.
+    Pack(Option<u64>),
+}
+
+fn parse_number(text: &str) -> Option<u64> {
+    let text = text.trim_end_matches(['u', 'U', 'l', 'L']);
+    if let Some(hex) = text.strip_prefix("0x").or(text.strip_prefix("0X")) {
+        u64::from_str_radix(hex, 16).ok()
+    } else if text.len() > 1 && text.starts_with('0') {
+        u64::from_str_radix(&text[1..], 8).ok()
+    } else {
+        text.parse().ok()
+    }
+}
+
+struct Tokenizer {
+    pack_stack: Vec<Option<u64>>,
+    pack: Option<u64>,
+    defines: HashMap<String, u64>,
+    tokens: Vec<Token>,
+}
+
+impl Tokenizer {
+    fn tokenize(source: &str) -> Result<Vec<Token>, String> {
+        let mut tokenizer = Tokenizer {
+            pack_stack: vec![],
+            pack: None,
+            defines: HashMap::new(),
+            tokens: vec![],
+        };
+        tokenizer.run(source)?;
+        Ok(tokenizer.tokens)
+    }
+
+    fn run(&mut self, source: &str) -> Result<(), String> {
+        let chars: Vec<char> = source.chars().collect();
+        let mut i = 0;
+        let mut line_start = true;
+
+        while i < chars.len() {
+            let c = chars[i];
+            if c == '\n' {
+                line_start = true;
+                i += 1;
+            } else if c.is_whitespace() {
+                i += 1;
+            } else if c == '/' && chars.get(i + 1) == Some(&'/') {
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+            } else if c == '/' && chars.get(i + 1) == Some(&'*') {
+                i += 2;
+                while i < chars.len() && !(chars[i] == '*' && chars.get(i + 1) == Some(&'/')) {
+                    i += 1;
+                }
+                i += 2;
+            } else if c == '#' && line_start {
+                let mut line = String::new();
+                i += 1;
+                while i < chars.len() && chars[i] != '\n' {
+                    if chars[i] == '\\' && chars.get(i + 1) == Some(&'\n') {
+                        i += 2;
+                        continue;
+                    }
+                    if chars[i] == '/' && chars.get(i + 1) == Some(&'/') {
+                        break;
+                    }
+                    if chars[i] == '/' && chars.get(i + 1) == Some(&'*') {
+                        // Block comments may continue past the end of the line.
+                        i += 2;
+                        while i < chars.len()
+                            && !(chars[i] == '*' && chars.get(i + 1) == Some(&'/'))
+                        {
+                            i += 1;
+                        }
+                        i += 2;
+                        line.push(' ');
+                        continue;
+                    }
+                    line.push(chars[i]);
+                    i += 1;
+                }
+                self.directive(&line)?;
+            } else if c.is_ascii_alphabetic() || c == '_' {
+                let start = i;
+                while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '_') {
+                    i += 1;
+                }
+                let word: String = chars[start..i].iter().collect();
+                match self.defines.get(&word) {
+                    Some(&value) => self.tokens.push(Token::Number(value)),
+                    None => self.tokens.push(Token::Ident(word)),
+                }
+                line_start = false;
+            } else if c.is_ascii_digit() {
+                let start = i;
+                while i < chars.len() && chars[i].is_ascii_alphanumeric() {
+                    i += 1;
+                }
+                let text: String = chars[start..i].iter().collect();
+                let value = parse_number(&text).ok_or(format!("invalid number {}", text))?;
+                self.tokens.push(Token::Number(value));
+                line_start = false;
+            } else if c == '"' || c == '\'' {
+                let start = i;
+                i += 1;
+                while i < chars.len() && chars[i] != c {
+                    if chars[i] == '\\' {
+                        i += 1;
+                    }
+                    i += 1;
+                }
+                i += 1;
+                if c == '\'' {
+                    let value = chars.get(start + 1).copied().unwrap_or('\0') as u64;
+                    self.tokens.push(Token::Number(value));
+                } else {
+                    self.tokens.push(Token::Str);
+                }
+                line_start = false;
+            } else {
+                self.tokens.push(Token::Punct(c));
+                i += 1;
+                line_start = false;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn directive(&mut self, line: &str) -> Result<(), String> {
+        let words: Vec<&str> = line
+            .split(|c: char| c.is_whitespace() || "(),".contains(c))
+            .filter(|word| !word.is_empty())
+            .collect();
+
+        match words.as_slice() {
+            ["define", name, value, ..] => {
+                if let Some(value) = parse_number(value.trim_matches(['(', ')'])) {
+                    self.defines.insert(name.to_string(), value);
+                }
+            }
+            ["pragma", "pack", args @ ..] => {
+                let number = |word: &&str| parse_number(word);
+                match args {
+                    [] => self.pack = None,
+                    ["push", rest @ ..] => {
+                        self.pack_stack.push(self.pack);
+                        if let Some(value) = rest.iter().find_map(number) {
+                            self.pack = Some(value);
+                        }
+                    }
+                    ["pop", ..] => self.pack = self.pack_stack.pop().flatten(),
+                    [value] => {
+                        self.pack = Some(number(value).ok_or(format!("invalid #pragma {}", line))?)
+                    }
+                    _ => return Err(format!("unsupported #pragma {}", line.trim())),
+                }
+                if let Some(pack) = self.pack {
+                    if !pack.is_power_of_two() {
+                        return Err(format!("invalid packing {}", pack));
+                    }
+                }
+                self.tokens.push(Token::Pack(self.pack));
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+}
+
+const QUALIFIERS: &[&str] = &[
+    "const",
+    "volatile",
+    "static",
+    "extern",
+    "inline",
+    "register",
+    "restrict",
+    "__restrict",
+    "__extension__",
+];
+
+fn builtin_typedefs() -> HashMap<String, Type> {
+    use ScalarKind::*;
+    [
+        ("int8_t", Signed, 1),
+        ("int16_t", Signed, 2),
+        ("int32_t", Signed, 4),
+        ("int64_t", Signed, 8),
+        ("uint8_t", Unsigned, 1),
+        ("uint16_t", Unsigned, 2),
+        ("uint32_t", Unsigned, 4),
+        ("uint64_t", Unsigned, 8),
+        ("size_t", Unsigned, 8),
+        ("ssize_t", Signed, 8),
+        ("ptrdiff_t", Signed, 8),
+        ("intptr_t", Signed, 8),
+        ("uintptr_t", Unsigned, 8),
+        ("off_t", Signed, 8),
+        ("wchar_t", Signed, 4),
+        ("char16_t", Unsigned, 2),
+        ("char32_t", Unsigned, 4),
+    ]
+    .into_iter()
+    .map(|(name, kind, size)| (name.to_string(), Type::Scalar(kind, size)))
+    .collect()
+}
+
+// The structs, unions and typedefs declared in a header.
+pub struct Header {
+    records: HashMap<String, Rc<Record>>,
+    typedefs: HashMap<String, Type>,
+    // Why declarations we couldn't make sense of failed, by name.
+    errors: HashMap<String, String>,
+}
+
+impl Header {
+    pub fn parse(source: &str) -> Result<Header, String> {
+        let mut parser = Parser {
+            tokens: Tokenizer::tokenize(source)?,
+            position: 0,
+            pack: None,
+            records: HashMap::new(),
+            typedefs: builtin_typedefs(),
+            errors: HashMap::new(),
+        };
+        parser.parse();
+
+        Ok(Header {
+            records: parser.records,
+            typedefs: parser.typedefs,
+            errors: parser.errors,
+        })
+    }
+
+    // Looks up a struct or union by its tag, or by a typedef referring to it.
+    pub fn record(&self, name: &str) -> Result<Rc<Record>, String> {
+        let name = name
+            .strip_prefix("struct ")
+            .or(name.strip_prefix("union "))
+            .unwrap_or(name)
+            .trim();
+        match self.typedefs.get(name) {
+            Some(Type::Record(record)) => Ok(record.clone()),
+            _ => match (self.records.get(name), self.errors.get(name)) {
+                (Some(record), _) => Ok(record.clone()),
+                (None, Some(error)) => Err(format!("{}: {}", name, error)),
+                (None, None) => Err(format!("no struct or union named {}", name)),
+            },
+        }
+    }
+}
+
+// Picks out the names a (possibly malformed) declaration was meant to declare,
+// i.e. the tag of a struct or union and any typedef name.
+fn declared_names(tokens: &[Token]) -> Vec<String> {
+    let mut names = vec![];
+    let ident = |index: usize| match tokens.get(index) {
+        Some(Token::Ident(word)) => Some(word.clone()),
+        _ => None,
+    };
+
+    let start = usize::from(ident(0).as_deref() == Some("typedef"));
+    if let (Some("struct" | "union"), Some(tag)) = (ident(start).as_deref(), ident(start + 1)) {
+        names.push(tag);
+    }
+    if start == 1 && tokens.last() == Some(&Token::Punct(';')) && tokens.len() >= 2 {
+        if let Some(alias) = ident(tokens.len() - 2) {
+            names.push(alias);
+        }
+    }
+
+    names
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+    pack: Option<u64>,
+    records: HashMap<String, Rc<Record>>,
+    typedefs: HashMap<String, Type>,
+    errors: HashMap<String, String>,
+}
+
+impl Parser {
+    fn peek(&mut self) -> Option<&Token> {
+        while let Some(Token::Pack(pack)) = self.tokens.get(self.position) {
+            self.pack = *pack;
+            self.position += 1;
+        }
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.peek().cloned();
+        if token.is_some() {
+            self.position += 1;
+        }
+        token
+    }
+
+    fn peek_ident(&mut self) -> Option<String> {
+        match self.peek() {
+            Some(Token::Ident(word)) => Some(word.clone()),
+            _ => None,
+        }
+    }
+
+    fn accept(&mut self, c: char) -> bool {
+        if self.peek() == Some(&Token::Punct(c)) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), String> {
+        if self.accept(c) {
+            Ok(())
+        } else {
+            Err(format!("expected '{}', found {}", c, self.describe_next()))
+        }
+    }
+
+    fn describe_next(&mut self) -> String {
+        match self.peek() {
+            Some(Token::Ident(word)) => format!("'{}'", word),
+            Some(Token::Number(value)) => format!("{}", value),
+            Some(Token::Punct(c)) => format!("'{}'", c),
+            Some(_) => "string".to_string(),
+            None => "end of file".to_string(),
+        }
+    }
+
+    // Skips a balanced group starting at an opening bracket.
+    fn skip_group(&mut self, open: char, close: char) {
+        let mut depth = 0;
+        while let Some(token) = self.next() {
+            if token == Token::Punct(open) {
+                depth += 1;
+            } else if token == Token::Punct(close) {
+                depth -= 1;
+                if depth == 0 {
+                    break;
+                }
+            }
+        }
+    }
+
+    // Skips `__attribute__((...))`, returning true if it contained `packed`.
+    fn skip_attributes(&mut self) -> bool {
+        let mut packed = false;
+        while let Some(word) = self.peek_ident() {
+            if word != "__attribute__" && word != "__attribute" {
+                break;
+            }
+            self.position += 1;
+            let start = self.position;
+            self.skip_group('(', ')');
+            packed |= self.tokens[start..self.position]
+                .iter()
+                .any(|token| *token == Token::Ident("packed".to_string()));
+        }
+        packed
+    }
+
+    fn skip_qualifiers(&mut self) {
+        while let Some(word) = self.peek_ident() {
+            if QUALIFIERS.contains(&word.as_str()) {
+                self.position += 1;
+            } else if !self.skip_attributes() && word != "__attribute__" {
+                break;
+            }
+        }
+    }
+
+    // Skips whatever construct we're in up to the end of the statement.
+    fn skip_statement(&mut self) {
+        while let Some(token) = self.peek().cloned() {
+            match token {
+                Token::Punct(';') => {
+                    self.position += 1;
+                    return;
+                }
+                Token::Punct('{') => {
+                    // function bodies aren't followed by a ';'
+                    let is_function =
+                        self.position > 0 && self.tokens[self.position - 1] == Token::Punct(')');
+                    self.skip_group('{', '}');
+                    if is_function {
+                        return;
+                    }
+                }
+                Token::Punct('(') => self.skip_group('(', ')'),
+                _ => self.position += 1,
+            }
+        }
+    }
+
+    fn parse(&mut self) {
+        while self.peek().is_some() {
+            // `extern "C" {` wrappers and their closing braces.
+            if self.peek_ident().as_deref() == Some("extern")
+                && self.tokens.get(self.position + 1) == Some(&Token::Str)
+            {
+                self.position += 2;
+                self.accept('{');
+                continue;
+            }
+            if self.accept('}') || self.accept(';') {
+                continue;
+            }
+
+            let start = self.position;
+            if let Err(error) = self.parse_declaration() {
+                // Skip whatever we didn't understand, but remember why in case
+                // it turns out to be the struct we're asked for.
+                self.position = start;
+                self.skip_statement();
+                for name in declared_names(&self.tokens[start..self.position]) {
+                    self.errors.entry(name).or_insert(error.clone());
+                }
+            }
+        }
+    }
+
+    fn parse_declaration(&mut self) -> Result<(), String> {
+        let is_typedef = self.peek_ident().as_deref() == Some("typedef");
+        if is_typedef {
+            self.position += 1;
+        }
+
+        let base = self.parse_specifier()?;
+        if self.accept(';') {
+            return Ok(());
+        }
+
+        loop {
+            let (name, ty, _) = self.parse_declarator(base.clone())?;
+            if self.peek() == Some(&Token::Punct('{')) {
+                // a function definition
+                self.skip_group('{', '}');
+                return Ok(());
+            }
+            if let (true, Some(name)) = (is_typedef, name) {
+                self.typedefs.insert(name, ty);
+            }
+            if !self.accept(',') {
+                break;
+            }
+        }
+
+        self.expect(';')
+    }
+
+    fn parse_specifier(&mut self) -> Result<Type, String> {
+        self.skip_qualifiers();
+
+        let word = self
+            .peek_ident()
+            .ok_or_else(|| format!("expected a type, found {}", self.describe_next()))?;
+        match word.as_str() {
+            "struct" | "union" => {
+                self.position += 1;
+                return self.parse_record(word == "union");
+            }
+            "enum" => {
+                self.position += 1;
+                self.skip_attributes();
+                if self.peek_ident().is_some() {
+                    self.position += 1;
+                }
+                if self.peek() == Some(&Token::Punct('{')) {
+                    self.skip_group('{', '}');
+                }
+                return Ok(Type::Scalar(ScalarKind::Signed, 4));
+            }
+            _ => {}
+        }
+
+        if let Some(ty) = self.typedefs.get(&word) {
+            let ty = ty.clone();
+            self.position += 1;
+            self.skip_qualifiers();
+            return Ok(ty);
+        }
+
+        // Builtin types, made up of any combination of these words.
+        let mut signed = None;
+        let mut longs = 0;
+        let mut base = None;
+        while let Some(word) = self.peek_ident() {
+            match word.as_str() {
+                "signed" | "__signed__" => signed = Some(true),
+                "unsigned" => signed = Some(false),
+                "long" => longs += 1,
+                "short" | "int" | "char" | "float" | "double" | "_Bool" | "bool" | "void" => {
+                    base = Some(word)
+                }
+                word if QUALIFIERS.contains(&word) => {}
+                _ => break,
+            }
+            self.position += 1;
+        }
+
+        use ScalarKind::*;
+        let integer = |size| match signed {
+            Some(false) => Type::Scalar(Unsigned, size),
+            _ => Type::Scalar(Signed, size),
+        };
+        match (base.as_deref(), longs) {
+            (Some("char"), _) => Ok(match signed {
+                None => Type::Scalar(Char, 1),
+                Some(_) => integer(1),
+            }),
+            (Some("short"), _) => Ok(integer(2)),
+            (Some("float"), _) => Ok(Type::Scalar(Float, 4)),
+            (Some("double"), 0) => Ok(Type::Scalar(Float, 8)),
+            (Some("double"), _) => Ok(Type::Scalar(Float, 16)),
+            (Some("_Bool"), _) | (Some("bool"), _) => Ok(Type::Scalar(Bool, 1)),
+            (Some("void"), _) => Ok(Type::Opaque("void".to_string())),
+            (_, 0) if base.is_some() || signed.is_some() => Ok(integer(4)),
+            (_, longs) if longs > 0 => Ok(integer(8)),
+            // A pointer to a type we know nothing about, e.g. `FILE *`.
+            _ if self.tokens.get(self.position + 1) == Some(&Token::Punct('*')) => {
+                self.position += 1;
+                Ok(Type::Opaque(word))
+            }
+            _ => Err(format!("unknown type '{}'", word)),
+        }
+    }
+
+    fn parse_record(&mut self, is_union: bool) -> Result<Type, String> {
+        let mut packed = self.skip_attributes();
+        let name = self.peek_ident();
+        if name.is_some() {
+            self.position += 1;
+        }
+        let kind = if is_union { "union" } else { "struct" };
+
+        if !self.accept('{') {
+            let name = name.ok_or(format!("expected {} name or body", kind))?;
+            return Ok(match self.records.get(&name) {
+                Some(record) => Type::Record(record.clone()),
+                None => Type::Opaque(format!("{} {}", kind, name)),
+            });
+        }
+
+        let pack = self.pack;
+        let mut decls = vec![];
+        while !self.accept('}') {
+            if self.peek().is_none() {
+                return Err(format!("unterminated {}", kind));
+            }
+            if self.accept(';') {
+                continue;
+            }
+
+            let base = self.parse_specifier()?;
+            if self.accept(';') {
+                // An anonymous struct or union; its members are accessed as if
+                // they were members of the containing record.
+                if let Type::Record(_) = base {
+                    decls.push(MemberDecl {
+                        name: None,
+                        ty: base,
+                        bit_width: None,
+                    });
+                }
+                continue;
+            }
+            loop {
+                let (name, ty, bit_width) = self.parse_declarator(base.clone())?;
+                decls.push(MemberDecl {
+                    name,
+                    ty,
+                    bit_width,
+                });
+                if !self.accept(',') {
+                    break;
+                }
+            }
+            self.expect(';')?;
+        }
+        packed |= self.skip_attributes();
+
+        let pack = if packed { Some(1) } else { pack };
+        let record = Rc::new(layout_record(name.clone(), is_union, decls, pack).map_err(
+            |error| format!("in {} {}: {}", kind, name.as_deref().unwrap_or(""), error),
+        )?);
+        if let Some(name) = name {
+            self.records.insert(name, record.clone());
+        }
+
+        Ok(Type::Record(record))
+    }
+
+    fn parse_declarator(
+        &mut self,
+        mut ty: Type,
+    ) -> Result<(Option<String>, Type, Option<u32>), String> {
+        let pointer = Type::Scalar(ScalarKind::Pointer, 8);
+
+        while self.accept('*') {
+            ty = pointer.clone();
+            self.skip_qualifiers();
+        }
+
+        let mut name = None;
+        if self.accept('(') {
+            // A function pointer, e.g. `int (*callback)(void *)`.
+            while self.accept('*') {
+                self.skip_qualifiers();
+            }
+            name = self.peek_ident();
+            if name.is_some() {
+                self.position += 1;
+            }
+            self.expect(')')?;
+            ty = pointer.clone();
+        } else if let Some(word) = self.peek_ident() {
+            name = Some(word);
+            self.position += 1;
+        }
+
+        if self.peek() == Some(&Token::Punct('(')) {
+            // A function declaration (or the argument list of a function
+            // pointer).
+            self.skip_group('(', ')');
+            if !matches!(ty, Type::Scalar(ScalarKind::Pointer, _)) {
+                ty = Type::Opaque("function".to_string());
+            }
+        }
+
+        let mut dimensions = vec![];
+        while self.accept('[') {
+            match self.next() {
+                // A flexible array member takes up no space.
+                Some(Token::Punct(']')) => {
+                    dimensions.push(0);
+                    continue;
+                }
+                Some(Token::Number(count)) => dimensions.push(count),
+                _ => return Err("array sizes must be numbers or #defines".to_string()),
+            }
+            self.expect(']')?;
+        }
+        for count in dimensions.into_iter().rev() {
+            ty = Type::Array(Box::new(ty), count);
+        }
+
+        let mut bit_width = None;
+        if self.accept(':') {
+            match self.next() {
+                Some(Token::Number(width)) if width <= 64 => bit_width = Some(width as u32),
+                _ => return Err("invalid bitfield width".to_string()),
+            }
+        }
+        self.skip_attributes();
+
+        Ok((name, ty, bit_width))
+    }
+}
+
+// Arrays with more elements than this are shown as a single field.
+const MAX_ARRAY_FIELDS: u64 = 256;
+
+// Flattens the layout of `record` into annotations, one per member, placed at
+// byte offset `base`. The layout is x86_64's, so the members are little endian
+// whichever endianness is selected.
+pub fn annotate(record: Rc<Record>, label: &str, base: u64) -> Vec<Annotation> {
+    let mut annotations = vec![];
+    annotate_type(
+        &Type::Record(record),
+        label,
+        base * 8,
+        None,
+        0,
+        &mut annotations,
+    );
+    annotations
+        .into_iter()
+        .map(|annotation| annotation.endianness(Endianness::Little))
+        .collect()
+}
+
+fn annotate_type(
+    ty: &Type,
+    label: &str,
+    bit_offset: u64,
+    bit_width: Option<u32>,
+    depth: usize,
+    out: &mut Vec<Annotation>,
+) {
+    let (size, _) = match ty.size_align() {
+        Ok(size_align) => size_align,
+        Err(_) => return,
+    };
+    let start = bit_offset / 8;
+
+    if let Some(width) = bit_width {
+        let shift = (bit_offset % 8) as u32;
+        let bytes = (shift as u64 + width as u64).div_ceil(8);
+        let signed = matches!(
+            ty,
+            Type::Scalar(ScalarKind::Signed, _) | Type::Scalar(ScalarKind::Char, _)
+        );
+        out.push(
+            Annotation::new(
+                start..start + bytes,
+                label,
+                Format::Bits {
+                    shift,
+                    width,
+                    signed,
+                },
+            )
+            .depth(depth),
+        );
+        return;
+    }
+
+    let range = start..start + size;
+    match ty {
+        Type::Scalar(kind, _) => {
+            let format = match kind {
+                ScalarKind::Signed | ScalarKind::Char => Format::Signed,
+                ScalarKind::Unsigned | ScalarKind::Bool => Format::Unsigned,
+                ScalarKind::Float => Format::Float,
+                ScalarKind::Pointer => Format::Hex,
+            };
+            out.push(Annotation::new(range, label, format).depth(depth));
+        }
+        Type::Array(element, count) => {
+            if let Type::Scalar(ScalarKind::Char, _) = **element {
+                out.push(Annotation::new(range, label, Format::Text).depth(depth));
+                return;
+            }
+            out.push(Annotation::new(range, label, Format::None).depth(depth));
+            if *count > MAX_ARRAY_FIELDS {
+                return;
+            }
+            let (element_size, _) = element.size_align().unwrap_or((0, 1));
+            for index in 0..*count {
+                annotate_type(
+                    element,
+                    &format!("{}[{}]", label, index),
+                    bit_offset + index * element_size * 8,
+                    None,
+                    depth + 1,
+                    out,
+                );
+            }
+        }
+        Type::Record(_) => {
+            out.push(Annotation::new(range, label, Format::None).depth(depth));
+            annotate_members(ty, label, bit_offset, depth, out);
+        }
+        Type::Opaque(_) => {}
+    }
+}
+
+fn annotate_members(
+    ty: &Type,
+    label: &str,
+    bit_offset: u64,
+    depth: usize,
+    out: &mut Vec<Annotation>,
+) {
+    if let Type::Record(record) = ty {
+        for member in &record.members {
+            let member_offset = bit_offset + member.bit_offset;
+            match &member.name {
+                Some(name) => annotate_type(
+                    &member.ty,
+                    &format!("{}.{}", label, name),
+                    member_offset,
+                    member.bit_width,
+                    depth + 1,
+                    out,
+                ),
+                None => annotate_members(&member.ty, label, member_offset, depth, out),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod cstruct_tests {
+    use super::*;
+
+    fn record(source: &str, name: &str) -> Rc<Record> {
+        Header::parse(source).unwrap().record(name).unwrap()
+    }
+
+    fn offsets(record: &Record) -> Vec<(String, u64)> {
+        record
+            .members
+            .iter()
+            .map(|m| (m.name.clone().unwrap_or_default(), m.bit_offset))
+            .collect()
+    }
+
+    #[test]
+    fn test_primitives() {
+        let r = record(
+            "struct s { char a; int b; short c; long d; unsigned char e; double f; };",
+            "s",
+        );
+        assert_eq!(
+            offsets(&r),
+            vec![
+                ("a".to_string(), 0),
+                ("b".to_string(), 32),
+                ("c".to_string(), 64),
+                ("d".to_string(), 128),
+                ("e".to_string(), 192),
+                ("f".to_string(), 256),
+            ]
+        );
+        assert_eq!((r.size, r.align), (40, 8));
+    }
+
+    #[test]
+    fn test_arrays_pointers_and_nesting() {
+        let source = "
+            #include <stdint.h>
+            #define LEN 3
+            typedef struct inner { uint16_t x; uint8_t y; } inner_t;
+            struct outer {
+                uint8_t tag;
+                inner_t items[LEN];
+                void *next;
+                int (*callback)(int, char *);
+                char name[5];
+            };
+        ";
+        let inner = record(source, "inner_t");
+        assert_eq!((inner.size, inner.align), (4, 2));
+
+        let outer = record(source, "struct outer");
+        assert_eq!(
+            offsets(&outer),
+            vec![
+                ("tag".to_string(), 0),
+                ("items".to_string(), 16),
+                ("next".to_string(), 128),
+                ("callback".to_string(), 192),
+                ("name".to_string(), 256),
+            ]
+        );
+        assert_eq!((outer.size, outer.align), (40, 8));
+    }
+
+    #[test]
+    fn test_unions_and_anonymous_members() {
+        let r = record(
+            "struct s { int kind; union { char c; double d; }; struct { short a, b; } pair; };",
+            "s",
+        );
+        assert_eq!(offsets(&r)[1], (String::new(), 64));
+        assert_eq!(offsets(&r)[2], ("pair".to_string(), 128));
+        assert_eq!((r.size, r.align), (24, 8));
+
+        let u = record("union u { char c[5]; int i; };", "u");
+        assert_eq!((u.size, u.align), (8, 4));
+    }
+
+    #[test]
+    fn test_pragma_pack() {
+        let source = "
+            #pragma pack(push, 1)
+            struct packed { char a; int b; short c; };
+            #pragma pack(pop)
+            struct normal { char a; int b; };
+            #pragma pack(2)
+            struct two { char a; int b; };
+            #pragma pack()
+            struct attr { char a; long b; } __attribute__((packed));
+        ";
+        assert_eq!(record(source, "packed").size, 7);
+        assert_eq!(record(source, "normal").size, 8);
+        assert_eq!(record(source, "two").size, 6);
+        assert_eq!(record(source, "attr").size, 9);
+    }
+
+    #[test]
+    fn test_bitfields() {
+        let r = record(
+            "struct s { unsigned a : 3; unsigned b : 30; unsigned : 0; unsigned char c : 4; int d : 2; };",
+            "s",
+        );
+        let bits: Vec<u64> = r.members.iter().map(|m| m.bit_offset).collect();
+        // `b` doesn't fit in the rest of the first int, `c` starts at the next
+        // int due to the zero-width bitfield.
+        assert_eq!(bits, vec![0, 32, 64, 68]);
+        assert_eq!((r.size, r.align), (12, 4));
+
+        let r = record(
+            "#pragma pack(1)\nstruct p { char a; unsigned b : 30; unsigned c : 6; };",
+            "p",
+        );
+        let bits: Vec<u64> = r.members.iter().map(|m| m.bit_offset).collect();
+        assert_eq!(bits, vec![0, 8, 38]);
+        assert_eq!(r.size, 6);
+    }
+
+    #[test]
+    fn test_skips_unrelated_declarations() {
+        let source = "
+            #ifndef HEADER_H
+            extern \"C\" {
+            enum color { RED = 'r', GREEN };
+            static inline int add(int a, int b) { return a + b; }
+            int printf(const char *fmt, ...);
+            struct s { enum color c; long long x; };
+            }
+            #endif
+        ";
+        assert_eq!(record(source, "s").size, 16);
+    }
+
+    #[test]
+    fn test_errors() {
+        let error =
+            |source: &str, name: &str| Header::parse(source).unwrap().record(name).unwrap_err();
+        assert_eq!(
+            error("struct s { struct missing m; };", "s"),
+            "s: in struct s: incomplete type struct missing"
+        );
+        assert_eq!(
+            error("typedef struct { pid_t pid; } info_t;", "info_t"),
+            "info_t: unknown type 'pid_t'"
+        );
+        assert_eq!(
+            error("struct s { char c : 9; };", "s"),
+            "s: in struct s: bitfield c is wider than its type"
+        );
+        assert_eq!(error("struct s { int a; ", "s"), "s: unterminated struct");
+        assert_eq!(
+            error("struct s { char c[0x4000000000000000][8]; };", "s"),
+            "s: in struct s: array is too large"
+        );
+        assert_eq!(
+            error(
+                "struct s { char a[0x1000000000000000]; char b[0x1000000000000000]; };",
+                "s"
+            ),
+            "s: in struct s: struct is too large"
+        );
+        assert_eq!(error("struct s;", "s"), "no struct or union named s");
+        assert!(Header::parse("#pragma pack(3)").is_err());
+
+        // A broken struct doesn't prevent using the others.
+        let header = Header::parse("struct a { bogus_t x; }; struct b { int y; };").unwrap();
+        assert!(header.record("a").is_err());
+        assert_eq!(header.record("b").unwrap().size, 4);
+    }
+
+    #[test]
+    fn test_annotate() {
+        let r = record(
+            "struct s { char a; struct { short x; } b; unsigned c : 4; };",
+            "s",
+        );
+        let annotations = annotate(r, "s", 0x100);
+        let summary: Vec<(String, std::ops::Range<u64>, usize)> = annotations
+            .iter()
+            .map(|a| (a.label.clone(), a.range.clone(), a.depth))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("s".to_string(), 0x100..0x108, 0),
+                ("s.a".to_string(), 0x100..0x101, 1),
+                ("s.b".to_string(), 0x102..0x104, 1),
+                ("s.b.x".to_string(), 0x102..0x104, 2),
+                ("s.c".to_string(), 0x104..0x105, 1),
+            ]
+        );
+        assert_eq!(
+            annotations[4].format,
+            Format::Bits {
+                shift: 0,
+                width: 4,
+                signed: false
+            }
+        );
+    }
+
+    #[test]
+    fn test_annotate_big_endian() {
+        let r = record(
+            "struct s { unsigned a : 4; unsigned b : 8; short c; };",
+            "s",
+        );
+        let annotations = annotate(r, "s", 0);
+        let value = |index: usize, data: &[u8]| {
+            let annotation: &Annotation = &annotations[index];
+            annotation.value(data, Endianness::Big)
+        };
+        assert_eq!(annotations[2].label, "s.b");
+        assert_eq!(value(2, &[0x21, 0x43]), Some("50".to_string()));
+        assert_eq!(annotations[3].label, "s.c");
+        assert_eq!(value(3, &[0x34, 0x12]), Some("4660".to_string()));
+    }
+}
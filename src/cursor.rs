@@ -1,15 +1,29 @@
-use std::cmp::min;
+use std::cmp::{max, min};
 use std::ops::Range;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
 pub struct Cursor {
     pub(super) start: u64,
     pub(super) end: u64, // one past the last character
+    // Set for a block selection, which only covers some columns of each row.
+    pub(super) block: Option<Block>,
+}
+
+// The columns `left..right` of rows `columns` bytes wide.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Block {
+    pub columns: u64,
+    pub left: u64,
+    pub right: u64,
 }
 
 impl Cursor {
     pub fn new(start: u64, end: u64) -> Self {
-        Cursor { start, end }
+        Cursor {
+            start,
+            end,
+            block: None,
+        }
     }
 
     pub fn start(&self) -> u64 {
@@ -19,8 +33,27 @@ impl Cursor {
         self.end
     }
 
+    pub fn block(&self) -> Option<Block> {
+        self.block
+    }
+
     pub fn contains(&self, location: u64) -> bool {
-        self.start <= location && location < self.end
+        let in_block = self
+            .block
+            .is_none_or(|block| (block.left..block.right).contains(&(location % block.columns)));
+        self.start <= location && location < self.end && in_block
+    }
+
+    // The runs of bytes covered, one for each row of a block selection.
+    pub fn ranges(&self) -> Vec<Range<u64>> {
+        let Some(block) = self.block else {
+            return std::iter::once(self.start..self.end).collect();
+        };
+        let first = self.start - self.start % block.columns;
+        (first..self.end)
+            .step_by(block.columns as usize)
+            .map(|row| row + block.left..row + block.right)
+            .collect()
     }
 
     pub fn increment(&mut self, delta: u64) {
@@ -79,6 +112,55 @@ impl Cursor {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SelectionMode {
+    Linear,
+    Block,
+}
+
+// A visual selection, from where it was started to wherever the cursor has
+// since moved, which may be before it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Selection {
+    pub mode: SelectionMode,
+    pub anchor: u64,
+    pub head: u64,
+}
+
+impl Selection {
+    pub fn new(mode: SelectionMode, at: u64) -> Self {
+        Selection {
+            mode,
+            anchor: at,
+            head: at,
+        }
+    }
+
+    // The bytes selected. A block selection takes the same columns, those
+    // between the anchor's and the head's, from each row `columns` wide.
+    pub fn cursor(&self, columns: u64) -> Cursor {
+        let first = min(self.anchor, self.head);
+        let last = max(self.anchor, self.head);
+        match self.mode {
+            SelectionMode::Linear => Cursor::new(first, last.saturating_add(1)),
+            SelectionMode::Block => {
+                let columns = columns.max(1);
+                let left = min(self.anchor % columns, self.head % columns);
+                let right = max(self.anchor % columns, self.head % columns) + 1;
+                Cursor {
+                    start: first - first % columns + left,
+                    end: (last - last % columns).saturating_add(right),
+                    block: Some(Block {
+                        columns,
+                        left,
+                        right,
+                    }),
+                }
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod cursor_tests {
     use super::*;
@@ -105,6 +187,7 @@ mod cursor_tests {
         let c = Cursor {
             start: 100,
             end: 200,
+            ..Default::default()
         };
         assert_eq!(c.start(), 100);
         assert_eq!(c.end(), 200);
@@ -115,6 +198,7 @@ mod cursor_tests {
         let c = Cursor {
             start: 100,
             end: 200,
+            ..Default::default()
         };
         assert!(!c.contains(0));
         assert!(!c.contains(99));
@@ -129,7 +213,11 @@ mod cursor_tests {
 
     #[test]
     fn test_increment() {
-        let mut c = Cursor { start: 0, end: 0 };
+        let mut c = Cursor {
+            start: 0,
+            end: 0,
+            ..Default::default()
+        };
         c.increment(1);
         assert_eq!(c, Cursor::new(1, 1));
         c.increment(5);
@@ -137,7 +225,11 @@ mod cursor_tests {
         c.increment(u64::MAX);
         assert_eq!(c, Cursor::new(u64::MAX, u64::MAX));
 
-        let mut c = Cursor { start: 0, end: 1 };
+        let mut c = Cursor {
+            start: 0,
+            end: 1,
+            ..Default::default()
+        };
         c.increment(1);
         assert_eq!(c, Cursor::new(1, 2));
         c.increment(10);
@@ -148,6 +240,7 @@ mod cursor_tests {
         let mut c = Cursor {
             start: 0,
             end: 9999,
+            ..Default::default()
         };
         c.increment(1);
         assert_eq!(c, Cursor::new(1, 10000));
@@ -178,6 +271,7 @@ mod cursor_tests {
         let mut c = Cursor {
             start: 10000,
             end: 20000,
+            ..Default::default()
         };
         c.decrement(1);
         assert_eq!(c, Cursor::new(9999, 19999));
@@ -322,6 +416,43 @@ mod cursor_tests {
         c.clamp(128u64..256u64);
         assert_eq!(c, Cursor::new(252u64, 256u64));
     }
+
+    #[test]
+    fn test_selection() {
+        let mut selection = Selection::new(SelectionMode::Linear, 20);
+        assert_eq!(selection.cursor(16), Cursor::new(20, 21));
+        selection.head = 40;
+        assert_eq!(selection.cursor(16), Cursor::new(20, 41));
+        selection.head = 3;
+        assert_eq!(selection.cursor(16), Cursor::new(3, 21));
+        assert_eq!(
+            selection.cursor(16).ranges(),
+            vec![Range { start: 3, end: 21 }]
+        );
+    }
+
+    #[test]
+    fn test_block_selection() {
+        // Columns 2 to 5 of the rows at 16, 32 and 48.
+        let mut selection = Selection::new(SelectionMode::Block, 16 + 5);
+        selection.head = 48 + 2;
+        let c = selection.cursor(16);
+        assert_eq!((c.start(), c.end()), (16 + 2, 48 + 6));
+        assert!(c.contains(16 + 2));
+        assert!(c.contains(32 + 5));
+        assert!(!c.contains(32 + 6));
+        assert!(!c.contains(32 + 1));
+        assert!(c.contains(48 + 5));
+        assert!(!c.contains(64 + 2));
+        assert_eq!(c.ranges(), vec![18..22, 34..38, 50..54]);
+
+        // The head can be up and to the left of the anchor.
+        selection.head = 1;
+        let c = selection.cursor(16);
+        assert_eq!((c.start(), c.end()), (1, 16 + 6));
+        assert!(!c.contains(6));
+        assert!(!c.contains(16));
+    }
 }
 
 #[derive(Debug)]
@@ -352,7 +483,7 @@ impl CursorStack {
     }
 
     pub fn top(&self) -> Cursor {
-        self.cursors[self.top_index()].clone()
+        self.cursors[self.top_index()]
     }
 
     pub fn top_mut(&mut self) -> &mut Cursor {
@@ -469,3 +600,119 @@ mod cursor_stack_tests {
         assert_eq!(stack.top(), Cursor::new(2, 4));
     }
 }
+
+// Bit cursors are never wider than this, the most that decodes to an integer.
+pub const MAX_BITS: u64 = 128;
+
+// A cursor that can start and end part way through a byte, for packed
+// bitstreams. Locations are in bits from the start of the source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BitCursor {
+    start: u64,
+    end: u64, // one past the last bit
+}
+
+impl BitCursor {
+    pub fn new(start: u64, end: u64) -> Self {
+        BitCursor { start, end }
+    }
+
+    // All the bits of `cursor`, or as many as fit.
+    pub fn from_bytes(cursor: Cursor) -> Self {
+        let start = cursor.start.saturating_mul(8);
+        let width = (cursor.end - cursor.start).saturating_mul(8);
+        BitCursor::new(start, start.saturating_add(width.clamp(1, MAX_BITS)))
+    }
+
+    pub fn start(&self) -> u64 {
+        self.start
+    }
+
+    pub fn width(&self) -> u64 {
+        self.end - self.start
+    }
+
+    pub fn contains(&self, bit: u64) -> bool {
+        self.start <= bit && bit < self.end
+    }
+
+    // The bytes the bits are in.
+    pub fn bytes(&self) -> Cursor {
+        Cursor::new(self.start / 8, self.end.div_ceil(8))
+    }
+
+    pub fn increment(&mut self, delta: u64) {
+        let width = self.width();
+        self.end = self.end.saturating_add(delta);
+        self.start = self.end - width;
+    }
+
+    pub fn decrement(&mut self, delta: u64) {
+        let width = self.width();
+        self.start = self.start.saturating_sub(delta);
+        self.end = self.start + width;
+    }
+
+    pub fn grow(&mut self) {
+        if self.width() < MAX_BITS {
+            self.end = self.end.saturating_add(1);
+        }
+    }
+
+    pub fn shrink(&mut self) {
+        if self.width() > 1 {
+            self.end -= 1;
+        }
+    }
+
+    // Moves to the byte `cursor` starts at, keeping the same position within
+    // it, if the cursor has been moved elsewhere.
+    pub fn follow(&mut self, cursor: Cursor) {
+        if self.start / 8 != cursor.start {
+            let width = self.width();
+            self.start = cursor.start.saturating_mul(8) + self.start % 8;
+            self.end = self.start.saturating_add(width);
+        }
+    }
+}
+
+#[cfg(test)]
+mod bit_cursor_tests {
+    use super::*;
+
+    #[test]
+    fn test_bytes() {
+        assert_eq!(BitCursor::new(3, 9).bytes(), Cursor::new(0, 2));
+        assert_eq!(BitCursor::new(8, 16).bytes(), Cursor::new(1, 2));
+        assert_eq!(
+            BitCursor::from_bytes(Cursor::new(2, 3)),
+            BitCursor::new(16, 24)
+        );
+        assert_eq!(BitCursor::from_bytes(Cursor::new(0, 100)).width(), MAX_BITS);
+    }
+
+    #[test]
+    fn test_moving() {
+        let mut c = BitCursor::new(6, 10);
+        c.increment(1);
+        assert_eq!(c, BitCursor::new(7, 11));
+        c.decrement(10);
+        assert_eq!(c, BitCursor::new(0, 4));
+        c.shrink();
+        c.shrink();
+        c.shrink();
+        c.shrink();
+        assert_eq!(c, BitCursor::new(0, 1));
+        c.grow();
+        assert_eq!(c, BitCursor::new(0, 2));
+    }
+
+    #[test]
+    fn test_follow() {
+        let mut c = BitCursor::new(13, 17);
+        c.follow(Cursor::new(1, 3));
+        assert_eq!(c, BitCursor::new(13, 17));
+        c.follow(Cursor::new(4, 5));
+        assert_eq!(c, BitCursor::new(37, 41));
+    }
+}
@@ -0,0 +1,183 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Endianness {
+    Little,
+    Big,
+}
+
+impl Endianness {
+    pub fn toggle(self) -> Self {
+        match self {
+            Endianness::Little => Endianness::Big,
+            Endianness::Big => Endianness::Little,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Endianness::Little => "LE",
+            Endianness::Big => "BE",
+        }
+    }
+}
+
+// Which bit of a byte comes first in a bitstream: the most significant, as
+// in most network protocols, or the least, as in DEFLATE.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BitOrder {
+    #[default]
+    Msb,
+    Lsb,
+}
+
+impl BitOrder {
+    pub fn toggle(self) -> Self {
+        match self {
+            BitOrder::Msb => BitOrder::Lsb,
+            BitOrder::Lsb => BitOrder::Msb,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            BitOrder::Msb => "MSB",
+            BitOrder::Lsb => "LSB",
+        }
+    }
+
+    // The mask for the `bit`th bit of a byte, counting in this order.
+    pub fn mask(self, bit: u64) -> u8 {
+        match self {
+            BitOrder::Msb => 0x80 >> (bit % 8),
+            BitOrder::Lsb => 1 << (bit % 8),
+        }
+    }
+}
+
+// Interprets `width` bits of `data` starting from bit `start`, up to 128 of
+// them, as a signed and unsigned integer. The first bit is the most
+// significant in MSB-first order, and the least in LSB-first order.
+pub fn decode_bits(data: &[u8], start: u64, width: u64, order: BitOrder) -> (i128, u128) {
+    let width = width.min(128);
+    let mut unsigned = 0u128;
+    for i in 0..width {
+        let bit = start + i;
+        let set = data
+            .get((bit / 8) as usize)
+            .is_some_and(|byte| byte & order.mask(bit) != 0) as u128;
+        match order {
+            BitOrder::Msb => unsigned = unsigned << 1 | set,
+            BitOrder::Lsb => unsigned |= set << i,
+        }
+    }
+    let signed = match width {
+        0 => 0,
+        128 => unsigned as i128,
+        _ => ((unsigned << (128 - width)) as i128) >> (128 - width),
+    };
+    (signed, unsigned)
+}
+
+// Interprets up to 16 bytes as a signed and unsigned integer. Any bytes beyond
+// the first 16 are ignored.
+pub fn decode_integer(data: &[u8], endianness: Endianness) -> (i128, u128) {
+    let data = &data[..data.len().min(16)];
+    let len = data.len();
+
+    let sign_byte = match endianness {
+        Endianness::Little => data.last(),
+        Endianness::Big => data.first(),
+    };
+    let fill = if sign_byte.copied().unwrap_or(0) & 0x80 != 0 {
+        0xff
+    } else {
+        0x00
+    };
+
+    let mut unsigned = [0u8; 16];
+    let mut signed = [fill; 16];
+
+    match endianness {
+        Endianness::Little => {
+            unsigned[..len].copy_from_slice(data);
+            signed[..len].copy_from_slice(data);
+            (i128::from_le_bytes(signed), u128::from_le_bytes(unsigned))
+        }
+        Endianness::Big => {
+            unsigned[16 - len..].copy_from_slice(data);
+            signed[16 - len..].copy_from_slice(data);
+            (i128::from_be_bytes(signed), u128::from_be_bytes(unsigned))
+        }
+    }
+}
+
+// Interprets 4 or 8 bytes as an IEEE 754 floating point number.
+pub fn decode_float(data: &[u8], endianness: Endianness) -> Option<f64> {
+    let (_, bits) = decode_integer(data, endianness);
+    match data.len() {
+        4 => Some(f32::from_bits(bits as u32) as f64),
+        8 => Some(f64::from_bits(bits as u64)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod decode_tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_integer_little() {
+        assert_eq!(decode_integer(&[], Endianness::Little), (0, 0));
+        assert_eq!(
+            decode_integer(&[0x01, 0x02], Endianness::Little),
+            (0x0201, 0x0201)
+        );
+        assert_eq!(decode_integer(&[0xff], Endianness::Little), (-1, 0xff));
+        assert_eq!(
+            decode_integer(&[0x00, 0x80], Endianness::Little),
+            (-32768, 0x8000)
+        );
+    }
+
+    #[test]
+    fn test_decode_integer_big() {
+        assert_eq!(
+            decode_integer(&[0x01, 0x02], Endianness::Big),
+            (0x0102, 0x0102)
+        );
+        assert_eq!(decode_integer(&[0xff], Endianness::Big), (-1, 0xff));
+        assert_eq!(
+            decode_integer(&[0x80, 0x00], Endianness::Big),
+            (-32768, 0x8000)
+        );
+    }
+
+    #[test]
+    fn test_decode_integer_truncates() {
+        let data: Vec<u8> = (1..=20).collect();
+        let (_, le) = decode_integer(&data, Endianness::Little);
+        assert_eq!(le, u128::from_le_bytes(data[..16].try_into().unwrap()));
+        let (_, be) = decode_integer(&data, Endianness::Big);
+        assert_eq!(be, u128::from_be_bytes(data[..16].try_into().unwrap()));
+    }
+
+    #[test]
+    fn test_decode_bits() {
+        let data = [0b1011_0010, 0b0111_0001];
+        assert_eq!(decode_bits(&data, 0, 4, BitOrder::Msb), (-5, 0b1011));
+        assert_eq!(decode_bits(&data, 6, 4, BitOrder::Msb), (-7, 0b1001));
+        assert_eq!(decode_bits(&data, 0, 4, BitOrder::Lsb), (2, 0b0010));
+        // Bits 6, 7, 8 and 9 are 0, 1, 1 and 0, least significant first.
+        assert_eq!(decode_bits(&data, 6, 4, BitOrder::Lsb), (6, 0b0110));
+        assert_eq!(decode_bits(&data, 0, 16, BitOrder::Msb), (-19855, 0xb271));
+        assert_eq!(decode_bits(&data, 0, 0, BitOrder::Msb), (0, 0));
+    }
+
+    #[test]
+    fn test_decode_float() {
+        let one = 1.0f32.to_le_bytes();
+        assert_eq!(decode_float(&one, Endianness::Little), Some(1.0));
+        let half = 0.5f64.to_be_bytes();
+        assert_eq!(decode_float(&half, Endianness::Big), Some(0.5));
+        assert_eq!(decode_float(&[0, 0], Endianness::Little), None);
+    }
+}
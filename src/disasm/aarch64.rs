@@ -0,0 +1,647 @@
+// AArch64 (A64): branches, PC-relative addressing, moves, integer
+// arithmetic and logic, bitfields, and the usual loads and stores. Floating
+// point and SIMD arithmetic aren't decoded.
+
+use super::{sign_extend, signed_hex, text, Instruction};
+
+const CONDITIONS: [&str; 16] = [
+    "eq", "ne", "hs", "lo", "mi", "pl", "vs", "vc", "hi", "ls", "ge", "lt", "gt", "le", "al", "nv",
+];
+
+const SHIFTS: [&str; 4] = ["lsl", "lsr", "asr", "ror"];
+
+const EXTENDS: [&str; 8] = [
+    "uxtb", "uxth", "uxtw", "uxtx", "sxtb", "sxth", "sxtw", "sxtx",
+];
+
+// Barrier options by CRm.
+const BARRIERS: &[(u32, &str)] = &[
+    (0xf, "sy"),
+    (0xe, "st"),
+    (0xd, "ld"),
+    (0xb, "ish"),
+    (0xa, "ishst"),
+    (0x9, "ishld"),
+    (0x7, "nsh"),
+    (0x3, "osh"),
+];
+
+// System registers by op0:op1:CRn:CRm:op2, for MRS and MSR.
+const SYSTEM_REGISTERS: &[(u32, &str)] = &[
+    (0xda10, "nzcv"),
+    (0xda20, "fpcr"),
+    (0xda21, "fpsr"),
+    (0xde82, "tpidr_el0"),
+    (0xde83, "tpidrro_el0"),
+    (0xc600, "vbar_el1"),
+    (0xdf02, "cntvct_el0"),
+    (0xd807, "dczid_el0"),
+];
+
+fn bits(word: u32, low: u32, len: u32) -> u32 {
+    (word >> low) & ((1 << len) - 1)
+}
+
+// Register `n`, where 31 is the stack pointer or the zero register depending
+// on the instruction.
+fn reg(n: u32, sf: bool, sp: bool) -> String {
+    match (n, sf, sp) {
+        (31, true, true) => "sp".to_string(),
+        (31, false, true) => "wsp".to_string(),
+        (31, true, false) => "xzr".to_string(),
+        (31, false, false) => "wzr".to_string(),
+        (n, true, _) => format!("x{}", n),
+        (n, false, _) => format!("w{}", n),
+    }
+}
+
+// A SIMD and floating point register of `size` bytes.
+fn vreg(n: u32, size: u32) -> String {
+    let prefix = match size {
+        1 => 'b',
+        2 => 'h',
+        4 => 's',
+        8 => 'd',
+        _ => 'q',
+    };
+    format!("{}{}", prefix, n)
+}
+
+fn address(base: u32, offset: i64) -> String {
+    match offset {
+        0 => format!("[{}]", reg(base, true, true)),
+        offset => format!("[{}, {}]", reg(base, true, true), signed_hex(offset)),
+    }
+}
+
+// The immediate of a logical instruction, given as a rotated run of ones
+// replicated across the register; None for the reserved encodings.
+fn bit_mask(n: u32, imms: u32, immr: u32, sf: bool) -> Option<u64> {
+    let combined = (n << 6) | (!imms & 0x3f);
+    if combined == 0 || (!sf && n == 1) {
+        return None;
+    }
+    let size = 1u32 << (31 - combined.leading_zeros());
+    if size < 2 {
+        return None;
+    }
+    let levels = size - 1;
+    let (s, r) = (imms & levels, immr & levels);
+    if s == levels {
+        return None;
+    }
+    let mask = if size == 64 {
+        u64::MAX
+    } else {
+        (1 << size) - 1
+    };
+    let ones = (1u64 << (s + 1)) - 1;
+    let mut value = match r {
+        0 => ones,
+        r => ((ones >> r) | (ones << (size - r))) & mask,
+    };
+    let mut width = size;
+    while width < 64 {
+        value |= value << width;
+        width *= 2;
+    }
+    Some(if sf { value } else { value & 0xffff_ffff })
+}
+
+// The mnemonic and register for a load or store of a general purpose
+// register, by size and opc.
+fn load_store(size: u32, opc: u32) -> Option<(&'static str, bool)> {
+    Some(match (size, opc) {
+        (0, 0) => ("strb", false),
+        (0, 1) => ("ldrb", false),
+        (0, 2) => ("ldrsb", true),
+        (0, 3) => ("ldrsb", false),
+        (1, 0) => ("strh", false),
+        (1, 1) => ("ldrh", false),
+        (1, 2) => ("ldrsh", true),
+        (1, 3) => ("ldrsh", false),
+        (2, 0) => ("str", false),
+        (2, 1) => ("ldr", false),
+        (2, 2) => ("ldrsw", true),
+        (3, 0) => ("str", true),
+        (3, 1) => ("ldr", true),
+        _ => return None,
+    })
+}
+
+// The mnemonic, transfer register and access size in bytes of a load or
+// store with the given size, V and opc fields.
+fn transfer(word: u32, size: u32, opc: u32) -> Option<(&'static str, String, u32)> {
+    let rt = word & 31;
+    if word >> 26 & 1 == 0 {
+        let (mnemonic, sf) = load_store(size, opc)?;
+        return Some((mnemonic, reg(rt, sf, false), 1 << size));
+    }
+    let bytes = match opc {
+        0 | 1 => 1 << size,
+        _ if size == 0 => 16,
+        _ => return None,
+    };
+    let mnemonic = if opc & 1 == 1 { "ldr" } else { "str" };
+    Some((mnemonic, vreg(rt, bytes), bytes))
+}
+
+pub fn decode(data: &[u8], address: u64) -> Option<Instruction> {
+    let word = u32::from_le_bytes(data.get(0..4)?.try_into().ok()?);
+    let (text, target) = decode_word(word, address)
+        .unwrap_or_else(|| (text(".inst", format!("{:#010x}", word)), None));
+    Some(Instruction {
+        len: 4,
+        text,
+        target,
+    })
+}
+
+fn decode_word(w: u32, pc: u64) -> Option<(String, Option<u64>)> {
+    let rd = w & 31;
+    let rn = bits(w, 5, 5);
+    let rm = bits(w, 16, 5);
+    let sf = w >> 31 == 1;
+    let to = |offset: i64| pc.wrapping_add(offset as u64);
+    let plain = |mnemonic: &str, operands: String| Some((text(mnemonic, operands), None));
+
+    // Branches
+    if w & 0x7c00_0000 == 0x1400_0000 {
+        let target = to(sign_extend(w & 0x3ff_ffff, 26) * 4);
+        let mnemonic = if sf { "bl" } else { "b" };
+        return Some((text(mnemonic, format!("{:#x}", target)), Some(target)));
+    }
+    if w & 0xff00_0010 == 0x5400_0000 {
+        let target = to(sign_extend(bits(w, 5, 19), 19) * 4);
+        let mnemonic = format!("b.{}", CONDITIONS[(w & 0xf) as usize]);
+        return Some((text(&mnemonic, format!("{:#x}", target)), Some(target)));
+    }
+    if w & 0x7e00_0000 == 0x3400_0000 {
+        let target = to(sign_extend(bits(w, 5, 19), 19) * 4);
+        let mnemonic = if w >> 24 & 1 == 1 { "cbnz" } else { "cbz" };
+        let operands = format!("{}, {:#x}", reg(rd, sf, false), target);
+        return Some((text(mnemonic, operands), Some(target)));
+    }
+    if w & 0x7e00_0000 == 0x3600_0000 {
+        let target = to(sign_extend(bits(w, 5, 14), 14) * 4);
+        let bit = (w >> 31) << 5 | bits(w, 19, 5);
+        let mnemonic = if w >> 24 & 1 == 1 { "tbnz" } else { "tbz" };
+        let operands = format!("{}, #{}, {:#x}", reg(rd, sf, false), bit, target);
+        return Some((text(mnemonic, operands), Some(target)));
+    }
+    if w & 0xff9f_fc1f == 0xd61f_0000 {
+        return match bits(w, 21, 2) {
+            0 => plain("br", reg(rn, true, false)),
+            1 => plain("blr", reg(rn, true, false)),
+            2 if rn == 30 => plain("ret", String::new()),
+            2 => plain("ret", reg(rn, true, false)),
+            _ => None,
+        };
+    }
+
+    // Exceptions, hints, barriers and system registers
+    if w & 0xffe0_001f == 0xd400_0001 {
+        return plain("svc", format!("#{:#x}", bits(w, 5, 16)));
+    }
+    if w & 0xffe0_001f == 0xd420_0000 {
+        return plain("brk", format!("#{:#x}", bits(w, 5, 16)));
+    }
+    if w & 0xffff_f01f == 0xd503_201f {
+        return match bits(w, 5, 7) {
+            0 => plain("nop", String::new()),
+            1 => plain("yield", String::new()),
+            2 => plain("wfe", String::new()),
+            3 => plain("wfi", String::new()),
+            4 => plain("sev", String::new()),
+            5 => plain("sevl", String::new()),
+            0x19 => plain("paciasp", String::new()),
+            0x1d => plain("autiasp", String::new()),
+            0x20 => plain("bti", String::new()),
+            0x22 => plain("bti", "c".to_string()),
+            0x24 => plain("bti", "j".to_string()),
+            0x26 => plain("bti", "jc".to_string()),
+            hint => plain("hint", format!("#{:#x}", hint)),
+        };
+    }
+    if w & 0xffff_f01f == 0xd503_301f {
+        let crm = bits(w, 8, 4);
+        let option = BARRIERS
+            .iter()
+            .find(|(value, _)| *value == crm)
+            .map_or(format!("#{:#x}", crm), |(_, name)| name.to_string());
+        return match bits(w, 5, 3) {
+            4 => plain("dsb", option),
+            5 => plain("dmb", option),
+            6 => plain("isb", String::new()),
+            _ => None,
+        };
+    }
+    if w & 0xffd0_0000 == 0xd510_0000 {
+        let encoding = bits(w, 5, 15) | 1 << 15;
+        let name = SYSTEM_REGISTERS
+            .iter()
+            .find(|(value, _)| *value == encoding)
+            .map_or(
+                format!(
+                    "s{}_{}_c{}_c{}_{}",
+                    2 + bits(w, 19, 1),
+                    bits(w, 16, 3),
+                    bits(w, 12, 4),
+                    bits(w, 8, 4),
+                    bits(w, 5, 3)
+                ),
+                |(_, name)| name.to_string(),
+            );
+        return if w >> 21 & 1 == 1 {
+            plain("mrs", format!("{}, {}", reg(rd, true, false), name))
+        } else {
+            plain("msr", format!("{}, {}", name, reg(rd, true, false)))
+        };
+    }
+
+    // PC-relative addressing
+    if w & 0x1f00_0000 == 0x1000_0000 {
+        let imm = sign_extend(bits(w, 5, 19) << 2 | bits(w, 29, 2), 21);
+        let (mnemonic, target) = if sf {
+            ("adrp", (pc & !0xfff).wrapping_add((imm << 12) as u64))
+        } else {
+            ("adr", to(imm))
+        };
+        let operands = format!("{}, {:#x}", reg(rd, true, false), target);
+        return Some((text(mnemonic, operands), Some(target)));
+    }
+    if w & 0x3b00_0000 == 0x1800_0000 {
+        let target = to(sign_extend(bits(w, 5, 19), 19) * 4);
+        let opc = w >> 30;
+        let (mnemonic, register) = match (w >> 26 & 1, opc) {
+            (0, 0) => ("ldr", reg(rd, false, false)),
+            (0, 1) => ("ldr", reg(rd, true, false)),
+            (0, 2) => ("ldrsw", reg(rd, true, false)),
+            (1, 0..=2) => ("ldr", vreg(rd, 4 << opc)),
+            _ => return None,
+        };
+        let operands = format!("{}, {:#x}", register, target);
+        return Some((text(mnemonic, operands), Some(target)));
+    }
+
+    // Arithmetic and logic with immediates
+    if w & 0x1f80_0000 == 0x1100_0000 {
+        let (sub, set) = (w >> 30 & 1 == 1, w >> 29 & 1 == 1);
+        let imm = (bits(w, 10, 12) as u64) << (12 * bits(w, 22, 1));
+        let source = reg(rn, sf, true);
+        return if !sub && !set && imm == 0 && (rd == 31 || rn == 31) {
+            plain("mov", format!("{}, {}", reg(rd, sf, true), source))
+        } else if set && rd == 31 {
+            let mnemonic = if sub { "cmp" } else { "cmn" };
+            plain(mnemonic, format!("{}, #{:#x}", source, imm))
+        } else {
+            let mnemonic = match (sub, set) {
+                (false, false) => "add",
+                (false, true) => "adds",
+                (true, false) => "sub",
+                (true, true) => "subs",
+            };
+            let operands = format!("{}, {}, #{:#x}", reg(rd, sf, !set), source, imm);
+            plain(mnemonic, operands)
+        };
+    }
+    if w & 0x1f80_0000 == 0x1200_0000 {
+        let opc = bits(w, 29, 2);
+        let imm = bit_mask(bits(w, 22, 1), bits(w, 10, 6), bits(w, 16, 6), sf)?;
+        return match opc {
+            3 if rd == 31 => plain("tst", format!("{}, #{:#x}", reg(rn, sf, false), imm)),
+            1 if rn == 31 => plain("mov", format!("{}, #{:#x}", reg(rd, sf, true), imm)),
+            _ => {
+                let mnemonic = ["and", "orr", "eor", "ands"][opc as usize];
+                let operands = format!(
+                    "{}, {}, #{:#x}",
+                    reg(rd, sf, opc != 3),
+                    reg(rn, sf, false),
+                    imm
+                );
+                plain(mnemonic, operands)
+            }
+        };
+    }
+    if w & 0x1f80_0000 == 0x1280_0000 {
+        let hw = bits(w, 21, 2);
+        if !sf && hw > 1 {
+            return None;
+        }
+        let imm = bits(w, 5, 16) as u64;
+        let shift = 16 * hw;
+        let width_mask = if sf { u64::MAX } else { 0xffff_ffff };
+        let destination = reg(rd, sf, false);
+        return match bits(w, 29, 2) {
+            0 => plain(
+                "mov",
+                format!("{}, #{:#x}", destination, !(imm << shift) & width_mask),
+            ),
+            2 => plain("mov", format!("{}, #{:#x}", destination, imm << shift)),
+            3 if shift == 0 => plain("movk", format!("{}, #{:#x}", destination, imm)),
+            3 => plain(
+                "movk",
+                format!("{}, #{:#x}, lsl #{}", destination, imm, shift),
+            ),
+            _ => None,
+        };
+    }
+    if w & 0x1f80_0000 == 0x1300_0000 {
+        let opc = bits(w, 29, 2);
+        let (immr, imms) = (bits(w, 16, 6), bits(w, 10, 6));
+        let width = if sf { 64 } else { 32 };
+        if opc == 3 || immr >= width || imms >= width {
+            return None;
+        }
+        let (d, n) = (reg(rd, sf, false), reg(rn, sf, false));
+        let narrow = reg(rn, false, false);
+        return match (opc, immr, imms) {
+            (0, 0, 7) => plain("sxtb", format!("{}, {}", d, narrow)),
+            (0, 0, 15) => plain("sxth", format!("{}, {}", d, narrow)),
+            (0, 0, 31) if sf => plain("sxtw", format!("{}, {}", d, narrow)),
+            (2, 0, 7) if !sf => plain("uxtb", format!("{}, {}", d, n)),
+            (2, 0, 15) if !sf => plain("uxth", format!("{}, {}", d, n)),
+            (0, _, s) if s == width - 1 => plain("asr", format!("{}, {}, #{}", d, n, immr)),
+            (2, _, s) if s == width - 1 => plain("lsr", format!("{}, {}, #{}", d, n, immr)),
+            (2, r, s) if s + 1 == r => plain("lsl", format!("{}, {}, #{}", d, n, width - r)),
+            (_, r, s) if s >= r => {
+                let mnemonic = ["sbfx", "bfxil", "ubfx"][opc as usize];
+                plain(mnemonic, format!("{}, {}, #{}, #{}", d, n, r, s - r + 1))
+            }
+            (_, r, s) => {
+                let mnemonic = ["sbfiz", "bfi", "ubfiz"][opc as usize];
+                plain(
+                    mnemonic,
+                    format!("{}, {}, #{}, #{}", d, n, width - r, s + 1),
+                )
+            }
+        };
+    }
+
+    // Arithmetic and logic on registers
+    if w & 0x1f00_0000 == 0x0a00_0000 {
+        let opc = bits(w, 29, 2);
+        let invert = bits(w, 21, 1);
+        let amount = bits(w, 10, 6);
+        let shift = match amount {
+            0 => String::new(),
+            amount => format!(", {} #{}", SHIFTS[bits(w, 22, 2) as usize], amount),
+        };
+        let (d, n, m) = (reg(rd, sf, false), reg(rn, sf, false), reg(rm, sf, false));
+        return match (opc, invert) {
+            (1, 0) if rn == 31 && shift.is_empty() => plain("mov", format!("{}, {}", d, m)),
+            (1, 1) if rn == 31 => plain("mvn", format!("{}, {}{}", d, m, shift)),
+            (3, 0) if rd == 31 => plain("tst", format!("{}, {}{}", n, m, shift)),
+            _ => {
+                let mnemonic = ["and", "bic", "orr", "orn", "eor", "eon", "ands", "bics"]
+                    [(opc * 2 + invert) as usize];
+                plain(mnemonic, format!("{}, {}, {}{}", d, n, m, shift))
+            }
+        };
+    }
+    if w & 0x1f20_0000 == 0x0b00_0000 {
+        let (sub, set) = (w >> 30 & 1 == 1, w >> 29 & 1 == 1);
+        let kind = bits(w, 22, 2);
+        if kind == 3 {
+            return None;
+        }
+        let shift = match bits(w, 10, 6) {
+            0 => String::new(),
+            amount => format!(", {} #{}", SHIFTS[kind as usize], amount),
+        };
+        let (d, n, m) = (reg(rd, sf, false), reg(rn, sf, false), reg(rm, sf, false));
+        return match (sub, set) {
+            (_, true) if rd == 31 => {
+                let mnemonic = if sub { "cmp" } else { "cmn" };
+                plain(mnemonic, format!("{}, {}{}", n, m, shift))
+            }
+            (true, _) if rn == 31 => {
+                let mnemonic = if set { "negs" } else { "neg" };
+                plain(mnemonic, format!("{}, {}{}", d, m, shift))
+            }
+            _ => {
+                let mnemonic = ["add", "adds", "sub", "subs"][(sub as usize) * 2 + set as usize];
+                plain(mnemonic, format!("{}, {}, {}{}", d, n, m, shift))
+            }
+        };
+    }
+    if w & 0x1f20_0000 == 0x0b20_0000 {
+        let (sub, set) = (w >> 30 & 1 == 1, w >> 29 & 1 == 1);
+        let option = bits(w, 13, 3);
+        let extend = match bits(w, 10, 3) {
+            0 => format!(", {}", EXTENDS[option as usize]),
+            amount => format!(", {} #{}", EXTENDS[option as usize], amount),
+        };
+        let m = reg(rm, sf && option & 3 == 3, false);
+        let n = reg(rn, sf, true);
+        return if set && rd == 31 {
+            let mnemonic = if sub { "cmp" } else { "cmn" };
+            plain(mnemonic, format!("{}, {}{}", n, m, extend))
+        } else {
+            let mnemonic = ["add", "adds", "sub", "subs"][(sub as usize) * 2 + set as usize];
+            let d = reg(rd, sf, !set);
+            plain(mnemonic, format!("{}, {}, {}{}", d, n, m, extend))
+        };
+    }
+    if w & 0x1f00_0000 == 0x1b00_0000 {
+        let ra = bits(w, 10, 5);
+        let negate = w >> 15 & 1 == 1;
+        let (d, n, m) = (reg(rd, sf, false), reg(rn, sf, false), reg(rm, sf, false));
+        let (wn, wm) = (reg(rn, false, false), reg(rm, false, false));
+        return match (bits(w, 21, 3), negate, ra) {
+            (0, false, 31) => plain("mul", format!("{}, {}, {}", d, n, m)),
+            (0, true, 31) => plain("mneg", format!("{}, {}, {}", d, n, m)),
+            (0, false, _) => plain(
+                "madd",
+                format!("{}, {}, {}, {}", d, n, m, reg(ra, sf, false)),
+            ),
+            (0, true, _) => plain(
+                "msub",
+                format!("{}, {}, {}, {}", d, n, m, reg(ra, sf, false)),
+            ),
+            (1, false, 31) if sf => plain("smull", format!("{}, {}, {}", d, wn, wm)),
+            (5, false, 31) if sf => plain("umull", format!("{}, {}, {}", d, wn, wm)),
+            (2, false, _) if sf => plain("smulh", format!("{}, {}, {}", d, n, m)),
+            (6, false, _) if sf => plain("umulh", format!("{}, {}, {}", d, n, m)),
+            _ => None,
+        };
+    }
+    if w & 0x5fe0_0000 == 0x1ac0_0000 {
+        let mnemonic = match bits(w, 10, 6) {
+            2 => "udiv",
+            3 => "sdiv",
+            8 => "lsl",
+            9 => "lsr",
+            10 => "asr",
+            11 => "ror",
+            _ => return None,
+        };
+        let operands = format!(
+            "{}, {}, {}",
+            reg(rd, sf, false),
+            reg(rn, sf, false),
+            reg(rm, sf, false)
+        );
+        return plain(mnemonic, operands);
+    }
+    if w & 0x1fe0_0000 == 0x1a80_0000 {
+        let op = bits(w, 30, 1) * 2 + bits(w, 10, 2);
+        let cond = bits(w, 12, 4);
+        if bits(w, 11, 1) == 1 || bits(w, 29, 1) == 1 {
+            return None;
+        }
+        let inverse = CONDITIONS[(cond ^ 1) as usize];
+        let (d, n, m) = (reg(rd, sf, false), reg(rn, sf, false), reg(rm, sf, false));
+        return match op {
+            1 | 2 if rn == 31 && rm == 31 && cond < 14 => {
+                let mnemonic = if op == 1 { "cset" } else { "csetm" };
+                plain(mnemonic, format!("{}, {}", d, inverse))
+            }
+            1..=3 if rn == rm && rn != 31 && cond < 14 => {
+                let mnemonic = ["cinc", "cinv", "cneg"][op as usize - 1];
+                plain(mnemonic, format!("{}, {}, {}", d, n, inverse))
+            }
+            _ => {
+                let mnemonic = ["csel", "csinc", "csinv", "csneg"][op as usize];
+                let operands = format!("{}, {}, {}, {}", d, n, m, CONDITIONS[cond as usize]);
+                plain(mnemonic, operands)
+            }
+        };
+    }
+
+    // Loads and stores
+    if w & 0x3b00_0000 == 0x3900_0000 {
+        let (mnemonic, register, size) = transfer(w, w >> 30, bits(w, 22, 2))?;
+        let offset = bits(w, 10, 12) as i64 * size as i64;
+        return plain(mnemonic, format!("{}, {}", register, address(rn, offset)));
+    }
+    if w & 0x3b20_0000 == 0x3800_0000 {
+        let (mnemonic, register, _) = transfer(w, w >> 30, bits(w, 22, 2))?;
+        let offset = sign_extend(bits(w, 12, 9), 9);
+        let base = reg(rn, true, true);
+        return match bits(w, 10, 2) {
+            0 => {
+                let unscaled = format!("{}u{}", &mnemonic[..2], &mnemonic[2..]);
+                plain(&unscaled, format!("{}, {}", register, address(rn, offset)))
+            }
+            1 => plain(
+                mnemonic,
+                format!("{}, [{}], {}", register, base, signed_hex(offset)),
+            ),
+            3 => plain(
+                mnemonic,
+                format!("{}, [{}, {}]!", register, base, signed_hex(offset)),
+            ),
+            _ => None,
+        };
+    }
+    if w & 0x3b20_0c00 == 0x3820_0800 {
+        let size = w >> 30;
+        let (mnemonic, register, bytes) = transfer(w, size, bits(w, 22, 2))?;
+        let option = bits(w, 13, 3);
+        let amount = if bits(w, 12, 1) == 1 {
+            bytes.trailing_zeros()
+        } else {
+            0
+        };
+        let index = reg(rm, option & 1 == 1, false);
+        let base = reg(rn, true, true);
+        let extend = match (option, amount) {
+            (3, 0) => String::new(),
+            (3, amount) => format!(", lsl #{}", amount),
+            (option, 0) => format!(", {}", EXTENDS[option as usize]),
+            (option, amount) => format!(", {} #{}", EXTENDS[option as usize], amount),
+        };
+        return plain(
+            mnemonic,
+            format!("{}, [{}, {}{}]", register, base, index, extend),
+        );
+    }
+    if w & 0x3a00_0000 == 0x2800_0000 {
+        let opc = w >> 30;
+        let load = w >> 22 & 1 == 1;
+        let vector = w >> 26 & 1 == 1;
+        let rt2 = bits(w, 10, 5);
+        let (mnemonic, first, second, size) = match (vector, opc) {
+            (false, 0) => ("p", reg(rd, false, false), reg(rt2, false, false), 4),
+            (false, 1) if load => ("psw", reg(rd, true, false), reg(rt2, true, false), 4),
+            (false, 2) => ("p", reg(rd, true, false), reg(rt2, true, false), 8),
+            (true, 0..=2) => ("p", vreg(rd, 4 << opc), vreg(rt2, 4 << opc), 4 << opc),
+            _ => return None,
+        };
+        let index = bits(w, 23, 2);
+        let mnemonic = format!(
+            "{}{}{}",
+            if load { "ld" } else { "st" },
+            if index == 0 { "n" } else { "" },
+            mnemonic
+        );
+        let offset = sign_extend(bits(w, 15, 7), 7) * size as i64;
+        let base = reg(rn, true, true);
+        let operands = match index {
+            1 => format!("{}, {}, [{}], {}", first, second, base, signed_hex(offset)),
+            3 => format!("{}, {}, [{}, {}]!", first, second, base, signed_hex(offset)),
+            _ => format!("{}, {}, {}", first, second, address(rn, offset)),
+        };
+        return plain(&mnemonic, operands);
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod aarch64_tests {
+    use super::*;
+
+    fn disassemble(word: u32, address: u64) -> (String, Option<u64>) {
+        let instruction = decode(&word.to_le_bytes(), address).unwrap();
+        assert_eq!(instruction.len, 4);
+        (instruction.text, instruction.target)
+    }
+
+    #[test]
+    fn test_branches() {
+        assert_eq!(
+            disassemble(0x9400_0010, 0x1000),
+            ("bl      0x1040".to_string(), Some(0x1040))
+        );
+        assert_eq!(
+            disassemble(0x17ff_ffff, 0x1000),
+            ("b       0xffc".to_string(), Some(0xffc))
+        );
+        assert_eq!(
+            disassemble(0x5400_0041, 0x1000),
+            ("b.ne    0x1008".to_string(), Some(0x1008))
+        );
+        assert_eq!(disassemble(0xd65f_03c0, 0).0, "ret");
+        assert_eq!(disassemble(0xd63f_0100, 0).0, "blr     x8");
+        assert_eq!(
+            disassemble(0x9000_0000, 0x1234),
+            ("adrp    x0, 0x1000".to_string(), Some(0x1000))
+        );
+    }
+
+    #[test]
+    fn test_instructions() {
+        assert_eq!(
+            disassemble(0xa9bf_7bfd, 0).0,
+            "stp     x29, x30, [sp, #-0x10]!"
+        );
+        assert_eq!(disassemble(0x9100_03fd, 0).0, "mov     x29, sp");
+        assert_eq!(disassemble(0xf940_0420, 0).0, "ldr     x0, [x1, #0x8]");
+        assert_eq!(disassemble(0xd280_0540, 0).0, "mov     x0, #0x2a");
+        assert_eq!(disassemble(0x3200_1fe0, 0).0, "mov     w0, #0xff");
+        assert_eq!(disassemble(0xaa01_03e0, 0).0, "mov     x0, x1");
+        assert_eq!(disassemble(0xd503_201f, 0).0, "nop");
+        assert_eq!(disassemble(0xffff_ffff, 0).0, ".inst   0xffffffff");
+        assert_eq!(decode(&[0x1f, 0x20, 0x03], 0), None);
+    }
+
+    #[test]
+    fn test_bit_mask() {
+        assert_eq!(bit_mask(0, 0b000111, 0, false), Some(0xff));
+        assert_eq!(bit_mask(1, 0, 1, true), Some(0x8000_0000_0000_0000));
+        assert_eq!(bit_mask(0, 0b111100, 0, true), Some(0x5555_5555_5555_5555));
+        assert_eq!(bit_mask(0, 0b111111, 0, true), None);
+    }
+}
@@ -0,0 +1,596 @@
+// 32-bit ARM, both the ARM (A32) and Thumb instruction sets: branches,
+// data processing, multiplies, loads and stores, and for Thumb-2 the long
+// branches. Anything else is shown as a raw word.
+
+use super::{sign_extend, text, Instruction};
+
+const CONDITIONS: [&str; 16] = [
+    "eq", "ne", "hs", "lo", "mi", "pl", "vs", "vc", "hi", "ls", "ge", "lt", "gt", "le", "", "",
+];
+
+const SHIFTS: [&str; 4] = ["lsl", "lsr", "asr", "ror"];
+
+const DATA_PROCESSING: [&str; 16] = [
+    "and", "eor", "sub", "rsb", "add", "adc", "sbc", "rsc", "tst", "teq", "cmp", "cmn", "orr",
+    "mov", "bic", "mvn",
+];
+
+fn bits(word: u32, low: u32, len: u32) -> u32 {
+    (word >> low) & ((1 << len) - 1)
+}
+
+fn reg(n: u32) -> String {
+    match n {
+        13 => "sp".to_string(),
+        14 => "lr".to_string(),
+        15 => "pc".to_string(),
+        n => format!("r{}", n),
+    }
+}
+
+fn reg_list(mask: u32) -> String {
+    let registers: Vec<String> = (0..16).filter(|n| mask >> n & 1 == 1).map(reg).collect();
+    format!("{{{}}}", registers.join(", "))
+}
+
+fn offset(base: u32, offset: i64) -> String {
+    match offset {
+        0 => format!("[{}]", reg(base)),
+        offset if offset < 0 => format!("[{}, #-{:#x}]", reg(base), offset.unsigned_abs()),
+        offset => format!("[{}, #{:#x}]", reg(base), offset),
+    }
+}
+
+// Where a PC-relative reference goes, with the PC read `ahead` bytes past the
+// instruction and word-aligned for loads and `adr`.
+fn relative(pc: u64, ahead: u64, offset: i64, align: bool) -> u64 {
+    let base = pc.wrapping_add(ahead);
+    let base = if align { base & !3 } else { base };
+    base.wrapping_add(offset as u64)
+}
+
+pub fn decode_arm(data: &[u8], address: u64) -> Option<Instruction> {
+    let word = u32::from_le_bytes(data.get(0..4)?.try_into().ok()?);
+    let (text, target) =
+        arm_word(word, address).unwrap_or_else(|| (text(".inst", format!("{:#010x}", word)), None));
+    Some(Instruction {
+        len: 4,
+        text,
+        target,
+    })
+}
+
+// The operand of a data processing instruction that isn't a register: an
+// immediate, or a register shifted by an immediate or another register.
+fn shifter_operand(w: u32) -> String {
+    if w >> 25 & 1 == 1 {
+        let rotation = 2 * bits(w, 8, 4);
+        return format!("#{:#x}", (w & 0xff).rotate_right(rotation));
+    }
+    let rm = reg(w & 15);
+    let kind = bits(w, 5, 2);
+    if w >> 4 & 1 == 1 {
+        return format!("{}, {} {}", rm, SHIFTS[kind as usize], reg(bits(w, 8, 4)));
+    }
+    match (kind, bits(w, 7, 5)) {
+        (0, 0) => rm,
+        (3, 0) => format!("{}, rrx", rm),
+        (1 | 2, 0) => format!("{}, {} #32", rm, SHIFTS[kind as usize]),
+        (kind, amount) => format!("{}, {} #{}", rm, SHIFTS[kind as usize], amount),
+    }
+}
+
+fn arm_word(w: u32, pc: u64) -> Option<(String, Option<u64>)> {
+    let cond = w >> 28;
+    let rn = bits(w, 16, 4);
+    let rd = bits(w, 12, 4);
+    let set = w >> 20 & 1 == 1;
+    let with = |mnemonic: &str| format!("{}{}", mnemonic, CONDITIONS[cond as usize]);
+    let plain = |mnemonic: String, operands: String| Some((text(&mnemonic, operands), None));
+
+    if cond == 15 {
+        // Only BLX with an immediate, which switches to Thumb.
+        if w & 0x0e00_0000 != 0x0a00_0000 {
+            return None;
+        }
+        let imm = sign_extend(w & 0xff_ffff, 24) * 4 + (w >> 24 & 1) as i64 * 2;
+        let target = relative(pc, 8, imm, false);
+        return Some((text("blx", format!("{:#x}", target)), Some(target)));
+    }
+
+    if w & 0x0e00_0000 == 0x0a00_0000 {
+        let target = relative(pc, 8, sign_extend(w & 0xff_ffff, 24) * 4, false);
+        let mnemonic = with(if w >> 24 & 1 == 1 { "bl" } else { "b" });
+        return Some((text(&mnemonic, format!("{:#x}", target)), Some(target)));
+    }
+    if w & 0x0fff_ffd0 == 0x012f_ff10 {
+        let mnemonic = with(if w >> 5 & 1 == 1 { "blx" } else { "bx" });
+        return plain(mnemonic, reg(w & 15));
+    }
+    if w & 0x0f00_0000 == 0x0f00_0000 {
+        return plain(with("svc"), format!("#{:#x}", w & 0xff_ffff));
+    }
+    if w & 0x0fff_ffff == 0x0320_f000 {
+        return plain(with("nop"), String::new());
+    }
+
+    // Multiplies
+    if w & 0x0fc0_00f0 == 0x0000_0090 {
+        let (rd, rn, rs, rm) = (rn, rd, bits(w, 8, 4), w & 15);
+        let suffix = if set { "s" } else { "" };
+        return if w >> 21 & 1 == 1 {
+            plain(
+                with(&format!("mla{}", suffix)),
+                format!("{}, {}, {}, {}", reg(rd), reg(rm), reg(rs), reg(rn)),
+            )
+        } else {
+            plain(
+                with(&format!("mul{}", suffix)),
+                format!("{}, {}, {}", reg(rd), reg(rm), reg(rs)),
+            )
+        };
+    }
+    if w & 0x0f80_00f0 == 0x0080_0090 {
+        let mnemonic = ["umull", "umlal", "smull", "smlal"][bits(w, 21, 2) as usize];
+        let suffix = if set { "s" } else { "" };
+        let operands = format!(
+            "{}, {}, {}, {}",
+            reg(rd),
+            reg(rn),
+            reg(w & 15),
+            reg(bits(w, 8, 4))
+        );
+        return plain(with(&format!("{}{}", mnemonic, suffix)), operands);
+    }
+
+    // Halfword and signed byte loads and stores
+    if w & 0x0e00_0090 == 0x0000_0090 && bits(w, 5, 2) != 0 {
+        let load = set;
+        let mnemonic = match (load, bits(w, 5, 2)) {
+            (false, 1) => "strh",
+            (true, 1) => "ldrh",
+            (true, 2) => "ldrsb",
+            (true, 3) => "ldrsh",
+            _ => return None,
+        };
+        let up = w >> 23 & 1 == 1;
+        let address = if w >> 22 & 1 == 1 {
+            let imm = (bits(w, 8, 4) << 4 | (w & 15)) as i64;
+            (Some(if up { imm } else { -imm }), None)
+        } else {
+            let sign = if up { "" } else { "-" };
+            (None, Some(format!("{}{}", sign, reg(w & 15))))
+        };
+        return transfer(w, pc, with(mnemonic), reg(rd), address);
+    }
+
+    // Data processing
+    if w & 0x0c00_0000 == 0x0000_0000 {
+        let register = w >> 25 & 1 == 0;
+        if register && w & 0x90 == 0x90 {
+            return None;
+        }
+        let opcode = bits(w, 21, 4);
+        let compare = (8..12).contains(&opcode);
+        if compare && !set {
+            return None;
+        }
+        let suffix = if set && !compare { "s" } else { "" };
+        let mnemonic = with(&format!("{}{}", DATA_PROCESSING[opcode as usize], suffix));
+        let operand = shifter_operand(w);
+        return match opcode {
+            8..=11 => plain(mnemonic, format!("{}, {}", reg(rn), operand)),
+            13 | 15 => plain(mnemonic, format!("{}, {}", reg(rd), operand)),
+            // `add rd, pc, #imm` is how ARM code takes addresses.
+            4 | 2 if rn == 15 && !register => {
+                let imm = (w & 0xff).rotate_right(2 * bits(w, 8, 4)) as i64;
+                let imm = if opcode == 2 { -imm } else { imm };
+                let target = relative(pc, 8, imm, true);
+                let operands = format!("{}, {:#x}", reg(rd), target);
+                Some((text(&with("adr"), operands), Some(target)))
+            }
+            _ => plain(mnemonic, format!("{}, {}, {}", reg(rd), reg(rn), operand)),
+        };
+    }
+
+    // Word and byte loads and stores
+    if w & 0x0c00_0000 == 0x0400_0000 {
+        let register = w >> 25 & 1 == 1;
+        if register && w >> 4 & 1 == 1 {
+            return None;
+        }
+        let load = set;
+        let byte = w >> 22 & 1 == 1;
+        let mnemonic = match (load, byte) {
+            (false, false) => "str",
+            (false, true) => "strb",
+            (true, false) => "ldr",
+            (true, true) => "ldrb",
+        };
+        // `pop {rt}` and `push {rt}` are loads and stores with writeback.
+        if rn == 13 && w & 0x0fff_0fff == 0x049d_0004 {
+            return plain(with("pop"), format!("{{{}}}", reg(rd)));
+        }
+        if rn == 13 && w & 0x0fff_0fff == 0x052d_0004 {
+            return plain(with("push"), format!("{{{}}}", reg(rd)));
+        }
+        let up = w >> 23 & 1 == 1;
+        let address = if register {
+            let sign = if up { "" } else { "-" };
+            let shift = match (bits(w, 5, 2), bits(w, 7, 5)) {
+                (0, 0) => String::new(),
+                (kind, amount) => format!(", {} #{}", SHIFTS[kind as usize], amount),
+            };
+            (None, Some(format!("{}{}{}", sign, reg(w & 15), shift)))
+        } else {
+            let imm = (w & 0xfff) as i64;
+            (Some(if up { imm } else { -imm }), None)
+        };
+        return transfer(w, pc, with(mnemonic), reg(rd), address);
+    }
+
+    // Multiple loads and stores
+    if w & 0x0e00_0000 == 0x0800_0000 {
+        let list = reg_list(w & 0xffff);
+        let writeback = w >> 21 & 1 == 1;
+        let mode = bits(w, 23, 2);
+        return match (set, mode, rn, writeback) {
+            (true, 1, 13, true) => plain(with("pop"), list),
+            (false, 2, 13, true) => plain(with("push"), list),
+            _ => {
+                let name = ["da", "ia", "db", "ib"][mode as usize];
+                let mnemonic = format!("{}{}", if set { "ldm" } else { "stm" }, name);
+                let bang = if writeback { "!" } else { "" };
+                plain(with(&mnemonic), format!("{}{}, {}", reg(rn), bang, list))
+            }
+        };
+    }
+
+    None
+}
+
+// A single load or store at an immediate offset, the first of `address`, or
+// a register one, the second. Loads relative to the PC have a target.
+fn transfer(
+    w: u32,
+    pc: u64,
+    mnemonic: String,
+    rt: String,
+    address: (Option<i64>, Option<String>),
+) -> Option<(String, Option<u64>)> {
+    let rn = bits(w, 16, 4);
+    let pre = w >> 24 & 1 == 1;
+    let writeback = w >> 21 & 1 == 1;
+    let operands = match (address, pre) {
+        ((Some(imm), _), true) if rn == 15 && !writeback => {
+            let target = relative(pc, 8, imm, true);
+            let operands = format!("{}, {:#x}", rt, target);
+            return Some((text(&mnemonic, operands), Some(target)));
+        }
+        ((Some(imm), _), true) => {
+            let bang = if writeback { "!" } else { "" };
+            format!("{}, {}{}", rt, offset(rn, imm), bang)
+        }
+        ((Some(imm), _), false) if imm < 0 => {
+            format!("{}, [{}], #-{:#x}", rt, reg(rn), imm.unsigned_abs())
+        }
+        ((Some(imm), _), false) => format!("{}, [{}], #{:#x}", rt, reg(rn), imm),
+        ((None, Some(index)), true) => {
+            let bang = if writeback { "!" } else { "" };
+            format!("{}, [{}, {}]{}", rt, reg(rn), index, bang)
+        }
+        ((None, Some(index)), false) => format!("{}, [{}], {}", rt, reg(rn), index),
+        ((None, None), _) => return None,
+    };
+    Some((text(&mnemonic, operands), None))
+}
+
+pub fn decode_thumb(data: &[u8], address: u64) -> Option<Instruction> {
+    let first = u16::from_le_bytes(data.get(0..2)?.try_into().ok()?);
+    if first >> 11 >= 0b11101 {
+        let second = u16::from_le_bytes(data.get(2..4)?.try_into().ok()?);
+        let word = (first as u32) << 16 | second as u32;
+        let (text, target) = thumb_wide(word, address)
+            .unwrap_or_else(|| (text(".inst.w", format!("{:#010x}", word)), None));
+        return Some(Instruction {
+            len: 4,
+            text,
+            target,
+        });
+    }
+    let (text, target) = thumb_narrow(first as u32, address)
+        .unwrap_or_else(|| (text(".inst.n", format!("{:#06x}", first)), None));
+    Some(Instruction {
+        len: 2,
+        text,
+        target,
+    })
+}
+
+fn thumb_narrow(h: u32, pc: u64) -> Option<(String, Option<u64>)> {
+    let low = |at: u32| reg(bits(h, at, 3));
+    let plain = |mnemonic: &str, operands: String| Some((text(mnemonic, operands), None));
+    let branch = |mnemonic: &str, target: u64| {
+        Some((text(mnemonic, format!("{:#x}", target)), Some(target)))
+    };
+
+    match h >> 11 {
+        0b00000..=0b00010 => {
+            let mnemonic = ["lsls", "lsrs", "asrs"][(h >> 11) as usize];
+            let amount = match bits(h, 6, 5) {
+                0 if h >> 11 != 0 => 32,
+                amount => amount,
+            };
+            if h >> 11 == 0 && amount == 0 {
+                return plain("movs", format!("{}, {}", low(0), low(3)));
+            }
+            plain(mnemonic, format!("{}, {}, #{}", low(0), low(3), amount))
+        }
+        0b00011 => {
+            let mnemonic = if h >> 9 & 1 == 1 { "subs" } else { "adds" };
+            let operand = if h >> 10 & 1 == 1 {
+                format!("#{}", bits(h, 6, 3))
+            } else {
+                low(6)
+            };
+            plain(mnemonic, format!("{}, {}, {}", low(0), low(3), operand))
+        }
+        0b00100..=0b00111 => {
+            let mnemonic = ["movs", "cmp", "adds", "subs"][bits(h, 11, 2) as usize];
+            plain(mnemonic, format!("{}, #{:#x}", low(8), h & 0xff))
+        }
+        0b01000 if h >> 10 & 1 == 0 => {
+            let mnemonic = [
+                "ands", "eors", "lsls", "lsrs", "asrs", "adcs", "sbcs", "rors", "tst", "rsbs",
+                "cmp", "cmn", "orrs", "muls", "bics", "mvns",
+            ][bits(h, 6, 4) as usize];
+            match bits(h, 6, 4) {
+                9 => plain(mnemonic, format!("{}, {}, #0", low(0), low(3))),
+                _ => plain(mnemonic, format!("{}, {}", low(0), low(3))),
+            }
+        }
+        0b01000 => {
+            let rd = reg(bits(h, 7, 1) << 3 | (h & 7));
+            let rm = reg(bits(h, 3, 4));
+            match bits(h, 8, 2) {
+                0 => plain("add", format!("{}, {}", rd, rm)),
+                1 => plain("cmp", format!("{}, {}", rd, rm)),
+                2 => plain("mov", format!("{}, {}", rd, rm)),
+                _ if h >> 7 & 1 == 1 => plain("blx", rm),
+                _ => plain("bx", rm),
+            }
+        }
+        0b01001 => {
+            let target = relative(pc, 4, (h & 0xff) as i64 * 4, true);
+            Some((
+                text("ldr", format!("{}, {:#x}", low(8), target)),
+                Some(target),
+            ))
+        }
+        0b01010 | 0b01011 => {
+            let mnemonic = [
+                "str", "strh", "strb", "ldrsb", "ldr", "ldrh", "ldrb", "ldrsh",
+            ][bits(h, 9, 3) as usize];
+            plain(mnemonic, format!("{}, [{}, {}]", low(0), low(3), low(6)))
+        }
+        0b01100..=0b10001 => {
+            let (mnemonic, scale) = match h >> 11 {
+                0b01100 => ("str", 4),
+                0b01101 => ("ldr", 4),
+                0b01110 => ("strb", 1),
+                0b01111 => ("ldrb", 1),
+                0b10000 => ("strh", 2),
+                _ => ("ldrh", 2),
+            };
+            let imm = bits(h, 6, 5) as i64 * scale;
+            plain(
+                mnemonic,
+                format!("{}, {}", low(0), offset(bits(h, 3, 3), imm)),
+            )
+        }
+        0b10010 | 0b10011 => {
+            let mnemonic = if h >> 11 & 1 == 1 { "ldr" } else { "str" };
+            plain(
+                mnemonic,
+                format!("{}, {}", low(8), offset(13, (h & 0xff) as i64 * 4)),
+            )
+        }
+        0b10100 => {
+            let target = relative(pc, 4, (h & 0xff) as i64 * 4, true);
+            Some((
+                text("adr", format!("{}, {:#x}", low(8), target)),
+                Some(target),
+            ))
+        }
+        0b10101 => plain("add", format!("{}, sp, #{:#x}", low(8), (h & 0xff) * 4)),
+        0b10110 | 0b10111 => thumb_misc(h, pc),
+        0b11000 | 0b11001 => {
+            let load = h >> 11 & 1 == 1;
+            let list = reg_list(h & 0xff);
+            let rn = bits(h, 8, 3);
+            let bang = if load && h >> rn & 1 == 1 { "" } else { "!" };
+            let mnemonic = if load { "ldmia" } else { "stmia" };
+            plain(mnemonic, format!("{}{}, {}", reg(rn), bang, list))
+        }
+        0b11010 | 0b11011 => match bits(h, 8, 4) {
+            14 => plain("udf", format!("#{}", h & 0xff)),
+            15 => plain("svc", format!("#{}", h & 0xff)),
+            cond => {
+                let target = relative(pc, 4, sign_extend(h & 0xff, 8) * 2, false);
+                branch(&format!("b{}", CONDITIONS[cond as usize]), target)
+            }
+        },
+        0b11100 => {
+            let target = relative(pc, 4, sign_extend(h & 0x7ff, 11) * 2, false);
+            branch("b", target)
+        }
+        _ => None,
+    }
+}
+
+// The 16-bit instructions starting 1011.
+fn thumb_misc(h: u32, pc: u64) -> Option<(String, Option<u64>)> {
+    let low = |at: u32| reg(bits(h, at, 3));
+    let plain = |mnemonic: &str, operands: String| Some((text(mnemonic, operands), None));
+
+    if h & 0xf500 == 0xb100 {
+        let imm = (bits(h, 9, 1) << 6 | bits(h, 3, 5)) as i64 * 2;
+        let target = relative(pc, 4, imm, false);
+        let mnemonic = if h >> 11 & 1 == 1 { "cbnz" } else { "cbz" };
+        return Some((
+            text(mnemonic, format!("{}, {:#x}", low(0), target)),
+            Some(target),
+        ));
+    }
+    match bits(h, 8, 4) {
+        0b0000 => {
+            let mnemonic = if h >> 7 & 1 == 1 { "sub" } else { "add" };
+            plain(mnemonic, format!("sp, sp, #{:#x}", (h & 0x7f) * 4))
+        }
+        0b0010 => {
+            let mnemonic = ["sxth", "sxtb", "uxth", "uxtb"][bits(h, 6, 2) as usize];
+            plain(mnemonic, format!("{}, {}", low(0), low(3)))
+        }
+        0b0100 | 0b0101 => plain("push", reg_list((h & 0xff) | bits(h, 8, 1) << 14)),
+        0b1100 | 0b1101 => plain("pop", reg_list((h & 0xff) | bits(h, 8, 1) << 15)),
+        0b1010 => match bits(h, 6, 2) {
+            0 => plain("rev", format!("{}, {}", low(0), low(3))),
+            1 => plain("rev16", format!("{}, {}", low(0), low(3))),
+            3 => plain("revsh", format!("{}, {}", low(0), low(3))),
+            _ => None,
+        },
+        0b1110 => plain("bkpt", format!("#{:#x}", h & 0xff)),
+        0b1111 if h & 0xf == 0 => {
+            let hint = ["nop", "yield", "wfe", "wfi", "sev"].get(bits(h, 4, 4) as usize)?;
+            plain(hint, String::new())
+        }
+        0b1111 => {
+            // If-then: which of the following instructions run on the
+            // condition rather than its inverse.
+            let cond = bits(h, 4, 4);
+            let mask = h & 0xf;
+            let count = 3 - mask.trailing_zeros();
+            let suffix: String = (0..count)
+                .map(|i| {
+                    if mask >> (3 - i) & 1 == cond & 1 {
+                        't'
+                    } else {
+                        'e'
+                    }
+                })
+                .collect();
+            plain(
+                &format!("it{}", suffix),
+                CONDITIONS[cond as usize].to_string(),
+            )
+        }
+        _ => None,
+    }
+}
+
+fn thumb_wide(w: u32, pc: u64) -> Option<(String, Option<u64>)> {
+    let (first, second) = (w >> 16, w & 0xffff);
+    if first >> 11 != 0b11110 || second >> 15 != 1 {
+        return None;
+    }
+    let s = bits(first, 10, 1);
+    let (j1, j2) = (bits(second, 13, 1), bits(second, 11, 1));
+
+    if second >> 12 & 0b101 == 0b000 {
+        // Conditional branch; the conditions 111x mean something else.
+        let cond = bits(first, 6, 4);
+        if cond >= 14 {
+            return None;
+        }
+        let imm = s << 20 | j2 << 19 | j1 << 18 | bits(first, 0, 6) << 12 | (second & 0x7ff) << 1;
+        let target = relative(pc, 4, sign_extend(imm, 21), false);
+        let mnemonic = format!("b{}.w", CONDITIONS[cond as usize]);
+        return Some((text(&mnemonic, format!("{:#x}", target)), Some(target)));
+    }
+
+    let (i1, i2) = (!(j1 ^ s) & 1, !(j2 ^ s) & 1);
+    let imm = s << 24 | i1 << 23 | i2 << 22 | bits(first, 0, 10) << 12 | (second & 0x7ff) << 1;
+    let offset = sign_extend(imm, 25);
+    let (mnemonic, target) = match second >> 12 & 0b101 {
+        0b001 => ("b.w", relative(pc, 4, offset, false)),
+        0b101 => ("bl", relative(pc, 4, offset, false)),
+        // BLX switches to ARM, whose instructions are word-aligned.
+        _ => ("blx", relative(pc, 4, offset, true)),
+    };
+    Some((text(mnemonic, format!("{:#x}", target)), Some(target)))
+}
+
+#[cfg(test)]
+mod arm_tests {
+    use super::*;
+
+    fn arm(word: u32, address: u64) -> (String, Option<u64>) {
+        let instruction = decode_arm(&word.to_le_bytes(), address).unwrap();
+        (instruction.text, instruction.target)
+    }
+
+    fn thumb(halves: &[u16], address: u64) -> (String, Option<u64>, u64) {
+        let data: Vec<u8> = halves.iter().flat_map(|h| h.to_le_bytes()).collect();
+        let instruction = decode_thumb(&data, address).unwrap();
+        (instruction.text, instruction.target, instruction.len)
+    }
+
+    #[test]
+    fn test_arm() {
+        assert_eq!(
+            arm(0xeb00_0002, 0x1000),
+            ("bl      0x1010".to_string(), Some(0x1010))
+        );
+        assert_eq!(arm(0x1aff_fffe, 0x1000).0, "bne     0x1000");
+        assert_eq!(arm(0xe12f_ff1e, 0).0, "bx      lr");
+        assert_eq!(arm(0xe92d_4010, 0).0, "push    {r4, lr}");
+        assert_eq!(arm(0xe8bd_8010, 0).0, "pop     {r4, pc}");
+        assert_eq!(arm(0xe3a0_0001, 0).0, "mov     r0, #0x1");
+        assert_eq!(arm(0xe081_0102, 0).0, "add     r0, r1, r2, lsl #2");
+        assert_eq!(arm(0xe591_0004, 0).0, "ldr     r0, [r1, #0x4]");
+        assert_eq!(arm(0xe5b1_0004, 0).0, "ldr     r0, [r1, #0x4]!");
+        assert_eq!(
+            arm(0xe59f_0010, 0x1000),
+            ("ldr     r0, 0x1018".to_string(), Some(0x1018))
+        );
+        assert_eq!(arm(0xe003_0291, 0).0, "mul     r3, r1, r2");
+        assert_eq!(arm(0xe352_0000, 0).0, "cmp     r2, #0x0");
+        assert_eq!(arm(0xef00_0000, 0).0, "svc     #0x0");
+        assert_eq!(arm(0xf7f0_00f0, 0).0, ".inst   0xf7f000f0");
+    }
+
+    #[test]
+    fn test_thumb() {
+        assert_eq!(
+            thumb(&[0xb510], 0),
+            ("push    {r4, lr}".to_string(), None, 2)
+        );
+        assert_eq!(thumb(&[0xbd10], 0).0, "pop     {r4, pc}");
+        assert_eq!(thumb(&[0x2001], 0).0, "movs    r0, #0x1");
+        assert_eq!(thumb(&[0x4770], 0).0, "bx      lr");
+        assert_eq!(thumb(&[0x1840], 0).0, "adds    r0, r0, r1");
+        assert_eq!(thumb(&[0xbf00], 0).0, "nop");
+        assert_eq!(thumb(&[0xbf0c], 0).0, "ite     eq");
+        assert_eq!(
+            thumb(&[0xd0fe], 0x1000),
+            ("beq     0x1000".to_string(), Some(0x1000), 2)
+        );
+        assert_eq!(
+            thumb(&[0x4801], 0x1002),
+            ("ldr     r0, 0x1008".to_string(), Some(0x1008), 2)
+        );
+        assert_eq!(
+            thumb(&[0xf000, 0xf802], 0x1000),
+            ("bl      0x1008".to_string(), Some(0x1008), 4)
+        );
+        assert_eq!(
+            thumb(&[0xf7ff, 0xfffe], 0x1000),
+            ("bl      0x1000".to_string(), Some(0x1000), 4)
+        );
+        assert_eq!(
+            thumb(&[0xb118], 0x1000),
+            ("cbz     r0, 0x100a".to_string(), Some(0x100a), 2)
+        );
+        assert_eq!(decode_thumb(&[0x00, 0xf0], 0), None);
+    }
+}
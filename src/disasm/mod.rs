@@ -0,0 +1,425 @@
+// Disassembly of the code at the cursor. x86 goes through iced-x86; the
+// others have small decoders of their own here covering the instructions
+// compilers emit most, with anything else shown as a raw word.
+//
+// Instruction streams are taken to be little-endian, which is what all of
+// these are in practice.
+
+pub mod aarch64;
+pub mod arm;
+pub mod riscv;
+pub mod x86;
+
+use std::ops::Range;
+
+use itertools::Itertools;
+
+use tui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Modifier, Style},
+    text::{Span, Spans},
+    widgets::{Paragraph, Widget},
+};
+
+use crate::formats::{elf, pe};
+use crate::terminal::color;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Architecture {
+    X86,
+    X86_64,
+    AArch64,
+    Arm,
+    Thumb,
+    RiscV32,
+    RiscV64,
+}
+
+const ARCHITECTURES: &[Architecture] = &[
+    Architecture::X86_64,
+    Architecture::X86,
+    Architecture::AArch64,
+    Architecture::Arm,
+    Architecture::Thumb,
+    Architecture::RiscV64,
+    Architecture::RiscV32,
+];
+
+impl Architecture {
+    pub fn next(self) -> Architecture {
+        let index = ARCHITECTURES.iter().position(|&a| a == self).unwrap_or(0);
+        ARCHITECTURES[(index + 1) % ARCHITECTURES.len()]
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Architecture::X86 => "x86",
+            Architecture::X86_64 => "x86-64",
+            Architecture::AArch64 => "aarch64",
+            Architecture::Arm => "arm",
+            Architecture::Thumb => "thumb",
+            Architecture::RiscV32 => "riscv32",
+            Architecture::RiscV64 => "riscv64",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Architecture> {
+        ARCHITECTURES.iter().copied().find(|a| a.name() == name)
+    }
+
+    pub fn names() -> Vec<&'static str> {
+        ARCHITECTURES.iter().map(|a| a.name()).collect()
+    }
+
+    // The architecture for an ELF `e_machine`. 32-bit ARM code may be ARM or
+    // Thumb; we start out assuming ARM.
+    pub fn from_elf(machine: u64, is64: bool) -> Option<Architecture> {
+        match machine {
+            elf::EM_386 => Some(Architecture::X86),
+            elf::EM_X86_64 => Some(Architecture::X86_64),
+            elf::EM_AARCH64 => Some(Architecture::AArch64),
+            elf::EM_ARM => Some(Architecture::Arm),
+            elf::EM_RISCV if is64 => Some(Architecture::RiscV64),
+            elf::EM_RISCV => Some(Architecture::RiscV32),
+            _ => None,
+        }
+    }
+
+    // The architecture for a PE `Machine`. Windows on ARM is Thumb-2 only.
+    pub fn from_pe(machine: u64) -> Option<Architecture> {
+        match machine {
+            pe::IMAGE_FILE_MACHINE_I386 => Some(Architecture::X86),
+            pe::IMAGE_FILE_MACHINE_AMD64 => Some(Architecture::X86_64),
+            pe::IMAGE_FILE_MACHINE_ARM64 => Some(Architecture::AArch64),
+            pe::IMAGE_FILE_MACHINE_ARM => Some(Architecture::Arm),
+            pe::IMAGE_FILE_MACHINE_ARMNT => Some(Architecture::Thumb),
+            pe::IMAGE_FILE_MACHINE_RISCV64 => Some(Architecture::RiscV64),
+            _ => None,
+        }
+    }
+
+    // Instructions start at multiples of this, and bytes that don't decode
+    // are skipped this many at a time.
+    pub fn alignment(self) -> u64 {
+        match self {
+            Architecture::X86 | Architecture::X86_64 => 1,
+            Architecture::AArch64 | Architecture::Arm => 4,
+            Architecture::Thumb | Architecture::RiscV32 | Architecture::RiscV64 => 2,
+        }
+    }
+
+    // How many bytes an address takes.
+    pub fn pointer_size(self) -> u64 {
+        match self {
+            Architecture::X86_64 | Architecture::AArch64 | Architecture::RiscV64 => 8,
+            _ => 4,
+        }
+    }
+
+    // The longest an instruction can be.
+    pub fn max_len(self) -> u64 {
+        match self {
+            Architecture::X86 | Architecture::X86_64 => 15,
+            _ => 4,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Instruction {
+    pub len: u64,
+    pub text: String,
+    // Where a relative branch goes, or what a PC-relative load or address
+    // refers to.
+    pub target: Option<u64>,
+}
+
+// Decodes the instruction at the start of `data`, which is at `address` in
+// memory. Returns None if `data` ends before the instruction does; bytes
+// that aren't a known instruction come out as a directive instead.
+pub fn decode(architecture: Architecture, data: &[u8], address: u64) -> Option<Instruction> {
+    match architecture {
+        Architecture::X86 => x86::decode(data, address, 32),
+        Architecture::X86_64 => x86::decode(data, address, 64),
+        Architecture::AArch64 => aarch64::decode(data, address),
+        Architecture::Arm => arm::decode_arm(data, address),
+        Architecture::Thumb => arm::decode_thumb(data, address),
+        Architecture::RiscV32 => riscv::decode(data, address, 32),
+        Architecture::RiscV64 => riscv::decode(data, address, 64),
+    }
+}
+
+// Pads the mnemonic so that operands line up.
+pub(crate) fn text(mnemonic: &str, operands: String) -> String {
+    if operands.is_empty() {
+        mnemonic.to_string()
+    } else {
+        format!("{:8}{}", mnemonic, operands)
+    }
+}
+
+pub(crate) fn sign_extend(value: u32, bits: u32) -> i64 {
+    let shift = 64 - bits;
+    ((value as i64) << shift) >> shift
+}
+
+// Signed immediates as disassemblers usually show them.
+pub(crate) fn signed_hex(value: i64) -> String {
+    if value < 0 {
+        format!("#-{:#x}", value.unsigned_abs())
+    } else {
+        format!("#{:#x}", value)
+    }
+}
+
+// How far before the cursor to start decoding, so that on x86 the
+// instruction boundaries have a chance to fall into place.
+const SYNC_WINDOW: u64 = 0x400;
+
+// Code at the cursor, decoded with instruction boundaries that line up with
+// `origin`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Disassembly {
+    pub architecture: Architecture,
+    pub origin: u64,
+}
+
+// An instruction as listed, at `offset` in the file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Line {
+    pub offset: u64,
+    pub address: u64,
+    pub bytes: Vec<u8>,
+    pub instruction: Instruction,
+}
+
+impl Line {
+    pub fn range(&self) -> Range<u64> {
+        self.offset..self.offset + self.instruction.len
+    }
+}
+
+impl Disassembly {
+    pub fn new(architecture: Architecture, origin: u64) -> Self {
+        Disassembly {
+            architecture,
+            origin,
+        }
+    }
+
+    // Moves `start` by `count` bytes, staying within the `len` bytes of the
+    // file, and takes the instruction boundaries along to the new position.
+    pub fn nudge(&mut self, start: u64, count: u64, forward: bool, len: u64) -> u64 {
+        let start = if forward {
+            start.saturating_add(count).min(len.saturating_sub(1))
+        } else {
+            start.saturating_sub(count)
+        };
+        self.origin = start;
+        start
+    }
+
+    // Where to start decoding to list instructions around `cursor`. This
+    // stays put while the cursor moves within a window, so that the
+    // boundaries found don't shift about as it does.
+    pub fn sync_point(&self, cursor: u64) -> u64 {
+        if cursor >= self.origin {
+            let windows = (cursor - self.origin) / SYNC_WINDOW;
+            return self.origin + windows.saturating_sub(1) * SYNC_WINDOW;
+        }
+        // Before the origin there's nothing to line up with but alignment.
+        let windows = (self.origin - cursor).div_ceil(SYNC_WINDOW) + 1;
+        self.origin
+            .checked_sub(windows * SYNC_WINDOW)
+            .unwrap_or(self.origin % self.architecture.alignment())
+    }
+
+    // How many bytes from the sync point to decode for `rows` instructions
+    // around `cursor`.
+    pub fn window(&self, cursor: u64, rows: u64) -> Range<u64> {
+        let start = self.sync_point(cursor);
+        let after = (rows + 1).saturating_mul(self.architecture.max_len());
+        start..cursor.saturating_add(after)
+    }
+
+    // Decodes `data`, which starts at `start` in the file, into up to `rows`
+    // lines with the one containing `cursor` in the middle where possible.
+    // `address` gives the memory address of each file offset.
+    pub fn lines(
+        &self,
+        data: &[u8],
+        start: u64,
+        cursor: u64,
+        rows: u64,
+        address: impl Fn(u64) -> u64,
+    ) -> Vec<Line> {
+        let mut lines = vec![];
+        let mut position = 0;
+        let mut at_cursor = None;
+        while let Some(instruction) = data
+            .get(position..)
+            .and_then(|rest| decode(self.architecture, rest, address(start + position as u64)))
+        {
+            let offset = start + position as u64;
+            let end = position + instruction.len as usize;
+            let line = Line {
+                offset,
+                address: address(offset),
+                bytes: data[position..end].to_vec(),
+                instruction,
+            };
+            position = end;
+            if at_cursor.is_none() && line.range().end > cursor {
+                at_cursor = Some(lines.len());
+            }
+            lines.push(line);
+            if let Some(index) = at_cursor {
+                if lines.len() >= index + rows as usize {
+                    break;
+                }
+            }
+        }
+
+        let index = at_cursor.unwrap_or(lines.len());
+        let first = index.saturating_sub(rows as usize / 2);
+        let last = (first + rows as usize).min(lines.len());
+        let first = last.saturating_sub(rows as usize).min(first);
+        lines.drain(..first);
+        lines.truncate(last - first);
+        lines
+    }
+}
+
+// A symbol name for a target address, if we know one.
+pub type Label = Option<String>;
+
+pub struct DisassemblyView {
+    pub disassembly: Disassembly,
+    pub lines: Vec<(Line, Label)>,
+    pub cursor: u64,
+}
+
+// Bytes of an instruction to show before eliding the rest.
+const MAX_BYTES: usize = 8;
+
+impl DisassemblyView {
+    pub fn rows(height: u16) -> u16 {
+        height.saturating_sub(1)
+    }
+}
+
+impl Widget for DisassemblyView {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let style = Style::default()
+            .bg(color(32, 32, 32))
+            .fg(color(192, 192, 192));
+        let style_header = style.fg(color(128, 160, 255)).add_modifier(Modifier::BOLD);
+        let style_cursor = Style::default().bg(color(0, 96, 0)).fg(color(96, 255, 96));
+        let style_bytes = style.fg(color(128, 128, 128));
+        let style_target = style.fg(color(255, 192, 96));
+
+        let header = format!(
+            "{:>12}  {:>16}  {:width$}  {}, synced at {:#x}",
+            "offset",
+            "address",
+            "bytes",
+            self.disassembly.architecture.name(),
+            self.disassembly.origin,
+            width = 3 * MAX_BYTES,
+        );
+        let mut lines = vec![Spans::from(Span::styled(header, style_header))];
+
+        for (line, label) in &self.lines {
+            let row_style = if line.range().contains(&self.cursor) {
+                style_cursor
+            } else {
+                style
+            };
+            let mut hex = line
+                .bytes
+                .iter()
+                .take(MAX_BYTES)
+                .map(|byte| format!("{:02x}", byte))
+                .join(" ");
+            if line.bytes.len() > MAX_BYTES {
+                hex.push('…');
+            }
+            let mut spans = vec![
+                Span::styled(
+                    format!("{:#012x}  {:#016x}  ", line.offset, line.address),
+                    row_style,
+                ),
+                Span::styled(
+                    format!("{:width$}  ", hex, width = 3 * MAX_BYTES),
+                    style_bytes,
+                ),
+                Span::styled(line.instruction.text.clone(), row_style),
+            ];
+            if let Some(target) = line.instruction.target {
+                spans.push(Span::styled(
+                    match label {
+                        Some(label) => format!("  → {:#x} <{}>", target, label),
+                        None => format!("  → {:#x}", target),
+                    },
+                    style_target,
+                ));
+            }
+            lines.push(Spans::from(spans));
+        }
+
+        Paragraph::new(lines).style(style).render(area, buf);
+    }
+}
+
+#[cfg(test)]
+mod disasm_tests {
+    use super::*;
+
+    #[test]
+    fn test_sync_point() {
+        let disassembly = Disassembly::new(Architecture::X86_64, 0x1003);
+        assert_eq!(disassembly.sync_point(0x1003), 0x1003);
+        assert_eq!(disassembly.sync_point(0x1500), 0x1003);
+        assert_eq!(disassembly.sync_point(0x1c00), 0x1403);
+        assert_eq!(disassembly.sync_point(0x1000), 0x803);
+        assert_eq!(disassembly.sync_point(0x10), 0);
+
+        let disassembly = Disassembly::new(Architecture::AArch64, 0x1002);
+        assert_eq!(disassembly.sync_point(0), 2);
+    }
+
+    #[test]
+    fn test_nudge() {
+        let mut disassembly = Disassembly::new(Architecture::AArch64, 0);
+        assert_eq!(disassembly.nudge(0x10, 1, true, 0x100), 0x11);
+        assert_eq!(disassembly.origin, 0x11);
+        assert_eq!(disassembly.nudge(0x10, 0x20, false, 0x100), 0);
+        // Moving past the end stops on the last byte, however far it goes.
+        assert_eq!(disassembly.nudge(u64::MAX - 1, u64::MAX, true, 0x100), 0xff);
+        assert_eq!(disassembly.origin, 0xff);
+    }
+
+    #[test]
+    fn test_lines() {
+        // nops, with a bl to 8 bytes ahead at 0x14.
+        let mut data = [0x1f, 0x20, 0x03, 0xd5].repeat(16);
+        data[0x14..0x18].copy_from_slice(&[0x02, 0, 0, 0x94]);
+        let disassembly = Disassembly::new(Architecture::AArch64, 0);
+        let address = |offset| offset + 0x400000;
+
+        let lines = disassembly.lines(&data, 0, 0x15, 5, address);
+        let offsets: Vec<u64> = lines.iter().map(|line| line.offset).collect();
+        assert_eq!(offsets, vec![0x0c, 0x10, 0x14, 0x18, 0x1c]);
+        assert_eq!(lines[2].address, 0x400014);
+        assert_eq!(lines[2].bytes, vec![0x02, 0, 0, 0x94]);
+        assert_eq!(lines[2].instruction.target, Some(0x40001c));
+
+        // Near either end, the cursor's line moves off the middle.
+        let lines = disassembly.lines(&data, 0, 0, 5, address);
+        assert_eq!(lines.first().unwrap().offset, 0);
+        let lines = disassembly.lines(&data, 0, 0x3c, 5, address);
+        assert_eq!(lines.first().unwrap().offset, 0x2c);
+        assert_eq!(lines.last().unwrap().offset, 0x3c);
+    }
+}
@@ -0,0 +1,479 @@
+// RISC-V, RV32 and RV64 with the M and C extensions and the Zicsr and
+// Zifencei instructions. Compressed instructions are expanded and shown as
+// what they stand for, the way objdump does.
+
+use super::{sign_extend, text, Instruction};
+
+const REGISTERS: [&str; 32] = [
+    "zero", "ra", "sp", "gp", "tp", "t0", "t1", "t2", "s0", "s1", "a0", "a1", "a2", "a3", "a4",
+    "a5", "a6", "a7", "s2", "s3", "s4", "s5", "s6", "s7", "s8", "s9", "s10", "s11", "t3", "t4",
+    "t5", "t6",
+];
+
+const CSRS: &[(u32, &str)] = &[
+    (0x001, "fflags"),
+    (0x002, "frm"),
+    (0x003, "fcsr"),
+    (0x100, "sstatus"),
+    (0x105, "stvec"),
+    (0x141, "sepc"),
+    (0x142, "scause"),
+    (0x180, "satp"),
+    (0x300, "mstatus"),
+    (0x304, "mie"),
+    (0x305, "mtvec"),
+    (0x341, "mepc"),
+    (0x342, "mcause"),
+    (0x344, "mip"),
+    (0xc00, "cycle"),
+    (0xc01, "time"),
+    (0xc02, "instret"),
+    (0xf14, "mhartid"),
+];
+
+fn bits(word: u32, low: u32, len: u32) -> u32 {
+    (word >> low) & ((1 << len) - 1)
+}
+
+fn reg(n: u32) -> &'static str {
+    REGISTERS[n as usize & 31]
+}
+
+pub fn decode(data: &[u8], address: u64, xlen: u32) -> Option<Instruction> {
+    let low = u16::from_le_bytes(data.get(0..2)?.try_into().ok()?);
+    if low & 3 != 3 {
+        let (text, target) = expand(low as u32, xlen)
+            .and_then(|word| decode_word(word, address, xlen))
+            .unwrap_or_else(|| (text(".2byte", format!("{:#06x}", low)), None));
+        return Some(Instruction {
+            len: 2,
+            text,
+            target,
+        });
+    }
+    let word = u32::from_le_bytes(data.get(0..4)?.try_into().ok()?);
+    let (text, target) = decode_word(word, address, xlen)
+        .unwrap_or_else(|| (text(".4byte", format!("{:#010x}", word)), None));
+    Some(Instruction {
+        len: 4,
+        text,
+        target,
+    })
+}
+
+fn decode_word(w: u32, pc: u64, xlen: u32) -> Option<(String, Option<u64>)> {
+    let rv64 = xlen == 64;
+    let opcode = w & 0x7f;
+    let (rd, rs1, rs2) = (bits(w, 7, 5), bits(w, 15, 5), bits(w, 20, 5));
+    let funct3 = bits(w, 12, 3);
+    let funct7 = w >> 25;
+    let imm_i = sign_extend(w >> 20, 12);
+    let imm_s = sign_extend((w >> 25) << 5 | rd, 5 + 7);
+    let plain = |mnemonic: &str, operands: String| Some((text(mnemonic, operands), None));
+    let to = |offset: i64| pc.wrapping_add(offset as u64);
+
+    match opcode {
+        0x37 => plain("lui", format!("{}, {:#x}", reg(rd), w >> 12)),
+        0x17 => {
+            let target = to(sign_extend(w & 0xffff_f000, 32));
+            let operands = format!("{}, {:#x}", reg(rd), w >> 12);
+            Some((text("auipc", operands), Some(target)))
+        }
+        0x6f => {
+            let imm = (w >> 31) << 20
+                | bits(w, 12, 8) << 12
+                | bits(w, 20, 1) << 11
+                | bits(w, 21, 10) << 1;
+            let target = to(sign_extend(imm, 21));
+            let line = match rd {
+                0 => text("j", format!("{:#x}", target)),
+                1 => text("jal", format!("{:#x}", target)),
+                rd => text("jal", format!("{}, {:#x}", reg(rd), target)),
+            };
+            Some((line, Some(target)))
+        }
+        0x67 if funct3 == 0 => match (rd, rs1, imm_i) {
+            (0, 1, 0) => plain("ret", String::new()),
+            (0, rs1, 0) => plain("jr", reg(rs1).to_string()),
+            (1, rs1, 0) => plain("jalr", reg(rs1).to_string()),
+            (rd, rs1, imm) => plain("jalr", format!("{}, {}({})", reg(rd), imm, reg(rs1))),
+        },
+        0x63 => {
+            let imm =
+                (w >> 31) << 12 | bits(w, 7, 1) << 11 | bits(w, 25, 6) << 5 | bits(w, 8, 4) << 1;
+            let target = to(sign_extend(imm, 13));
+            let mnemonic = match funct3 {
+                0 => "beq",
+                1 => "bne",
+                4 => "blt",
+                5 => "bge",
+                6 => "bltu",
+                7 => "bgeu",
+                _ => return None,
+            };
+            let line = match (funct3, rs2) {
+                (0 | 1, 0) => text(
+                    &format!("{}z", mnemonic),
+                    format!("{}, {:#x}", reg(rs1), target),
+                ),
+                _ => text(
+                    mnemonic,
+                    format!("{}, {}, {:#x}", reg(rs1), reg(rs2), target),
+                ),
+            };
+            Some((line, Some(target)))
+        }
+        0x03 => {
+            let mnemonic = match funct3 {
+                0 => "lb",
+                1 => "lh",
+                2 => "lw",
+                3 if rv64 => "ld",
+                4 => "lbu",
+                5 => "lhu",
+                6 if rv64 => "lwu",
+                _ => return None,
+            };
+            plain(mnemonic, format!("{}, {}({})", reg(rd), imm_i, reg(rs1)))
+        }
+        0x23 => {
+            let mnemonic = match funct3 {
+                0 => "sb",
+                1 => "sh",
+                2 => "sw",
+                3 if rv64 => "sd",
+                _ => return None,
+            };
+            plain(mnemonic, format!("{}, {}({})", reg(rs2), imm_s, reg(rs1)))
+        }
+        0x13 => {
+            let shamt = bits(w, 20, if rv64 { 6 } else { 5 });
+            let shift_kind = w >> if rv64 { 26 } else { 25 };
+            let arithmetic = if rv64 { 0x10 } else { 0x20 };
+            match (funct3, rd, rs1, imm_i) {
+                (0, 0, 0, 0) => plain("nop", String::new()),
+                (0, rd, 0, imm) => plain("li", format!("{}, {}", reg(rd), imm)),
+                (0, rd, rs1, 0) => plain("mv", format!("{}, {}", reg(rd), reg(rs1))),
+                (3, rd, rs1, 1) => plain("seqz", format!("{}, {}", reg(rd), reg(rs1))),
+                (4, rd, rs1, -1) => plain("not", format!("{}, {}", reg(rd), reg(rs1))),
+                (1, ..) if shift_kind == 0 => {
+                    plain("slli", format!("{}, {}, {}", reg(rd), reg(rs1), shamt))
+                }
+                (5, ..) if shift_kind == 0 || shift_kind == arithmetic => {
+                    let mnemonic = if shift_kind == 0 { "srli" } else { "srai" };
+                    plain(mnemonic, format!("{}, {}, {}", reg(rd), reg(rs1), shamt))
+                }
+                (1 | 5, ..) => None,
+                (funct3, rd, rs1, imm) => {
+                    let mnemonic =
+                        ["addi", "", "slti", "sltiu", "xori", "", "ori", "andi"][funct3 as usize];
+                    plain(mnemonic, format!("{}, {}, {}", reg(rd), reg(rs1), imm))
+                }
+            }
+        }
+        0x1b if rv64 => match (funct3, funct7) {
+            (0, _) if imm_i == 0 => plain("sext.w", format!("{}, {}", reg(rd), reg(rs1))),
+            (0, _) => plain("addiw", format!("{}, {}, {}", reg(rd), reg(rs1), imm_i)),
+            (1, 0) => plain("slliw", format!("{}, {}, {}", reg(rd), reg(rs1), rs2)),
+            (5, 0) => plain("srliw", format!("{}, {}, {}", reg(rd), reg(rs1), rs2)),
+            (5, 0x20) => plain("sraiw", format!("{}, {}, {}", reg(rd), reg(rs1), rs2)),
+            _ => None,
+        },
+        0x33 => {
+            let mnemonic = match (funct7, funct3) {
+                (0, 0) if rs1 == 0 => return plain("mv", format!("{}, {}", reg(rd), reg(rs2))),
+                (0x20, 0) if rs1 == 0 => return plain("neg", format!("{}, {}", reg(rd), reg(rs2))),
+                (0, funct3) => {
+                    ["add", "sll", "slt", "sltu", "xor", "srl", "or", "and"][funct3 as usize]
+                }
+                (0x20, 0) => "sub",
+                (0x20, 5) => "sra",
+                (1, funct3) => [
+                    "mul", "mulh", "mulhsu", "mulhu", "div", "divu", "rem", "remu",
+                ][funct3 as usize],
+                _ => return None,
+            };
+            plain(mnemonic, format!("{}, {}, {}", reg(rd), reg(rs1), reg(rs2)))
+        }
+        0x3b if rv64 => {
+            let mnemonic = match (funct7, funct3) {
+                (0x20, 0) if rs1 == 0 => {
+                    return plain("negw", format!("{}, {}", reg(rd), reg(rs2)))
+                }
+                (0, 0) => "addw",
+                (0, 1) => "sllw",
+                (0, 5) => "srlw",
+                (0x20, 0) => "subw",
+                (0x20, 5) => "sraw",
+                (1, 0) => "mulw",
+                (1, 4) => "divw",
+                (1, 5) => "divuw",
+                (1, 6) => "remw",
+                (1, 7) => "remuw",
+                _ => return None,
+            };
+            plain(mnemonic, format!("{}, {}, {}", reg(rd), reg(rs1), reg(rs2)))
+        }
+        0x0f => match funct3 {
+            0 => {
+                let set = |bits: u32| -> String {
+                    "iorw"
+                        .chars()
+                        .enumerate()
+                        .filter(|(i, _)| bits >> (3 - i) & 1 == 1)
+                        .map(|(_, c)| c)
+                        .collect()
+                };
+                let (pred, succ) = (bits(w, 24, 4), bits(w, 20, 4));
+                if pred == 0xf && succ == 0xf {
+                    plain("fence", String::new())
+                } else {
+                    plain("fence", format!("{}, {}", set(pred), set(succ)))
+                }
+            }
+            1 => plain("fence.i", String::new()),
+            _ => None,
+        },
+        0x73 => {
+            let csr = w >> 20;
+            let name = CSRS
+                .iter()
+                .find(|(value, _)| *value == csr)
+                .map_or(format!("{:#x}", csr), |(_, name)| name.to_string());
+            match (funct3, rd, rs1) {
+                (0, 0, 0) => match csr {
+                    0x000 => plain("ecall", String::new()),
+                    0x001 => plain("ebreak", String::new()),
+                    0x102 => plain("sret", String::new()),
+                    0x302 => plain("mret", String::new()),
+                    0x105 => plain("wfi", String::new()),
+                    _ => None,
+                },
+                (2, rd, 0) => plain("csrr", format!("{}, {}", reg(rd), name)),
+                (1..=3, 0, rs1) => {
+                    let mnemonic = ["", "csrw", "csrs", "csrc"][funct3 as usize];
+                    plain(mnemonic, format!("{}, {}", name, reg(rs1)))
+                }
+                (1..=3, rd, rs1) => {
+                    let mnemonic = ["", "csrrw", "csrrs", "csrrc"][funct3 as usize];
+                    plain(mnemonic, format!("{}, {}, {}", reg(rd), name, reg(rs1)))
+                }
+                (5..=7, rd, imm) => {
+                    let mnemonic = ["csrrwi", "csrrsi", "csrrci"][funct3 as usize - 5];
+                    plain(mnemonic, format!("{}, {}, {}", reg(rd), name, imm))
+                }
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+// Encoders for the instruction formats compressed instructions expand to.
+fn i_type(imm: i64, rs1: u32, funct3: u32, rd: u32, opcode: u32) -> u32 {
+    (imm as u32 & 0xfff) << 20 | rs1 << 15 | funct3 << 12 | rd << 7 | opcode
+}
+
+fn s_type(imm: i64, rs2: u32, rs1: u32, funct3: u32) -> u32 {
+    let imm = imm as u32;
+    (imm >> 5 & 0x7f) << 25 | rs2 << 20 | rs1 << 15 | funct3 << 12 | (imm & 0x1f) << 7 | 0x23
+}
+
+fn r_type(funct7: u32, rs2: u32, rs1: u32, funct3: u32, rd: u32, opcode: u32) -> u32 {
+    funct7 << 25 | rs2 << 20 | rs1 << 15 | funct3 << 12 | rd << 7 | opcode
+}
+
+fn b_type(imm: i64, rs1: u32, funct3: u32) -> u32 {
+    let imm = imm as u32;
+    (imm >> 12 & 1) << 31
+        | (imm >> 5 & 0x3f) << 25
+        | rs1 << 15
+        | funct3 << 12
+        | (imm >> 1 & 0xf) << 8
+        | (imm >> 11 & 1) << 7
+        | 0x63
+}
+
+fn j_type(imm: i64, rd: u32) -> u32 {
+    let imm = imm as u32;
+    (imm >> 20 & 1) << 31
+        | (imm >> 1 & 0x3ff) << 21
+        | (imm >> 11 & 1) << 20
+        | (imm >> 12 & 0xff) << 12
+        | rd << 7
+        | 0x6f
+}
+
+// The 32-bit instruction a compressed one stands for.
+fn expand(h: u32, xlen: u32) -> Option<u32> {
+    let rv64 = xlen == 64;
+    let funct3 = h >> 13;
+    let rd = bits(h, 7, 5);
+    let rs2 = bits(h, 2, 5);
+    // The registers x8 to x15, as encoded in three bits.
+    let (rd_short, rs1_short) = (8 + bits(h, 2, 3), 8 + bits(h, 7, 3));
+    let imm6 = sign_extend(bits(h, 12, 1) << 5 | bits(h, 2, 5), 6);
+    let shamt = bits(h, 12, 1) << 5 | bits(h, 2, 5);
+    let jump = || {
+        let imm = bits(h, 12, 1) << 11
+            | bits(h, 11, 1) << 4
+            | bits(h, 9, 2) << 8
+            | bits(h, 8, 1) << 10
+            | bits(h, 7, 1) << 6
+            | bits(h, 6, 1) << 7
+            | bits(h, 3, 3) << 1
+            | bits(h, 2, 1) << 5;
+        sign_extend(imm, 12)
+    };
+    let branch = || {
+        let imm = bits(h, 12, 1) << 8
+            | bits(h, 10, 2) << 3
+            | bits(h, 5, 2) << 6
+            | bits(h, 3, 2) << 1
+            | bits(h, 2, 1) << 5;
+        sign_extend(imm, 9)
+    };
+
+    Some(match (h & 3, funct3) {
+        (0, 0) => {
+            let imm =
+                bits(h, 11, 2) << 4 | bits(h, 7, 4) << 6 | bits(h, 6, 1) << 2 | bits(h, 5, 1) << 3;
+            if imm == 0 {
+                return None;
+            }
+            i_type(imm as i64, 2, 0, rd_short, 0x13)
+        }
+        (0, 2) => {
+            let imm = bits(h, 10, 3) << 3 | bits(h, 6, 1) << 2 | bits(h, 5, 1) << 6;
+            i_type(imm as i64, rs1_short, 2, rd_short, 0x03)
+        }
+        (0, 3) if rv64 => {
+            let imm = bits(h, 10, 3) << 3 | bits(h, 5, 2) << 6;
+            i_type(imm as i64, rs1_short, 3, rd_short, 0x03)
+        }
+        (0, 6) => {
+            let imm = bits(h, 10, 3) << 3 | bits(h, 6, 1) << 2 | bits(h, 5, 1) << 6;
+            s_type(imm as i64, rd_short, rs1_short, 2)
+        }
+        (0, 7) if rv64 => {
+            let imm = bits(h, 10, 3) << 3 | bits(h, 5, 2) << 6;
+            s_type(imm as i64, rd_short, rs1_short, 3)
+        }
+        (1, 0) => i_type(imm6, rd, 0, rd, 0x13),
+        (1, 1) if rv64 && rd != 0 => i_type(imm6, rd, 0, rd, 0x1b),
+        (1, 1) if !rv64 => j_type(jump(), 1),
+        (1, 2) => i_type(imm6, 0, 0, rd, 0x13),
+        (1, 3) if rd == 2 => {
+            let imm = bits(h, 12, 1) << 9
+                | bits(h, 6, 1) << 4
+                | bits(h, 5, 1) << 6
+                | bits(h, 3, 2) << 7
+                | bits(h, 2, 1) << 5;
+            match sign_extend(imm, 10) {
+                0 => return None,
+                imm => i_type(imm, 2, 0, 2, 0x13),
+            }
+        }
+        (1, 3) if imm6 != 0 => (imm6 as u32 & 0xfffff) << 12 | rd << 7 | 0x37,
+        (1, 4) => {
+            let rd = rs1_short;
+            match (bits(h, 10, 2), bits(h, 12, 1), bits(h, 5, 2)) {
+                (0, ..) if rv64 || shamt < 32 => i_type(shamt as i64, rd, 5, rd, 0x13),
+                (1, ..) if rv64 || shamt < 32 => i_type((0x400 | shamt) as i64, rd, 5, rd, 0x13),
+                (2, ..) => i_type(imm6, rd, 7, rd, 0x13),
+                (3, 0, op) => {
+                    let (funct7, funct3) = [(0x20, 0), (0, 4), (0, 6), (0, 7)][op as usize];
+                    r_type(funct7, rd_short, rd, funct3, rd, 0x33)
+                }
+                (3, 1, 0) if rv64 => r_type(0x20, rd_short, rd, 0, rd, 0x3b),
+                (3, 1, 1) if rv64 => r_type(0, rd_short, rd, 0, rd, 0x3b),
+                _ => return None,
+            }
+        }
+        (1, 5) => j_type(jump(), 0),
+        (1, 6) => b_type(branch(), rs1_short, 0),
+        (1, 7) => b_type(branch(), rs1_short, 1),
+        (2, 0) if rv64 || shamt < 32 => i_type(shamt as i64, rd, 1, rd, 0x13),
+        (2, 2) if rd != 0 => {
+            let imm = bits(h, 12, 1) << 5 | bits(h, 4, 3) << 2 | bits(h, 2, 2) << 6;
+            i_type(imm as i64, 2, 2, rd, 0x03)
+        }
+        (2, 3) if rv64 && rd != 0 => {
+            let imm = bits(h, 12, 1) << 5 | bits(h, 5, 2) << 3 | bits(h, 2, 3) << 6;
+            i_type(imm as i64, 2, 3, rd, 0x03)
+        }
+        (2, 4) => match (bits(h, 12, 1), rd, rs2) {
+            (0, 0, _) => return None,
+            (0, rs1, 0) => i_type(0, rs1, 0, 0, 0x67),
+            (0, rd, rs2) => r_type(0, rs2, 0, 0, rd, 0x33),
+            (1, 0, 0) => 0x0010_0073,
+            (1, rs1, 0) => i_type(0, rs1, 0, 1, 0x67),
+            (_, rd, rs2) => r_type(0, rs2, rd, 0, rd, 0x33),
+        },
+        (2, 6) => {
+            let imm = bits(h, 9, 4) << 2 | bits(h, 7, 2) << 6;
+            s_type(imm as i64, rs2, 2, 2)
+        }
+        (2, 7) if rv64 => {
+            let imm = bits(h, 10, 3) << 3 | bits(h, 7, 3) << 6;
+            s_type(imm as i64, rs2, 2, 3)
+        }
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod riscv_tests {
+    use super::*;
+
+    fn riscv(data: &[u8], address: u64) -> (String, Option<u64>, u64) {
+        let instruction = decode(data, address, 64).unwrap();
+        (instruction.text, instruction.target, instruction.len)
+    }
+
+    fn word(word: u32, address: u64) -> (String, Option<u64>) {
+        let (text, target, len) = riscv(&word.to_le_bytes(), address);
+        assert_eq!(len, 4);
+        (text, target)
+    }
+
+    fn half(half: u16, address: u64) -> (String, Option<u64>) {
+        let (text, target, len) = riscv(&half.to_le_bytes(), address);
+        assert_eq!(len, 2);
+        (text, target)
+    }
+
+    #[test]
+    fn test_instructions() {
+        assert_eq!(word(0x0000_8067, 0).0, "ret");
+        assert_eq!(word(0xff01_0113, 0).0, "addi    sp, sp, -16");
+        assert_eq!(word(0x0011_3423, 0).0, "sd      ra, 8(sp)");
+        assert_eq!(word(0x0001_2537, 0).0, "lui     a0, 0x12");
+        assert_eq!(
+            word(0x0100_00ef, 0x1000),
+            ("jal     0x1010".to_string(), Some(0x1010))
+        );
+        assert_eq!(
+            word(0x0005_0463, 0x1000),
+            ("beqz    a0, 0x1008".to_string(), Some(0x1008))
+        );
+        assert_eq!(word(0x0000_0073, 0).0, "ecall");
+        assert_eq!(word(0xffff_ffff, 0).0, ".4byte  0xffffffff");
+    }
+
+    #[test]
+    fn test_compressed() {
+        assert_eq!(half(0x1141, 0).0, "addi    sp, sp, -16");
+        assert_eq!(half(0xe406, 0).0, "sd      ra, 8(sp)");
+        assert_eq!(half(0x60a2, 0).0, "ld      ra, 8(sp)");
+        assert_eq!(half(0x8082, 0).0, "ret");
+        assert_eq!(half(0x4501, 0).0, "li      a0, 0");
+        assert_eq!(half(0x852e, 0).0, "mv      a0, a1");
+        assert_eq!(
+            half(0xa001, 0x1000),
+            ("j       0x1000".to_string(), Some(0x1000))
+        );
+        assert_eq!(half(0x0000, 0).0, ".2byte  0x0000");
+        assert_eq!(decode(&[0x13, 0x01, 0x01], 0, 64), None);
+    }
+}
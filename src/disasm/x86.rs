@@ -0,0 +1,79 @@
+// x86 and x86-64, decoded by iced-x86 and shown in Intel syntax.
+
+use iced_x86::{Decoder, DecoderError, DecoderOptions, Formatter, IntelFormatter, OpKind};
+
+use super::Instruction;
+
+pub fn decode(data: &[u8], address: u64, bitness: u32) -> Option<Instruction> {
+    let mut decoder = Decoder::with_ip(bitness, data, address, DecoderOptions::NONE);
+    let instruction = decoder.decode();
+    match decoder.last_error() {
+        DecoderError::NoMoreBytes => return None,
+        DecoderError::InvalidInstruction => {
+            return Some(Instruction {
+                len: 1,
+                text: super::text(".byte", format!("{:#04x}", data[0])),
+                target: None,
+            })
+        }
+        _ => {}
+    }
+
+    let mut formatter = IntelFormatter::new();
+    let options = formatter.options_mut();
+    options.set_first_operand_char_index(8);
+    options.set_space_after_operand_separator(true);
+    options.set_hex_prefix("0x");
+    options.set_hex_suffix("");
+    options.set_uppercase_hex(false);
+    options.set_branch_leading_zeros(false);
+    options.set_show_branch_size(false);
+    let mut text = String::new();
+    formatter.format(&instruction, &mut text);
+
+    let branch = instruction.op_count() > 0
+        && matches!(
+            instruction.op0_kind(),
+            OpKind::NearBranch16 | OpKind::NearBranch32 | OpKind::NearBranch64
+        );
+    let target = if branch {
+        Some(instruction.near_branch_target())
+    } else if instruction.is_ip_rel_memory_operand() {
+        Some(instruction.ip_rel_memory_address())
+    } else {
+        None
+    };
+
+    Some(Instruction {
+        len: instruction.len() as u64,
+        text,
+        target,
+    })
+}
+
+#[cfg(test)]
+mod x86_tests {
+    use super::*;
+
+    #[test]
+    fn test_decode() {
+        // call rel32, to 0x1000 + 5 + 0x10
+        let call = decode(&[0xe8, 0x10, 0, 0, 0], 0x1000, 64).unwrap();
+        assert_eq!(call.len, 5);
+        assert_eq!(call.text, "call    0x1015");
+        assert_eq!(call.target, Some(0x1015));
+
+        // mov rax, [rip + 8]
+        let load = decode(&[0x48, 0x8b, 0x05, 8, 0, 0, 0], 0x2000, 64).unwrap();
+        assert_eq!(load.len, 7);
+        assert_eq!(load.target, Some(0x2007 + 8));
+
+        let nop = decode(&[0x90], 0, 32).unwrap();
+        assert_eq!(nop.text, "nop");
+        assert_eq!(nop.target, None);
+
+        // Truncated, then not an instruction.
+        assert_eq!(decode(&[0xe8, 0x10], 0, 64), None);
+        assert_eq!(decode(&[0x06, 0x90], 0, 64).unwrap().text, ".byte   0x06");
+    }
+}
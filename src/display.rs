@@ -1,29 +1,253 @@
+use std::ops::Range;
+
 use tui::{
     style::Style,
     text::{Span, Spans},
     widgets::{Paragraph, Widget},
 };
 
-use crate::{cursor::Cursor, terminal::color};
+use unicode_width::UnicodeWidthChar;
+
+use crate::{
+    cursor::{BitCursor, Cursor},
+    decode::{decode_integer, BitOrder, Endianness},
+    encoding::{Cell, Encoding},
+    terminal::{color, color_hsl},
+};
+
+// A range of bytes to be drawn in a particular style, e.g. an annotation.
+#[derive(Debug, Clone)]
+pub struct Highlight {
+    pub range: Range<u64>,
+    pub style: Style,
+}
+
+fn cursor_style() -> Style {
+    Style::default().bg(color(0, 96, 0)).fg(color(96, 255, 96))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ByteClass {
+    Nul,
+    Printable,  // ASCII, other than whitespace
+    Whitespace, // space, \t, \n, \v, \f and \r
+    Control,    // the rest of ASCII, including DEL
+    HighBit,
+    Ff,
+}
+
+impl ByteClass {
+    pub fn of(byte: u8) -> ByteClass {
+        match byte {
+            0x00 => ByteClass::Nul,
+            b' ' | b'\t' | b'\n' | 0x0b | 0x0c | b'\r' => ByteClass::Whitespace,
+            0x21..=0x7e => ByteClass::Printable,
+            0x01..=0x1f | 0x7f => ByteClass::Control,
+            0x80..=0xfe => ByteClass::HighBit,
+            0xff => ByteClass::Ff,
+        }
+    }
+}
+
+// Runs of text shorter than this are more likely to be chance than strings,
+// as with strings(1).
+const MIN_STRING: usize = 4;
+
+// The run of printable ASCII around `offset`, if it's long enough to be a
+// string.
+pub fn string_at(data: &[u8], offset: usize) -> Option<Range<usize>> {
+    let text =
+        |byte: &u8| ByteClass::of(*byte) == ByteClass::Printable || matches!(byte, b' ' | b'\t');
+    if !text(data.get(offset)?) {
+        return None;
+    }
+    let start = data[..offset]
+        .iter()
+        .rposition(|byte| !text(byte))
+        .map_or(0, |before| before + 1);
+    let end = data[offset..]
+        .iter()
+        .position(|byte| !text(byte))
+        .map_or(data.len(), |after| offset + after);
+    (end - start >= MIN_STRING).then_some(start..end)
+}
+
+// How bytes are colored when they're neither under the cursor nor
+// highlighted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Coloring {
+    #[default]
+    None,
+    ByteClass,
+    Value, // each value its own hue
+}
+
+impl Coloring {
+    pub fn next(self) -> Coloring {
+        match self {
+            Coloring::None => Coloring::ByteClass,
+            Coloring::ByteClass => Coloring::Value,
+            Coloring::Value => Coloring::None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Coloring::None => "none",
+            Coloring::ByteClass => "byte class",
+            Coloring::Value => "value",
+        }
+    }
+
+    fn style(self, byte: u8) -> Style {
+        let fg = match self {
+            Coloring::None => return Style::default(),
+            Coloring::ByteClass => match ByteClass::of(byte) {
+                ByteClass::Nul => color(96, 96, 96),
+                ByteClass::Printable => color(64, 208, 224),
+                ByteClass::Whitespace => color(96, 224, 96),
+                ByteClass::Control => color(224, 96, 224),
+                ByteClass::HighBit => color(240, 208, 64),
+                ByteClass::Ff => color(255, 96, 64),
+            },
+            Coloring::Value if byte == 0 => color(96, 96, 96),
+            Coloring::Value => color_hsl(byte as f64 * 360.0 / 256.0, 0.8, 0.65),
+        };
+        Style::default().fg(fg)
+    }
+}
+
+// The cursor takes precedence over highlights, and later highlights take
+// precedence over earlier ones, which all take precedence over coloring.
+fn style_at(
+    location: u64,
+    value: u8,
+    cursor: Cursor,
+    highlights: &[Highlight],
+    coloring: Coloring,
+) -> Style {
+    if cursor.contains(location) {
+        return cursor_style();
+    }
+
+    highlights
+        .iter()
+        .rev()
+        .find(|highlight| highlight.range.contains(&location))
+        .map_or_else(|| coloring.style(value), |highlight| highlight.style)
+}
+
+// How the value of each cell of the byte grid is written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CellFormat {
+    #[default]
+    Hex,
+    Decimal,
+    Signed,
+    Octal,
+    Binary,
+}
 
+impl CellFormat {
+    pub fn next(self) -> CellFormat {
+        match self {
+            CellFormat::Hex => CellFormat::Decimal,
+            CellFormat::Decimal => CellFormat::Signed,
+            CellFormat::Signed => CellFormat::Octal,
+            CellFormat::Octal => CellFormat::Binary,
+            CellFormat::Binary => CellFormat::Hex,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            CellFormat::Hex => "hex",
+            CellFormat::Decimal => "decimal",
+            CellFormat::Signed => "signed decimal",
+            CellFormat::Octal => "octal",
+            CellFormat::Binary => "binary",
+        }
+    }
+
+    // Writes a `word` byte value, padded to the width of the widest one.
+    fn format(self, (signed, unsigned): (i128, u128), word: u64) -> String {
+        let width = self.width(word) as usize;
+        match self {
+            CellFormat::Hex => format!("{:0width$x}", unsigned),
+            CellFormat::Decimal => format!("{:width$}", unsigned),
+            CellFormat::Signed => format!("{:width$}", signed),
+            CellFormat::Octal => format!("{:0width$o}", unsigned),
+            CellFormat::Binary => format!("{:0width$b}", unsigned),
+        }
+    }
+
+    // How many characters the widest `word` byte value takes.
+    pub fn width(self, word: u64) -> u64 {
+        let bits = 8 * word as u32;
+        let max = u128::MAX >> (128 - bits);
+        let len = match self {
+            CellFormat::Hex => return 2 * word,
+            CellFormat::Binary => return 8 * word,
+            CellFormat::Decimal => max.to_string().len(),
+            CellFormat::Signed => (-(1i128 << (bits - 1))).to_string().len(),
+            CellFormat::Octal => format!("{:o}", max).len(),
+        };
+        len as u64
+    }
+}
+
+// How a grid of bytes is laid out: each `word` bytes make up a cell of
+// `cell` characters, with a space between every `group` bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Shape {
+    pub word: u64,
+    pub cell: u64,
+    pub group: u64,
+}
+
+impl Shape {
+    // How many characters wide `columns` bytes are.
+    pub fn width(&self, columns: u64) -> u64 {
+        columns / self.word * self.cell + columns.div_ceil(self.group.max(1)).saturating_sub(1)
+    }
+}
+
+// The bytes of the source as a grid of numbers: hex by default, but also
+// decimal, octal or binary, and for words of several bytes at a time.
 #[derive(Clone)]
-pub struct HexDisplay {
+pub struct ByteGridDisplay {
     style: Style,
     data: Vec<u8>,
     data_start: u64,
     pub cursor: Cursor,
+    pub highlights: Vec<Highlight>,
+    pub coloring: Coloring,
+    pub columns: u64,
+    pub group: u64, // bytes between spaces
+    pub format: CellFormat,
+    pub word: u64, // bytes per cell
+    pub endianness: Endianness,
 }
 
-impl HexDisplay {
-    pub fn default() -> Self {
-        HexDisplay {
+impl Default for ByteGridDisplay {
+    fn default() -> Self {
+        ByteGridDisplay {
             style: Style::default(),
             data: vec![],
             data_start: 0,
-            cursor: Cursor { start: 0, end: 0 },
+            cursor: Cursor::new(0, 0),
+            highlights: vec![],
+            coloring: Coloring::None,
+            columns: DEFAULT_COLUMNS,
+            group: DEFAULT_GROUP,
+            format: CellFormat::Hex,
+            word: 1,
+            endianness: Endianness::Little,
         }
     }
+}
 
+impl ByteGridDisplay {
     pub fn set_data(&mut self, data: Vec<u8>, data_start: u64) {
         self.data = data;
         self.data_start = data_start;
@@ -33,59 +257,183 @@ impl HexDisplay {
         self.style = style;
         self
     }
+
+    // Hex bytes can be grouped, but anything else needs a space between
+    // every cell to be legible.
+    pub fn shape(&self) -> Shape {
+        let group = match self.format {
+            CellFormat::Hex => self.group.max(self.word),
+            _ => self.word,
+        };
+        Shape {
+            word: self.word,
+            cell: self.format.width(self.word),
+            group,
+        }
+    }
 }
 
-pub const COLUMNS: u8 = 2 * 8;
+pub const DEFAULT_COLUMNS: u64 = 16;
+pub const DEFAULT_GROUP: u64 = 2;
 
-fn render_hex(bytes: &[u8], bytes_start: u64, cursor: Cursor) -> Vec<Spans> {
-    let mut lines: Vec<Spans> = vec![];
-    let mut spans = vec![];
+// The bit display has eight characters for every byte.
+pub fn bit_shape(group: u64) -> Shape {
+    Shape {
+        word: 1,
+        cell: 8,
+        group,
+    }
+}
 
-    const BLOCKSIZE: u8 = 2;
-    let mut column = 0;
-    let mut byte = bytes_start;
+// Likewise for the unicode display, which uses two cells per byte.
+pub fn unicode_width(columns: u64) -> u64 {
+    2 * columns
+}
 
-    let cursor_style = Style::default().bg(color(0, 96, 0)).fg(color(96, 255, 96));
-    bytes.iter().for_each(|value| {
-        let style = if cursor.contains(byte) {
-            cursor_style
-        } else {
-            Style::default()
-        };
-        if column > 0 && column % BLOCKSIZE == 0 {
-            spans.push(Span::styled(
-                " ",
-                if byte == cursor.start() {
-                    Style::default()
-                } else {
-                    style
-                },
-            ));
-        };
+fn render_grid<'a>(display: &ByteGridDisplay) -> Vec<Spans<'a>> {
+    let shape = display.shape();
+    let rows = display.data.chunks(display.columns.max(1) as usize);
+    rows.enumerate()
+        .map(|(row, bytes)| {
+            let mut spans = vec![];
+            let mut previous_style = Style::default();
+            for (index, word) in bytes.chunks(shape.word as usize).enumerate() {
+                let column = index as u64 * shape.word;
+                let location = display.data_start + row as u64 * display.columns + column;
+
+                // A word is under the cursor if any of its bytes are, and
+                // otherwise styled like its first byte.
+                let styles = word.iter().enumerate().map(|(i, &value)| {
+                    let location = location + i as u64;
+                    let style = style_at(
+                        location,
+                        value,
+                        display.cursor,
+                        &display.highlights,
+                        display.coloring,
+                    );
+                    (display.cursor.contains(location), style)
+                });
+                let style = styles
+                    .clone()
+                    .find(|&(cursor, _)| cursor)
+                    .or(styles.clone().next())
+                    .map_or(Style::default(), |(_, style)| style);
+
+                if column > 0 && column.is_multiple_of(shape.group) {
+                    // Only fill the gap between two cells if they're styled
+                    // alike.
+                    let gap = if style == previous_style {
+                        style
+                    } else {
+                        Style::default()
+                    };
+                    spans.push(Span::styled(" ", gap));
+                }
+                previous_style = style;
+
+                let value = decode_integer(word, display.endianness);
+                let text = display.format.format(value, shape.word);
+                spans.push(Span::styled(text, style));
+            }
+            Spans::from(spans)
+        })
+        .collect()
+}
 
-        let text = format!("{:02x}", value);
-        spans.push(Span::styled(text, style));
+impl Widget for ByteGridDisplay {
+    fn render(self, area: tui::layout::Rect, buf: &mut tui::buffer::Buffer) {
+        Paragraph::new(render_grid(&self))
+            .style(self.style)
+            .render(area, buf);
+    }
+}
 
-        column += 1;
-        if column == COLUMNS {
-            lines.push(Spans::from(spans.clone()));
-            spans.clear();
-            column = 0;
+// Like the hex display, but showing the bits of each byte, most significant
+// first, and a cursor that can cover any of them.
+#[derive(Clone)]
+pub struct BitDisplay {
+    style: Style,
+    data: Vec<u8>,
+    data_start: u64,
+    pub cursor: Cursor,
+    pub bit_cursor: Option<BitCursor>,
+    pub order: BitOrder,
+    pub highlights: Vec<Highlight>,
+    pub coloring: Coloring,
+    pub columns: u64,
+    pub group: u64,
+}
+
+impl BitDisplay {
+    pub fn new(style: Style) -> Self {
+        BitDisplay {
+            style,
+            data: vec![],
+            data_start: 0,
+            cursor: Cursor::new(0, 0),
+            bit_cursor: None,
+            order: BitOrder::default(),
+            highlights: vec![],
+            coloring: Coloring::None,
+            columns: DEFAULT_COLUMNS,
+            group: DEFAULT_GROUP,
         }
+    }
 
-        byte += 1;
-    });
+    pub fn set_data(&mut self, data: Vec<u8>, data_start: u64) {
+        self.data = data;
+        self.data_start = data_start;
+    }
 
-    if !spans.is_empty() {
-        lines.push(Spans::from(spans.clone()));
+    // The style of the bit drawn `column` cells into the byte at `location`.
+    fn style_at(&self, location: u64, value: u8, column: u64) -> Style {
+        let Some(bit_cursor) = self.bit_cursor else {
+            return style_at(
+                location,
+                value,
+                self.cursor,
+                &self.highlights,
+                self.coloring,
+            );
+        };
+        // In LSB-first order the first bit in the stream is drawn last.
+        let bit = match self.order {
+            BitOrder::Msb => column,
+            BitOrder::Lsb => 7 - column,
+        };
+        if bit_cursor.contains(location * 8 + bit) {
+            return cursor_style();
+        }
+        let no_cursor = Cursor::new(0, 0);
+        style_at(location, value, no_cursor, &self.highlights, self.coloring)
     }
 
-    lines
+    fn render_lines<'a>(&self) -> Vec<Spans<'a>> {
+        let rows = self.data.chunks(self.columns.max(1) as usize);
+        rows.enumerate()
+            .map(|(row, bytes)| {
+                let mut spans = vec![];
+                for (column, &value) in bytes.iter().enumerate() {
+                    let column = column as u64;
+                    let location = self.data_start + row as u64 * self.columns + column;
+                    if column > 0 && column.is_multiple_of(self.group) {
+                        spans.push(Span::raw(" "));
+                    }
+                    for bit in 0..8 {
+                        let text = if value & (0x80 >> bit) != 0 { "1" } else { "0" };
+                        spans.push(Span::styled(text, self.style_at(location, value, bit)));
+                    }
+                }
+                Spans::from(spans)
+            })
+            .collect()
+    }
 }
 
-impl Widget for HexDisplay {
+impl Widget for BitDisplay {
     fn render(self, area: tui::layout::Rect, buf: &mut tui::buffer::Buffer) {
-        Paragraph::new(render_hex(&self.data, self.data_start, self.cursor))
+        Paragraph::new(self.render_lines())
             .style(self.style)
             .render(area, buf);
     }
@@ -97,18 +445,28 @@ pub struct UnicodeDisplay {
     data: Vec<u8>,
     data_start: u64,
     pub cursor: Cursor,
+    pub highlights: Vec<Highlight>,
+    pub coloring: Coloring,
+    pub columns: u64,
+    pub encoding: Encoding,
 }
 
-impl UnicodeDisplay {
-    pub fn default() -> Self {
+impl Default for UnicodeDisplay {
+    fn default() -> Self {
         UnicodeDisplay {
             style: Style::default(),
             data: vec![],
             data_start: 0,
             cursor: Cursor::new(0, 0),
+            highlights: vec![],
+            coloring: Coloring::None,
+            columns: DEFAULT_COLUMNS,
+            encoding: Encoding::default(),
         }
     }
+}
 
+impl UnicodeDisplay {
     pub fn set_data(&mut self, data: Vec<u8>, data_start: u64) {
         self.data = data;
         self.data_start = data_start;
@@ -155,38 +513,42 @@ fn render_unicode_byte_as_hex(byte: u8) -> String {
     )
 }
 
-fn render_unicode_byte(byte: u8) -> String {
-    match byte {
-        // non-printable lower range
-        0 => "  ".to_string(),
-        0x01..=0x1f => render_unicode_byte_as_hex(byte),
-
-        // printable ASCII
-        0x20..=0x7e => format!("{} ", byte as char),
-
-        // non-printable upper range, including not just everything with the
-        // high bit set (non-ASCII), but also 127/0x7f (DEL).
-        0x7f..=0xff => render_unicode_byte_as_hex(byte),
+// Every byte takes two cells, whether it's a character, part of one, or
+// shown in hex.
+fn render_unicode_byte(byte: u8, cell: Cell) -> String {
+    match cell {
+        Cell::Glyph(c) => match c.width() {
+            Some(2) => c.to_string(),
+            Some(1) => format!("{} ", c),
+            _ => render_unicode_byte_as_hex(byte),
+        },
+        Cell::Continuation => "  ".to_string(),
+        Cell::Invalid if byte == 0 => "  ".to_string(),
+        Cell::Invalid => render_unicode_byte_as_hex(byte),
     }
 }
 
-fn render_unicode(bytes: &[u8], bytes_start: u64, cursor: Cursor) -> Vec<Spans> {
+fn render_unicode<'a>(
+    bytes: &[u8],
+    bytes_start: u64,
+    cursor: Cursor,
+    highlights: &[Highlight],
+    coloring: Coloring,
+    columns: u64,
+    encoding: Encoding,
+) -> Vec<Spans<'a>> {
+    let cells = encoding.decode(bytes, bytes_start);
     let mut column = 0;
     let mut lines: Vec<Spans> = vec![];
     let mut spans = vec![];
 
     let mut byte = bytes_start;
 
-    let cursor_style = Style::default().bg(color(0, 96, 0)).fg(color(96, 255, 96));
-    bytes.iter().map(|b| render_unicode_byte(*b)).for_each(|s| {
-        let style = if cursor.contains(byte) {
-            cursor_style
-        } else {
-            Style::default()
-        };
-        spans.push(Span::styled(s, style));
+    bytes.iter().zip(cells).for_each(|(&value, cell)| {
+        let style = style_at(byte, value, cursor, highlights, coloring);
+        spans.push(Span::styled(render_unicode_byte(value, cell), style));
         column += 1;
-        if column == COLUMNS {
+        if column == columns {
             lines.push(Spans::from(spans.clone()));
             spans.clear();
             column = 0;
@@ -204,8 +566,192 @@ fn render_unicode(bytes: &[u8], bytes_start: u64, cursor: Cursor) -> Vec<Spans>
 
 impl Widget for UnicodeDisplay {
     fn render(self, area: tui::layout::Rect, buf: &mut tui::buffer::Buffer) {
-        let text = render_unicode(&self.data, self.data_start, self.cursor);
+        let text = render_unicode(
+            &self.data,
+            self.data_start,
+            self.cursor,
+            &self.highlights,
+            self.coloring,
+            self.columns,
+            self.encoding,
+        );
 
         Paragraph::new(text).style(self.style).render(area, buf);
     }
 }
+
+#[cfg(test)]
+mod display_tests {
+    use super::*;
+
+    #[test]
+    fn test_byte_class() {
+        let classes: Vec<ByteClass> = [0x00, b'A', b' ', b'\n', 0x1b, 0x7f, 0x80, 0xfe, 0xff]
+            .into_iter()
+            .map(ByteClass::of)
+            .collect();
+        assert_eq!(
+            classes,
+            vec![
+                ByteClass::Nul,
+                ByteClass::Printable,
+                ByteClass::Whitespace,
+                ByteClass::Whitespace,
+                ByteClass::Control,
+                ByteClass::Control,
+                ByteClass::HighBit,
+                ByteClass::HighBit,
+                ByteClass::Ff,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_string_at() {
+        let data = b"\x00\x01hello world\x00ab\x00";
+        assert_eq!(string_at(data, 2), Some(2..13));
+        assert_eq!(string_at(data, 12), Some(2..13));
+        assert_eq!(string_at(data, 1), None);
+        // Too short.
+        assert_eq!(string_at(data, 14), None);
+        assert_eq!(string_at(data, 100), None);
+        assert_eq!(string_at(b"text", 0), Some(0..4));
+    }
+
+    fn grid_text(display: &ByteGridDisplay) -> Vec<String> {
+        render_grid(display)
+            .iter()
+            .map(|line| line.0.iter().map(|span| span.content.as_ref()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn test_columns_and_groups() {
+        let text = |columns, group| {
+            let mut display = ByteGridDisplay {
+                columns,
+                group,
+                ..Default::default()
+            };
+            display.set_data(vec![0, 1, 2, 3, 4, 5], 0);
+            grid_text(&display)
+        };
+        assert_eq!(text(4, 2), vec!["0001 0203", "0405"]);
+        assert_eq!(text(3, 1), vec!["00 01 02", "03 04 05"]);
+        assert_eq!(text(8, 4), vec!["00010203 0405"]);
+
+        let hex = |group| ByteGridDisplay {
+            group,
+            ..Default::default()
+        };
+        assert_eq!(hex(2).shape().width(4), 9);
+        assert_eq!(hex(1).shape().width(3), 8);
+        assert_eq!(hex(8).shape().width(188), 376 + 23);
+        assert_eq!(bit_shape(2).width(4), 33);
+    }
+
+    #[test]
+    fn test_cell_formats() {
+        let text = |format, word, endianness| {
+            let mut display = ByteGridDisplay {
+                columns: 4,
+                format,
+                word,
+                endianness,
+                ..Default::default()
+            };
+            display.set_data(vec![0x01, 0xff, 0x80, 0x7f], 0);
+            grid_text(&display)[0].clone()
+        };
+        use Endianness::*;
+        assert_eq!(text(CellFormat::Decimal, 1, Little), "  1 255 128 127");
+        assert_eq!(text(CellFormat::Signed, 1, Little), "   1   -1 -128  127");
+        assert_eq!(text(CellFormat::Octal, 1, Little), "001 377 200 177");
+        assert_eq!(
+            text(CellFormat::Binary, 1, Little),
+            "00000001 11111111 10000000 01111111"
+        );
+
+        // Words, in either endianness.
+        assert_eq!(text(CellFormat::Hex, 2, Little), "ff01 7f80");
+        assert_eq!(text(CellFormat::Hex, 2, Big), "01ff 807f");
+        assert_eq!(text(CellFormat::Hex, 4, Big), "01ff807f");
+        assert_eq!(text(CellFormat::Decimal, 2, Big), "  511 32895");
+        assert_eq!(text(CellFormat::Signed, 2, Big), "   511 -32641");
+
+        assert_eq!(CellFormat::Decimal.width(8), 20);
+        assert_eq!(CellFormat::Signed.width(8), 20);
+        assert_eq!(CellFormat::Octal.width(4), 11);
+    }
+
+    #[test]
+    fn test_word_cursor() {
+        // The cursor on any byte of a word highlights all of it.
+        let mut display = ByteGridDisplay {
+            columns: 4,
+            word: 2,
+            cursor: Cursor::new(3, 4),
+            ..Default::default()
+        };
+        display.set_data(vec![0, 1, 2, 3], 0);
+        let styles: Vec<Style> = render_grid(&display)[0]
+            .0
+            .iter()
+            .map(|span| span.style)
+            .collect();
+        assert_eq!(
+            styles,
+            vec![Style::default(), Style::default(), cursor_style()]
+        );
+    }
+
+    #[test]
+    fn test_bit_cursor() {
+        let mut display = BitDisplay::new(Style::default());
+        display.set_data(vec![0xa5, 0x0f], 0x10);
+        display.columns = 2;
+        display.group = 1;
+        display.bit_cursor = Some(BitCursor::new(0x80 + 6, 0x80 + 10));
+        let text: String = display.render_lines()[0]
+            .0
+            .iter()
+            .map(|span| span.content.as_ref())
+            .collect();
+        assert_eq!(text, "10100101 00001111");
+
+        // The last two bits of the first byte and the first two of the
+        // second, or the other way around in LSB-first order.
+        let covered = |display: &BitDisplay| -> Vec<(u64, u64)> {
+            (0x10..0x12)
+                .flat_map(|location| (0..8).map(move |column| (location, column)))
+                .filter(|&(location, column)| {
+                    display.style_at(location, 0, column) == cursor_style()
+                })
+                .collect()
+        };
+        assert_eq!(
+            covered(&display),
+            vec![(0x10, 6), (0x10, 7), (0x11, 0), (0x11, 1)]
+        );
+        display.order = BitOrder::Lsb;
+        assert_eq!(
+            covered(&display),
+            vec![(0x10, 0), (0x10, 1), (0x11, 6), (0x11, 7)]
+        );
+    }
+
+    #[test]
+    fn test_coloring_precedence() {
+        let cursor = Cursor::new(0, 1);
+        let highlights = [Highlight {
+            range: 1..2,
+            style: Style::default().bg(color(1, 2, 3)),
+        }];
+        let style = |location| style_at(location, 0xff, cursor, &highlights, Coloring::ByteClass);
+        assert_eq!(style(0), cursor_style());
+        assert_eq!(style(1), highlights[0].style);
+        assert_eq!(style(2), Coloring::ByteClass.style(0xff));
+        assert_ne!(style(2), Style::default());
+        assert_eq!(Coloring::None.style(0xff), Style::default());
+    }
+}
@@ -0,0 +1,310 @@
+// Decoding bytes for the text pane. Every byte gets a cell of its own, so a
+// character made of several bytes is drawn in the cell of the first of them,
+// with the cells of the rest left blank.
+
+use encoding_rs::SHIFT_JIS;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Encoding {
+    #[default]
+    Ascii,
+    Latin1,
+    Windows1252,
+    Cp437,
+    Ebcdic, // code page 037
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    ShiftJis,
+}
+
+const ENCODINGS: &[Encoding] = &[
+    Encoding::Ascii,
+    Encoding::Latin1,
+    Encoding::Windows1252,
+    Encoding::Cp437,
+    Encoding::Ebcdic,
+    Encoding::Utf8,
+    Encoding::Utf16Le,
+    Encoding::Utf16Be,
+    Encoding::ShiftJis,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cell {
+    Glyph(char),
+    // Part of the character in an earlier cell.
+    Continuation,
+    // Not a character, or not one worth drawing, e.g. a control character.
+    Invalid,
+}
+
+impl Encoding {
+    pub fn next(self) -> Encoding {
+        let index = ENCODINGS.iter().position(|&e| e == self).unwrap_or(0);
+        ENCODINGS[(index + 1) % ENCODINGS.len()]
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Encoding::Ascii => "ascii",
+            Encoding::Latin1 => "latin-1",
+            Encoding::Windows1252 => "windows-1252",
+            Encoding::Cp437 => "cp437",
+            Encoding::Ebcdic => "ebcdic",
+            Encoding::Utf8 => "utf-8",
+            Encoding::Utf16Le => "utf-16le",
+            Encoding::Utf16Be => "utf-16be",
+            Encoding::ShiftJis => "shift-jis",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Encoding> {
+        let name = name.to_ascii_lowercase();
+        ENCODINGS.iter().copied().find(|e| e.name() == name)
+    }
+
+    pub fn names() -> Vec<&'static str> {
+        ENCODINGS.iter().map(|e| e.name()).collect()
+    }
+
+    // Decodes `data`, which starts at `location` in the source, one cell per
+    // byte.
+    pub fn decode(self, data: &[u8], location: u64) -> Vec<Cell> {
+        let single = |decode: fn(u8) -> char| data.iter().map(|&b| glyph(decode(b))).collect();
+        match self {
+            Encoding::Ascii => single(|b| if b < 0x80 { b as char } else { '\0' }),
+            Encoding::Latin1 => single(|b| b as char),
+            Encoding::Windows1252 => single(|b| match b {
+                0x80..=0x9f => WINDOWS_1252[b as usize - 0x80],
+                _ => b as char,
+            }),
+            Encoding::Cp437 => single(|b| match b {
+                0x00..=0x1f => CP437_LOW[b as usize],
+                0x7f => '⌂',
+                0x80..=0xff => CP437_HIGH[b as usize - 0x80],
+                _ => b as char,
+            }),
+            Encoding::Ebcdic => single(|b| CP037[b as usize]),
+            Encoding::Utf8 => decode_utf8(data),
+            Encoding::Utf16Le => decode_utf16(data, location, u16::from_le_bytes),
+            Encoding::Utf16Be => decode_utf16(data, location, u16::from_be_bytes),
+            Encoding::ShiftJis => decode_shift_jis(data),
+        }
+    }
+}
+
+fn glyph(c: char) -> Cell {
+    if c.is_control() {
+        Cell::Invalid
+    } else {
+        Cell::Glyph(c)
+    }
+}
+
+// Pushes the cells for a character made of `len` bytes.
+fn push(cells: &mut Vec<Cell>, c: char, len: usize) {
+    cells.push(glyph(c));
+    cells.extend(std::iter::repeat_n(Cell::Continuation, len - 1));
+}
+
+fn decode_utf8(data: &[u8]) -> Vec<Cell> {
+    let mut cells = Vec::with_capacity(data.len());
+    while cells.len() < data.len() {
+        let rest = &data[cells.len()..];
+        let len = match rest[0] {
+            0x00..=0x7f => 1,
+            0xc2..=0xdf => 2,
+            0xe0..=0xef => 3,
+            0xf0..=0xf4 => 4,
+            _ => 0,
+        };
+        match rest.get(..len).map(std::str::from_utf8) {
+            Some(Ok(text)) if len > 0 => push(&mut cells, text.chars().next().unwrap(), len),
+            _ => cells.push(Cell::Invalid),
+        }
+    }
+    cells
+}
+
+// Code units start at even locations, so if `data` doesn't then its first
+// byte is half of one we can't see.
+fn decode_utf16(data: &[u8], location: u64, unit: fn([u8; 2]) -> u16) -> Vec<Cell> {
+    let mut cells = Vec::with_capacity(data.len());
+    if location % 2 == 1 && !data.is_empty() {
+        cells.push(Cell::Invalid);
+    }
+    let unit_at = |at: usize| data.get(at..at + 2).map(|b| unit([b[0], b[1]]));
+    while cells.len() < data.len() {
+        let at = cells.len();
+        let Some(first) = unit_at(at) else {
+            cells.push(Cell::Invalid);
+            continue;
+        };
+        let units = match first {
+            0xd800..=0xdbff => match unit_at(at + 2) {
+                Some(second @ 0xdc00..=0xdfff) => vec![first, second],
+                _ => vec![first],
+            },
+            _ => vec![first],
+        };
+        match char::decode_utf16(units.iter().copied()).next() {
+            Some(Ok(c)) => push(&mut cells, c, 2 * units.len()),
+            _ => cells.extend([Cell::Invalid, Cell::Invalid]),
+        }
+    }
+    cells
+}
+
+fn decode_shift_jis(data: &[u8]) -> Vec<Cell> {
+    let mut cells = Vec::with_capacity(data.len());
+    while cells.len() < data.len() {
+        let rest = &data[cells.len()..];
+        let len = match rest[0] {
+            0x81..=0x9f | 0xe0..=0xfc => 2,
+            _ => 1,
+        };
+        let text = rest
+            .get(..len)
+            .and_then(|bytes| SHIFT_JIS.decode_without_bom_handling_and_without_replacement(bytes));
+        match text.and_then(|text| text.chars().next()) {
+            Some(c) => push(&mut cells, c, len),
+            None => cells.push(Cell::Invalid),
+        }
+    }
+    cells
+}
+
+// Windows-1252 is Latin-1 but for these, from 0x80; the gaps are left as the
+// C1 controls they are in Latin-1.
+const WINDOWS_1252: [char; 32] = [
+    '€', '\u{81}', '‚', 'ƒ', '„', '…', '†', '‡', 'ˆ', '‰', 'Š', '‹', 'Œ', '\u{8d}', 'Ž', '\u{8f}',
+    '\u{90}', '‘', '’', '“', '”', '•', '–', '—', '˜', '™', 'š', '›', 'œ', '\u{9d}', 'ž', 'Ÿ',
+];
+
+// The glyphs DOS showed for control characters, from 0x00.
+const CP437_LOW: [char; 32] = [
+    '\u{0}', '☺', '☻', '♥', '♦', '♣', '♠', '•', '◘', '○', '◙', '♂', '♀', '♪', '♫', '☼', '►', '◄',
+    '↕', '‼', '¶', '§', '▬', '↨', '↑', '↓', '→', '←', '∟', '↔', '▲', '▼',
+];
+
+// CP437 from 0x80: accented letters, box drawing and maths.
+const CP437_HIGH: [char; 128] = [
+    'Ç', 'ü', 'é', 'â', 'ä', 'à', 'å', 'ç', 'ê', 'ë', 'è', 'ï', 'î', 'ì', 'Ä', 'Å', 'É', 'æ', 'Æ',
+    'ô', 'ö', 'ò', 'û', 'ù', 'ÿ', 'Ö', 'Ü', '¢', '£', '¥', '₧', 'ƒ', 'á', 'í', 'ó', 'ú', 'ñ', 'Ñ',
+    'ª', 'º', '¿', '⌐', '¬', '½', '¼', '¡', '«', '»', '░', '▒', '▓', '│', '┤', '╡', '╢', '╖', '╕',
+    '╣', '║', '╗', '╝', '╜', '╛', '┐', '└', '┴', '┬', '├', '─', '┼', '╞', '╟', '╚', '╔', '╩', '╦',
+    '╠', '═', '╬', '╧', '╨', '╤', '╥', '╙', '╘', '╒', '╓', '╫', '╪', '┘', '┌', '█', '▄', '▌', '▐',
+    '▀', 'α', 'ß', 'Γ', 'π', 'Σ', 'σ', 'µ', 'τ', 'Φ', 'Θ', 'Ω', 'δ', '∞', 'φ', 'ε', '∩', '≡', '±',
+    '≥', '≤', '⌠', '⌡', '÷', '≈', '°', '∙', '·', '√', 'ⁿ', '²', '■', '\u{a0}',
+];
+
+// EBCDIC as used by IBM mainframes in the US and Canada.
+const CP037: [char; 256] = [
+    '\u{0}', '\u{1}', '\u{2}', '\u{3}', '\u{9c}', '\u{9}', '\u{86}', '\u{7f}', '\u{97}', '\u{8d}',
+    '\u{8e}', '\u{b}', '\u{c}', '\u{d}', '\u{e}', '\u{f}', '\u{10}', '\u{11}', '\u{12}', '\u{13}',
+    '\u{9d}', '\u{85}', '\u{8}', '\u{87}', '\u{18}', '\u{19}', '\u{92}', '\u{8f}', '\u{1c}',
+    '\u{1d}', '\u{1e}', '\u{1f}', '\u{80}', '\u{81}', '\u{82}', '\u{83}', '\u{84}', '\u{a}',
+    '\u{17}', '\u{1b}', '\u{88}', '\u{89}', '\u{8a}', '\u{8b}', '\u{8c}', '\u{5}', '\u{6}',
+    '\u{7}', '\u{90}', '\u{91}', '\u{16}', '\u{93}', '\u{94}', '\u{95}', '\u{96}', '\u{4}',
+    '\u{98}', '\u{99}', '\u{9a}', '\u{9b}', '\u{14}', '\u{15}', '\u{9e}', '\u{1a}', ' ', '\u{a0}',
+    'â', 'ä', 'à', 'á', 'ã', 'å', 'ç', 'ñ', '¢', '.', '<', '(', '+', '|', '&', 'é', 'ê', 'ë', 'è',
+    'í', 'î', 'ï', 'ì', 'ß', '!', '$', '*', ')', ';', '¬', '-', '/', 'Â', 'Ä', 'À', 'Á', 'Ã', 'Å',
+    'Ç', 'Ñ', '¦', ',', '%', '_', '>', '?', 'ø', 'É', 'Ê', 'Ë', 'È', 'Í', 'Î', 'Ï', 'Ì', '`', ':',
+    '#', '@', '\u{27}', '=', '"', 'Ø', 'a', 'b', 'c', 'd', 'e', 'f', 'g', 'h', 'i', '«', '»', 'ð',
+    'ý', 'þ', '±', '°', 'j', 'k', 'l', 'm', 'n', 'o', 'p', 'q', 'r', 'ª', 'º', 'æ', '¸', 'Æ', '¤',
+    'µ', '~', 's', 't', 'u', 'v', 'w', 'x', 'y', 'z', '¡', '¿', 'Ð', 'Ý', 'Þ', '®', '^', '£', '¥',
+    '·', '©', '§', '¶', '¼', '½', '¾', '[', ']', '¯', '¨', '´', '×', '{', 'A', 'B', 'C', 'D', 'E',
+    'F', 'G', 'H', 'I', '\u{ad}', 'ô', 'ö', 'ò', 'ó', 'õ', '}', 'J', 'K', 'L', 'M', 'N', 'O', 'P',
+    'Q', 'R', '¹', 'û', 'ü', 'ù', 'ú', 'ÿ', '\u{5c}', '÷', 'S', 'T', 'U', 'V', 'W', 'X', 'Y', 'Z',
+    '²', 'Ô', 'Ö', 'Ò', 'Ó', 'Õ', '0', '1', '2', '3', '4', '5', '6', '7', '8', '9', '³', 'Û', 'Ü',
+    'Ù', 'Ú', '\u{9f}',
+];
+
+#[cfg(test)]
+mod encoding_tests {
+    use super::*;
+
+    #[test]
+    fn test_single_byte() {
+        let data = [b'A', 0x01, 0xc4, 0x80, 0xc9];
+        let decode = |encoding: Encoding| encoding.decode(&data, 0);
+        use Cell::*;
+        assert_eq!(
+            decode(Encoding::Ascii),
+            vec![Glyph('A'), Invalid, Invalid, Invalid, Invalid]
+        );
+        assert_eq!(
+            decode(Encoding::Latin1),
+            vec![Glyph('A'), Invalid, Glyph('Ä'), Invalid, Glyph('É')]
+        );
+        assert_eq!(decode(Encoding::Windows1252)[3], Glyph('€'));
+        assert_eq!(
+            decode(Encoding::Cp437),
+            vec![Glyph('A'), Glyph('☺'), Glyph('─'), Glyph('Ç'), Glyph('╔')]
+        );
+        assert_eq!(
+            Encoding::Ebcdic.decode(&[0xc8, 0x85, 0x40, 0xf1], 0),
+            vec![Glyph('H'), Glyph('e'), Glyph(' '), Glyph('1')]
+        );
+    }
+
+    #[test]
+    fn test_multi_byte() {
+        use Cell::*;
+        // "aé€" and a stray continuation byte.
+        assert_eq!(
+            Encoding::Utf8.decode(b"a\xc3\xa9\xe2\x82\xac\x80", 0),
+            vec![
+                Glyph('a'),
+                Glyph('é'),
+                Continuation,
+                Glyph('€'),
+                Continuation,
+                Continuation,
+                Invalid
+            ]
+        );
+        // Cut short at the end.
+        assert_eq!(
+            Encoding::Utf8.decode(b"\xe2\x82", 0),
+            vec![Invalid, Invalid]
+        );
+
+        // "A" and U+1F600, which needs a surrogate pair.
+        let data = [0x41, 0x00, 0x3d, 0xd8, 0x00, 0xde];
+        assert_eq!(
+            Encoding::Utf16Le.decode(&data, 0),
+            vec![
+                Glyph('A'),
+                Continuation,
+                Glyph('😀'),
+                Continuation,
+                Continuation,
+                Continuation
+            ]
+        );
+        // Starting half way through a code unit.
+        assert_eq!(
+            Encoding::Utf16Be.decode(&[0x41, 0x00, 0x42], 1),
+            vec![Invalid, Glyph('B'), Continuation]
+        );
+
+        // "ｱ" (half width katakana) and "日".
+        assert_eq!(
+            Encoding::ShiftJis.decode(&[0xb1, 0x93, 0xfa], 0),
+            vec![Glyph('ｱ'), Glyph('日'), Continuation]
+        );
+    }
+
+    #[test]
+    fn test_names() {
+        let mut encoding = Encoding::default();
+        for _ in ENCODINGS {
+            assert_eq!(Encoding::from_name(encoding.name()), Some(encoding));
+            encoding = encoding.next();
+        }
+        assert_eq!(encoding, Encoding::Ascii);
+        assert_eq!(Encoding::from_name("UTF-8"), Some(Encoding::Utf8));
+    }
+}
@@ -0,0 +1,469 @@
+use std::ops::Range;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use crossterm::event::{KeyCode, KeyEvent};
+use tui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Span, Spans},
+    widgets::{Block, Borders, Clear, Paragraph, Widget},
+};
+
+use crate::source::SharedData;
+use crate::terminal::{color, color_hsl};
+
+// Files are split into at most this many blocks, so that huge files don't
+// take forever or use lots of memory.
+const MAX_BLOCKS: u64 = 1 << 14;
+const MIN_BLOCK_SIZE: u64 = 256;
+
+// What we know about the bytes in a block of data.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct BlockStats {
+    pub entropy: f64,   // Shannon entropy, in bits per byte
+    pub zeros: f64,     // fraction of bytes that are zero
+    pub printable: f64, // fraction of bytes that are printable ASCII
+}
+
+impl BlockStats {
+    pub fn of(data: &[u8]) -> BlockStats {
+        if data.is_empty() {
+            return BlockStats::default();
+        }
+        let mut counts = [0u64; 256];
+        for &b in data {
+            counts[b as usize] += 1;
+        }
+        let len = data.len() as f64;
+        let entropy = counts
+            .iter()
+            .filter(|&&count| count > 0)
+            .map(|&count| {
+                let p = count as f64 / len;
+                -p * p.log2()
+            })
+            .sum();
+        let printable: u64 = counts
+            .iter()
+            .enumerate()
+            .filter(|&(b, _)| matches!(b as u8, b' '..=b'~' | b'\t' | b'\n' | b'\r'))
+            .map(|(_, &count)| count)
+            .sum();
+        BlockStats {
+            entropy,
+            zeros: counts[0] as f64 / len,
+            printable: printable as f64 / len,
+        }
+    }
+
+    fn mean(blocks: &[BlockStats]) -> BlockStats {
+        let n = blocks.len().max(1) as f64;
+        let sum = |f: fn(&BlockStats) -> f64| blocks.iter().map(f).sum::<f64>() / n;
+        BlockStats {
+            entropy: sum(|b| b.entropy),
+            zeros: sum(|b| b.zeros),
+            printable: sum(|b| b.printable),
+        }
+    }
+
+    pub fn color(&self, shading: Shading) -> Color {
+        match shading {
+            // Blue and dark for low entropy through to bright red for
+            // compressed or encrypted data.
+            Shading::Entropy => {
+                let e = (self.entropy / 8.0).clamp(0.0, 1.0);
+                color_hsl(240.0 * (1.0 - e), 1.0, 0.08 + 0.42 * e)
+            }
+            Shading::ByteClass => {
+                if self.zeros > 0.9 {
+                    color(24, 24, 24)
+                } else if self.printable > 0.75 {
+                    color(32, 176, 32)
+                } else if self.entropy > 7.2 {
+                    color(208, 32, 32)
+                } else {
+                    color(48, 80, 192)
+                }
+            }
+        }
+    }
+}
+
+// What the footer minimap is colored by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shading {
+    Entropy,
+    ByteClass,
+}
+
+// Block statistics for a whole data source, computed in a background thread
+// the first time they're needed and kept from then on.
+#[derive(Clone)]
+pub struct EntropyMap {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    len: u64,
+    block_size: u64,
+    blocks: Arc<Mutex<Vec<BlockStats>>>,
+    cancel: Arc<AtomicBool>,
+}
+
+impl Drop for Inner {
+    fn drop(&mut self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+}
+
+impl EntropyMap {
+    pub fn start(data: SharedData) -> EntropyMap {
+        let len = (*data).as_ref().len() as u64;
+        let block_size = len
+            .div_ceil(MAX_BLOCKS)
+            .next_power_of_two()
+            .max(MIN_BLOCK_SIZE);
+        let blocks = Arc::new(Mutex::new(vec![]));
+        let cancel = Arc::new(AtomicBool::new(false));
+
+        let (thread_blocks, thread_cancel) = (blocks.clone(), cancel.clone());
+        thread::spawn(move || {
+            for chunk in (*data).as_ref().chunks(block_size as usize) {
+                if thread_cancel.load(Ordering::Relaxed) {
+                    return;
+                }
+                let stats = BlockStats::of(chunk);
+                thread_blocks.lock().unwrap().push(stats);
+            }
+        });
+
+        EntropyMap {
+            inner: Arc::new(Inner {
+                len,
+                block_size,
+                blocks,
+                cancel,
+            }),
+        }
+    }
+
+    pub fn len(&self) -> u64 {
+        self.inner.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inner.len == 0
+    }
+
+    // How many of the blocks have been done so far.
+    pub fn computed(&self) -> usize {
+        self.inner.blocks.lock().unwrap().len()
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.computed() as u64 == self.inner.len.div_ceil(self.inner.block_size)
+    }
+
+    // The average over the blocks covering `range`, or None if they haven't
+    // all been computed yet.
+    pub fn region(&self, range: Range<u64>) -> Option<BlockStats> {
+        let first = (range.start / self.inner.block_size) as usize;
+        let last = (range.end.max(range.start + 1) - 1) / self.inner.block_size;
+        let blocks = self.inner.blocks.lock().unwrap();
+        blocks.get(first..=last as usize).map(BlockStats::mean)
+    }
+
+    // The part of `range` covered by `cell` out of `cells`.
+    pub fn cell(range: &Range<u64>, cell: usize, cells: usize) -> Range<u64> {
+        let len = (range.end - range.start) as u128;
+        let cells = cells.max(1) as u128;
+        let at = |cell: usize| range.start + (len * cell as u128).div_ceil(cells) as u64;
+        at(cell)..at(cell + 1)
+    }
+
+    // A one-line map of the whole source, marking `location` (from 0 to 1).
+    pub fn minimap<'a>(&self, shading: Shading, location: f64, width: usize) -> Spans<'a> {
+        let marker = ((location * width as f64) as usize).min(width.saturating_sub(1));
+        let spans = (0..width)
+            .map(|cell| {
+                let region = EntropyMap::cell(&(0..self.len()), cell, width);
+                let (symbol, bg) = match self.region(region) {
+                    Some(stats) => (" ", stats.color(shading)),
+                    None => ("░", color(64, 64, 64)),
+                };
+                if cell == marker {
+                    Span::styled(
+                        "▼",
+                        Style::default()
+                            .bg(bg)
+                            .fg(color(255, 255, 255))
+                            .add_modifier(Modifier::BOLD),
+                    )
+                } else {
+                    Span::styled(symbol, Style::default().bg(bg).fg(color(128, 128, 128)))
+                }
+            })
+            .collect::<Vec<_>>();
+        Spans::from(spans)
+    }
+}
+
+pub enum EntropyEvent {
+    Pending,
+    Jump(u64),
+    Cancel,
+}
+
+// A full-screen graph of the entropy of the source, one row per region,
+// which can be zoomed into and jumped from.
+pub struct EntropyView {
+    map: EntropyMap,
+    // The ranges shown, each zoomed in from the previous one.
+    zoom: Vec<Range<u64>>,
+    // The start of the selected row, which stays put as the rows change.
+    location: u64,
+    // Where we were last drawn.
+    area: Rect,
+}
+
+impl EntropyView {
+    pub fn new(map: EntropyMap, location: u64) -> EntropyView {
+        let len = map.len();
+        EntropyView {
+            map,
+            zoom: vec![Range { start: 0, end: len }],
+            location,
+            area: Rect::default(),
+        }
+    }
+
+    fn range(&self) -> Range<u64> {
+        self.zoom.last().unwrap().clone()
+    }
+
+    // There are no more rows than bytes, so that each row starts somewhere
+    // different.
+    fn rows(&self) -> usize {
+        let range = self.range();
+        (self.area.height.saturating_sub(2) as u64)
+            .min(range.end - range.start)
+            .max(1) as usize
+    }
+
+    fn selected(&self) -> usize {
+        self.row_of(self.location)
+    }
+
+    fn select(&mut self, row: usize) {
+        self.location = self.row_range(row.min(self.rows() - 1)).start;
+    }
+
+    fn row_of(&self, offset: u64) -> usize {
+        let range = self.range();
+        let len = (range.end - range.start).max(1) as u128;
+        let row = (offset.saturating_sub(range.start) as u128 * self.rows() as u128 / len) as usize;
+        row.min(self.rows() - 1)
+    }
+
+    pub fn row_range(&self, row: usize) -> Range<u64> {
+        EntropyMap::cell(&self.range(), row, self.rows())
+    }
+
+    // Remembers where the view is drawn, for mapping clicks to rows.
+    pub fn set_area(&mut self, area: Rect) {
+        self.area = area;
+    }
+
+    pub fn on_key(&mut self, key: KeyEvent) -> EntropyEvent {
+        const PAGE: usize = 10;
+        let selected = self.selected();
+
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') => return EntropyEvent::Cancel,
+            KeyCode::Enter => return EntropyEvent::Jump(self.location),
+            KeyCode::Up | KeyCode::Char('k') => self.select(selected.saturating_sub(1)),
+            KeyCode::Down | KeyCode::Char('j') => self.select(selected + 1),
+            KeyCode::PageUp => self.select(selected.saturating_sub(PAGE)),
+            KeyCode::PageDown => self.select(selected + PAGE),
+            KeyCode::Home => self.select(0),
+            KeyCode::End => self.select(usize::MAX),
+            // Zoom into the selected row, as long as there's something to
+            // spread over the rows.
+            KeyCode::Right | KeyCode::Char('l') | KeyCode::Char('+') => {
+                let range = self.row_range(selected);
+                if range.end - range.start > 1 {
+                    self.zoom.push(range);
+                }
+            }
+            KeyCode::Left | KeyCode::Char('h') | KeyCode::Char('-') if self.zoom.len() > 1 => {
+                self.zoom.pop();
+            }
+            _ => {}
+        }
+
+        EntropyEvent::Pending
+    }
+
+    // Clicking on a row jumps to it.
+    pub fn on_click(&mut self, column: u16, row: u16) -> EntropyEvent {
+        let inner = Block::default().borders(Borders::ALL).inner(self.area);
+        let row = row.wrapping_sub(inner.y) as usize;
+        if column < inner.x || column >= inner.right() || row >= self.rows() {
+            return EntropyEvent::Pending;
+        }
+        self.select(row);
+        EntropyEvent::Jump(self.location)
+    }
+}
+
+impl Widget for &EntropyView {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let style = Style::default()
+            .bg(color(16, 16, 32))
+            .fg(color(224, 224, 224));
+        let style_offset = style.fg(color(128, 160, 255));
+        let style_selected = Style::default()
+            .bg(color(0, 96, 0))
+            .fg(color(96, 255, 96))
+            .add_modifier(Modifier::BOLD);
+
+        let range = self.range();
+        let progress = if self.map.is_done() {
+            String::new()
+        } else {
+            " (computing)".to_string()
+        };
+
+        Clear.render(area, buf);
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .title(format!(
+                " entropy {:#x}..{:#x}{} ",
+                range.start, range.end, progress
+            ))
+            .style(style);
+        let inner = block.inner(area);
+        block.render(area, buf);
+
+        let offset_width = format!("{:x}", range.end).len().max(8);
+        let value_width = 5;
+        let bar_width = (inner.width as usize).saturating_sub(offset_width + value_width + 2);
+
+        let selected = self.selected();
+        let lines: Vec<Spans> = (0..self.rows().min(inner.height as usize))
+            .map(|row| {
+                let region = self.row_range(row);
+                let offset_style = if row == selected {
+                    style_selected
+                } else {
+                    style_offset
+                };
+                let offset = Span::styled(
+                    format!("{:0width$x} ", region.start, width = offset_width),
+                    offset_style,
+                );
+                let Some(stats) = self.map.region(region) else {
+                    return Spans::from(vec![offset, Span::styled("░".repeat(bar_width), style)]);
+                };
+                let filled = (stats.entropy / 8.0 * bar_width as f64).round() as usize;
+                Spans::from(vec![
+                    offset,
+                    Span::styled(
+                        "█".repeat(filled.min(bar_width)),
+                        style.fg(stats.color(Shading::Entropy)),
+                    ),
+                    Span::styled(" ".repeat(bar_width - filled.min(bar_width)), style),
+                    Span::styled(format!(" {:4.2}", stats.entropy), offset_style),
+                ])
+            })
+            .collect();
+        Paragraph::new(lines).style(style).render(inner, buf);
+    }
+}
+
+#[cfg(test)]
+mod entropy_tests {
+    use super::*;
+    use crossterm::event::KeyModifiers;
+    use std::time::{Duration, Instant};
+
+    fn wait(map: &EntropyMap) {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while !map.is_done() {
+            assert!(Instant::now() < deadline);
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    #[test]
+    fn test_block_stats() {
+        assert_eq!(BlockStats::of(&[0; 64]).entropy, 0.0);
+        assert_eq!(BlockStats::of(&[0; 64]).zeros, 1.0);
+        let all: Vec<u8> = (0..=255).collect();
+        assert_eq!(BlockStats::of(&all).entropy, 8.0);
+        assert_eq!(BlockStats::of(b"abab").entropy, 1.0);
+        assert_eq!(BlockStats::of(b"ab\0\0").printable, 0.5);
+    }
+
+    #[test]
+    fn test_map() {
+        // Zeros, then uniformly distributed bytes.
+        let mut data = vec![0u8; 1024];
+        data.extend((0..1024).map(|i| i as u8));
+        let map = EntropyMap::start(Arc::new(data));
+        wait(&map);
+
+        assert_eq!(map.region(0..1024).unwrap().entropy, 0.0);
+        assert_eq!(map.region(1024..2048).unwrap().entropy, 8.0);
+        assert_eq!(map.region(0..2048).unwrap().entropy, 4.0);
+        assert_eq!(map.region(100..101).unwrap().entropy, 0.0);
+        assert_eq!(map.region(2048..4096), None);
+        assert_eq!(map.minimap(Shading::Entropy, 0.0, 10).0.len(), 10);
+    }
+
+    #[test]
+    fn test_view() {
+        let map = EntropyMap::start(Arc::new(vec![0u8; 4096]));
+        let mut view = EntropyView::new(map, 3000);
+        // 8 rows of 512 bytes.
+        view.set_area(Rect::new(0, 0, 40, 10));
+        assert_eq!(view.selected(), 5);
+        view.on_key(key(KeyCode::Up));
+        view.on_key(key(KeyCode::Down));
+        assert!(matches!(
+            view.on_key(key(KeyCode::Enter)),
+            EntropyEvent::Jump(2560)
+        ));
+
+        view.on_key(key(KeyCode::Right));
+        assert_eq!(view.row_range(0), 2560..2624);
+        view.on_key(key(KeyCode::End));
+        assert!(matches!(
+            view.on_key(key(KeyCode::Enter)),
+            EntropyEvent::Jump(3008)
+        ));
+        view.on_key(key(KeyCode::Left));
+        assert_eq!(view.selected(), 5);
+
+        // Uneven rows still each start somewhere different.
+        view.set_area(Rect::new(0, 0, 40, 5));
+        for row in 0..3 {
+            view.select(row);
+            assert_eq!(view.selected(), row);
+        }
+
+        assert!(matches!(view.on_click(5, 2), EntropyEvent::Jump(1366)));
+        assert!(matches!(view.on_click(5, 4), EntropyEvent::Pending));
+        assert!(matches!(
+            view.on_key(key(KeyCode::Esc)),
+            EntropyEvent::Cancel
+        ));
+    }
+}
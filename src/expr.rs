@@ -0,0 +1,235 @@
+// Arithmetic on offsets, as typed at the goto prompt: literals in hex
+// (`0x`), octal (`0o`), binary (`0b`) or decimal, with an optional `K`, `M`
+// or `G` multiplier; `.` for the cursor, `$` for the end of the file and
+// `'a` for a mark; combined with `+`, `-`, `*`, `/` and parentheses. A
+// leading `+` or `-` makes the whole thing relative to the cursor.
+
+use std::collections::BTreeMap;
+
+// What the names in an expression stand for.
+pub struct Context<'a> {
+    pub cursor: u64,
+    pub len: u64,
+    pub marks: &'a BTreeMap<char, u64>,
+}
+
+pub fn evaluate(text: &str, context: &Context) -> Result<u64, String> {
+    let text = text.trim();
+    let relative = text.starts_with('+') || text.starts_with('-');
+    let mut parser = Parser {
+        chars: text.chars().collect(),
+        position: 0,
+        context,
+    };
+    let value = if relative {
+        if text.starts_with('+') {
+            parser.position = 1;
+        }
+        context.cursor as i128 + parser.expression()?
+    } else {
+        parser.expression()?
+    };
+    if let Some(c) = parser.peek() {
+        return Err(format!("unexpected '{}'", c));
+    }
+    u64::try_from(value).map_err(|_| format!("{} is not an offset", value))
+}
+
+// Text pasted at the goto prompt, where a bare run of hex digits, as copied
+// from a dump or a debugger, is taken as hex.
+pub fn pasted(text: &str) -> String {
+    let line = text.lines().next().unwrap_or("").trim();
+    let digits: String = line.split_whitespace().collect();
+    if !digits.is_empty() && digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        format!("0x{}", digits)
+    } else {
+        line.to_string()
+    }
+}
+
+struct Parser<'a> {
+    chars: Vec<char>,
+    position: usize,
+    context: &'a Context<'a>,
+}
+
+impl Parser<'_> {
+    // The next character that isn't a space, consumed.
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.position += 1;
+        Some(c)
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        while self.chars.get(self.position) == Some(&' ') {
+            self.position += 1;
+        }
+        self.chars.get(self.position).copied()
+    }
+
+    fn expression(&mut self) -> Result<i128, String> {
+        let mut value = self.term()?;
+        while let Some(c @ ('+' | '-')) = self.peek() {
+            self.position += 1;
+            let rhs = self.term()?;
+            value = if c == '+' {
+                value.checked_add(rhs)
+            } else {
+                value.checked_sub(rhs)
+            }
+            .ok_or("overflow")?;
+        }
+        Ok(value)
+    }
+
+    fn term(&mut self) -> Result<i128, String> {
+        let mut value = self.factor()?;
+        while let Some(c @ ('*' | '/')) = self.peek() {
+            self.position += 1;
+            let rhs = self.factor()?;
+            value = if c == '*' {
+                value.checked_mul(rhs).ok_or("overflow")?
+            } else {
+                value.checked_div(rhs).ok_or("division by zero")?
+            };
+        }
+        Ok(value)
+    }
+
+    fn factor(&mut self) -> Result<i128, String> {
+        match self.next() {
+            Some('-') => Ok(-self.factor()?),
+            Some('(') => {
+                let value = self.expression()?;
+                match self.next() {
+                    Some(')') => Ok(value),
+                    _ => Err("missing ')'".to_string()),
+                }
+            }
+            Some('.') => Ok(self.context.cursor as i128),
+            Some('$') => Ok(self.context.len as i128),
+            Some('\'') => {
+                let name = self
+                    .chars
+                    .get(self.position)
+                    .copied()
+                    .ok_or("missing mark")?;
+                self.position += 1;
+                self.context
+                    .marks
+                    .get(&name)
+                    .map(|&offset| offset as i128)
+                    .ok_or(format!("mark '{}' isn't set", name))
+            }
+            Some(c) if c.is_ascii_digit() => {
+                self.position -= 1;
+                self.number()
+            }
+            Some(c) => Err(format!("unexpected '{}'", c)),
+            None => Err("missing value".to_string()),
+        }
+    }
+
+    fn number(&mut self) -> Result<i128, String> {
+        let start = self.position;
+        let prefix: String = self.chars[start..].iter().take(2).collect();
+        let (radix, prefix) = match prefix.to_ascii_lowercase().as_str() {
+            "0x" => (16, 2),
+            "0o" => (8, 2),
+            "0b" => (2, 2),
+            _ => (10, 0),
+        };
+        self.position += prefix;
+
+        let mut digits = String::new();
+        while let Some(&c) = self.chars.get(self.position) {
+            if c.is_digit(radix) {
+                digits.push(c);
+            } else if c != '_' {
+                break;
+            }
+            self.position += 1;
+        }
+        let value = u64::from_str_radix(&digits, radix).map_err(|_| {
+            let text: String = self.chars[start..self.position].iter().collect();
+            format!("invalid number: {}", text)
+        })?;
+
+        let multiplier = match self.chars.get(self.position) {
+            Some('k' | 'K') => 1 << 10,
+            Some('m' | 'M') => 1 << 20,
+            Some('g' | 'G') => 1 << 30,
+            _ => 1,
+        };
+        if multiplier > 1 {
+            self.position += 1;
+        }
+        Ok(value as i128 * multiplier)
+    }
+}
+
+#[cfg(test)]
+mod expr_tests {
+    use super::*;
+
+    fn eval(text: &str) -> Result<u64, String> {
+        let marks = BTreeMap::from([('a', 0x1000)]);
+        let context = Context {
+            cursor: 0x100,
+            len: 0x10000,
+            marks: &marks,
+        };
+        evaluate(text, &context)
+    }
+
+    #[test]
+    fn test_literals() {
+        assert_eq!(eval("0x1f"), Ok(0x1f));
+        assert_eq!(eval("0X1F"), Ok(0x1f));
+        assert_eq!(eval("31"), Ok(31));
+        assert_eq!(eval("0o17"), Ok(0o17));
+        assert_eq!(eval("0b101"), Ok(5));
+        assert_eq!(eval("1_000"), Ok(1000));
+        assert_eq!(eval("4K"), Ok(4096));
+        assert_eq!(eval("0x2M"), Ok(0x200000));
+        assert_eq!(eval("1g"), Ok(1 << 30));
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        assert_eq!(eval("'a + 0x10"), Ok(0x1010));
+        assert_eq!(eval("$ - 16"), Ok(0xfff0));
+        assert_eq!(eval(". * 2"), Ok(0x200));
+        assert_eq!(eval("2 + 3 * 4"), Ok(14));
+        assert_eq!(eval("(2 + 3) * 4"), Ok(20));
+        assert_eq!(eval("$ / 2 - -1"), Ok(0x8001));
+        assert_eq!(eval("+0x10"), Ok(0x110));
+        assert_eq!(eval("-0x10"), Ok(0xf0));
+        assert_eq!(eval(" - 0x10 * 2"), Ok(0xe0));
+    }
+
+    #[test]
+    fn test_pasted() {
+        assert_eq!(pasted("00401000\n"), "0x00401000");
+        assert_eq!(pasted(" 7ffe 1234 "), "0x7ffe1234");
+        assert_eq!(pasted("0x10"), "0x10");
+        assert_eq!(pasted("'a + 4K"), "'a + 4K");
+        assert_eq!(eval(&pasted("100")), Ok(0x100));
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!(eval("").is_err());
+        assert!(eval("0x").is_err());
+        assert!(eval("'b").is_err());
+        assert!(eval("'").is_err());
+        assert!(eval("(1 + 2").is_err());
+        assert!(eval("1 2").is_err());
+        assert!(eval("5 / 0").is_err());
+        assert!(eval(". - 0x200").is_err());
+        assert!(eval("-1K").is_err());
+        assert!(eval("0x10000000000000000").is_err());
+        assert!(eval("x").is_err());
+    }
+}
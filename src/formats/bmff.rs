@@ -0,0 +1,239 @@
+// ISO base media files (MP4, MOV, HEIF, ...): a tree of boxes, each a size
+// and a four-character type, some of which contain further boxes.
+
+use super::container::{chunk, fourcc, ContainerWalker};
+use super::{Analysis, Fields, Reader};
+use crate::annotation::{Annotation, Format};
+use crate::decode::Endianness;
+
+// Boxes that consist only of other boxes.
+const CONTAINERS: &[&str] = &[
+    "moov", "trak", "mdia", "minf", "stbl", "dinf", "edts", "udta", "mvex", "moof", "traf", "mfra",
+    "ipro", "sinf", "schi", "iprp", "ipco",
+];
+
+// Deeper nesting than this is more likely corruption than a real file.
+const MAX_DEPTH: usize = 16;
+
+pub struct Bmff;
+
+impl ContainerWalker for Bmff {
+    fn detect(&self, data: &[u8]) -> bool {
+        matches!(data.get(4..8), Some(b"ftyp") | Some(b"styp"))
+    }
+
+    fn walk(&self, data: &[u8], analysis: &mut Analysis) {
+        let r = Reader::new(data, Endianness::Big);
+        let len = data.len() as u64;
+        analysis.description = format!(
+            "ISO media, brand {}",
+            fourcc(&data[8..12.min(len as usize)])
+        );
+
+        let end = walk_boxes(r, 0..len, 0, "", analysis);
+        if end < len {
+            analysis
+                .annotations
+                .push(Annotation::new(end..len, "trailing data", Format::None));
+        }
+    }
+}
+
+// Walks the boxes in `range`, returning where the last one ended.
+fn walk_boxes(
+    r: Reader,
+    range: std::ops::Range<u64>,
+    depth: usize,
+    path: &str,
+    analysis: &mut Analysis,
+) -> u64 {
+    let mut offset = range.start;
+    while offset.saturating_add(8) <= range.end {
+        let (Some(size), Some(kind)) = (r.u32(offset), r.bytes(offset + 4, 4)) else {
+            break;
+        };
+        let kind = fourcc(kind);
+        let name = format!("{}{}", path, kind);
+
+        // A size of 1 means a 64-bit size follows the type; 0 means the box
+        // extends to the end of its parent.
+        let (size, header) = match size {
+            0 => (range.end - offset, 8),
+            1 => match r.u64(offset + 8) {
+                Some(size) => (size, 16),
+                None => break,
+            },
+            size => (size as u64, 8),
+        };
+        let end = offset.saturating_add(size);
+
+        let error = if size < header {
+            Some("box is smaller than its header".to_string())
+        } else if end > range.end {
+            Some(format!(
+                "box runs {} bytes past the end of its parent",
+                end - range.end
+            ))
+        } else {
+            None
+        };
+
+        chunk(
+            analysis,
+            offset..end.min(range.end),
+            name.clone(),
+            depth * 2,
+        );
+        let mut fields = Fields::new(
+            &mut analysis.annotations,
+            &name,
+            offset,
+            depth * 2 + 1,
+            Endianness::Big,
+        );
+        fields.field("size", 4, Format::Unsigned);
+        if let (Some(error), 8) = (&error, header) {
+            fields.error(error);
+        }
+        fields.field("type", 4, Format::Text);
+        if header == 16 {
+            fields.field("largesize", 8, Format::Unsigned);
+            if let Some(error) = &error {
+                fields.error(error);
+            }
+        }
+        // The size can't be trusted, so neither can anything after it.
+        if error.is_some() {
+            return range.end;
+        }
+
+        let body = offset + header;
+        match kind.as_str() {
+            kind if CONTAINERS.contains(&kind) && depth < MAX_DEPTH => {
+                walk_boxes(r, body..end, depth + 1, &format!("{}/", name), analysis);
+            }
+            // `meta` is a full box (with a version and flags) in MP4 but not
+            // in QuickTime, where its first child immediately follows.
+            "meta" if depth < MAX_DEPTH => {
+                let children = if r.bytes(body + 4, 4) == Some(b"hdlr") {
+                    body
+                } else {
+                    Fields::new(
+                        &mut analysis.annotations,
+                        &name,
+                        body,
+                        depth * 2 + 1,
+                        Endianness::Big,
+                    )
+                    .field("version", 1, Format::Unsigned)
+                    .field("flags", 3, Format::Hex);
+                    body + 4
+                };
+                walk_boxes(
+                    r,
+                    children.min(end)..end,
+                    depth + 1,
+                    &format!("{}/", name),
+                    analysis,
+                );
+            }
+            "ftyp" | "styp" if end - body >= 8 => {
+                let mut fields = Fields::new(
+                    &mut analysis.annotations,
+                    &name,
+                    body,
+                    depth * 2 + 1,
+                    Endianness::Big,
+                );
+                fields
+                    .field("major_brand", 4, Format::Text)
+                    .field("minor_version", 4, Format::Hex);
+                for index in 0..(end - body - 8) / 4 {
+                    fields.field(&format!("compatible_brands[{}]", index), 4, Format::Text);
+                }
+            }
+            _ if end > body => {
+                Fields::new(
+                    &mut analysis.annotations,
+                    &name,
+                    body,
+                    depth * 2 + 1,
+                    Endianness::Big,
+                )
+                .field("data", end - body, Format::None);
+            }
+            _ => {}
+        }
+
+        offset = end;
+    }
+    offset.min(range.end)
+}
+
+#[cfg(test)]
+mod bmff_tests {
+    use crate::formats::container::analyze;
+
+    fn mp4_box(kind: &[u8], data: &[u8]) -> Vec<u8> {
+        let mut result = ((data.len() + 8) as u32).to_be_bytes().to_vec();
+        result.extend(kind);
+        result.extend(data);
+        result
+    }
+
+    fn build_mp4() -> Vec<u8> {
+        let mut data = mp4_box(b"ftyp", b"isom\0\0\x02\0isomiso2");
+        let trak = mp4_box(b"trak", &mp4_box(b"tkhd", &[0; 12]));
+        let mut moov = mp4_box(b"mvhd", &[0; 8]);
+        moov.extend(trak);
+        data.extend(mp4_box(b"moov", &moov));
+        data.extend(mp4_box(b"mdat", b"frames"));
+        data
+    }
+
+    #[test]
+    fn test_walk() {
+        let data = build_mp4();
+        let analysis = analyze(&data).unwrap();
+        assert_eq!(analysis.description, "ISO media, brand isom");
+        assert!(analysis.annotations.iter().all(|a| a.error.is_none()));
+
+        let names: Vec<&str> = analysis.sections.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec![
+                "ftyp",
+                "moov",
+                "moov/mvhd",
+                "moov/trak",
+                "moov/trak/tkhd",
+                "mdat"
+            ]
+        );
+        assert_eq!(analysis.sections[4].range, 56..76);
+        assert!(analysis
+            .annotations
+            .iter()
+            .any(|a| a.label == "ftyp.compatible_brands[1]" && a.range == (20..24)));
+    }
+
+    #[test]
+    fn test_length_mismatch() {
+        let mut data = build_mp4();
+        data[51] = 0x40; // trak size, now larger than moov
+        let analysis = analyze(&data).unwrap();
+        let errors: Vec<(&str, &str)> = analysis
+            .annotations
+            .iter()
+            .filter_map(|a| Some((a.label.as_str(), a.error.as_deref()?)))
+            .collect();
+        assert_eq!(
+            errors,
+            vec![(
+                "moov/trak.size",
+                "box runs 36 bytes past the end of its parent"
+            )]
+        );
+        assert!(analysis.description.ends_with("(1 error)"));
+    }
+}
@@ -0,0 +1,66 @@
+// Formats made of a sequence of (possibly nested) length-prefixed chunks,
+// such as PNG, ZIP, RIFF and MP4. Each is recognized by its magic at offset
+// 0 and walked into a tree of annotations.
+
+use std::ops::Range;
+
+use super::{bmff, png, riff, zip, Analysis, Region};
+use crate::annotation::{Annotation, Format};
+
+pub trait ContainerWalker {
+    // Whether `data` starts with this format's magic.
+    fn detect(&self, data: &[u8]) -> bool;
+
+    // Annotates the chunks in `data`, marking any that are inconsistent
+    // (bad checksums, lengths running past their parent, ...) as errors.
+    fn walk(&self, data: &[u8], analysis: &mut Analysis);
+}
+
+pub const WALKERS: &[&(dyn ContainerWalker + Sync)] =
+    &[&png::Png, &zip::Zip, &riff::Riff, &bmff::Bmff];
+
+pub fn analyze(data: &[u8]) -> Option<Analysis> {
+    let walker = WALKERS.iter().find(|walker| walker.detect(data))?;
+    let mut analysis = Analysis::default();
+    walker.walk(data, &mut analysis);
+
+    let errors = analysis
+        .annotations
+        .iter()
+        .filter(|annotation| annotation.error.is_some())
+        .count();
+    match errors {
+        0 => {}
+        1 => analysis.description.push_str(" (1 error)"),
+        _ => analysis
+            .description
+            .push_str(&format!(" ({} errors)", errors)),
+    }
+    Some(analysis)
+}
+
+// Adds a chunk to the tree: an annotation covering all of it, and a region
+// so that it shows up in the navigator.
+pub fn chunk(analysis: &mut Analysis, range: Range<u64>, label: String, depth: usize) {
+    analysis.sections.push(Region {
+        name: label.clone(),
+        range: range.clone(),
+    });
+    analysis
+        .annotations
+        .push(Annotation::new(range, label, Format::None).depth(depth));
+}
+
+// Renders a four-character code, e.g. a PNG chunk type or an MP4 box type.
+pub fn fourcc(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|&b| {
+            if b.is_ascii_graphic() || b == b' ' {
+                b as char
+            } else {
+                '.'
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,730 @@
+// ELF executables, shared objects, relocatable objects and core dumps.
+
+use super::{name_of, Analysis, Fields, Mapping, Reader, Region, Symbol};
+use crate::annotation::{Annotation, Format};
+use crate::decode::Endianness;
+use crate::disasm::Architecture;
+
+const CLASS: &[(u64, &str)] = &[(1, "ELFCLASS32"), (2, "ELFCLASS64")];
+const DATA: &[(u64, &str)] = &[(1, "ELFDATA2LSB"), (2, "ELFDATA2MSB")];
+const OSABI: &[(u64, &str)] = &[
+    (0, "ELFOSABI_SYSV"),
+    (3, "ELFOSABI_LINUX"),
+    (9, "ELFOSABI_FREEBSD"),
+    (255, "ELFOSABI_STANDALONE"),
+];
+
+const ET_REL: u64 = 1;
+const TYPE: &[(u64, &str)] = &[
+    (0, "ET_NONE"),
+    (ET_REL, "ET_REL"),
+    (2, "ET_EXEC"),
+    (3, "ET_DYN"),
+    (4, "ET_CORE"),
+];
+
+pub const EM_386: u64 = 3;
+pub const EM_ARM: u64 = 40;
+pub const EM_X86_64: u64 = 62;
+pub const EM_AARCH64: u64 = 183;
+pub const EM_RISCV: u64 = 243;
+const MACHINE: &[(u64, &str)] = &[
+    (EM_386, "EM_386"),
+    (8, "EM_MIPS"),
+    (20, "EM_PPC"),
+    (21, "EM_PPC64"),
+    (22, "EM_S390"),
+    (EM_ARM, "EM_ARM"),
+    (EM_X86_64, "EM_X86_64"),
+    (EM_AARCH64, "EM_AARCH64"),
+    (EM_RISCV, "EM_RISCV"),
+];
+
+const PT_LOAD: u64 = 1;
+const PT_NOTE: u64 = 4;
+const SEGMENT_TYPE: &[(u64, &str)] = &[
+    (0, "PT_NULL"),
+    (PT_LOAD, "PT_LOAD"),
+    (2, "PT_DYNAMIC"),
+    (3, "PT_INTERP"),
+    (PT_NOTE, "PT_NOTE"),
+    (5, "PT_SHLIB"),
+    (6, "PT_PHDR"),
+    (7, "PT_TLS"),
+    (0x6474e550, "PT_GNU_EH_FRAME"),
+    (0x6474e551, "PT_GNU_STACK"),
+    (0x6474e552, "PT_GNU_RELRO"),
+    (0x6474e553, "PT_GNU_PROPERTY"),
+];
+
+const SHT_SYMTAB: u64 = 2;
+const SHT_NOBITS: u64 = 8;
+const SHT_DYNSYM: u64 = 11;
+const SECTION_TYPE: &[(u64, &str)] = &[
+    (0, "SHT_NULL"),
+    (1, "SHT_PROGBITS"),
+    (SHT_SYMTAB, "SHT_SYMTAB"),
+    (3, "SHT_STRTAB"),
+    (4, "SHT_RELA"),
+    (5, "SHT_HASH"),
+    (6, "SHT_DYNAMIC"),
+    (7, "SHT_NOTE"),
+    (SHT_NOBITS, "SHT_NOBITS"),
+    (9, "SHT_REL"),
+    (SHT_DYNSYM, "SHT_DYNSYM"),
+    (14, "SHT_INIT_ARRAY"),
+    (15, "SHT_FINI_ARRAY"),
+    (16, "SHT_PREINIT_ARRAY"),
+    (17, "SHT_GROUP"),
+    (18, "SHT_SYMTAB_SHNDX"),
+    (0x6ffffff6, "SHT_GNU_HASH"),
+    (0x6ffffffd, "SHT_GNU_verdef"),
+    (0x6ffffffe, "SHT_GNU_verneed"),
+    (0x6fffffff, "SHT_GNU_versym"),
+];
+
+const NOTE_CORE: &[(u64, &str)] = &[
+    (1, "NT_PRSTATUS"),
+    (2, "NT_PRFPREG"),
+    (3, "NT_PRPSINFO"),
+    (4, "NT_TASKSTRUCT"),
+    (6, "NT_AUXV"),
+    (0x202, "NT_X86_XSTATE"),
+    (0x46494c45, "NT_FILE"),
+    (0x53494749, "NT_SIGINFO"),
+];
+const NOTE_GNU: &[(u64, &str)] = &[
+    (1, "NT_GNU_ABI_TAG"),
+    (3, "NT_GNU_BUILD_ID"),
+    (4, "NT_GNU_GOLD_VERSION"),
+    (5, "NT_GNU_PROPERTY_TYPE_0"),
+];
+
+// Symbols with more entries than this only get one annotation per entry
+// rather than one per field, to keep the number of annotations manageable.
+const MAX_DETAILED_SYMBOLS: u64 = 4096;
+
+struct Section {
+    name: String,
+    name_offset: u64,
+    kind: u64,
+    address: u64,
+    offset: u64,
+    size: u64,
+    link: u64,
+    entsize: u64,
+}
+
+struct Elf<'a> {
+    r: Reader<'a>,
+    is64: bool,
+    analysis: Analysis,
+}
+
+impl Elf<'_> {
+    fn word(&self) -> u64 {
+        if self.is64 {
+            8
+        } else {
+            4
+        }
+    }
+
+    fn fields(&mut self, prefix: impl Into<String>, base: u64, depth: usize) -> Fields<'_> {
+        Fields::new(
+            &mut self.analysis.annotations,
+            prefix,
+            base,
+            depth,
+            self.r.endianness,
+        )
+    }
+
+    fn container(&mut self, range: std::ops::Range<u64>, label: String, depth: usize) {
+        self.analysis
+            .annotations
+            .push(Annotation::new(range, label, Format::None).depth(depth));
+    }
+}
+
+pub fn analyze(data: &[u8]) -> Option<Analysis> {
+    if data.get(0..4) != Some(b"\x7fELF") {
+        return None;
+    }
+    let is64 = match data.get(4)? {
+        1 => false,
+        2 => true,
+        _ => return None,
+    };
+    let endianness = match data.get(5)? {
+        1 => Endianness::Little,
+        2 => Endianness::Big,
+        _ => return None,
+    };
+
+    let mut elf = Elf {
+        r: Reader::new(data, endianness),
+        is64,
+        analysis: Analysis::default(),
+    };
+    let r = elf.r;
+    let w = elf.word();
+
+    let kind = r.u16(16)? as u64;
+    let machine = r.u16(18)? as u64;
+    let entry = r.uint(24, w)?;
+    let phoff = r.uint(24 + w, w)?;
+    let shoff = r.uint(24 + 2 * w, w)?;
+    let ehsize = r.u16(28 + 3 * w)? as u64;
+    let phentsize = r.u16(30 + 3 * w)? as u64;
+    let phnum = r.u16(32 + 3 * w)? as u64;
+    let shentsize = r.u16(34 + 3 * w)? as u64;
+    let shnum = r.u16(36 + 3 * w)? as u64;
+    let shstrndx = r.u16(38 + 3 * w)? as u64;
+
+    elf.analysis.description = format!(
+        "ELF{} {} {} {}",
+        if is64 { 64 } else { 32 },
+        if endianness == Endianness::Little {
+            "LSB"
+        } else {
+            "MSB"
+        },
+        name_of(MACHINE, machine).map_or(format!("machine {}", machine), |name| name[3..]
+            .to_lowercase()
+            .replace('_', "-")),
+        name_of(TYPE, kind).map_or("", |name| &name[3..]),
+    );
+
+    elf.analysis.architecture = match Architecture::from_elf(machine, is64) {
+        // ARM entry points with the low bit set are in Thumb code.
+        Some(Architecture::Arm) if entry & 1 == 1 => Some(Architecture::Thumb),
+        architecture => architecture,
+    };
+
+    elf.container(0..ehsize, "ehdr".to_string(), 0);
+    elf.fields("ehdr", 0, 1)
+        .field("e_ident.magic", 4, Format::Hex)
+        .field("e_ident.class", 1, Format::Enum(CLASS))
+        .field("e_ident.data", 1, Format::Enum(DATA))
+        .field("e_ident.version", 1, Format::Unsigned)
+        .field("e_ident.osabi", 1, Format::Enum(OSABI))
+        .field("e_ident.abiversion", 1, Format::Unsigned)
+        .field("e_ident.pad", 7, Format::None)
+        .field("e_type", 2, Format::Enum(TYPE))
+        .field("e_machine", 2, Format::Enum(MACHINE))
+        .field("e_version", 4, Format::Unsigned)
+        .field("e_entry", w, Format::Hex)
+        .field("e_phoff", w, Format::Hex)
+        .field("e_shoff", w, Format::Hex)
+        .field("e_flags", 4, Format::Hex)
+        .field("e_ehsize", 2, Format::Unsigned)
+        .field("e_phentsize", 2, Format::Unsigned)
+        .field("e_phnum", 2, Format::Unsigned)
+        .field("e_shentsize", 2, Format::Unsigned)
+        .field("e_shnum", 2, Format::Unsigned)
+        .field("e_shstrndx", 2, Format::Unsigned);
+
+    for index in 0..phnum {
+        let base = phoff.saturating_add(index * phentsize);
+        if r.bytes(base, phentsize).is_none() {
+            break;
+        }
+        elf.program_header(index, base, phentsize, shnum == 0);
+    }
+
+    let sections: Vec<Section> = (0..shnum)
+        .map_while(|index| elf.read_section(shoff.saturating_add(index * shentsize)))
+        .collect();
+    let shstrtab = sections.get(shstrndx as usize).map(|s| s.offset);
+
+    let sections: Vec<Section> = sections
+        .into_iter()
+        .map(|section| Section {
+            name: shstrtab
+                .and_then(|table| r.cstr(table.saturating_add(section.name_offset)))
+                .unwrap_or("")
+                .to_string(),
+            ..section
+        })
+        .collect();
+
+    for (index, section) in sections.iter().enumerate() {
+        let base = shoff + index as u64 * shentsize;
+        elf.section_header(index as u64, base, shentsize, section);
+    }
+
+    for section in sections.iter() {
+        if section.kind == SHT_SYMTAB || section.kind == SHT_DYNSYM {
+            elf.symbols(section, &sections, kind == ET_REL);
+        }
+    }
+
+    let mut analysis = elf.analysis;
+    analysis
+        .symbols
+        .sort_by(|a, b| (a.offset, &a.name).cmp(&(b.offset, &b.name)));
+    analysis.symbols.dedup();
+
+    Some(analysis)
+}
+
+impl Elf<'_> {
+    fn program_header(&mut self, index: u64, base: u64, size: u64, is_region: bool) {
+        let r = self.r;
+        let w = self.word();
+        let (kind, offset, address, filesz) = if self.is64 {
+            (
+                r.u32(base),
+                r.u64(base + 8),
+                r.u64(base + 16),
+                r.u64(base + 32),
+            )
+        } else {
+            (
+                r.u32(base),
+                r.u32(base + 4).map(u64::from),
+                r.u32(base + 8).map(u64::from),
+                r.u32(base + 16).map(u64::from),
+            )
+        };
+        let (Some(kind), Some(offset), Some(address), Some(filesz)) =
+            (kind, offset, address, filesz)
+        else {
+            return;
+        };
+        let kind = kind as u64;
+
+        let prefix = format!("phdr[{}]", index);
+        let type_name =
+            name_of(SEGMENT_TYPE, kind).map_or(String::new(), |name| format!(" {}", &name[3..]));
+        self.container(base..base + size, format!("{}{}", prefix, type_name), 0);
+        let is64 = self.is64;
+        let mut fields = self.fields(prefix, base, 1);
+        fields.field("p_type", 4, Format::Enum(SEGMENT_TYPE));
+        if is64 {
+            fields.field("p_flags", 4, Format::Hex);
+        }
+        fields
+            .field("p_offset", w, Format::Hex)
+            .field("p_vaddr", w, Format::Hex)
+            .field("p_paddr", w, Format::Hex)
+            .field("p_filesz", w, Format::Hex)
+            .field("p_memsz", w, Format::Hex);
+        if !is64 {
+            fields.field("p_flags", 4, Format::Hex);
+        }
+        fields.field("p_align", w, Format::Hex);
+
+        if kind == PT_LOAD && filesz > 0 {
+            self.analysis.mappings.push(Mapping {
+                offset,
+                size: filesz,
+                address,
+            });
+        }
+        // Core dumps have no sections, so their segments are the best we can
+        // do to tell the user where they are.
+        if is_region && filesz > 0 {
+            self.analysis.sections.push(Region {
+                name: format!("segment[{}]{}", index, type_name),
+                range: offset..offset.saturating_add(filesz),
+            });
+        }
+        if kind == PT_NOTE {
+            self.notes(index, offset, filesz);
+        }
+    }
+
+    fn notes(&mut self, segment: u64, offset: u64, size: u64) {
+        let r = self.r;
+        let end = offset.saturating_add(size);
+        let mut base = offset;
+        let mut index = 0;
+
+        while base.saturating_add(12) <= end {
+            let (Some(namesz), Some(descsz), Some(kind)) =
+                (r.u32(base), r.u32(base + 4), r.u32(base + 8))
+            else {
+                return;
+            };
+            let name_size = (namesz as u64).div_ceil(4) * 4;
+            let desc_size = (descsz as u64).div_ceil(4) * 4;
+            let owner = r
+                .bytes(base + 12, namesz as u64)
+                .map(|name| {
+                    String::from_utf8_lossy(name)
+                        .trim_end_matches('\0')
+                        .to_string()
+                })
+                .unwrap_or_default();
+            let types = match owner.as_str() {
+                "CORE" | "LINUX" => Format::Enum(NOTE_CORE),
+                "GNU" => Format::Enum(NOTE_GNU),
+                _ => Format::Unsigned,
+            };
+            let note_end = base + 12 + name_size + desc_size;
+            if note_end > end {
+                return;
+            }
+
+            let prefix = format!("note[{}.{}]", segment, index);
+            let type_name = match types {
+                Format::Enum(names) => name_of(names, kind as u64).unwrap_or(""),
+                _ => "",
+            };
+            self.container(
+                base..note_end,
+                format!("{} {} {}", prefix, owner, type_name),
+                0,
+            );
+            self.fields(prefix, base, 1)
+                .field("n_namesz", 4, Format::Unsigned)
+                .field("n_descsz", 4, Format::Unsigned)
+                .field("n_type", 4, types)
+                .field("name", name_size, Format::Text)
+                .field("desc", desc_size, Format::None);
+
+            base = note_end;
+            index += 1;
+        }
+    }
+
+    // Reads a section header, leaving the name empty until we know where the
+    // section name table is.
+    fn read_section(&self, base: u64) -> Option<Section> {
+        let r = self.r;
+        let w = self.word();
+        Some(Section {
+            name: String::new(),
+            name_offset: r.u32(base)? as u64,
+            kind: r.u32(base + 4)? as u64,
+            address: r.uint(base + 8 + w, w)?,
+            offset: r.uint(base + 8 + 2 * w, w)?,
+            size: r.uint(base + 8 + 3 * w, w)?,
+            link: r.u32(base + 8 + 4 * w)? as u64,
+            entsize: r.uint(base + 16 + 5 * w, w)?,
+        })
+    }
+
+    fn section_header(&mut self, index: u64, base: u64, size: u64, section: &Section) {
+        let w = self.word();
+        let prefix = format!("shdr[{}]", index);
+        self.container(base..base + size, format!("{} {}", prefix, section.name), 0);
+        self.fields(prefix, base, 1)
+            .field("sh_name", 4, Format::Unsigned)
+            .field("sh_type", 4, Format::Enum(SECTION_TYPE))
+            .field("sh_flags", w, Format::Hex)
+            .field("sh_addr", w, Format::Hex)
+            .field("sh_offset", w, Format::Hex)
+            .field("sh_size", w, Format::Hex)
+            .field("sh_link", 4, Format::Unsigned)
+            .field("sh_info", 4, Format::Unsigned)
+            .field("sh_addralign", w, Format::Unsigned)
+            .field("sh_entsize", w, Format::Unsigned);
+
+        if section.kind != SHT_NOBITS && section.kind != 0 && section.size > 0 {
+            self.analysis.sections.push(Region {
+                name: section.name.clone(),
+                range: section.offset..section.offset.saturating_add(section.size),
+            });
+        }
+    }
+
+    fn symbols(&mut self, table: &Section, sections: &[Section], relocatable: bool) {
+        let r = self.r;
+        let w = self.word();
+        let entsize = if table.entsize > 0 {
+            table.entsize
+        } else {
+            8 + 2 * w
+        };
+        let count = table.size / entsize;
+        // Entries are read field by field from `base`, so make sure none of
+        // those offsets can overflow before trusting the table's position.
+        if table
+            .offset
+            .checked_add(table.size)
+            .and_then(|end| end.checked_add(24))
+            .is_none()
+        {
+            return;
+        }
+        let strtab = sections.get(table.link as usize).map(|s| s.offset);
+        let label = table.name.trim_start_matches('.').to_string();
+
+        for index in 0..count {
+            let base = table.offset + index * entsize;
+            let entry = if self.is64 {
+                (
+                    r.u32(base),
+                    r.u8(base + 4),
+                    r.u16(base + 6),
+                    r.u64(base + 8),
+                    r.u64(base + 16),
+                )
+            } else {
+                (
+                    r.u32(base),
+                    r.u8(base + 12),
+                    r.u16(base + 14),
+                    r.u32(base + 4).map(u64::from),
+                    r.u32(base + 8).map(u64::from),
+                )
+            };
+            let (Some(name), Some(info), Some(shndx), Some(value), Some(size)) = entry else {
+                return;
+            };
+            let name = strtab
+                .and_then(|strtab| r.cstr(strtab.saturating_add(name as u64)))
+                .unwrap_or("")
+                .to_string();
+
+            let prefix = format!("{}[{}]", label, index);
+            self.container(base..base + entsize, format!("{} {}", prefix, name), 0);
+            if count <= MAX_DETAILED_SYMBOLS {
+                let is64 = self.is64;
+                let mut fields = self.fields(prefix, base, 1);
+                fields.field("st_name", 4, Format::Unsigned);
+                if is64 {
+                    fields
+                        .field("st_info", 1, Format::Hex)
+                        .field("st_other", 1, Format::Hex)
+                        .field("st_shndx", 2, Format::Unsigned)
+                        .field("st_value", 8, Format::Hex)
+                        .field("st_size", 8, Format::Unsigned);
+                } else {
+                    fields
+                        .field("st_value", 4, Format::Hex)
+                        .field("st_size", 4, Format::Unsigned)
+                        .field("st_info", 1, Format::Hex)
+                        .field("st_other", 1, Format::Hex)
+                        .field("st_shndx", 2, Format::Unsigned);
+                }
+            }
+
+            // Skip undefined and special (absolute, common) symbols, as well as
+            // section and file symbols, none of which point into the file.
+            let symbol_type = info & 0xf;
+            if name.is_empty()
+                || shndx == 0
+                || shndx >= 0xff00
+                || symbol_type == 3
+                || symbol_type == 4
+            {
+                continue;
+            }
+            let Some(section) = sections.get(shndx as usize) else {
+                continue;
+            };
+            if section.kind == SHT_NOBITS {
+                continue;
+            }
+            let offset = if relocatable {
+                section.offset.checked_add(value)
+            } else {
+                value
+                    .checked_sub(section.address)
+                    .filter(|&delta| delta <= section.size)
+                    .and_then(|delta| section.offset.checked_add(delta))
+            };
+            let Some(offset) = offset else {
+                continue;
+            };
+
+            self.analysis.symbols.push(Symbol {
+                name,
+                offset,
+                // No symbol can be bigger than the file it's in.
+                size: size.min(r.data.len() as u64),
+                address: value,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod elf_tests {
+    use super::*;
+
+    // Builds a minimal little-endian ELF64 executable with one loadable
+    // segment, a .text section and a symbol table with a single function.
+    fn build_elf64() -> Vec<u8> {
+        let mut data = vec![0u8; 0x400];
+        let put = |data: &mut Vec<u8>, offset: usize, bytes: &[u8]| {
+            data[offset..offset + bytes.len()].copy_from_slice(bytes)
+        };
+
+        // ehdr
+        put(&mut data, 0, b"\x7fELF\x02\x01\x01");
+        put(&mut data, 16, &2u16.to_le_bytes()); // ET_EXEC
+        put(&mut data, 18, &62u16.to_le_bytes()); // EM_X86_64
+        put(&mut data, 24, &0x401000u64.to_le_bytes()); // e_entry
+        put(&mut data, 32, &64u64.to_le_bytes()); // e_phoff
+        put(&mut data, 40, &0x200u64.to_le_bytes()); // e_shoff
+        put(&mut data, 52, &64u16.to_le_bytes()); // e_ehsize
+        put(&mut data, 54, &56u16.to_le_bytes()); // e_phentsize
+        put(&mut data, 56, &1u16.to_le_bytes()); // e_phnum
+        put(&mut data, 58, &64u16.to_le_bytes()); // e_shentsize
+        put(&mut data, 60, &5u16.to_le_bytes()); // e_shnum
+        put(&mut data, 62, &4u16.to_le_bytes()); // e_shstrndx
+
+        // phdr[0]: PT_LOAD 0x100..0x200 at 0x401000
+        put(&mut data, 64, &1u32.to_le_bytes());
+        put(&mut data, 64 + 8, &0x100u64.to_le_bytes());
+        put(&mut data, 64 + 16, &0x401000u64.to_le_bytes());
+        put(&mut data, 64 + 32, &0x100u64.to_le_bytes());
+        put(&mut data, 64 + 40, &0x100u64.to_le_bytes());
+
+        let section = |data: &mut Vec<u8>,
+                       index: usize,
+                       name: u32,
+                       kind: u32,
+                       addr: u64,
+                       offset: u64,
+                       size: u64,
+                       link: u32,
+                       entsize: u64| {
+            let base = 0x200 + index * 64;
+            data[base..base + 4].copy_from_slice(&name.to_le_bytes());
+            data[base + 4..base + 8].copy_from_slice(&kind.to_le_bytes());
+            data[base + 16..base + 24].copy_from_slice(&addr.to_le_bytes());
+            data[base + 24..base + 32].copy_from_slice(&offset.to_le_bytes());
+            data[base + 32..base + 40].copy_from_slice(&size.to_le_bytes());
+            data[base + 40..base + 44].copy_from_slice(&link.to_le_bytes());
+            data[base + 56..base + 64].copy_from_slice(&entsize.to_le_bytes());
+        };
+        section(&mut data, 1, 1, 1, 0x401000, 0x100, 0x100, 0, 0); // .text
+        section(&mut data, 2, 7, 2, 0, 0x340, 48, 3, 24); // .symtab
+        section(&mut data, 3, 15, 3, 0, 0x3a0, 16, 0, 0); // .strtab
+        section(&mut data, 4, 23, 3, 0, 0x3c0, 33, 0, 0); // .shstrtab
+        put(&mut data, 0x3c0, b"\0.text\0.symtab\0.strtab\0.shstrtab\0");
+        put(&mut data, 0x3a0, b"\0main\0");
+
+        // symtab[1]: main, FUNC, in section 1, at 0x401010, 0x20 bytes
+        put(&mut data, 0x340 + 24, &1u32.to_le_bytes());
+        put(&mut data, 0x340 + 24 + 4, &[0x12]);
+        put(&mut data, 0x340 + 24 + 6, &1u16.to_le_bytes());
+        put(&mut data, 0x340 + 24 + 8, &0x401010u64.to_le_bytes());
+        put(&mut data, 0x340 + 24 + 16, &0x20u64.to_le_bytes());
+
+        data
+    }
+
+    #[test]
+    fn test_not_elf() {
+        assert!(analyze(b"").is_none());
+        assert!(analyze(b"\x7fELF").is_none());
+        assert!(analyze(b"MZ\x90\x00").is_none());
+    }
+
+    #[test]
+    fn test_header() {
+        let data = build_elf64();
+        let analysis = analyze(&data).unwrap();
+        assert_eq!(analysis.description, "ELF64 LSB x86-64 EXEC");
+        assert_eq!(analysis.architecture, Some(Architecture::X86_64));
+
+        let machine = analysis
+            .annotations
+            .iter()
+            .find(|a| a.label == "ehdr.e_machine")
+            .unwrap();
+        assert_eq!(machine.range, 18..20);
+        assert_eq!(
+            machine.value(&data[18..20], Endianness::Big).unwrap(),
+            "62 (EM_X86_64)"
+        );
+
+        assert!(analysis
+            .annotations
+            .iter()
+            .any(|a| a.label == "phdr[0] LOAD" && a.range == (64..120)));
+        assert!(analysis
+            .annotations
+            .iter()
+            .any(|a| a.label == "shdr[1] .text" && a.range == (0x240..0x280)));
+    }
+
+    #[test]
+    fn test_sections_symbols_and_mappings() {
+        let data = build_elf64();
+        let analysis = analyze(&data).unwrap();
+
+        let names: Vec<&str> = analysis.sections.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, vec![".text", ".symtab", ".strtab", ".shstrtab"]);
+
+        assert_eq!(
+            analysis.symbols,
+            vec![Symbol {
+                name: "main".to_string(),
+                offset: 0x110,
+                size: 0x20,
+                address: 0x401010,
+            }]
+        );
+        assert_eq!(analysis.section_at(0x120).unwrap().name, ".text");
+        assert_eq!(analysis.symbol_at(0x120).unwrap().name, "main");
+
+        assert_eq!(analysis.offset_to_address(0x110), Some(0x401010));
+        assert_eq!(analysis.address_to_offset(0x401000), Some(0x100));
+        assert_eq!(analysis.offset_to_address(0x50), None);
+    }
+
+    #[test]
+    fn test_hostile_values() {
+        let put = |data: &mut Vec<u8>, offset: usize, value: u64| {
+            data[offset..offset + 8].copy_from_slice(&value.to_le_bytes())
+        };
+
+        // A symbol as big as can be is cut down to the size of the file.
+        let mut data = build_elf64();
+        put(&mut data, 0x340 + 24 + 16, u64::MAX);
+        let analysis = analyze(&data).unwrap();
+        assert_eq!(analysis.symbols[0].size, data.len() as u64);
+        assert_eq!(analysis.symbol_at(0x120).unwrap().name, "main");
+
+        // A section placed at the very end of the address space, and a
+        // symbol table too, are skipped.
+        let mut data = build_elf64();
+        put(&mut data, 0x240 + 24, u64::MAX - 4);
+        assert!(analyze(&data).unwrap().symbols.is_empty());
+        let mut data = build_elf64();
+        put(&mut data, 0x280 + 24, u64::MAX - 8);
+        put(&mut data, 0x280 + 56, 1);
+        assert!(analyze(&data).unwrap().symbols.is_empty());
+    }
+
+    #[test]
+    fn test_wrapping_mapping() {
+        let put = |data: &mut Vec<u8>, offset: usize, value: u64| {
+            data[offset..offset + 8].copy_from_slice(&value.to_le_bytes())
+        };
+
+        // A segment loaded at the very end of the address space: the part
+        // that would wrap around has no address.
+        let mut data = build_elf64();
+        put(&mut data, 64 + 16, u64::MAX - 0x10);
+        let analysis = analyze(&data).unwrap();
+        assert_eq!(analysis.offset_to_address(0x110), Some(u64::MAX));
+        assert_eq!(analysis.offset_to_address(0x120), None);
+        assert_eq!(analysis.address_to_offset(u64::MAX), Some(0x110));
+
+        // And one from the very end of the file's offsets.
+        let mut data = build_elf64();
+        put(&mut data, 64 + 8, u64::MAX - 0x10);
+        let analysis = analyze(&data).unwrap();
+        assert_eq!(analysis.address_to_offset(0x401010), Some(u64::MAX));
+        assert_eq!(analysis.address_to_offset(0x401020), None);
+    }
+
+    #[test]
+    fn test_truncated() {
+        let data = build_elf64();
+        // Cut off in the middle of the section headers: we still get the
+        // header and whatever we could read.
+        let analysis = analyze(&data[..0x250]).unwrap();
+        assert!(analysis.symbols.is_empty());
+        assert_eq!(analysis.mappings.len(), 1);
+    }
+}
@@ -0,0 +1,290 @@
+// Parsers for file formats we know how to take apart. Each produces an
+// `Analysis` describing what it found.
+
+pub mod bmff;
+pub mod container;
+pub mod elf;
+pub mod pe;
+pub mod png;
+pub mod riff;
+pub mod zip;
+
+use std::ops::Range;
+
+use crate::annotation::{Annotation, Format};
+use crate::decode::{decode_integer, Endianness};
+use crate::disasm::Architecture;
+
+// A named range of the file, e.g. an ELF section.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Region {
+    pub name: String,
+    pub range: Range<u64>,
+}
+
+// A named location in the file, e.g. a symbol. The size may be zero.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Symbol {
+    pub name: String,
+    pub offset: u64,
+    pub size: u64,
+    pub address: u64,
+}
+
+// A part of the file that is mapped into memory at `address`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mapping {
+    pub offset: u64,
+    pub size: u64,
+    pub address: u64,
+}
+
+#[derive(Debug, Default)]
+pub struct Analysis {
+    pub description: String,
+    pub annotations: Vec<Annotation>,
+    pub sections: Vec<Region>,
+    pub symbols: Vec<Symbol>,
+    pub mappings: Vec<Mapping>,
+    // The address relative virtual addresses (RVAs) are relative to.
+    pub image_base: Option<u64>,
+    // The instruction set the code in the file is for, if we know it.
+    pub architecture: Option<Architecture>,
+}
+
+impl Analysis {
+    // Mappings come from the file, so they may run past the end of the
+    // address space; what lies beyond it has no address, or no offset.
+    pub fn offset_to_address(&self, offset: u64) -> Option<u64> {
+        self.mappings
+            .iter()
+            .find(|m| m.offset <= offset && offset - m.offset < m.size)
+            .and_then(|m| m.address.checked_add(offset - m.offset))
+    }
+
+    pub fn address_to_offset(&self, address: u64) -> Option<u64> {
+        self.mappings
+            .iter()
+            .find(|m| m.address <= address && address - m.address < m.size)
+            .and_then(|m| m.offset.checked_add(address - m.address))
+    }
+
+    pub fn rva_to_offset(&self, rva: u64) -> Option<u64> {
+        self.address_to_offset(self.image_base?.checked_add(rva)?)
+    }
+
+    // Returns the smallest section containing `offset`.
+    pub fn section_at(&self, offset: u64) -> Option<&Region> {
+        self.sections
+            .iter()
+            .filter(|s| s.range.contains(&offset))
+            .min_by_key(|s| s.range.end - s.range.start)
+    }
+
+    // Returns the symbol containing `offset`, or failing that, the closest
+    // zero-sized symbol before it.
+    pub fn symbol_at(&self, offset: u64) -> Option<&Symbol> {
+        self.symbols
+            .iter()
+            .filter(|s| s.offset <= offset && (offset - s.offset < s.size || s.size == 0))
+            .max_by_key(|s| (s.offset.saturating_add(s.size) > offset, s.offset))
+    }
+}
+
+// Tries each of the parsers we know about on `data`.
+pub fn analyze(data: &[u8]) -> Option<Analysis> {
+    elf::analyze(data)
+        .or_else(|| pe::analyze(data))
+        .or_else(|| container::analyze(data))
+}
+
+pub fn name_of(names: &[(u64, &'static str)], value: u64) -> Option<&'static str> {
+    names
+        .iter()
+        .find(|(v, _)| *v == value)
+        .map(|(_, name)| *name)
+}
+
+// Bounds-checked reads of integers in a given byte order.
+#[derive(Clone, Copy)]
+pub struct Reader<'a> {
+    pub data: &'a [u8],
+    pub endianness: Endianness,
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(data: &'a [u8], endianness: Endianness) -> Self {
+        Reader { data, endianness }
+    }
+
+    pub fn bytes(&self, offset: u64, size: u64) -> Option<&'a [u8]> {
+        let start = usize::try_from(offset).ok()?;
+        let end = start.checked_add(usize::try_from(size).ok()?)?;
+        self.data.get(start..end)
+    }
+
+    pub fn uint(&self, offset: u64, size: u64) -> Option<u64> {
+        self.bytes(offset, size)
+            .map(|bytes| decode_integer(bytes, self.endianness).1 as u64)
+    }
+
+    pub fn u8(&self, offset: u64) -> Option<u8> {
+        self.uint(offset, 1).map(|v| v as u8)
+    }
+
+    pub fn u16(&self, offset: u64) -> Option<u16> {
+        self.uint(offset, 2).map(|v| v as u16)
+    }
+
+    pub fn u32(&self, offset: u64) -> Option<u32> {
+        self.uint(offset, 4).map(|v| v as u32)
+    }
+
+    pub fn u64(&self, offset: u64) -> Option<u64> {
+        self.uint(offset, 8)
+    }
+
+    // Reads a NUL-terminated string.
+    pub fn cstr(&self, offset: u64) -> Option<&'a str> {
+        let rest = self.data.get(usize::try_from(offset).ok()?..)?;
+        let len = rest.iter().position(|&b| b == 0)?;
+        std::str::from_utf8(&rest[..len]).ok()
+    }
+}
+
+// Annotates consecutive fields of a structure, e.g. a file header.
+pub struct Fields<'a> {
+    out: &'a mut Vec<Annotation>,
+    prefix: String,
+    base: u64,
+    offset: u64,
+    depth: usize,
+    endianness: Endianness,
+}
+
+impl<'a> Fields<'a> {
+    pub fn new(
+        out: &'a mut Vec<Annotation>,
+        prefix: impl Into<String>,
+        base: u64,
+        depth: usize,
+        endianness: Endianness,
+    ) -> Self {
+        Fields {
+            out,
+            prefix: prefix.into(),
+            base,
+            offset: 0,
+            depth,
+            endianness,
+        }
+    }
+
+    pub fn field(&mut self, name: &str, size: u64, format: Format) -> &mut Self {
+        let start = self.base + self.offset;
+        self.out.push(
+            Annotation::new(
+                start..start + size,
+                format!("{}.{}", self.prefix, name),
+                format,
+            )
+            .endianness(self.endianness)
+            .depth(self.depth),
+        );
+        self.offset += size;
+        self
+    }
+
+    // Marks the most recently added field as wrong.
+    pub fn error(&mut self, error: impl Into<String>) -> &mut Self {
+        if let Some(last) = self.out.last_mut() {
+            last.error = Some(error.into());
+        }
+        self
+    }
+
+    pub fn skip(&mut self, size: u64) -> &mut Self {
+        self.offset += size;
+        self
+    }
+}
+
+#[cfg(test)]
+mod formats_tests {
+    use super::*;
+
+    #[test]
+    fn test_reader() {
+        let data = [0x7f, b'E', b'L', b'F', 0x01, 0x02, b'h', b'i', 0];
+        let le = Reader::new(&data, Endianness::Little);
+        let be = Reader::new(&data, Endianness::Big);
+        assert_eq!(le.u8(0), Some(0x7f));
+        assert_eq!(le.u16(4), Some(0x0201));
+        assert_eq!(be.u16(4), Some(0x0102));
+        assert_eq!(le.u32(6), None);
+        assert_eq!(le.u64(u64::MAX), None);
+        assert_eq!(le.cstr(6), Some("hi"));
+        assert_eq!(le.cstr(9), None);
+    }
+
+    #[test]
+    fn test_mappings_and_lookups() {
+        let analysis = Analysis {
+            sections: vec![
+                Region {
+                    name: "outer".to_string(),
+                    range: 0x100..0x200,
+                },
+                Region {
+                    name: "inner".to_string(),
+                    range: 0x180..0x190,
+                },
+            ],
+            symbols: vec![
+                Symbol {
+                    name: "label".to_string(),
+                    offset: 0x100,
+                    size: 0,
+                    address: 0x1100,
+                },
+                Symbol {
+                    name: "function".to_string(),
+                    offset: 0x110,
+                    size: 0x10,
+                    address: 0x1110,
+                },
+            ],
+            mappings: vec![Mapping {
+                offset: 0x100,
+                size: 0x100,
+                address: 0x1100,
+            }],
+            ..Default::default()
+        };
+
+        assert_eq!(analysis.offset_to_address(0x180), Some(0x1180));
+        assert_eq!(analysis.offset_to_address(0x200), None);
+        assert_eq!(analysis.address_to_offset(0x11ff), Some(0x1ff));
+        assert_eq!(analysis.address_to_offset(0x1000), None);
+
+        assert_eq!(analysis.section_at(0x100).unwrap().name, "outer");
+        assert_eq!(analysis.section_at(0x185).unwrap().name, "inner");
+        assert!(analysis.section_at(0x200).is_none());
+
+        assert_eq!(analysis.symbol_at(0x105).unwrap().name, "label");
+        assert_eq!(analysis.symbol_at(0x115).unwrap().name, "function");
+        assert_eq!(analysis.symbol_at(0x125).unwrap().name, "label");
+        assert!(analysis.symbol_at(0x50).is_none());
+
+        let huge = Analysis {
+            symbols: vec![Symbol {
+                name: "huge".to_string(),
+                offset: 0x100,
+                size: u64::MAX,
+                address: 0x1100,
+            }],
+            ..Default::default()
+        };
+        assert_eq!(huge.symbol_at(0x200).unwrap().name, "huge");
+    }
+}
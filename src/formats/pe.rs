@@ -0,0 +1,741 @@
+// PE/COFF executables and DLLs.
+
+use super::{name_of, Analysis, Fields, Mapping, Reader, Region, Symbol};
+use crate::annotation::{Annotation, Format};
+use crate::decode::Endianness;
+use crate::disasm::Architecture;
+
+pub const IMAGE_FILE_MACHINE_I386: u64 = 0x14c;
+pub const IMAGE_FILE_MACHINE_ARM: u64 = 0x1c0;
+pub const IMAGE_FILE_MACHINE_ARMNT: u64 = 0x1c4;
+pub const IMAGE_FILE_MACHINE_AMD64: u64 = 0x8664;
+pub const IMAGE_FILE_MACHINE_ARM64: u64 = 0xaa64;
+pub const IMAGE_FILE_MACHINE_RISCV64: u64 = 0x5064;
+const MACHINE: &[(u64, &str)] = &[
+    (0, "IMAGE_FILE_MACHINE_UNKNOWN"),
+    (IMAGE_FILE_MACHINE_I386, "IMAGE_FILE_MACHINE_I386"),
+    (IMAGE_FILE_MACHINE_ARM, "IMAGE_FILE_MACHINE_ARM"),
+    (IMAGE_FILE_MACHINE_ARMNT, "IMAGE_FILE_MACHINE_ARMNT"),
+    (0x200, "IMAGE_FILE_MACHINE_IA64"),
+    (0x5032, "IMAGE_FILE_MACHINE_RISCV32"),
+    (IMAGE_FILE_MACHINE_RISCV64, "IMAGE_FILE_MACHINE_RISCV64"),
+    (IMAGE_FILE_MACHINE_AMD64, "IMAGE_FILE_MACHINE_AMD64"),
+    (IMAGE_FILE_MACHINE_ARM64, "IMAGE_FILE_MACHINE_ARM64"),
+];
+
+const PE32: u64 = 0x10b;
+const PE32_PLUS: u64 = 0x20b;
+const MAGIC: &[(u64, &str)] = &[(PE32, "PE32"), (PE32_PLUS, "PE32+")];
+
+const SUBSYSTEM: &[(u64, &str)] = &[
+    (0, "IMAGE_SUBSYSTEM_UNKNOWN"),
+    (1, "IMAGE_SUBSYSTEM_NATIVE"),
+    (2, "IMAGE_SUBSYSTEM_WINDOWS_GUI"),
+    (3, "IMAGE_SUBSYSTEM_WINDOWS_CUI"),
+    (9, "IMAGE_SUBSYSTEM_WINDOWS_CE_GUI"),
+    (10, "IMAGE_SUBSYSTEM_EFI_APPLICATION"),
+    (11, "IMAGE_SUBSYSTEM_EFI_BOOT_SERVICE_DRIVER"),
+    (12, "IMAGE_SUBSYSTEM_EFI_RUNTIME_DRIVER"),
+    (13, "IMAGE_SUBSYSTEM_EFI_ROM"),
+    (14, "IMAGE_SUBSYSTEM_XBOX"),
+    (16, "IMAGE_SUBSYSTEM_WINDOWS_BOOT_APPLICATION"),
+];
+
+const IMAGE_FILE_DLL: u64 = 0x2000;
+
+const DIRECTORY_EXPORT: usize = 0;
+const DIRECTORY_IMPORT: usize = 1;
+const DIRECTORIES: &[&str] = &[
+    "export",
+    "import",
+    "resource",
+    "exception",
+    "certificate",
+    "basereloc",
+    "debug",
+    "architecture",
+    "globalptr",
+    "tls",
+    "load_config",
+    "bound_import",
+    "iat",
+    "delay_import",
+    "clr_runtime",
+    "reserved",
+];
+
+// Limits on untrusted counts, so a corrupt file can't make us spin.
+const MAX_SECTIONS: u64 = 96;
+const MAX_IMPORTS: u64 = 65536;
+
+struct Section {
+    name: String,
+    virtual_size: u64,
+    virtual_address: u64,
+    raw_size: u64,
+    raw_offset: u64,
+}
+
+struct Pe<'a> {
+    r: Reader<'a>,
+    is64: bool,
+    image_base: u64,
+    size_of_headers: u64,
+    entry_point: u64,
+    sections: Vec<Section>,
+    analysis: Analysis,
+}
+
+impl Pe<'_> {
+    fn word(&self) -> u64 {
+        if self.is64 {
+            8
+        } else {
+            4
+        }
+    }
+
+    fn fields(&mut self, prefix: impl Into<String>, base: u64, depth: usize) -> Fields<'_> {
+        Fields::new(
+            &mut self.analysis.annotations,
+            prefix,
+            base,
+            depth,
+            Endianness::Little,
+        )
+    }
+
+    fn container(&mut self, range: std::ops::Range<u64>, label: String, depth: usize) {
+        self.analysis
+            .annotations
+            .push(Annotation::new(range, label, Format::None).depth(depth));
+    }
+
+    // Translates a relative virtual address to a file offset using the
+    // section table, the same way the loader would.
+    fn rva_to_offset(&self, rva: u64) -> Option<u64> {
+        if rva < self.size_of_headers {
+            return Some(rva);
+        }
+        self.sections
+            .iter()
+            .find(|s| rva >= s.virtual_address && rva - s.virtual_address < s.raw_size)
+            .map(|s| s.raw_offset + (rva - s.virtual_address))
+    }
+}
+
+pub fn analyze(data: &[u8]) -> Option<Analysis> {
+    if data.get(0..2) != Some(b"MZ") {
+        return None;
+    }
+    let r = Reader::new(data, Endianness::Little);
+    let lfanew = r.u32(0x3c)? as u64;
+    if r.bytes(lfanew, 4)? != b"PE\0\0" {
+        return None;
+    }
+
+    let coff = lfanew + 4;
+    let machine = r.u16(coff)? as u64;
+    let section_count = r.u16(coff + 2)? as u64;
+    let optional_size = r.u16(coff + 16)? as u64;
+    let characteristics = r.u16(coff + 18)? as u64;
+    let optional = coff + 20;
+    let magic = r.u16(optional)? as u64;
+    let is64 = match magic {
+        PE32 => false,
+        PE32_PLUS => true,
+        _ => return None,
+    };
+
+    let mut pe = Pe {
+        r,
+        is64,
+        image_base: 0,
+        size_of_headers: 0,
+        entry_point: 0,
+        sections: vec![],
+        analysis: Analysis::default(),
+    };
+
+    pe.dos_header();
+    pe.container(lfanew..optional, "pe_header".to_string(), 0);
+    pe.fields("pe_header", lfanew, 1)
+        .field("Signature", 4, Format::Text)
+        .field("Machine", 2, Format::Enum(MACHINE))
+        .field("NumberOfSections", 2, Format::Unsigned)
+        .field("TimeDateStamp", 4, Format::Hex)
+        .field("PointerToSymbolTable", 4, Format::Hex)
+        .field("NumberOfSymbols", 4, Format::Unsigned)
+        .field("SizeOfOptionalHeader", 2, Format::Unsigned)
+        .field("Characteristics", 2, Format::Hex);
+
+    let directories = pe.optional_header(optional, optional_size);
+
+    let section_table = optional.saturating_add(optional_size);
+    for index in 0..section_count.min(MAX_SECTIONS) {
+        if !pe.section_header(index, section_table.saturating_add(index * 40)) {
+            break;
+        }
+    }
+
+    pe.analysis.description = format!(
+        "{} {} {}",
+        if is64 { "PE32+" } else { "PE32" },
+        name_of(MACHINE, machine).map_or(format!("machine {:#x}", machine), |name| name[19..]
+            .to_lowercase()),
+        if characteristics & IMAGE_FILE_DLL != 0 {
+            "DLL"
+        } else {
+            "EXE"
+        },
+    );
+
+    pe.analysis.architecture = Architecture::from_pe(machine);
+
+    // The headers are mapped at the image base, then each section at its
+    // virtual address.
+    let image_base = pe.image_base;
+    pe.analysis.image_base = Some(image_base);
+    pe.analysis.mappings.push(Mapping {
+        offset: 0,
+        size: pe.size_of_headers.min(data.len() as u64),
+        address: image_base,
+    });
+    // The image base comes from the file, so a section may land past the end
+    // of the address space, in which case it isn't mapped anywhere.
+    for section in pe.sections.iter() {
+        let size = if section.virtual_size > 0 {
+            section.virtual_size.min(section.raw_size)
+        } else {
+            section.raw_size
+        };
+        let address = image_base
+            .checked_add(section.virtual_address)
+            .filter(|_| size > 0);
+        if let Some(address) = address {
+            pe.analysis.mappings.push(Mapping {
+                offset: section.raw_offset,
+                size,
+                address,
+            });
+        }
+    }
+
+    if pe.entry_point != 0 {
+        if let Some(offset) = pe.rva_to_offset(pe.entry_point) {
+            pe.analysis.symbols.push(Symbol {
+                name: "entry".to_string(),
+                offset,
+                size: 0,
+                address: image_base.saturating_add(pe.entry_point),
+            });
+        }
+    }
+    if let Some(&(rva, size)) = directories.get(DIRECTORY_EXPORT) {
+        if rva != 0 {
+            pe.exports(rva, size);
+        }
+    }
+    if let Some(&(rva, _)) = directories.get(DIRECTORY_IMPORT) {
+        if rva != 0 {
+            pe.imports(rva);
+        }
+    }
+
+    let mut analysis = pe.analysis;
+    analysis
+        .symbols
+        .sort_by(|a, b| (a.offset, &a.name).cmp(&(b.offset, &b.name)));
+    analysis.symbols.dedup();
+
+    Some(analysis)
+}
+
+impl Pe<'_> {
+    fn dos_header(&mut self) {
+        self.container(0..64, "dos_header".to_string(), 0);
+        self.fields("dos_header", 0, 1)
+            .field("e_magic", 2, Format::Text)
+            .field("e_cblp", 2, Format::Unsigned)
+            .field("e_cp", 2, Format::Unsigned)
+            .field("e_crlc", 2, Format::Unsigned)
+            .field("e_cparhdr", 2, Format::Unsigned)
+            .field("e_minalloc", 2, Format::Unsigned)
+            .field("e_maxalloc", 2, Format::Unsigned)
+            .field("e_ss", 2, Format::Hex)
+            .field("e_sp", 2, Format::Hex)
+            .field("e_csum", 2, Format::Hex)
+            .field("e_ip", 2, Format::Hex)
+            .field("e_cs", 2, Format::Hex)
+            .field("e_lfarlc", 2, Format::Hex)
+            .field("e_ovno", 2, Format::Unsigned)
+            .field("e_res", 8, Format::None)
+            .field("e_oemid", 2, Format::Hex)
+            .field("e_oeminfo", 2, Format::Hex)
+            .field("e_res2", 20, Format::None)
+            .field("e_lfanew", 4, Format::Hex);
+    }
+
+    // Annotates the optional header and returns the (rva, size) of each data
+    // directory.
+    fn optional_header(&mut self, base: u64, size: u64) -> Vec<(u64, u64)> {
+        let r = self.r;
+        let w = self.word();
+        let is64 = self.is64;
+
+        self.container(base..base + size, "optional_header".to_string(), 0);
+        let mut fields = self.fields("optional_header", base, 1);
+        fields
+            .field("Magic", 2, Format::Enum(MAGIC))
+            .field("MajorLinkerVersion", 1, Format::Unsigned)
+            .field("MinorLinkerVersion", 1, Format::Unsigned)
+            .field("SizeOfCode", 4, Format::Hex)
+            .field("SizeOfInitializedData", 4, Format::Hex)
+            .field("SizeOfUninitializedData", 4, Format::Hex)
+            .field("AddressOfEntryPoint", 4, Format::Hex)
+            .field("BaseOfCode", 4, Format::Hex);
+        if !is64 {
+            fields.field("BaseOfData", 4, Format::Hex);
+        }
+        fields
+            .field("ImageBase", w, Format::Hex)
+            .field("SectionAlignment", 4, Format::Hex)
+            .field("FileAlignment", 4, Format::Hex)
+            .field("MajorOperatingSystemVersion", 2, Format::Unsigned)
+            .field("MinorOperatingSystemVersion", 2, Format::Unsigned)
+            .field("MajorImageVersion", 2, Format::Unsigned)
+            .field("MinorImageVersion", 2, Format::Unsigned)
+            .field("MajorSubsystemVersion", 2, Format::Unsigned)
+            .field("MinorSubsystemVersion", 2, Format::Unsigned)
+            .field("Win32VersionValue", 4, Format::Hex)
+            .field("SizeOfImage", 4, Format::Hex)
+            .field("SizeOfHeaders", 4, Format::Hex)
+            .field("CheckSum", 4, Format::Hex)
+            .field("Subsystem", 2, Format::Enum(SUBSYSTEM))
+            .field("DllCharacteristics", 2, Format::Hex)
+            .field("SizeOfStackReserve", w, Format::Hex)
+            .field("SizeOfStackCommit", w, Format::Hex)
+            .field("SizeOfHeapReserve", w, Format::Hex)
+            .field("SizeOfHeapCommit", w, Format::Hex)
+            .field("LoaderFlags", 4, Format::Hex)
+            .field("NumberOfRvaAndSizes", 4, Format::Unsigned);
+
+        let (image_base, rest) = if is64 { (24, 108) } else { (28, 92) };
+        self.image_base = r.uint(base + image_base, w).unwrap_or(0);
+        self.size_of_headers = r.u32(base + 60).unwrap_or(0) as u64;
+        self.entry_point = r.u32(base + 16).unwrap_or(0) as u64;
+        let count = (r.u32(base + rest).unwrap_or(0) as u64)
+            .min(DIRECTORIES.len() as u64)
+            .min(size.saturating_sub(rest + 4) / 8);
+
+        let mut directories = vec![];
+        for index in 0..count {
+            let entry_base = base + rest + 4 + index * 8;
+            let (Some(rva), Some(size)) = (r.u32(entry_base), r.u32(entry_base + 4)) else {
+                break;
+            };
+            let prefix = format!("data_directory[{}]", DIRECTORIES[index as usize]);
+            self.container(entry_base..entry_base + 8, prefix.clone(), 1);
+            self.fields(prefix, entry_base, 2)
+                .field("VirtualAddress", 4, Format::Hex)
+                .field("Size", 4, Format::Hex);
+            directories.push((rva as u64, size as u64));
+        }
+
+        directories
+    }
+
+    // Reads and annotates a section header, returning false if it's beyond
+    // the end of the file.
+    fn section_header(&mut self, index: u64, base: u64) -> bool {
+        let r = self.r;
+        let (
+            Some(name),
+            Some(virtual_size),
+            Some(virtual_address),
+            Some(raw_size),
+            Some(raw_offset),
+        ) = (
+            r.bytes(base, 8),
+            r.u32(base + 8),
+            r.u32(base + 12),
+            r.u32(base + 16),
+            r.u32(base + 20),
+        )
+        else {
+            return false;
+        };
+        let name = String::from_utf8_lossy(name)
+            .trim_end_matches('\0')
+            .to_string();
+
+        let prefix = format!("section[{}]", index);
+        self.container(base..base + 40, format!("{} {}", prefix, name), 0);
+        self.fields(prefix, base, 1)
+            .field("Name", 8, Format::Text)
+            .field("VirtualSize", 4, Format::Hex)
+            .field("VirtualAddress", 4, Format::Hex)
+            .field("SizeOfRawData", 4, Format::Hex)
+            .field("PointerToRawData", 4, Format::Hex)
+            .field("PointerToRelocations", 4, Format::Hex)
+            .field("PointerToLinenumbers", 4, Format::Hex)
+            .field("NumberOfRelocations", 2, Format::Unsigned)
+            .field("NumberOfLinenumbers", 2, Format::Unsigned)
+            .field("Characteristics", 4, Format::Hex);
+
+        let section = Section {
+            name,
+            virtual_size: virtual_size as u64,
+            virtual_address: virtual_address as u64,
+            raw_size: raw_size as u64,
+            raw_offset: raw_offset as u64,
+        };
+        if section.raw_size > 0 {
+            self.analysis.sections.push(Region {
+                name: section.name.clone(),
+                range: section.raw_offset..section.raw_offset.saturating_add(section.raw_size),
+            });
+        }
+        self.sections.push(section);
+        true
+    }
+
+    fn exports(&mut self, rva: u64, size: u64) {
+        let r = self.r;
+        let Some(base) = self.rva_to_offset(rva) else {
+            return;
+        };
+        let (Some(name), Some(ordinal_base), Some(function_count), Some(name_count)) = (
+            r.u32(base + 12),
+            r.u32(base + 16),
+            r.u32(base + 20),
+            r.u32(base + 24),
+        ) else {
+            return;
+        };
+        let (Some(functions), Some(names), Some(ordinals)) =
+            (r.u32(base + 28), r.u32(base + 32), r.u32(base + 36))
+        else {
+            return;
+        };
+        let dll = self
+            .rva_to_offset(name as u64)
+            .and_then(|offset| r.cstr(offset))
+            .unwrap_or("");
+
+        self.container(base..base + 40, format!("export_directory {}", dll), 0);
+        self.fields("export_directory", base, 1)
+            .field("Characteristics", 4, Format::Hex)
+            .field("TimeDateStamp", 4, Format::Hex)
+            .field("MajorVersion", 2, Format::Unsigned)
+            .field("MinorVersion", 2, Format::Unsigned)
+            .field("Name", 4, Format::Hex)
+            .field("Base", 4, Format::Unsigned)
+            .field("NumberOfFunctions", 4, Format::Unsigned)
+            .field("NumberOfNames", 4, Format::Unsigned)
+            .field("AddressOfFunctions", 4, Format::Hex)
+            .field("AddressOfNames", 4, Format::Hex)
+            .field("AddressOfNameOrdinals", 4, Format::Hex);
+
+        let function_count = (function_count as u64).min(MAX_IMPORTS);
+        let name_count = (name_count as u64).min(MAX_IMPORTS);
+        let (Some(functions), Some(names), Some(ordinals)) = (
+            self.rva_to_offset(functions as u64),
+            self.rva_to_offset(names as u64),
+            self.rva_to_offset(ordinals as u64),
+        ) else {
+            return;
+        };
+
+        // Exports without a name are known by their ordinal.
+        let mut labels: Vec<Option<String>> = vec![None; function_count as usize];
+        for index in 0..name_count {
+            let (Some(name), Some(ordinal)) = (
+                r.u32(names.saturating_add(index * 4)),
+                r.u16(ordinals.saturating_add(index * 2)),
+            ) else {
+                break;
+            };
+            let name = self
+                .rva_to_offset(name as u64)
+                .and_then(|offset| r.cstr(offset));
+            if let (Some(name), Some(label)) = (name, labels.get_mut(ordinal as usize)) {
+                *label = Some(name.to_string());
+            }
+        }
+
+        for (index, label) in labels.into_iter().enumerate() {
+            let Some(function) = r.u32(functions.saturating_add(index as u64 * 4)) else {
+                break;
+            };
+            let function = function as u64;
+            // Forwarded exports point at a string inside the export
+            // directory rather than at code.
+            if function == 0 || (function >= rva && function - rva < size) {
+                continue;
+            }
+            let Some(offset) = self.rva_to_offset(function) else {
+                continue;
+            };
+            let name =
+                label.unwrap_or_else(|| format!("{}#{}", dll, ordinal_base as u64 + index as u64));
+            self.analysis.symbols.push(Symbol {
+                name,
+                offset,
+                size: 0,
+                address: self.image_base.saturating_add(function),
+            });
+        }
+    }
+
+    // Walks the import descriptors, naming each import address table slot
+    // after the function the loader will put there.
+    fn imports(&mut self, rva: u64) {
+        let r = self.r;
+        let w = self.word();
+        let Some(base) = self.rva_to_offset(rva) else {
+            return;
+        };
+
+        for index in 0..MAX_IMPORTS {
+            let descriptor = base.saturating_add(index * 20);
+            let (Some(lookup), Some(name), Some(iat)) = (
+                r.u32(descriptor),
+                r.u32(descriptor + 12),
+                r.u32(descriptor + 16),
+            ) else {
+                return;
+            };
+            if lookup == 0 && name == 0 && iat == 0 {
+                self.container(descriptor..descriptor + 20, "import[end]".to_string(), 0);
+                return;
+            }
+            let dll = self
+                .rva_to_offset(name as u64)
+                .and_then(|offset| r.cstr(offset))
+                .unwrap_or("")
+                .to_string();
+
+            let prefix = format!("import[{}]", index);
+            self.container(
+                descriptor..descriptor + 20,
+                format!("{} {}", prefix, dll),
+                0,
+            );
+            self.fields(prefix, descriptor, 1)
+                .field("OriginalFirstThunk", 4, Format::Hex)
+                .field("TimeDateStamp", 4, Format::Hex)
+                .field("ForwarderChain", 4, Format::Hex)
+                .field("Name", 4, Format::Hex)
+                .field("FirstThunk", 4, Format::Hex);
+
+            // Bound imports overwrite the IAT, so prefer the lookup table for
+            // names when there is one.
+            let names = if lookup != 0 { lookup } else { iat } as u64;
+            let (Some(names), Some(slots)) =
+                (self.rva_to_offset(names), self.rva_to_offset(iat as u64))
+            else {
+                continue;
+            };
+            let ordinal_flag = 1u64 << (w * 8 - 1);
+
+            for slot in 0..MAX_IMPORTS {
+                let Some(thunk) = r.uint(names.saturating_add(slot * w), w) else {
+                    break;
+                };
+                if thunk == 0 {
+                    break;
+                }
+                let function = if thunk & ordinal_flag != 0 {
+                    format!("#{}", thunk & 0xffff)
+                } else {
+                    let Some(hint) = self.rva_to_offset(thunk & 0x7fff_ffff) else {
+                        continue;
+                    };
+                    let Some(function) = r.cstr(hint.saturating_add(2)) else {
+                        continue;
+                    };
+                    let end = hint + 2 + function.len() as u64 + 1;
+                    self.container(hint..end, format!("import_by_name {}", function), 0);
+                    self.fields("import_by_name", hint, 1)
+                        .field("Hint", 2, Format::Unsigned)
+                        .field("Name", end - hint - 2, Format::Text);
+                    function.to_string()
+                };
+                let name = format!("{}!{}", dll, function);
+
+                let offset = slots + slot * w;
+                self.container(offset..offset + w, format!("iat {}", name), 0);
+                self.analysis.symbols.push(Symbol {
+                    name,
+                    offset,
+                    size: w,
+                    address: self.image_base.saturating_add(iat as u64 + slot * w),
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod pe_tests {
+    use super::*;
+
+    // Builds a minimal PE32+ DLL with a single .text section at RVA 0x1000
+    // (file offset 0x200) that holds an export and an import of its own.
+    fn build_pe64() -> Vec<u8> {
+        let mut data = vec![0u8; 0x400];
+        let put = |data: &mut Vec<u8>, offset: usize, bytes: &[u8]| {
+            data[offset..offset + bytes.len()].copy_from_slice(bytes)
+        };
+        let text = |rva: u32| 0x200 + (rva as usize - 0x1000);
+
+        put(&mut data, 0, b"MZ");
+        put(&mut data, 0x3c, &0x80u32.to_le_bytes()); // e_lfanew
+        put(&mut data, 0x80, b"PE\0\0");
+        put(&mut data, 0x84, &0x8664u16.to_le_bytes()); // Machine
+        put(&mut data, 0x86, &1u16.to_le_bytes()); // NumberOfSections
+        put(&mut data, 0x94, &0xf0u16.to_le_bytes()); // SizeOfOptionalHeader
+        put(&mut data, 0x96, &0x2022u16.to_le_bytes()); // Characteristics
+
+        let optional = 0x98;
+        put(&mut data, optional, &0x20bu16.to_le_bytes()); // Magic
+        put(&mut data, optional + 16, &0x1010u32.to_le_bytes()); // entry
+        put(&mut data, optional + 24, &0x180000000u64.to_le_bytes()); // ImageBase
+        put(&mut data, optional + 60, &0x200u32.to_le_bytes()); // SizeOfHeaders
+        put(&mut data, optional + 108, &16u32.to_le_bytes()); // NumberOfRvaAndSizes
+        put(&mut data, optional + 112, &0x1100u32.to_le_bytes()); // export
+        put(&mut data, optional + 116, &0x40u32.to_le_bytes());
+        put(&mut data, optional + 120, &0x1080u32.to_le_bytes()); // import
+        put(&mut data, optional + 124, &0x28u32.to_le_bytes());
+
+        // section[0] .text: RVA 0x1000, 0x200 bytes at 0x200
+        let section = optional + 0xf0;
+        put(&mut data, section, b".text");
+        put(&mut data, section + 8, &0x180u32.to_le_bytes());
+        put(&mut data, section + 12, &0x1000u32.to_le_bytes());
+        put(&mut data, section + 16, &0x200u32.to_le_bytes());
+        put(&mut data, section + 20, &0x200u32.to_le_bytes());
+
+        // import[0] KERNEL32.dll: lookup table at 0x10c0, IAT at 0x10e0
+        put(&mut data, text(0x1080), &0x10c0u32.to_le_bytes());
+        put(&mut data, text(0x1080) + 12, &0x10b0u32.to_le_bytes());
+        put(&mut data, text(0x1080) + 16, &0x10e0u32.to_le_bytes());
+        put(&mut data, text(0x10b0), b"KERNEL32.dll\0");
+        put(&mut data, text(0x10c0), &0x10f0u64.to_le_bytes());
+        put(
+            &mut data,
+            text(0x10c8),
+            &(0x8000000000000007u64).to_le_bytes(),
+        );
+        put(&mut data, text(0x10e0), &0x10f0u64.to_le_bytes());
+        put(
+            &mut data,
+            text(0x10e8),
+            &(0x8000000000000007u64).to_le_bytes(),
+        );
+        put(&mut data, text(0x10f0) + 2, b"ExitProcess\0");
+
+        // export_directory test.dll: two functions, only the first named
+        let export = text(0x1100);
+        put(&mut data, export + 12, &0x1140u32.to_le_bytes()); // Name
+        put(&mut data, export + 16, &1u32.to_le_bytes()); // Base
+        put(&mut data, export + 20, &2u32.to_le_bytes()); // NumberOfFunctions
+        put(&mut data, export + 24, &1u32.to_le_bytes()); // NumberOfNames
+        put(&mut data, export + 28, &0x1150u32.to_le_bytes());
+        put(&mut data, export + 32, &0x1160u32.to_le_bytes());
+        put(&mut data, export + 36, &0x1170u32.to_le_bytes());
+        put(&mut data, text(0x1140), b"test.dll\0");
+        put(&mut data, text(0x1150), &0x1020u32.to_le_bytes());
+        put(&mut data, text(0x1154), &0x1030u32.to_le_bytes());
+        put(&mut data, text(0x1160), &0x1178u32.to_le_bytes());
+        put(&mut data, text(0x1178), b"frob\0");
+
+        data
+    }
+
+    #[test]
+    fn test_not_pe() {
+        assert!(analyze(b"").is_none());
+        assert!(analyze(b"MZ").is_none());
+        let mut data = build_pe64();
+        data[0x80] = b'N';
+        assert!(analyze(&data).is_none());
+    }
+
+    #[test]
+    fn test_headers() {
+        let data = build_pe64();
+        let analysis = analyze(&data).unwrap();
+        assert_eq!(analysis.description, "PE32+ amd64 DLL");
+        assert_eq!(analysis.architecture, Some(Architecture::X86_64));
+
+        let machine = analysis
+            .annotations
+            .iter()
+            .find(|a| a.label == "pe_header.Machine")
+            .unwrap();
+        assert_eq!(machine.range, 0x84..0x86);
+        assert_eq!(
+            machine.value(&data[0x84..0x86], Endianness::Big).unwrap(),
+            "34404 (IMAGE_FILE_MACHINE_AMD64)"
+        );
+
+        let labels: Vec<&str> = analysis
+            .annotations
+            .iter()
+            .map(|a| a.label.as_str())
+            .collect();
+        assert!(labels.contains(&"dos_header.e_lfanew"));
+        assert!(labels.contains(&"optional_header.ImageBase"));
+        assert!(labels.contains(&"data_directory[import].Size"));
+        assert!(labels.contains(&"section[0] .text"));
+        assert!(labels.contains(&"import[0] KERNEL32.dll"));
+        assert!(labels.contains(&"export_directory test.dll"));
+    }
+
+    #[test]
+    fn test_rva_translation() {
+        let data = build_pe64();
+        let analysis = analyze(&data).unwrap();
+        assert_eq!(analysis.image_base, Some(0x180000000));
+        assert_eq!(analysis.rva_to_offset(0x40), Some(0x40));
+        assert_eq!(analysis.rva_to_offset(0x1010), Some(0x210));
+        assert_eq!(analysis.rva_to_offset(0x1180), None);
+        assert_eq!(analysis.offset_to_address(0x210), Some(0x180001010));
+        assert_eq!(analysis.section_at(0x210).unwrap().name, ".text");
+    }
+
+    #[test]
+    fn test_image_base_at_the_end() {
+        let mut data = build_pe64();
+        let base = u64::MAX - 0x800;
+        data[0x98 + 24..0x98 + 32].copy_from_slice(&base.to_le_bytes());
+        let analysis = analyze(&data).unwrap();
+        assert_eq!(analysis.offset_to_address(0x40), Some(base + 0x40));
+        assert_eq!(analysis.offset_to_address(0x210), None);
+        assert_eq!(analysis.address_to_offset(u64::MAX), None);
+    }
+
+    #[test]
+    fn test_imports_and_exports() {
+        let data = build_pe64();
+        let analysis = analyze(&data).unwrap();
+        let symbols: Vec<(&str, u64)> = analysis
+            .symbols
+            .iter()
+            .map(|s| (s.name.as_str(), s.offset))
+            .collect();
+        assert_eq!(
+            symbols,
+            vec![
+                ("entry", 0x210),
+                ("frob", 0x220),
+                ("test.dll#2", 0x230),
+                ("KERNEL32.dll!ExitProcess", 0x2e0),
+                ("KERNEL32.dll!#7", 0x2e8),
+            ]
+        );
+        assert_eq!(analysis.symbols[3].address, 0x1800010e0);
+    }
+}
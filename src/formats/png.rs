@@ -0,0 +1,204 @@
+// PNG images: a signature followed by length-prefixed, CRC-protected chunks.
+
+use super::container::{chunk, fourcc, ContainerWalker};
+use super::{name_of, Analysis, Fields, Reader};
+use crate::annotation::{Annotation, Format};
+use crate::decode::Endianness;
+
+const SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+const COLOR_TYPE: &[(u64, &str)] = &[
+    (0, "grayscale"),
+    (2, "truecolor"),
+    (3, "indexed"),
+    (4, "grayscale+alpha"),
+    (6, "truecolor+alpha"),
+];
+const INTERLACE: &[(u64, &str)] = &[(0, "none"), (1, "Adam7")];
+
+pub struct Png;
+
+impl ContainerWalker for Png {
+    fn detect(&self, data: &[u8]) -> bool {
+        data.starts_with(SIGNATURE)
+    }
+
+    fn walk(&self, data: &[u8], analysis: &mut Analysis) {
+        let r = Reader::new(data, Endianness::Big);
+        let len = data.len() as u64;
+        analysis.description = "PNG image".to_string();
+        analysis
+            .annotations
+            .push(Annotation::new(0..8, "signature", Format::None));
+
+        let mut offset = SIGNATURE.len() as u64;
+        let mut index = 0;
+        while offset < len {
+            let (Some(length), Some(kind)) = (r.u32(offset), r.bytes(offset + 4, 4)) else {
+                analysis.annotations.push(
+                    Annotation::new(offset..len, "truncated chunk", Format::None)
+                        .error("chunk header is cut off"),
+                );
+                return;
+            };
+            let length = length as u64;
+            let kind = fourcc(kind);
+            let end = offset + 12 + length;
+            let prefix = format!("{}[{}]", kind, index);
+
+            chunk(analysis, offset..end.min(len), prefix.clone(), 0);
+            let mut fields = Fields::new(
+                &mut analysis.annotations,
+                &prefix,
+                offset,
+                1,
+                Endianness::Big,
+            );
+            fields.field("length", 4, Format::Unsigned);
+            if end > len {
+                fields.error(format!(
+                    "chunk runs {} bytes past the end of the file",
+                    end - len
+                ));
+                return;
+            }
+            fields.field("type", 4, Format::Text);
+            if !kind.bytes().all(|b| b.is_ascii_alphabetic()) {
+                fields.error("invalid chunk type");
+            }
+            let data_format = match kind.as_str() {
+                "tEXt" | "iTXt" | "zTXt" => Format::Text,
+                _ => Format::None,
+            };
+            if length > 0 {
+                fields.field("data", length, data_format);
+            }
+            fields.field("crc", 4, Format::Hex);
+            let stored = r.u32(offset + 8 + length).unwrap_or_default();
+            let computed =
+                crc32fast::hash(&data[(offset + 4) as usize..(offset + 8 + length) as usize]);
+            if stored != computed {
+                fields.error(format!("CRC mismatch, computed {:#010x}", computed));
+            }
+
+            if kind == "IHDR" && length >= 13 {
+                Fields::new(
+                    &mut analysis.annotations,
+                    &prefix,
+                    offset + 8,
+                    2,
+                    Endianness::Big,
+                )
+                .field("width", 4, Format::Unsigned)
+                .field("height", 4, Format::Unsigned)
+                .field("bit_depth", 1, Format::Unsigned)
+                .field("color_type", 1, Format::Enum(COLOR_TYPE))
+                .field("compression", 1, Format::Unsigned)
+                .field("filter", 1, Format::Unsigned)
+                .field("interlace", 1, Format::Enum(INTERLACE));
+
+                let (width, height) = (r.u32(offset + 8), r.u32(offset + 12));
+                let (depth, color) = (r.u8(offset + 16), r.u8(offset + 17));
+                if let (Some(width), Some(height), Some(depth), Some(color)) =
+                    (width, height, depth, color)
+                {
+                    analysis.description = format!(
+                        "PNG image {}x{} {}-bit {}",
+                        width,
+                        height,
+                        depth,
+                        name_of(COLOR_TYPE, color as u64).unwrap_or("unknown")
+                    );
+                }
+            }
+
+            offset = end;
+            index += 1;
+            if kind == "IEND" {
+                break;
+            }
+        }
+
+        if offset < len {
+            analysis
+                .annotations
+                .push(Annotation::new(offset..len, "trailing data", Format::None));
+        }
+    }
+}
+
+#[cfg(test)]
+mod png_tests {
+    use super::*;
+    use crate::formats::container::analyze;
+
+    fn png_chunk(kind: &[u8], data: &[u8]) -> Vec<u8> {
+        let mut chunk = (data.len() as u32).to_be_bytes().to_vec();
+        chunk.extend_from_slice(kind);
+        chunk.extend_from_slice(data);
+        let crc = crc32fast::hash(&chunk[4..]);
+        chunk.extend_from_slice(&crc.to_be_bytes());
+        chunk
+    }
+
+    fn build_png() -> Vec<u8> {
+        let mut data = SIGNATURE.to_vec();
+        data.extend(png_chunk(b"IHDR", &[0, 0, 0, 2, 0, 0, 0, 3, 8, 6, 0, 0, 0]));
+        data.extend(png_chunk(b"IDAT", b"pixels"));
+        data.extend(png_chunk(b"IEND", b""));
+        data
+    }
+
+    fn errors(analysis: &Analysis) -> Vec<(&str, &str)> {
+        analysis
+            .annotations
+            .iter()
+            .filter_map(|a| Some((a.label.as_str(), a.error.as_deref()?)))
+            .collect()
+    }
+
+    #[test]
+    fn test_walk() {
+        let data = build_png();
+        let analysis = analyze(&data).unwrap();
+        assert_eq!(analysis.description, "PNG image 2x3 8-bit truecolor+alpha");
+        assert!(errors(&analysis).is_empty());
+
+        let names: Vec<&str> = analysis.sections.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, vec!["IHDR[0]", "IDAT[1]", "IEND[2]"]);
+        assert_eq!(analysis.sections[1].range, 33..51);
+
+        let color = analysis
+            .annotations
+            .iter()
+            .find(|a| a.label == "IHDR[0].color_type")
+            .unwrap();
+        assert_eq!(color.range, 25..26);
+        assert_eq!(color.depth, 2);
+    }
+
+    #[test]
+    fn test_crc_mismatch() {
+        let mut data = build_png();
+        data[41] ^= 0xff; // a byte of "pixels"
+        let analysis = analyze(&data).unwrap();
+        let errors = errors(&analysis);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].0, "IDAT[1].crc");
+        assert!(errors[0].1.starts_with("CRC mismatch"));
+        assert!(analysis.description.ends_with("(1 error)"));
+    }
+
+    #[test]
+    fn test_truncated() {
+        let data = build_png();
+        let analysis = analyze(&data[..45]).unwrap();
+        assert_eq!(
+            errors(&analysis),
+            vec![(
+                "IDAT[1].length",
+                "chunk runs 6 bytes past the end of the file"
+            )]
+        );
+    }
+}
@@ -0,0 +1,231 @@
+// RIFF files (WAV, AVI, WebP, ...): a tree of chunks, each a four-character
+// id and a length, where RIFF and LIST chunks contain further chunks.
+
+use super::container::{chunk, fourcc, ContainerWalker};
+use super::{Analysis, Fields, Reader};
+use crate::annotation::{Annotation, Format};
+use crate::decode::Endianness;
+
+const WAVE_FORMAT: &[(u64, &str)] = &[
+    (1, "PCM"),
+    (2, "ADPCM"),
+    (3, "IEEE float"),
+    (6, "A-law"),
+    (7, "mu-law"),
+    (0x55, "MP3"),
+    (0xfffe, "extensible"),
+];
+
+// Deeper nesting than this is more likely corruption than a real file.
+const MAX_DEPTH: usize = 16;
+
+pub struct Riff;
+
+impl ContainerWalker for Riff {
+    fn detect(&self, data: &[u8]) -> bool {
+        data.len() >= 12 && (data.starts_with(b"RIFF") || data.starts_with(b"RIFX"))
+    }
+
+    fn walk(&self, data: &[u8], analysis: &mut Analysis) {
+        // RIFX is the same thing, but big-endian.
+        let endianness = if data.starts_with(b"RIFX") {
+            Endianness::Big
+        } else {
+            Endianness::Little
+        };
+        let r = Reader::new(data, endianness);
+        let len = data.len() as u64;
+        analysis.description = format!("RIFF {}", fourcc(&data[8..12]).trim_end());
+
+        let end = walk_chunks(r, 0..len, 0, "", analysis);
+        if end < len {
+            analysis
+                .annotations
+                .push(Annotation::new(end..len, "trailing data", Format::None));
+        }
+    }
+}
+
+// Walks the chunks in `range`, returning where the last one ended.
+fn walk_chunks(
+    r: Reader,
+    range: std::ops::Range<u64>,
+    depth: usize,
+    path: &str,
+    analysis: &mut Analysis,
+) -> u64 {
+    let mut offset = range.start;
+    while offset.saturating_add(8) <= range.end {
+        let (Some(id), Some(size)) = (r.bytes(offset, 4), r.u32(offset + 4)) else {
+            break;
+        };
+        let id = fourcc(id);
+        let size = size as u64;
+        let data_start = offset + 8;
+        let data_end = data_start + size;
+        let is_list = id == "RIFF" || id == "RIFX" || id == "LIST";
+        let list_type = match r.bytes(data_start, 4) {
+            Some(list_type) if is_list => Some(fourcc(list_type)),
+            _ => None,
+        };
+
+        let name = match &list_type {
+            Some(list_type) => format!("{}{}({})", path, id, list_type.trim_end()),
+            None => format!("{}{}", path, id.trim_end()),
+        };
+        chunk(
+            analysis,
+            offset..data_end.min(range.end),
+            name.clone(),
+            depth * 2,
+        );
+        let mut fields = Fields::new(
+            &mut analysis.annotations,
+            &name,
+            offset,
+            depth * 2 + 1,
+            r.endianness,
+        );
+        fields.field("id", 4, Format::Text);
+        fields.field("size", 4, Format::Unsigned);
+        if data_end > range.end {
+            fields.error(format!(
+                "chunk runs {} bytes past the end of its parent",
+                data_end - range.end
+            ));
+        }
+        let data_end = data_end.min(range.end);
+
+        match list_type {
+            Some(_) => {
+                fields.field("type", 4, Format::Text);
+                if depth < MAX_DEPTH {
+                    let children = (data_start + 4).min(data_end)..data_end;
+                    walk_chunks(r, children, depth + 1, &format!("{}/", name), analysis);
+                }
+            }
+            None if id == "fmt " && size >= 16 => {
+                fields
+                    .field("format", 2, Format::Enum(WAVE_FORMAT))
+                    .field("channels", 2, Format::Unsigned)
+                    .field("sample_rate", 4, Format::Unsigned)
+                    .field("byte_rate", 4, Format::Unsigned)
+                    .field("block_align", 2, Format::Unsigned)
+                    .field("bits_per_sample", 2, Format::Unsigned);
+                let values = (
+                    r.u16(data_start + 2),
+                    r.u32(data_start + 4),
+                    r.u16(data_start + 14),
+                );
+                if let (Some(channels), Some(rate), Some(bits)) = values {
+                    analysis.description.push_str(&format!(
+                        " {} Hz {}-bit {}",
+                        rate,
+                        bits,
+                        match channels {
+                            1 => "mono".to_string(),
+                            2 => "stereo".to_string(),
+                            _ => format!("{} channels", channels),
+                        }
+                    ));
+                }
+            }
+            None if id == "avih" && size >= 40 => {
+                fields
+                    .field("microseconds_per_frame", 4, Format::Unsigned)
+                    .field("max_bytes_per_second", 4, Format::Unsigned)
+                    .field("padding_granularity", 4, Format::Unsigned)
+                    .field("flags", 4, Format::Hex)
+                    .field("total_frames", 4, Format::Unsigned)
+                    .field("initial_frames", 4, Format::Unsigned)
+                    .field("streams", 4, Format::Unsigned)
+                    .field("suggested_buffer_size", 4, Format::Unsigned)
+                    .field("width", 4, Format::Unsigned)
+                    .field("height", 4, Format::Unsigned);
+            }
+            None if size > 0 => {
+                fields.field("data", data_end - data_start, Format::None);
+            }
+            None => {}
+        }
+
+        // Chunks are padded to an even length.
+        offset = data_end + (size & 1);
+    }
+    offset.min(range.end)
+}
+
+#[cfg(test)]
+mod riff_tests {
+    use crate::formats::container::analyze;
+
+    fn riff_chunk(id: &[u8], data: &[u8]) -> Vec<u8> {
+        let mut chunk = id.to_vec();
+        chunk.extend((data.len() as u32).to_le_bytes());
+        chunk.extend(data);
+        if data.len() % 2 == 1 {
+            chunk.push(0);
+        }
+        chunk
+    }
+
+    fn build_wav() -> Vec<u8> {
+        let mut fmt = vec![];
+        fmt.extend(1u16.to_le_bytes());
+        fmt.extend(2u16.to_le_bytes());
+        fmt.extend(44100u32.to_le_bytes());
+        fmt.extend((44100u32 * 4).to_le_bytes());
+        fmt.extend(4u16.to_le_bytes());
+        fmt.extend(16u16.to_le_bytes());
+
+        let mut info = b"INFO".to_vec();
+        info.extend(riff_chunk(b"INAM", b"tone"));
+
+        let mut wave = b"WAVE".to_vec();
+        wave.extend(riff_chunk(b"fmt ", &fmt));
+        wave.extend(riff_chunk(b"LIST", &info));
+        wave.extend(riff_chunk(b"data", &[1, 2, 3]));
+        riff_chunk(b"RIFF", &wave)
+    }
+
+    #[test]
+    fn test_walk() {
+        let data = build_wav();
+        let analysis = analyze(&data).unwrap();
+        assert_eq!(analysis.description, "RIFF WAVE 44100 Hz 16-bit stereo");
+        assert!(analysis.annotations.iter().all(|a| a.error.is_none()));
+
+        let names: Vec<&str> = analysis.sections.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec![
+                "RIFF(WAVE)",
+                "RIFF(WAVE)/fmt",
+                "RIFF(WAVE)/LIST(INFO)",
+                "RIFF(WAVE)/LIST(INFO)/INAM",
+                "RIFF(WAVE)/data",
+            ]
+        );
+        // The pad byte after the odd-sized data chunk isn't part of it.
+        assert_eq!(analysis.sections[4].range, 60..71);
+        assert_eq!(analysis.sections[0].range, 0..72);
+    }
+
+    #[test]
+    fn test_length_mismatch() {
+        let mut data = build_wav();
+        data[4] += 2; // RIFF size
+        data[40] = 0xff; // LIST size
+        let analysis = analyze(&data).unwrap();
+        let errors: Vec<&str> = analysis
+            .annotations
+            .iter()
+            .filter(|a| a.error.is_some())
+            .map(|a| a.label.as_str())
+            .collect();
+        assert_eq!(
+            errors,
+            vec!["RIFF(WAVE).size", "RIFF(WAVE)/LIST(INFO).size"]
+        );
+    }
+}
@@ -0,0 +1,462 @@
+// ZIP archives (and everything built on them: JAR, DOCX, APK, ...). The
+// central directory at the end of the file is the authoritative list of
+// entries; each points back at a local header followed by the entry's data.
+
+use super::container::{chunk, ContainerWalker};
+use super::{Analysis, Fields, Reader};
+use crate::annotation::{Annotation, Format};
+use crate::decode::Endianness;
+
+const LOCAL_HEADER: u32 = 0x04034b50;
+const CENTRAL_HEADER: u32 = 0x02014b50;
+const END_OF_CENTRAL_DIRECTORY: u32 = 0x06054b50;
+const DATA_DESCRIPTOR: u32 = 0x08074b50;
+
+const METHOD: &[(u64, &str)] = &[
+    (0, "stored"),
+    (8, "deflated"),
+    (9, "deflate64"),
+    (12, "bzip2"),
+    (14, "lzma"),
+    (93, "zstd"),
+    (95, "xz"),
+    (99, "aes"),
+];
+
+// Flag bit 3: the CRC and sizes follow the data rather than being in the
+// local header.
+const FLAG_DATA_DESCRIPTOR: u16 = 1 << 3;
+
+// Archives with more entries than this only get one annotation per entry
+// rather than one per field.
+const MAX_DETAILED_ENTRIES: usize = 4096;
+
+pub struct Zip;
+
+// What the central directory says about an entry.
+struct Entry {
+    name: String,
+    crc: u32,
+    compressed_size: u64,
+    local_offset: u64,
+}
+
+impl ContainerWalker for Zip {
+    fn detect(&self, data: &[u8]) -> bool {
+        data.starts_with(b"PK\x03\x04") || data.starts_with(b"PK\x05\x06")
+    }
+
+    fn walk(&self, data: &[u8], analysis: &mut Analysis) {
+        let r = Reader::new(data, Endianness::Little);
+        match find_end_of_central_directory(data) {
+            Some(end) => walk_central_directory(r, end, analysis),
+            None => {
+                analysis.description = "ZIP archive without central directory".to_string();
+                walk_local_headers(r, analysis);
+            }
+        }
+    }
+}
+
+// The end record is at least 22 bytes and may be followed by a comment of up
+// to 64K, so search backwards from the end of the file.
+fn find_end_of_central_directory(data: &[u8]) -> Option<u64> {
+    let last = data.len().checked_sub(22)?;
+    let first = last.saturating_sub(0xffff);
+    (first..=last)
+        .rev()
+        .find(|&offset| data[offset..offset + 4] == END_OF_CENTRAL_DIRECTORY.to_le_bytes())
+        .map(|offset| offset as u64)
+}
+
+fn walk_central_directory(r: Reader, end: u64, analysis: &mut Analysis) {
+    let (Some(total), Some(size), Some(start), Some(comment_length)) = (
+        r.u16(end + 10),
+        r.u32(end + 12),
+        r.u32(end + 16),
+        r.u16(end + 20),
+    ) else {
+        return;
+    };
+    let (total, size, start) = (total as usize, size as u64, start as u64);
+    let zip64 = start == 0xffffffff;
+
+    let mut entries = vec![];
+    let mut offset = start;
+    while !zip64 && offset < end && r.u32(offset) == Some(CENTRAL_HEADER) {
+        let Some((entry, next)) = central_header(r, entries.len(), offset, total, analysis) else {
+            break;
+        };
+        entries.push(entry);
+        offset = next;
+    }
+
+    let end_length = 22 + comment_length as u64;
+    chunk(
+        analysis,
+        end..end + end_length,
+        "end_of_central_directory".to_string(),
+        0,
+    );
+    let mut fields = Fields::new(
+        &mut analysis.annotations,
+        "end_of_central_directory",
+        end,
+        1,
+        Endianness::Little,
+    );
+    fields
+        .field("signature", 4, Format::Hex)
+        .field("disk_number", 2, Format::Unsigned)
+        .field("central_directory_disk", 2, Format::Unsigned)
+        .field("disk_entries", 2, Format::Unsigned)
+        .field("total_entries", 2, Format::Unsigned);
+    if !zip64 && entries.len() != total {
+        fields.error(format!(
+            "found {} entries in the central directory",
+            entries.len()
+        ));
+    }
+    fields
+        .field("central_directory_size", 4, Format::Hex)
+        .field("central_directory_offset", 4, Format::Hex);
+    if zip64 {
+        fields.error("ZIP64 archives are not supported");
+    } else if start.saturating_add(size) > end {
+        fields.error("central directory overlaps the end record");
+    }
+    fields.field("comment_length", 2, Format::Unsigned);
+    if comment_length > 0 {
+        fields.field("comment", comment_length as u64, Format::Text);
+        if end + end_length > r.data.len() as u64 {
+            fields.error("comment runs past the end of the file");
+        }
+    }
+
+    if zip64 {
+        analysis.description = "ZIP64 archive".to_string();
+        return;
+    }
+    for (index, entry) in entries.iter().enumerate() {
+        if r.u32(entry.local_offset) == Some(LOCAL_HEADER) {
+            local_header(r, index, entry.local_offset, Some(entry), total, analysis);
+        }
+    }
+    analysis.description = format!(
+        "ZIP archive, {} {}",
+        entries.len(),
+        if entries.len() == 1 {
+            "entry"
+        } else {
+            "entries"
+        }
+    );
+}
+
+// Annotates a central directory header, returning what it says about the
+// entry and where the next header starts.
+fn central_header(
+    r: Reader,
+    index: usize,
+    offset: u64,
+    total: usize,
+    analysis: &mut Analysis,
+) -> Option<(Entry, u64)> {
+    let crc = r.u32(offset + 16)?;
+    let compressed_size = r.u32(offset + 20)? as u64;
+    let name_length = r.u16(offset + 28)? as u64;
+    let extra_length = r.u16(offset + 30)? as u64;
+    let comment_length = r.u16(offset + 32)? as u64;
+    let local_offset = r.u32(offset + 42)? as u64;
+    let name = String::from_utf8_lossy(r.bytes(offset + 46, name_length)?).to_string();
+    let end = offset + 46 + name_length + extra_length + comment_length;
+
+    let prefix = format!("central[{}]", index);
+    analysis.annotations.push(Annotation::new(
+        offset..end,
+        format!("{} {}", prefix, name),
+        Format::None,
+    ));
+    if total <= MAX_DETAILED_ENTRIES {
+        let mut fields = Fields::new(
+            &mut analysis.annotations,
+            prefix,
+            offset,
+            1,
+            Endianness::Little,
+        );
+        fields
+            .field("signature", 4, Format::Hex)
+            .field("version_made_by", 2, Format::Unsigned)
+            .field("version_needed", 2, Format::Unsigned)
+            .field("flags", 2, Format::Hex)
+            .field("method", 2, Format::Enum(METHOD))
+            .field("mtime", 2, Format::Hex)
+            .field("mdate", 2, Format::Hex)
+            .field("crc32", 4, Format::Hex)
+            .field("compressed_size", 4, Format::Unsigned)
+            .field("uncompressed_size", 4, Format::Unsigned)
+            .field("name_length", 2, Format::Unsigned)
+            .field("extra_length", 2, Format::Unsigned)
+            .field("comment_length", 2, Format::Unsigned)
+            .field("disk_start", 2, Format::Unsigned)
+            .field("internal_attributes", 2, Format::Hex)
+            .field("external_attributes", 4, Format::Hex)
+            .field("local_header_offset", 4, Format::Hex);
+        if r.u32(local_offset) != Some(LOCAL_HEADER) {
+            fields.error("no local header at this offset");
+        }
+        fields.field("name", name_length, Format::Text);
+        if extra_length > 0 {
+            fields.field("extra", extra_length, Format::None);
+        }
+        if comment_length > 0 {
+            fields.field("comment", comment_length, Format::Text);
+        }
+    }
+
+    let entry = Entry {
+        name,
+        crc,
+        compressed_size,
+        local_offset,
+    };
+    Some((entry, end))
+}
+
+// Without a central directory all we can do is follow the local headers,
+// trusting the sizes they contain.
+fn walk_local_headers(r: Reader, analysis: &mut Analysis) {
+    let mut offset = 0;
+    let mut index = 0;
+    while r.u32(offset) == Some(LOCAL_HEADER) {
+        match local_header(r, index, offset, None, 0, analysis) {
+            Some(end) => offset = end,
+            None => break,
+        }
+        index += 1;
+    }
+}
+
+// Annotates a local header and the entry data after it, checking them
+// against the central directory if we have it. Returns where the entry ends.
+fn local_header(
+    r: Reader,
+    index: usize,
+    offset: u64,
+    central: Option<&Entry>,
+    total: usize,
+    analysis: &mut Analysis,
+) -> Option<u64> {
+    let len = r.data.len() as u64;
+    let flags = r.u16(offset + 6)?;
+    let method = r.u16(offset + 8)?;
+    let crc = r.u32(offset + 14)?;
+    let compressed_size = r.u32(offset + 18)? as u64;
+    let name_length = r.u16(offset + 26)? as u64;
+    let extra_length = r.u16(offset + 28)? as u64;
+    let name = String::from_utf8_lossy(r.bytes(offset + 30, name_length)?).to_string();
+    let has_descriptor = flags & FLAG_DATA_DESCRIPTOR != 0;
+
+    // Sizes in the local header are zero when a data descriptor is used, so
+    // prefer the central directory's.
+    let size = match central {
+        Some(central) => central.compressed_size,
+        None if has_descriptor && compressed_size == 0 => return None,
+        None => compressed_size,
+    };
+    let expected_crc = central.map_or(crc, |central| central.crc);
+    let data_start = offset + 30 + name_length + extra_length;
+    let data_end = data_start.saturating_add(size);
+
+    let mut end = data_end;
+    if has_descriptor {
+        let signed = r.u32(data_end) == Some(DATA_DESCRIPTOR);
+        end = data_end + if signed { 16 } else { 12 };
+    }
+
+    let prefix = format!("local[{}]", index);
+    chunk(analysis, offset..end.min(len), name.clone(), 0);
+    if total > MAX_DETAILED_ENTRIES {
+        return Some(end);
+    }
+
+    let mut fields = Fields::new(
+        &mut analysis.annotations,
+        &prefix,
+        offset,
+        1,
+        Endianness::Little,
+    );
+    fields
+        .field("signature", 4, Format::Hex)
+        .field("version_needed", 2, Format::Unsigned)
+        .field("flags", 2, Format::Hex)
+        .field("method", 2, Format::Enum(METHOD))
+        .field("mtime", 2, Format::Hex)
+        .field("mdate", 2, Format::Hex)
+        .field("crc32", 4, Format::Hex);
+    if !has_descriptor && crc != expected_crc {
+        fields.error(format!("central directory says {:#010x}", expected_crc));
+    }
+    fields.field("compressed_size", 4, Format::Unsigned);
+    if !has_descriptor && compressed_size != size {
+        fields.error(format!("central directory says {}", size));
+    }
+    fields
+        .field("uncompressed_size", 4, Format::Unsigned)
+        .field("name_length", 2, Format::Unsigned)
+        .field("extra_length", 2, Format::Unsigned)
+        .field("name", name_length, Format::Text);
+    if central.is_some_and(|central| central.name != name) {
+        fields.error("name differs from the central directory");
+    }
+    if extra_length > 0 {
+        fields.field("extra", extra_length, Format::None);
+    }
+
+    if size > 0 {
+        fields.field("data", size, Format::None);
+        if data_end > len {
+            fields.error(format!(
+                "data runs {} bytes past the end of the file",
+                data_end - len
+            ));
+            return Some(end);
+        }
+        // Stored entries can be checked without decompressing them.
+        if method == 0 {
+            let computed = crc32fast::hash(&r.data[data_start as usize..data_end as usize]);
+            if computed != expected_crc {
+                fields.error(format!("CRC mismatch, computed {:#010x}", computed));
+            }
+        }
+    }
+
+    if has_descriptor {
+        let mut fields = Fields::new(
+            &mut analysis.annotations,
+            format!("{}.data_descriptor", prefix),
+            data_end,
+            1,
+            Endianness::Little,
+        );
+        if end - data_end == 16 {
+            fields.field("signature", 4, Format::Hex);
+        }
+        fields.field("crc32", 4, Format::Hex);
+        let descriptor_crc = r.u32(end - 12);
+        if central.is_some() && descriptor_crc != Some(expected_crc) {
+            fields.error(format!("central directory says {:#010x}", expected_crc));
+        }
+        fields.field("compressed_size", 4, Format::Unsigned).field(
+            "uncompressed_size",
+            4,
+            Format::Unsigned,
+        );
+    }
+
+    Some(end)
+}
+
+#[cfg(test)]
+mod zip_tests {
+    use super::*;
+    use crate::formats::container::analyze;
+
+    // Builds an archive of stored entries with a central directory.
+    fn build_zip(entries: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut data = vec![];
+        let mut central = vec![];
+        for (name, contents) in entries {
+            let crc = crc32fast::hash(contents);
+            let offset = data.len() as u32;
+
+            data.extend(LOCAL_HEADER.to_le_bytes());
+            data.extend([20, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+            data.extend(crc.to_le_bytes());
+            data.extend((contents.len() as u32).to_le_bytes());
+            data.extend((contents.len() as u32).to_le_bytes());
+            data.extend((name.len() as u16).to_le_bytes());
+            data.extend([0, 0]);
+            data.extend(name.as_bytes());
+            data.extend(*contents);
+
+            central.extend(CENTRAL_HEADER.to_le_bytes());
+            central.extend([20, 0, 20, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+            central.extend(crc.to_le_bytes());
+            central.extend((contents.len() as u32).to_le_bytes());
+            central.extend((contents.len() as u32).to_le_bytes());
+            central.extend((name.len() as u16).to_le_bytes());
+            central.extend([0; 12]);
+            central.extend(offset.to_le_bytes());
+            central.extend(name.as_bytes());
+        }
+
+        let start = data.len() as u32;
+        data.extend(&central);
+        data.extend(END_OF_CENTRAL_DIRECTORY.to_le_bytes());
+        data.extend([0, 0, 0, 0]);
+        data.extend((entries.len() as u16).to_le_bytes());
+        data.extend((entries.len() as u16).to_le_bytes());
+        data.extend((central.len() as u32).to_le_bytes());
+        data.extend(start.to_le_bytes());
+        data.extend([0, 0]);
+        data
+    }
+
+    fn errors(analysis: &Analysis) -> Vec<(&str, &str)> {
+        analysis
+            .annotations
+            .iter()
+            .filter_map(|a| Some((a.label.as_str(), a.error.as_deref()?)))
+            .collect()
+    }
+
+    #[test]
+    fn test_walk() {
+        let data = build_zip(&[("a.txt", b"hello"), ("b/c.txt", b"world!")]);
+        let analysis = analyze(&data).unwrap();
+        assert_eq!(analysis.description, "ZIP archive, 2 entries");
+        assert!(errors(&analysis).is_empty());
+
+        let names: Vec<&str> = analysis.sections.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, vec!["end_of_central_directory", "a.txt", "b/c.txt"]);
+        assert_eq!(analysis.sections[1].range, 0..40);
+
+        let data_field = analysis
+            .annotations
+            .iter()
+            .find(|a| a.label == "local[1].data")
+            .unwrap();
+        assert_eq!(data_field.range, 77..83);
+    }
+
+    #[test]
+    fn test_mismatches() {
+        let mut data = build_zip(&[("a.txt", b"hello"), ("b/c.txt", b"world!")]);
+        data[35] = b'j'; // "hello" -> "jello"
+        data[40 + 18] = 7; // second local header's compressed size
+        let analysis = analyze(&data).unwrap();
+        let errors = errors(&analysis);
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[0].0, "local[0].data");
+        assert!(errors[0].1.starts_with("CRC mismatch"));
+        assert_eq!(
+            errors[1],
+            ("local[1].compressed_size", "central directory says 6")
+        );
+    }
+
+    #[test]
+    fn test_without_central_directory() {
+        let data = build_zip(&[("a.txt", b"hello"), ("b/c.txt", b"world!")]);
+        let analysis = analyze(&data[..83]).unwrap();
+        assert_eq!(
+            analysis.description,
+            "ZIP archive without central directory"
+        );
+        let names: Vec<&str> = analysis.sections.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, vec!["a.txt", "b/c.txt"]);
+    }
+}
@@ -0,0 +1,216 @@
+use tui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Modifier, Style},
+    text::{Span, Spans},
+    widgets::{Paragraph, Widget},
+};
+
+use crate::terminal::color;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Radix {
+    #[default]
+    Hex,
+    Decimal,
+    Octal,
+}
+
+impl Radix {
+    pub fn next(self) -> Radix {
+        match self {
+            Radix::Hex => Radix::Decimal,
+            Radix::Decimal => Radix::Octal,
+            Radix::Octal => Radix::Hex,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Radix::Hex => "hex",
+            Radix::Decimal => "decimal",
+            Radix::Octal => "octal",
+        }
+    }
+
+    fn base(self) -> u64 {
+        match self {
+            Radix::Hex => 16,
+            Radix::Decimal => 10,
+            Radix::Octal => 8,
+        }
+    }
+
+    // How many digits it takes to write `value`.
+    fn digits(self, mut value: u64) -> usize {
+        let mut digits = 1;
+        while value >= self.base() {
+            value /= self.base();
+            digits += 1;
+        }
+        digits
+    }
+
+    fn format(self, value: u64, width: usize) -> String {
+        match self {
+            Radix::Hex => format!("{:0width$x}", value),
+            Radix::Decimal => format!("{:width$}", value),
+            Radix::Octal => format!("{:0width$o}", value),
+        }
+    }
+}
+
+// Offsets are never shown with fewer digits than this, so that the gutter
+// doesn't jitter for tiny files.
+const MIN_DIGITS: usize = 4;
+
+// The column to the left of the hex display showing where each row starts,
+// and marking rows with bookmarks or annotations.
+#[derive(Debug, Clone, Default)]
+pub struct Gutter {
+    pub radix: Radix,
+    // Added to offsets, e.g. for the load address of a firmware image.
+    pub base: u64,
+    // Offsets are shown relative to this (a mark) if it's set.
+    pub relative_to: Option<u64>,
+    // The size of the source, which decides how wide the gutter is.
+    pub len: u64,
+    // Bookmarks, and the starts of annotations (and whether they're
+    // errors), in the rows being shown.
+    pub marks: Vec<(char, u64)>,
+    pub annotations: Vec<(u64, bool)>,
+    // The rows being shown.
+    pub first: u64,
+    pub columns: u64,
+    pub rows: u64,
+    // The offset of the cursor, whose row is highlighted.
+    pub cursor: u64,
+}
+
+impl Gutter {
+    fn digits(&self) -> usize {
+        let largest = match self.relative_to {
+            Some(_) => self.len,
+            None => self.base.saturating_add(self.len),
+        };
+        self.radix.digits(largest).max(MIN_DIGITS)
+    }
+
+    pub fn width(&self) -> u16 {
+        // A sign in relative mode, then the offset, a space and the marker.
+        let sign = self.relative_to.is_some() as usize;
+        (sign + self.digits() + 2) as u16
+    }
+
+    pub fn label(&self, offset: u64) -> String {
+        let digits = self.digits();
+        match self.relative_to {
+            Some(mark) if offset >= mark => {
+                format!("+{}", self.radix.format(offset - mark, digits))
+            }
+            Some(mark) => format!("-{}", self.radix.format(mark - offset, digits)),
+            None => self.radix.format(self.base.wrapping_add(offset), digits),
+        }
+    }
+
+    // A mark's name, an error, or the start of an annotation, in order of
+    // preference.
+    fn marker(&self, row: std::ops::Range<u64>) -> (char, Style) {
+        let style = Style::default();
+        if let Some((name, _)) = self.marks.iter().find(|(_, at)| row.contains(at)) {
+            return (
+                *name,
+                style.fg(color(255, 224, 64)).add_modifier(Modifier::BOLD),
+            );
+        }
+        let starts = self.annotations.iter().filter(|(at, _)| row.contains(at));
+        match starts.map(|&(_, error)| error).max() {
+            Some(true) => (
+                '!',
+                style.fg(color(255, 96, 96)).add_modifier(Modifier::BOLD),
+            ),
+            Some(false) => ('•', style.fg(color(128, 160, 255))),
+            None => (' ', style),
+        }
+    }
+}
+
+impl Widget for Gutter {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let style = Style::default()
+            .bg(color(24, 24, 24))
+            .fg(color(128, 128, 128));
+        let style_cursor = style.fg(color(224, 224, 224)).add_modifier(Modifier::BOLD);
+
+        let lines: Vec<Spans> = (0..self.rows)
+            .map(|row| {
+                let start = self.first + row * self.columns;
+                let end = start + self.columns;
+                let (marker, marker_style) = self.marker(start..end);
+                let label_style = if (start..end).contains(&self.cursor) {
+                    style_cursor
+                } else {
+                    style
+                };
+                Spans::from(vec![
+                    Span::styled(self.label(start), label_style),
+                    Span::styled(" ", style),
+                    Span::styled(marker.to_string(), style.patch(marker_style)),
+                ])
+            })
+            .collect();
+        Paragraph::new(lines).style(style).render(area, buf);
+    }
+}
+
+#[cfg(test)]
+mod gutter_tests {
+    use super::*;
+
+    #[test]
+    fn test_labels() {
+        let mut gutter = Gutter {
+            len: 0x12345,
+            ..Default::default()
+        };
+        assert_eq!(gutter.label(0x10), "00010");
+        assert_eq!(gutter.width(), 7);
+
+        gutter.base = 0x8000_0000;
+        assert_eq!(gutter.label(0x10), "80000010");
+
+        gutter.radix = Radix::Decimal;
+        assert_eq!(gutter.label(16), "2147483664");
+        gutter.base = 0;
+        assert_eq!(gutter.label(16), "   16");
+
+        gutter.radix = Radix::Octal;
+        assert_eq!(gutter.label(8), "000010");
+
+        gutter.radix = Radix::Hex;
+        gutter.relative_to = Some(0x100);
+        assert_eq!(gutter.label(0x110), "+00010");
+        assert_eq!(gutter.label(0xf0), "-00010");
+        assert_eq!(gutter.width(), 8);
+
+        // Small files still get a few digits.
+        let gutter = Gutter {
+            len: 10,
+            ..Default::default()
+        };
+        assert_eq!(gutter.label(0), "0000");
+    }
+
+    #[test]
+    fn test_markers() {
+        let gutter = Gutter {
+            marks: vec![('a', 0x24)],
+            annotations: vec![(0x00, false), (0x20, false), (0x44, false), (0x48, true)],
+            ..Default::default()
+        };
+        assert_eq!(gutter.marker(0x00..0x10).0, '•');
+        assert_eq!(gutter.marker(0x10..0x20).0, ' ');
+        assert_eq!(gutter.marker(0x20..0x30).0, 'a');
+        assert_eq!(gutter.marker(0x40..0x50).0, '!');
+    }
+}
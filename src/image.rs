@@ -0,0 +1,270 @@
+// Bytes drawn as pixels, two rows of them to a terminal cell using half
+// blocks, to spot framebuffer dumps, textures and sprites by eye.
+
+use tui::{buffer::Buffer, layout::Rect, style::Style, widgets::Widget};
+
+use crate::{
+    decode::{decode_integer, Endianness},
+    terminal::color,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PixelFormat {
+    Gray8,
+    Rgb888,
+    Rgba8888,
+    Rgb565,
+    Mono1, // most significant bit first, set bits white
+}
+
+const PIXEL_FORMATS: &[PixelFormat] = &[
+    PixelFormat::Gray8,
+    PixelFormat::Rgb888,
+    PixelFormat::Rgba8888,
+    PixelFormat::Rgb565,
+    PixelFormat::Mono1,
+];
+
+impl PixelFormat {
+    pub fn next(self) -> PixelFormat {
+        let index = PIXEL_FORMATS.iter().position(|&f| f == self).unwrap_or(0);
+        PIXEL_FORMATS[(index + 1) % PIXEL_FORMATS.len()]
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            PixelFormat::Gray8 => "gray8",
+            PixelFormat::Rgb888 => "rgb888",
+            PixelFormat::Rgba8888 => "rgba8888",
+            PixelFormat::Rgb565 => "rgb565",
+            PixelFormat::Mono1 => "mono1",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<PixelFormat> {
+        PIXEL_FORMATS.iter().copied().find(|f| f.name() == name)
+    }
+
+    pub fn names() -> Vec<&'static str> {
+        PIXEL_FORMATS.iter().map(|f| f.name()).collect()
+    }
+
+    pub fn bits(self) -> u64 {
+        match self {
+            PixelFormat::Gray8 => 8,
+            PixelFormat::Rgb888 => 24,
+            PixelFormat::Rgba8888 => 32,
+            PixelFormat::Rgb565 => 16,
+            PixelFormat::Mono1 => 1,
+        }
+    }
+
+    // The color of pixel `x` of the row in `row`, if the row's long enough.
+    // Alpha is ignored, since there's nothing to blend with.
+    fn pixel(self, row: &[u8], x: u64, endianness: Endianness) -> Option<(u8, u8, u8)> {
+        let start = (x * self.bits() / 8) as usize;
+        let bytes = row.get(start..start + self.bits().div_ceil(8) as usize)?;
+        Some(match self {
+            PixelFormat::Gray8 => (bytes[0], bytes[0], bytes[0]),
+            PixelFormat::Rgb888 | PixelFormat::Rgba8888 => (bytes[0], bytes[1], bytes[2]),
+            PixelFormat::Rgb565 => {
+                let (_, value) = decode_integer(bytes, endianness);
+                let scale = |value: u128, max: u128| (value * 255 / max) as u8;
+                (
+                    scale(value >> 11 & 0x1f, 0x1f),
+                    scale(value >> 5 & 0x3f, 0x3f),
+                    scale(value & 0x1f, 0x1f),
+                )
+            }
+            PixelFormat::Mono1 => match bytes[0] & (0x80 >> (x % 8)) {
+                0 => (0, 0, 0),
+                _ => (255, 255, 255),
+            },
+        })
+    }
+}
+
+// Rows of `width` pixels, `stride` bytes apart if that's set and packed
+// together otherwise, starting at `origin`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Image {
+    pub format: PixelFormat,
+    pub width: u64,
+    pub stride: Option<u64>,
+    pub origin: u64,
+}
+
+pub const DEFAULT_WIDTH: u64 = 64;
+pub const MAX_WIDTH: u64 = 0x4000;
+
+impl Image {
+    pub fn new(format: PixelFormat, origin: u64) -> Self {
+        Image {
+            format,
+            width: DEFAULT_WIDTH,
+            stride: None,
+            origin,
+        }
+    }
+
+    pub fn stride(&self) -> u64 {
+        self.stride
+            .unwrap_or_else(|| (self.width * self.format.bits()).div_ceil(8))
+            .max(1)
+    }
+
+    // Where the row `offset` falls in starts.
+    pub fn row_at(&self, offset: u64) -> u64 {
+        let stride = self.stride();
+        let phase = self.origin % stride;
+        if offset < phase {
+            return phase;
+        }
+        offset - (offset - phase) % stride
+    }
+
+    // The first row to show in `rows` rows for the cursor to be in the
+    // middle, where possible.
+    pub fn first(&self, cursor: u64, rows: u64) -> u64 {
+        let stride = self.stride();
+        let row = self.row_at(cursor);
+        let before = (row - self.origin % stride) / stride;
+        row - before.min(rows / 2) * stride
+    }
+}
+
+pub struct ImageView {
+    pub image: Image,
+    // The offset of the first row shown, and data from there.
+    pub first: u64,
+    pub data: Vec<u8>,
+    pub cursor: u64,
+    pub endianness: Endianness,
+}
+
+impl ImageView {
+    // The color of pixel `x` in row `y` of those shown, and whether the
+    // cursor is on it.
+    fn pixel(&self, x: u64, y: u64) -> Option<((u8, u8, u8), bool)> {
+        let image = self.image;
+        let row_start = y * image.stride();
+        let row_end = (row_start + image.stride()).min(self.data.len() as u64);
+        let row = self.data.get(row_start as usize..row_end as usize)?;
+        let rgb = image.format.pixel(row, x, self.endianness)?;
+
+        let bits = image.format.bits();
+        let start = self.first + row_start + x * bits / 8;
+        let end = self.first + row_start + ((x + 1) * bits).div_ceil(8);
+        Some((rgb, (start..end).contains(&self.cursor)))
+    }
+}
+
+impl Widget for ImageView {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let cursor = color(96, 255, 96);
+        let to_color = |pixel: Option<((u8, u8, u8), bool)>| match pixel {
+            Some((_, true)) => Some(cursor),
+            Some(((r, g, b), false)) => Some(color(r, g, b)),
+            None => None,
+        };
+
+        buf.set_style(area, Style::default().bg(color(16, 16, 16)));
+        let width = (area.width as u64).min(self.image.width);
+        for row in 0..area.height {
+            for x in 0..width {
+                let y = 2 * row as u64;
+                let top = to_color(self.pixel(x, y));
+                let bottom = to_color(self.pixel(x, y + 1));
+                let cell = buf.get_mut(area.x + x as u16, area.y + row);
+                match (top, bottom) {
+                    (Some(top), bottom) => {
+                        cell.set_symbol("▀").set_fg(top);
+                        if let Some(bottom) = bottom {
+                            cell.set_bg(bottom);
+                        }
+                    }
+                    (None, Some(bottom)) => {
+                        cell.set_symbol("▄").set_fg(bottom);
+                    }
+                    (None, None) => {}
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod image_tests {
+    use super::*;
+
+    #[test]
+    fn test_pixels() {
+        let le = Endianness::Little;
+        let row = [0x10, 0x20, 0x30, 0x40];
+        assert_eq!(
+            PixelFormat::Gray8.pixel(&row, 3, le),
+            Some((0x40, 0x40, 0x40))
+        );
+        assert_eq!(PixelFormat::Gray8.pixel(&row, 4, le), None);
+        assert_eq!(
+            PixelFormat::Rgb888.pixel(&row, 0, le),
+            Some((0x10, 0x20, 0x30))
+        );
+        assert_eq!(PixelFormat::Rgb888.pixel(&row, 1, le), None);
+        assert_eq!(
+            PixelFormat::Rgba8888.pixel(&row, 0, le),
+            Some((0x10, 0x20, 0x30))
+        );
+
+        // Pure red, green and blue.
+        let rgb565 = [0x00, 0xf8, 0xe0, 0x07, 0x1f, 0x00];
+        assert_eq!(PixelFormat::Rgb565.pixel(&rgb565, 0, le), Some((255, 0, 0)));
+        assert_eq!(PixelFormat::Rgb565.pixel(&rgb565, 1, le), Some((0, 255, 0)));
+        assert_eq!(PixelFormat::Rgb565.pixel(&rgb565, 2, le), Some((0, 0, 255)));
+
+        let mono = [0b1000_0001];
+        assert_eq!(
+            PixelFormat::Mono1.pixel(&mono, 0, le),
+            Some((255, 255, 255))
+        );
+        assert_eq!(PixelFormat::Mono1.pixel(&mono, 1, le), Some((0, 0, 0)));
+        assert_eq!(
+            PixelFormat::Mono1.pixel(&mono, 7, le),
+            Some((255, 255, 255))
+        );
+    }
+
+    #[test]
+    fn test_rows() {
+        let mut image = Image {
+            format: PixelFormat::Mono1,
+            width: 12,
+            stride: None,
+            origin: 0x101,
+        };
+        assert_eq!(image.stride(), 2);
+        assert_eq!(image.row_at(0x104), 0x103);
+        assert_eq!(image.first(0x103 + 20, 6), 0x103 + 14);
+
+        image.format = PixelFormat::Rgb888;
+        image.stride = Some(40);
+        assert_eq!(image.stride(), 40);
+        assert_eq!(image.row_at(0x10), 0x101 % 40);
+        assert_eq!(image.row_at(0x40), 0x101 % 40 + 40);
+        assert_eq!(image.row_at(0x101 + 39), 0x101);
+    }
+
+    #[test]
+    fn test_cursor() {
+        let view = ImageView {
+            image: Image::new(PixelFormat::Rgb565, 0),
+            first: 0x80,
+            data: vec![0; 0x100],
+            cursor: 0x80 + 128 + 5,
+            endianness: Endianness::Little,
+        };
+        assert_eq!(view.pixel(2, 1), Some(((0, 0, 0), true)));
+        assert_eq!(view.pixel(3, 1), Some(((0, 0, 0), false)));
+        assert_eq!(view.pixel(0, 2), None);
+    }
+}
@@ -0,0 +1,383 @@
+// Which keys do what. Each binding maps a sequence of keys, such as `gg` or
+// `<C-d>`, to a command line, which may start with a `:`; see `command`.
+//
+// Keys are written as themselves, with the others in angle brackets, e.g.
+// `<Enter>`, `<PageDown>`, `<S-Tab>`, `<C-d>` or `<A-f>`; `<lt>` is `<`.
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use crate::command;
+
+pub type Key = (KeyModifiers, KeyCode);
+
+const DEFAULT_BINDINGS: &[(&str, &str)] = &[
+    ("h", "left"),
+    ("<Left>", "left"),
+    ("l", "right"),
+    ("<Right>", "right"),
+    ("k", "up"),
+    ("<Up>", "up"),
+    ("j", "down"),
+    ("<Down>", "down"),
+    ("L", "grow"),
+    ("H", "shrink"),
+    ("<S-Tab>", "skip-left"),
+    // Alt-left and right, as crossterm reports them.
+    ("<A-b>", "skip-left"),
+    ("<Tab>", "skip-right"),
+    ("<A-f>", "skip-right"),
+    ("<PageUp>", "page-up"),
+    ("<PageDown>", "page-down"),
+    ("<Home>", "start"),
+    ("<End>", "end"),
+    ("<Enter>", "follow"),
+    (":", "command-line"),
+    ("g", "goto"),
+    ("m", "set-mark"),
+    ("'", "jump-to-mark"),
+    ("v", "visual"),
+    ("<C-v>", "visual-block"),
+    ("z", "undo"),
+    ("Z", "redo"),
+    ("y", "copy"),
+    ("Y", "cycle-copy-format"),
+    ("e", "toggle-endianness"),
+    ("a", "toggle-address-mode"),
+    ("P", "cycle-pointer-mode"),
+    ("n", "navigator"),
+    ("i", "cycle-footer"),
+    ("c", "cycle-coloring"),
+    ("I", "entropy"),
+    ("b", "bit-view"),
+    ("o", "toggle-bit-order"),
+    ("[", "fine-left"),
+    ("]", "fine-right"),
+    ("{", "fine-shrink"),
+    ("}", "fine-grow"),
+    ("p", "image"),
+    ("T", "table"),
+    ("d", "disasm"),
+    ("x", "cycle-format"),
+    ("X", "cycle-word"),
+    ("t", "cycle-encoding"),
+    ("w", "cycle-columns"),
+    ("W", "cycle-group"),
+    ("r", "cycle-radix"),
+    ("<C-z>", "suspend"),
+    ("q", "quit"),
+    ("<Esc>", "quit"),
+];
+
+// Shift is part of the character typed, so it's left out for those; other
+// keys keep it, e.g. shift-tab.
+pub fn normalize(key: KeyEvent) -> Key {
+    match key.code {
+        KeyCode::Char(_) => (key.modifiers - KeyModifiers::SHIFT, key.code),
+        _ => (key.modifiers, key.code),
+    }
+}
+
+pub fn parse(text: &str) -> Result<Vec<Key>, String> {
+    let mut keys = vec![];
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        if c != '<' {
+            keys.push((KeyModifiers::NONE, KeyCode::Char(c)));
+            rest = &rest[c.len_utf8()..];
+            continue;
+        }
+        let end = rest.find('>').ok_or(format!("missing '>' in {}", text))?;
+        keys.push(parse_special(&rest[1..end])?);
+        rest = &rest[end + 1..];
+    }
+    if keys.is_empty() {
+        return Err("no keys given".to_string());
+    }
+    Ok(keys)
+}
+
+// A key in angle brackets, without them.
+fn parse_special(text: &str) -> Result<Key, String> {
+    let mut modifiers = KeyModifiers::NONE;
+    let mut name = text;
+    while let Some((modifier, rest)) = name.split_once('-').filter(|(_, rest)| !rest.is_empty()) {
+        modifiers |= match modifier {
+            "C" | "c" => KeyModifiers::CONTROL,
+            "A" | "a" | "M" | "m" => KeyModifiers::ALT,
+            "S" | "s" => KeyModifiers::SHIFT,
+            _ => return Err(format!("unknown modifier in <{}>", text)),
+        };
+        name = rest;
+    }
+
+    let code = match name.to_ascii_lowercase().as_str() {
+        "enter" | "cr" => KeyCode::Enter,
+        "esc" => KeyCode::Esc,
+        "tab" if modifiers.contains(KeyModifiers::SHIFT) => KeyCode::BackTab,
+        "tab" => KeyCode::Tab,
+        "space" => KeyCode::Char(' '),
+        "lt" => KeyCode::Char('<'),
+        "bs" | "backspace" => KeyCode::Backspace,
+        "del" | "delete" => KeyCode::Delete,
+        "left" => KeyCode::Left,
+        "right" => KeyCode::Right,
+        "up" => KeyCode::Up,
+        "down" => KeyCode::Down,
+        "pageup" => KeyCode::PageUp,
+        "pagedown" => KeyCode::PageDown,
+        "home" => KeyCode::Home,
+        "end" => KeyCode::End,
+        lower => match (lower.strip_prefix('f'), name.chars().count()) {
+            (Some(number), _) if !number.is_empty() => number
+                .parse()
+                .ok()
+                .filter(|number| (1..=12).contains(number))
+                .map(KeyCode::F)
+                .ok_or(format!("unknown key <{}>", text))?,
+            (_, 1) => KeyCode::Char(name.chars().next().unwrap_or_default()),
+            _ => return Err(format!("unknown key <{}>", text)),
+        },
+    };
+    // Terminals report shift-x as `X`, which is what `<S-x>` has to match.
+    let code = match code {
+        KeyCode::Char(c) if modifiers.contains(KeyModifiers::SHIFT) => {
+            KeyCode::Char(c.to_ascii_uppercase())
+        }
+        code => code,
+    };
+    Ok(normalize(KeyEvent::new(code, modifiers)))
+}
+
+// The keys as `parse` reads them, e.g. to show what's been typed so far.
+pub fn display(keys: &[Key]) -> String {
+    keys.iter().map(|&key| display_key(key)).collect()
+}
+
+fn display_key((modifiers, code): Key) -> String {
+    let name = match code {
+        KeyCode::Char('<') => "lt".to_string(),
+        KeyCode::Char(' ') => "Space".to_string(),
+        KeyCode::Char(c) if modifiers.is_empty() => return c.to_string(),
+        KeyCode::Char(c) => c.to_string(),
+        KeyCode::Enter => "Enter".to_string(),
+        KeyCode::Esc => "Esc".to_string(),
+        KeyCode::Tab | KeyCode::BackTab => "Tab".to_string(),
+        KeyCode::Backspace => "BS".to_string(),
+        KeyCode::Delete => "Del".to_string(),
+        KeyCode::Left => "Left".to_string(),
+        KeyCode::Right => "Right".to_string(),
+        KeyCode::Up => "Up".to_string(),
+        KeyCode::Down => "Down".to_string(),
+        KeyCode::PageUp => "PageUp".to_string(),
+        KeyCode::PageDown => "PageDown".to_string(),
+        KeyCode::Home => "Home".to_string(),
+        KeyCode::End => "End".to_string(),
+        KeyCode::F(number) => format!("F{}", number),
+        code => format!("{:?}", code),
+    };
+    let mut prefix = String::new();
+    for (modifier, letter) in [
+        (KeyModifiers::CONTROL, "C-"),
+        (KeyModifiers::ALT, "A-"),
+        (KeyModifiers::SHIFT, "S-"),
+    ] {
+        if modifiers.contains(modifier) {
+            prefix.push_str(letter);
+        }
+    }
+    format!("<{}{}>", prefix, name)
+}
+
+// What a sequence of keys typed so far means.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Lookup<'a> {
+    // What it's bound to, if anything.
+    pub action: Option<&'a str>,
+    // Whether other bindings start with it, so that more keys may follow.
+    pub longer: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    bindings: Vec<(Vec<Key>, String)>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        let mut keymap = Keymap { bindings: vec![] };
+        for (keys, action) in DEFAULT_BINDINGS {
+            keymap.bind(keys, action).expect("bad default binding");
+        }
+        keymap
+    }
+}
+
+impl Keymap {
+    // Binds `keys` to the command line `action`, replacing what they were
+    // bound to before. An empty action unbinds them.
+    pub fn bind(&mut self, keys: &str, action: &str) -> Result<(), String> {
+        let keys = parse(keys)?;
+        command::parse(action.strip_prefix(':').unwrap_or(action))?;
+        self.bindings.retain(|(bound, _)| *bound != keys);
+        if !action.is_empty() {
+            self.bindings.push((keys, action.to_string()));
+        }
+        Ok(())
+    }
+
+    pub fn lookup(&self, keys: &[Key]) -> Lookup<'_> {
+        let mut lookup = Lookup {
+            action: None,
+            longer: false,
+        };
+        for (bound, action) in &self.bindings {
+            if bound == keys {
+                lookup.action = Some(action.as_str());
+            } else if bound.starts_with(keys) {
+                lookup.longer = true;
+            }
+        }
+        lookup
+    }
+}
+
+#[cfg(test)]
+mod keymap_tests {
+    use super::*;
+
+    fn key(code: KeyCode) -> Key {
+        (KeyModifiers::NONE, code)
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            parse("gg"),
+            Ok(vec![key(KeyCode::Char('g')), key(KeyCode::Char('g'))])
+        );
+        assert_eq!(
+            parse("<C-d><PageDown>G"),
+            Ok(vec![
+                (KeyModifiers::CONTROL, KeyCode::Char('d')),
+                key(KeyCode::PageDown),
+                key(KeyCode::Char('G')),
+            ])
+        );
+        assert_eq!(
+            parse("<S-Tab>"),
+            Ok(vec![(KeyModifiers::SHIFT, KeyCode::BackTab)])
+        );
+        assert_eq!(
+            parse("<A-S-x><lt><F5><C-->"),
+            Ok(vec![
+                (KeyModifiers::ALT, KeyCode::Char('X')),
+                key(KeyCode::Char('<')),
+                key(KeyCode::F(5)),
+                (KeyModifiers::CONTROL, KeyCode::Char('-')),
+            ])
+        );
+        assert_eq!(parse("<S-x>"), parse("X"));
+        assert!(parse("").is_err());
+        assert!(parse("<C-d").is_err());
+        assert!(parse("<Hyper-d>").is_err());
+        assert!(parse("<F13>").is_err());
+        assert!(parse("<Frobnicate>").is_err());
+    }
+
+    #[test]
+    fn test_display() {
+        for text in ["gg", "<C-d><PageDown>G", "<S-Tab>", "<A-x><lt><F5><C-->"] {
+            assert_eq!(display(&parse(text).unwrap()), text);
+        }
+        assert_eq!(display(&parse("<Space>").unwrap()), "<Space>");
+        assert_eq!(display(&parse("<A-S-x>").unwrap()), "<A-X>");
+    }
+
+    #[test]
+    fn test_normalize() {
+        let shifted = KeyEvent::new(KeyCode::Char('L'), KeyModifiers::SHIFT);
+        assert_eq!(normalize(shifted), key(KeyCode::Char('L')));
+        let alt_shifted =
+            KeyEvent::new(KeyCode::Char('X'), KeyModifiers::ALT | KeyModifiers::SHIFT);
+        assert_eq!(Ok(vec![normalize(alt_shifted)]), parse("<A-S-x>"));
+        let backtab = KeyEvent::new(KeyCode::BackTab, KeyModifiers::SHIFT);
+        assert_eq!(normalize(backtab), (KeyModifiers::SHIFT, KeyCode::BackTab));
+    }
+
+    #[test]
+    fn test_lookup() {
+        let mut keymap = Keymap::default();
+        let g = parse("g").unwrap();
+        assert_eq!(
+            keymap.lookup(&g),
+            Lookup {
+                action: Some("goto"),
+                longer: false
+            }
+        );
+
+        keymap.bind("gg", ":goto 0").unwrap();
+        assert_eq!(
+            keymap.lookup(&g),
+            Lookup {
+                action: Some("goto"),
+                longer: true
+            }
+        );
+        assert_eq!(keymap.lookup(&parse("gg").unwrap()).action, Some(":goto 0"));
+        assert_eq!(
+            keymap.lookup(&parse("gx").unwrap()),
+            Lookup {
+                action: None,
+                longer: false
+            }
+        );
+
+        keymap.bind("q", "").unwrap();
+        assert_eq!(keymap.lookup(&parse("q").unwrap()).action, None);
+        keymap.bind("<C-q>", "quit").unwrap();
+        assert_eq!(keymap.lookup(&parse("<C-q>").unwrap()).action, Some("quit"));
+        assert!(keymap.bind("x", "frobnicate").is_err());
+        assert!(keymap.bind("x", ":export").is_err());
+    }
+
+    #[test]
+    fn test_defaults() {
+        // Commands that take arguments there's no good default for, and are
+        // left to the command line.
+        const UNBOUND: &[&str] = &[
+            "select",
+            "mark",
+            "relative",
+            "rva",
+            "set",
+            "columns",
+            "group",
+            "encoding",
+            "endianness",
+            "base",
+            "pointer",
+            "struct",
+            "magic",
+            "scan",
+            "carve",
+            "export",
+            "help",
+        ];
+
+        let keymap = Keymap::default();
+        for command in command::COMMANDS {
+            let bound = keymap.bindings.iter().any(|(_, action)| {
+                let action = action.strip_prefix(':').unwrap_or(action);
+                action.split_whitespace().next() == Some(command.name)
+            });
+            assert_eq!(
+                bound,
+                !UNBOUND.contains(&command.name),
+                "{} is {}",
+                command.name,
+                if bound { "bound" } else { "unbound" }
+            );
+        }
+    }
+}
@@ -0,0 +1,294 @@
+// Works out where the gutter, hex and text panes go, and how many bytes per
+// row they show, degrading gracefully as the terminal gets narrower: first
+// stacking the panes, then hiding the text pane, then using fewer columns.
+
+use tui::layout::Rect;
+
+use crate::display::{unicode_width, Shape};
+
+// Rows longer than this are more likely a typo than a record size.
+pub const MAX_COLUMNS: u64 = 4096;
+
+// The gutter is dropped if it would leave less room than this for data.
+const MIN_DATA_WIDTH: u64 = 16;
+
+// Stacking the panes halves the rows shown, so only do it if there are
+// enough of them.
+const MIN_STACKED_ROWS: u16 = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Arrangement {
+    SideBySide,
+    Stacked, // hex above text
+    HexOnly,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pane {
+    Hex,
+    Text,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Panes {
+    pub arrangement: Arrangement,
+    // One per pane, unless there's no room for it.
+    pub gutters: Vec<Rect>,
+    pub hex: Rect,
+    pub text: Option<Rect>,
+    pub columns: u64,
+    // How the hex (or other byte grid) pane is laid out.
+    pub shape: Shape,
+    // Rows of data shown in each pane.
+    pub rows: u16,
+}
+
+impl Panes {
+    // Which pane is at the given cell, and which byte of those shown in it,
+    // counting from the first, is drawn there. Clicking the gap between two
+    // groups picks the byte before it.
+    pub fn byte_at(&self, column: u16, row: u16) -> Option<(Pane, u64)> {
+        let contains = |area: Rect| {
+            (area.x..area.right()).contains(&column) && (area.y..area.bottom()).contains(&row)
+        };
+        let (pane, area, cell) = if contains(self.hex) {
+            let x = (column - self.hex.x) as u64;
+            let Shape { word, cell, group } = self.shape;
+            let group_cells = group / word * cell + 1;
+            let within = ((x % group_cells) / cell).min(group / word - 1);
+            (Pane::Hex, self.hex, x / group_cells * group + within * word)
+        } else {
+            let text = self.text.filter(|&text| contains(text))?;
+            (Pane::Text, text, (column - text.x) as u64 / 2)
+        };
+
+        let row = (row - area.y) as u64;
+        if cell >= self.columns || row >= self.rows as u64 {
+            return None;
+        }
+        Some((pane, row * self.columns + cell))
+    }
+}
+
+// The most bytes per row, in whole groups of `step`, for which `needed`
+// cells fit in `width`; failing that in whole words, or just one word if
+// nothing does.
+fn fit(width: u64, step: u64, word: u64, needed: impl Fn(u64) -> u64) -> u64 {
+    if needed(step) > width {
+        return if step > word {
+            fit(width, word, word, needed)
+        } else {
+            word
+        };
+    }
+    let mut columns = step;
+    while columns + step <= MAX_COLUMNS && needed(columns + step) <= width {
+        columns += step;
+    }
+    columns
+}
+
+// Lays out `area` for rows of `columns` bytes, or as many as fit if that's
+// None, with the hex pane in the given shape. Rows are always whole words.
+pub fn arrange(area: Rect, gutter_width: u16, columns: Option<u64>, shape: Shape) -> Panes {
+    let Shape { word, group, .. } = shape;
+    let width = area.width as u64;
+    let gutter_cells = gutter_width as u64 + 1;
+    let show_gutter = width >= gutter_cells + MIN_DATA_WIDTH;
+    let available = if show_gutter {
+        width - gutter_cells
+    } else {
+        width
+    };
+
+    let side_by_side = |columns| shape.width(columns) + 1 + unicode_width(columns);
+    let hex_only = |columns| shape.width(columns);
+
+    let mut columns = match columns {
+        Some(columns) => (columns - columns % word).max(word),
+        None if side_by_side(group) <= available => fit(available, group, word, side_by_side),
+        None => fit(available, group, word, hex_only),
+    };
+    let arrangement = if side_by_side(columns) <= available {
+        Arrangement::SideBySide
+    } else if hex_only(columns) <= available && area.height >= 2 * MIN_STACKED_ROWS {
+        Arrangement::Stacked
+    } else {
+        if hex_only(columns) > available {
+            columns = fit(available, group.min(columns), word, hex_only);
+        }
+        Arrangement::HexOnly
+    };
+
+    let data_x = if show_gutter {
+        area.x + gutter_cells as u16
+    } else {
+        area.x
+    };
+    let gutter = |y, height| Rect::new(area.x, y, gutter_width, height);
+    let pane = |x, y, width: u64, height| {
+        let width = width.min((area.right() - x) as u64) as u16;
+        Rect::new(x, y, width, height)
+    };
+
+    let hex_cells = shape.width(columns) + 1;
+    let (rows, hex, text, gutters) = match arrangement {
+        Arrangement::SideBySide => {
+            let hex = pane(data_x, area.y, hex_cells, area.height);
+            let text = pane(hex.right(), area.y, available, area.height);
+            (
+                area.height,
+                hex,
+                Some(text),
+                vec![gutter(area.y, area.height)],
+            )
+        }
+        Arrangement::Stacked => {
+            let rows = area.height / 2;
+            let hex = pane(data_x, area.y, hex_cells, rows);
+            let text = pane(data_x, area.y + rows, available, rows);
+            let gutters = vec![gutter(area.y, rows), gutter(area.y + rows, rows)];
+            (rows, hex, Some(text), gutters)
+        }
+        Arrangement::HexOnly => {
+            let hex = pane(data_x, area.y, available, area.height);
+            (area.height, hex, None, vec![gutter(area.y, area.height)])
+        }
+    };
+
+    Panes {
+        arrangement,
+        gutters: if show_gutter { gutters } else { vec![] },
+        hex,
+        text,
+        columns,
+        shape,
+        rows,
+    }
+}
+
+#[cfg(test)]
+mod layout_tests {
+    use super::*;
+    use crate::display::bit_shape;
+
+    fn hex(group: u64) -> Shape {
+        Shape {
+            word: 1,
+            cell: 2,
+            group,
+        }
+    }
+
+    #[test]
+    fn test_side_by_side() {
+        // 16 columns in groups of 2 need 39 cells of hex, a gap, and 32 of
+        // text; plus a gutter of 6 and its gap.
+        let panes = arrange(Rect::new(0, 1, 80, 20), 6, Some(16), hex(2));
+        assert_eq!(panes.arrangement, Arrangement::SideBySide);
+        assert_eq!(panes.gutters, vec![Rect::new(0, 1, 6, 20)]);
+        assert_eq!(panes.hex, Rect::new(7, 1, 40, 20));
+        assert_eq!(panes.text, Some(Rect::new(47, 1, 33, 20)));
+        assert_eq!((panes.columns, panes.rows), (16, 20));
+
+        // Auto fits as many groups as there's room for.
+        let panes = arrange(Rect::new(0, 0, 120, 20), 6, None, hex(2));
+        assert_eq!(panes.columns, 24);
+        let panes = arrange(Rect::new(0, 0, 120, 20), 6, None, hex(8));
+        assert_eq!(panes.columns, 24);
+        let panes = arrange(Rect::new(0, 0, 120, 20), 6, None, hex(1));
+        assert_eq!(panes.columns, 22);
+    }
+
+    #[test]
+    fn test_degrading() {
+        // Too narrow for both side by side, so stack them.
+        let panes = arrange(Rect::new(0, 0, 60, 20), 6, Some(16), hex(2));
+        assert_eq!(panes.arrangement, Arrangement::Stacked);
+        assert_eq!(panes.rows, 10);
+        assert_eq!(panes.hex, Rect::new(7, 0, 40, 10));
+        assert_eq!(panes.text, Some(Rect::new(7, 10, 53, 10)));
+        assert_eq!(panes.gutters.len(), 2);
+
+        // Too short to stack them, so hide the text.
+        let panes = arrange(Rect::new(0, 0, 60, 6), 6, Some(16), hex(2));
+        assert_eq!(panes.arrangement, Arrangement::HexOnly);
+        assert_eq!((panes.text, panes.rows), (None, 6));
+
+        // Too narrow for the hex of a row, so show fewer columns.
+        let panes = arrange(Rect::new(0, 0, 30, 6), 6, Some(16), hex(2));
+        assert_eq!(
+            (panes.arrangement, panes.columns),
+            (Arrangement::HexOnly, 8)
+        );
+
+        // Too narrow for the gutter as well.
+        let panes = arrange(Rect::new(0, 0, 12, 6), 6, Some(16), hex(2));
+        assert!(panes.gutters.is_empty());
+        assert_eq!((panes.hex.x, panes.columns), (0, 4));
+
+        // Not even a byte fits, but we still show something.
+        let panes = arrange(Rect::new(0, 0, 1, 1), 6, None, hex(8));
+        assert_eq!((panes.columns, panes.hex.width), (1, 1));
+    }
+
+    #[test]
+    fn test_byte_at() {
+        let panes = arrange(Rect::new(0, 1, 80, 20), 6, Some(16), hex(2));
+        // The gutter and title bar.
+        assert_eq!(panes.byte_at(3, 5), None);
+        assert_eq!(panes.byte_at(10, 0), None);
+
+        // "0001 0203 ..." starting at column 7.
+        assert_eq!(panes.byte_at(7, 1), Some((Pane::Hex, 0)));
+        assert_eq!(panes.byte_at(10, 1), Some((Pane::Hex, 1)));
+        assert_eq!(panes.byte_at(11, 1), Some((Pane::Hex, 1)));
+        assert_eq!(panes.byte_at(12, 2), Some((Pane::Hex, 18)));
+        assert_eq!(panes.byte_at(45, 1), Some((Pane::Hex, 15)));
+        // The gap after the last group.
+        assert_eq!(panes.byte_at(46, 1), Some((Pane::Hex, 15)));
+
+        // Two cells per byte in the text pane.
+        assert_eq!(panes.byte_at(47, 1), Some((Pane::Text, 0)));
+        assert_eq!(panes.byte_at(50, 3), Some((Pane::Text, 33)));
+        assert_eq!(panes.byte_at(79, 1), None);
+
+        // Stacked, the text's rows start from the same byte as the hex's.
+        let panes = arrange(Rect::new(0, 0, 60, 20), 6, Some(16), hex(2));
+        assert_eq!(panes.byte_at(7, 9), Some((Pane::Hex, 144)));
+        assert_eq!(panes.byte_at(9, 10), Some((Pane::Text, 1)));
+
+        // Eight cells per byte in the bit pane.
+        let panes = arrange(Rect::new(0, 0, 120, 20), 6, Some(4), bit_shape(2));
+        assert_eq!(panes.byte_at(7 + 15, 0), Some((Pane::Hex, 1)));
+        assert_eq!(panes.byte_at(7 + 16, 0), Some((Pane::Hex, 1)));
+        assert_eq!(panes.byte_at(7 + 17, 0), Some((Pane::Hex, 2)));
+
+        // Clicking anywhere in a word picks its first byte; these are 16-bit
+        // decimal.
+        let shape = Shape {
+            word: 2,
+            cell: 5,
+            group: 2,
+        };
+        let panes = arrange(Rect::new(0, 0, 120, 20), 6, Some(8), shape);
+        assert_eq!(panes.byte_at(7 + 4, 0), Some((Pane::Hex, 0)));
+        assert_eq!(panes.byte_at(7 + 6, 0), Some((Pane::Hex, 2)));
+        assert_eq!(panes.byte_at(7 + 22, 0), Some((Pane::Hex, 6)));
+    }
+
+    #[test]
+    fn test_whole_words() {
+        let shape = Shape {
+            word: 4,
+            cell: 8,
+            group: 4,
+        };
+        let panes = arrange(Rect::new(0, 0, 120, 20), 6, Some(10), shape);
+        assert_eq!(panes.columns, 8);
+        let panes = arrange(Rect::new(0, 0, 120, 20), 6, Some(2), shape);
+        assert_eq!(panes.columns, 4);
+        let panes = arrange(Rect::new(0, 0, 20, 20), 6, None, shape);
+        assert_eq!(panes.columns, 4);
+    }
+}
@@ -1,19 +1,66 @@
+pub mod annotation;
+pub mod command;
+pub mod config;
+pub mod copy;
+pub mod cstruct;
 pub mod cursor;
+pub mod decode;
+pub mod disasm;
 pub mod display;
+pub mod encoding;
+pub mod entropy;
+pub mod expr;
+pub mod formats;
+pub mod gutter;
+pub mod image;
+pub mod keymap;
+pub mod layout;
+pub mod magic;
+pub mod navigator;
+pub mod pointer;
+pub mod prompt;
+pub mod scan;
 pub mod source;
+pub mod table;
 pub mod terminal;
 
-use crate::cursor::{Cursor, CursorStack};
-use crate::display::{HexDisplay, UnicodeDisplay};
+use crate::annotation::Annotations;
+use crate::command::{Arg, Call, Command, Value};
+use crate::config::Config;
+use crate::copy::CopyFormat;
+use crate::cursor::{BitCursor, Cursor, CursorStack, Selection, SelectionMode};
+use crate::decode::{decode_bits, decode_integer, BitOrder, Endianness};
+use crate::disasm::{Architecture, Disassembly, DisassemblyView, Line};
+use crate::display::{
+    bit_shape, string_at, BitDisplay, ByteGridDisplay, Coloring, UnicodeDisplay, DEFAULT_COLUMNS,
+};
+use crate::entropy::{EntropyEvent, EntropyMap, EntropyView, Shading};
+use crate::expr::Context;
+use crate::formats::Analysis;
+use crate::gutter::Gutter;
+use crate::image::{Image, ImageView, PixelFormat, MAX_WIDTH};
+use crate::keymap::{Key, Keymap};
+use crate::layout::{Panes, MAX_COLUMNS};
+use crate::magic::Magic;
+use crate::navigator::{Navigator, NavigatorEntry, NavigatorEvent};
+use crate::pointer::PointerMode;
+use crate::prompt::{Prompt, PromptEvent};
+use crate::scan::{Hit, Scan};
+use crate::table::{Element, Table, TableView};
 use crate::terminal::color;
-use crossterm::event::{poll, read, Event, KeyCode, KeyEvent, KeyModifiers};
-use display::COLUMNS;
+use crossterm::event::{
+    poll, read, Event, KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind,
+};
 use itertools::Itertools;
 use log::debug;
 use nix::{sys::signal, unistd::getpid};
 use source::{DataSource, Slice};
 use std::{
-    io,
+    collections::{BTreeMap, HashMap},
+    env, fs,
+    io::{self, Write},
+    ops::Range,
+    path::{Path, PathBuf},
     sync::{atomic::AtomicBool, Arc, Mutex},
     time::Duration,
 };
@@ -22,7 +69,7 @@ use tokio::time::{sleep_until, Instant};
 use tui::layout::Rect;
 use tui::style::Modifier;
 use tui::text::{Span, Spans};
-use tui::widgets::Paragraph;
+use tui::widgets::{Paragraph, Widget};
 use tui::{
     backend::Backend,
     layout::{Alignment, Constraint, Direction, Layout},
@@ -31,15 +78,149 @@ use tui::{
     Frame, Terminal,
 };
 
+// How much data to give the magic rules when identifying what's at the
+// cursor; enough for, e.g., an ISO 9660 header at 32K.
+const MAGIC_WINDOW: u64 = 0x10000;
+
+// Where user configuration lives: $XDG_CONFIG_HOME/heks, falling back to
+// ~/.config/heks.
+pub fn config_dir() -> Option<PathBuf> {
+    let base = match env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => home::home_dir()?.join(".config"),
+    };
+    Some(base.join("heks"))
+}
+
+// A message for the status line, shown until the next key press.
+enum Message {
+    Info(String),
+    Error(String),
+}
+
+// What the text typed at the prompt is for.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+enum PromptKind {
+    Command, // `:`
+    Goto,    // `g`, an offset expression
+}
+
+// What the location of the cursor is shown as.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum AddressMode {
+    Offset,
+    Virtual, // as mapped by the file format, e.g. ELF segments
+}
+
+// How many bytes to show per row.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Columns {
+    Fixed(u64),
+    Auto, // as many as fit
+}
+
+const COLUMN_PRESETS: &[u64] = &[8, 16, 32, 48, 64];
+const GROUP_SIZES: &[u64] = &[1, 2, 4, 8];
+const WORD_SIZES: &[u64] = &[1, 2, 4, 8];
+// Elements of a table further apart than this are likely a typo.
+const MAX_STRIDE: u64 = 0x10000;
+
+// How far one notch of the mouse wheel scrolls.
+const SCROLL_ROWS: u64 = 3;
+
+// The most `y` copies, well beyond what's useful to paste anywhere.
+const MAX_COPY: u64 = 1 << 24;
+
+// Two clicks on the same cell within this long make a double-click.
+const DOUBLE_CLICK: Duration = Duration::from_millis(400);
+
+// How the bytes under the cursor are read as a number in the info bar.
+#[derive(Clone, Copy)]
+enum Reading {
+    Bytes(Endianness),
+    Bits(BitCursor, BitOrder),
+}
+
+// What the footer shows.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Footer {
+    Rainbow,
+    Minimap(Shading), // of the whole file, marking the cursor
+}
+
 pub struct App {
     source: Box<dyn DataSource>,
-    hex_display: HexDisplay,
+    grid_display: ByteGridDisplay,
     unicode_display: UnicodeDisplay,
+    bit_display: BitDisplay,
     cursor_stack: CursorStack,
     display_height: u16, // Number of rows in the content displays
+    columns: Columns,
+    display_columns: u64, // Number of bytes per row, as last shown
     last_key: Option<KeyEvent>,
+    // What keys do, and the keys of a binding typed so far.
+    keymap: Keymap,
+    keys: Vec<Key>,
+    // The count typed in front of a binding, as in `5j`.
+    count: Option<u64>,
+    // Set in visual mode, where moving the cursor extends the selection.
+    selection: Option<Selection>,
+    // Set once a key asks to quit.
+    quitting: bool,
+    endianness: Endianness,
+    annotations: Annotations,
+    prompt: Option<Prompt>,
+    prompt_kind: PromptKind,
+    // What's been entered at each kind of prompt, oldest first.
+    history: HashMap<PromptKind, Vec<String>>,
+    message: Option<Message>,
+    analysis: Option<Analysis>,
+    address_mode: AddressMode,
+    // What values under the cursor are taken to point relative to.
+    pointer_mode: PointerMode,
+    // How `y` writes out the bytes under the cursor.
+    copy_format: CopyFormat,
+    navigator: Option<Navigator>,
+    magic: Magic,
+    file_type: Option<String>,
+    scan: Option<Scan>,
+    scan_status: Option<ScanStatus>,
+    footer: Footer,
+    footer_area: Rect,
+    entropy: Option<EntropyMap>,
+    entropy_computed: usize,
+    entropy_view: Option<EntropyView>,
+    coloring: Coloring,
+    gutter: Gutter,
+    marks: BTreeMap<char, u64>,
+    // Offsets in the gutter are shown relative to this mark.
+    relative_mark: Option<char>,
+    // The first key of a two-key command, such as `m` to set a mark.
+    pending: Option<char>,
+    // Where the panes were last drawn, and the bytes shown in them, for
+    // working out what the mouse is pointing at.
+    panes: Option<Panes>,
+    view: Range<u64>,
+    // Where a drag with the mouse started, and when and where the last click
+    // was.
+    drag: Option<u64>,
+    last_click: Option<(Instant, u16, u16)>,
+    // Whether the hex pane shows bits instead, and the cursor within them if
+    // there is one, which the byte cursor then follows.
+    bit_view: bool,
+    bit_cursor: Option<BitCursor>,
+    bit_order: BitOrder,
+    // Shown instead of the panes, if one's been opened.
+    table: Option<Table>,
+    image: Option<Image>,
+    disassembly: Option<Disassembly>,
+    // The instructions last listed, to step between and follow branches from.
+    listing: Vec<Line>,
 }
 
+// Percent done, hits found and whether it has finished, as last shown.
+type ScanStatus = (u64, usize, bool);
+
 impl App {
     pub fn new<B: Backend>(
         terminal: &mut Terminal<B>,
@@ -51,7 +232,8 @@ impl App {
             .bg(color(32, 32, 32))
             .fg(color(192, 192, 192));
 
-        let hex_display = HexDisplay::default().style(style_hex);
+        let grid_display = ByteGridDisplay::default().style(style_hex);
+        let bit_display = BitDisplay::new(style_hex);
 
         let style_unicode = Style::default()
             .bg(color(64, 64, 64))
@@ -59,16 +241,130 @@ impl App {
 
         let unicode_display = UnicodeDisplay::default().style(style_unicode);
 
+        let mut source = source;
+        let magic = Magic::builtin();
+        let file_type = magic.identify(source.fetch(0, u64::MAX).data);
+        let analysis = formats::analyze(source.fetch(0, u64::MAX).data);
+        let mut annotations = Annotations::default();
+        let mut message = None;
+        if let Some(analysis) = &analysis {
+            annotations.set_group("format", analysis.annotations.clone());
+            message = Some(Message::Info(analysis.description.clone()));
+        }
+
         Ok(App {
             source,
-            hex_display,
+            grid_display,
             unicode_display,
+            bit_display,
             cursor_stack: CursorStack::new(Cursor::new(0, 1)),
             display_height: 0,
+            columns: Columns::Fixed(DEFAULT_COLUMNS),
+            display_columns: DEFAULT_COLUMNS,
             last_key: None,
+            keymap: Keymap::default(),
+            keys: vec![],
+            count: None,
+            selection: None,
+            quitting: false,
+            endianness: Endianness::Little,
+            annotations,
+            prompt: None,
+            prompt_kind: PromptKind::Command,
+            history: HashMap::new(),
+            message,
+            analysis,
+            address_mode: AddressMode::Offset,
+            pointer_mode: PointerMode::Offset,
+            copy_format: CopyFormat::Hex,
+            navigator: None,
+            magic,
+            file_type,
+            scan: None,
+            scan_status: None,
+            footer: Footer::Rainbow,
+            footer_area: Rect::default(),
+            entropy: None,
+            entropy_computed: 0,
+            entropy_view: None,
+            coloring: Coloring::None,
+            gutter: Gutter::default(),
+            marks: BTreeMap::new(),
+            relative_mark: None,
+            pending: None,
+            panes: None,
+            view: 0..0,
+            drag: None,
+            last_click: None,
+            bit_view: false,
+            bit_cursor: None,
+            bit_order: BitOrder::default(),
+            table: None,
+            image: None,
+            disassembly: None,
+            listing: vec![],
         })
     }
 
+    // Applies the settings and key bindings from the config file at `path`,
+    // reporting the first thing wrong with it, if anything.
+    pub fn configure(&mut self, path: &Path, config: Result<Config, String>) {
+        let mut errors = vec![];
+        match config {
+            Ok(config) => {
+                for (name, value) in config.settings() {
+                    let line = format!("{} {}", name, shell_words::quote(&value));
+                    if let Err(error) = self.execute(&line) {
+                        errors.push(format!("{}: {}", name, error));
+                    }
+                }
+                for (keys, action) in &config.keys {
+                    if let Err(error) = self.keymap.bind(keys, action) {
+                        errors.push(format!("[keys] {}: {}", keys, error));
+                    }
+                }
+            }
+            Err(error) => errors.push(error),
+        }
+
+        if let Some(error) = errors.first() {
+            let more = match errors.len() {
+                1 => String::new(),
+                count => format!(" (and {} more)", count - 1),
+            };
+            self.message = Some(Message::Error(format!(
+                "{}: {}{}",
+                path.display(),
+                error,
+                more
+            )));
+        }
+    }
+
+    // Adds the rules in the magic file at `path`, which take precedence over
+    // the built-in ones.
+    pub fn load_magic(&mut self, path: &Path) {
+        let result = fs::read_to_string(path)
+            .map_err(|error| error.to_string())
+            .and_then(|source| Magic::parse(&source));
+        match result {
+            Ok(magic) => {
+                if magic.skipped > 0 {
+                    self.message = Some(Message::Info(format!(
+                        "{}: skipped {} unsupported rules",
+                        path.display(),
+                        magic.skipped
+                    )));
+                }
+                self.magic.extend(magic);
+                self.file_type = self.magic.identify(self.source.fetch(0, u64::MAX).data);
+            }
+            Err(error) => {
+                self.message = Some(Message::Error(format!("{}: {}", path.display(), error)));
+            }
+        }
+    }
+
     fn draw<B: Backend>(&mut self, terminal: &mut Terminal<B>) -> Result<(), io::Error> {
         terminal.draw(|f| self.paint(f))?;
 
@@ -80,7 +376,7 @@ impl App {
             .bg(color(0, 0, 192))
             .fg(color(224, 224, 224));
 
-        let (area_header, area_display, area_info, area_footer) = Layout::default()
+        let (area_header, area_display, area_info, area_status, area_footer) = Layout::default()
             .direction(Direction::Vertical)
             .constraints(
                 [
@@ -88,6 +384,7 @@ impl App {
                     Constraint::Min(1),
                     Constraint::Length(1),
                     Constraint::Length(1),
+                    Constraint::Length(1),
                 ]
                 .as_ref(),
             )
@@ -95,57 +392,354 @@ impl App {
             .into_iter()
             .collect_tuple()
             .unwrap();
-        self.display_height = area_display.height;
 
         let header = Block::default()
             .style(style_frame)
-            .title(format!("{} - {}", self.source.name(), "𝓱𝓮𝓴𝓼"))
+            .title(match &self.file_type {
+                Some(file_type) => format!(
+                    "{} ({}) [{}] - {}",
+                    self.source.name(),
+                    file_type,
+                    self.unicode_display.encoding.name(),
+                    "𝓱𝓮𝓴𝓼"
+                ),
+                None => format!(
+                    "{} [{}] - {}",
+                    self.source.name(),
+                    self.unicode_display.encoding.name(),
+                    "𝓱𝓮𝓴𝓼"
+                ),
+            })
             .title_alignment(Alignment::Center);
         f.render_widget(header, area_header);
 
-        let slice = App::fetch_and_clamp_cursor(
-            &mut self.cursor_stack,
-            self.source.as_mut(),
-            area_display.height,
-            COLUMNS as u16,
-        );
+        let data = match (self.table, self.image, self.disassembly) {
+            (Some(table), _, _) => self.paint_table(f, area_display, table),
+            (None, Some(image), _) => self.paint_image(f, area_display, image),
+            (None, None, Some(disassembly)) => self.paint_disassembly(f, area_display, disassembly),
+            (None, None, None) => self.paint_bytes(f, area_display),
+        };
 
-        App::paint_display(
+        let cursor = self.cursor();
+        let location = match self.bit_cursor {
+            Some(bits) => (
+                "bit".to_string(),
+                format!("{:#x}.{}", bits.start() / 8, bits.start() % 8),
+            ),
+            None => App::cursor_location(self.analysis.as_ref(), self.address_mode, cursor),
+        };
+        App::paint_info(
             f,
-            area_display,
-            self.hex_display.clone(),
-            self.unicode_display.clone(),
-            self.cursor_stack.top(),
-            slice,
+            area_info,
+            &data,
+            match self.bit_cursor {
+                Some(bits) => Reading::Bits(bits, self.bit_order),
+                None => Reading::Bytes(self.endianness),
+            },
+            location,
+            App::cursor_context(self.analysis.as_ref(), cursor),
         );
 
-        App::paint_info(f, area_info, self.cursor_stack.top(), slice);
+        self.paint_status(f, area_status);
 
-        let location = self.source.fraction(self.cursor_stack.top().start);
+        let location = self.source.fraction(self.cursor().start);
 
-        let rainbow = App::rainbow(location, area_footer.width as usize);
+        let width = area_footer.width as usize;
+        let spans = match (self.footer, &self.entropy) {
+            (Footer::Minimap(shading), Some(map)) => map.minimap(shading, location, width),
+            _ => App::rainbow(location, width),
+        };
         let footer = Block::default()
             .style(style_frame)
-            .title(rainbow)
+            .title(spans)
             .title_alignment(Alignment::Center);
         f.render_widget(footer, area_footer);
+        self.footer_area = area_footer;
+
+        if let Some(view) = &mut self.entropy_view {
+            view.set_area(area_display);
+            f.render_widget(&*view, area_display);
+        }
+
+        if let Some(navigator) = &self.navigator {
+            f.render_widget(navigator, App::popup_area(area_display));
+        }
+    }
+
+    fn popup_area(area: Rect) -> Rect {
+        let width = (area.width * 2 / 3).max(area.width.min(40));
+        let height = (area.height * 2 / 3).max(area.height.min(8));
+        Rect {
+            x: area.x + (area.width - width) / 2,
+            y: area.y + (area.height - height) / 2,
+            width,
+            height,
+        }
     }
 
+    // Paints the gutter, hex (or other) and text panes, returning the bytes
+    // under the cursor.
+    fn paint_bytes<B: Backend>(&mut self, f: &mut Frame<B>, area: Rect) -> Vec<u8> {
+        let len = self.source.fetch(0, u64::MAX).data.len() as u64;
+        let template = Gutter {
+            len,
+            relative_to: self
+                .relative_mark
+                .and_then(|name| self.marks.get(&name).copied()),
+            ..self.gutter.clone()
+        };
+        let columns = match self.columns {
+            Columns::Fixed(columns) => Some(columns),
+            Columns::Auto => None,
+        };
+        let panes = layout::arrange(
+            area,
+            template.width(),
+            columns,
+            if self.bit_view {
+                bit_shape(self.grid_display.group)
+            } else {
+                self.grid_display.shape()
+            },
+        );
+        let columns = panes.columns;
+        self.display_height = panes.rows;
+        self.display_columns = columns;
+        self.grid_display.columns = columns;
+        self.unicode_display.columns = columns;
+
+        self.sync_bit_cursor();
+
+        let slice = App::fetch_and_clamp_cursor(
+            &mut self.cursor_stack,
+            self.source.as_mut(),
+            panes.rows.max(1),
+            columns,
+            self.selection.map(|selection| selection.head),
+        );
+        // Not `self.cursor()`, as `slice` borrows the source.
+        let cursor = match self.selection {
+            Some(selection) => selection.cursor(columns),
+            None => self.cursor_stack.top(),
+        };
+
+        let highlights = self
+            .annotations
+            .highlights(slice.location_start..slice.location_end);
+        self.grid_display.highlights = highlights.clone();
+        self.unicode_display.highlights = highlights;
+        self.grid_display.coloring = self.coloring;
+        self.grid_display.endianness = self.endianness;
+        self.unicode_display.coloring = self.coloring;
+        self.bit_display.highlights = self.grid_display.highlights.clone();
+        self.bit_display.coloring = self.coloring;
+        self.bit_display.columns = columns;
+        self.bit_display.group = self.grid_display.group;
+        self.bit_display.bit_cursor = self.bit_cursor;
+        self.bit_display.order = self.bit_order;
+
+        let view = slice.location_start..slice.location_end;
+        let gutter = Gutter {
+            marks: self
+                .marks
+                .iter()
+                .map(|(&name, &offset)| (name, offset))
+                .filter(|(_, offset)| view.contains(offset))
+                .collect(),
+            annotations: self
+                .annotations
+                .iter()
+                .filter(|annotation| view.contains(&annotation.range.start))
+                .map(|annotation| (annotation.range.start, annotation.error.is_some()))
+                .collect(),
+            first: view.start,
+            columns,
+            rows: (view.end - view.start).div_ceil(columns),
+            cursor: cursor.start(),
+            ..template
+        };
+
+        let unicode_display = self.unicode_display.clone();
+        if self.bit_view {
+            let mut bit_display = self.bit_display.clone();
+            bit_display.cursor = cursor;
+            bit_display.set_data(slice.data.to_vec(), slice.location_start);
+            App::paint_display(
+                f,
+                &panes,
+                gutter,
+                bit_display,
+                unicode_display,
+                cursor,
+                slice,
+            );
+        } else {
+            let mut grid_display = self.grid_display.clone();
+            grid_display.cursor = cursor;
+            grid_display.set_data(slice.data.to_vec(), slice.location_start);
+            App::paint_display(
+                f,
+                &panes,
+                gutter,
+                grid_display,
+                unicode_display,
+                cursor,
+                slice,
+            );
+        }
+        self.panes = Some(panes);
+        self.view = view;
+
+        slice.fetch(cursor)
+    }
+
+    // Paints the table of array elements instead of the panes, with the
+    // cursor on one element and moving a stride at a time.
+    fn paint_table<B: Backend>(&mut self, f: &mut Frame<B>, area: Rect, table: Table) -> Vec<u8> {
+        let len = self.source.fetch(0, u64::MAX).data.len() as u64;
+        let size = table.element.size();
+        let cursor = self.cursor_stack.top().start().min(len.saturating_sub(1));
+        let element = table.element_at(cursor);
+        let cursor = Cursor::new(element, element + size);
+        *self.cursor_stack.top_mut() = cursor;
+
+        let rows = TableView::rows(area.height);
+        self.display_columns = table.stride;
+        self.display_height = rows;
+        self.panes = None;
+
+        // Enough for the rows, and the plot below them.
+        let first = table.first(element, rows);
+        let count = rows.max(area.width) as u64;
+        let end = first.saturating_add(count.saturating_mul(table.stride)) + size;
+        let slice = self.source.fetch(first, end);
+        let view = TableView {
+            table,
+            first,
+            data: slice.data.to_vec(),
+            cursor: element,
+            endianness: self.endianness,
+        };
+        f.render_widget(view, area);
+
+        slice.fetch(cursor)
+    }
+
+    // Paints the bytes as pixels instead of the panes, two rows to a line,
+    // with the cursor's row in the middle and moving a row at a time.
+    fn paint_image<B: Backend>(&mut self, f: &mut Frame<B>, area: Rect, image: Image) -> Vec<u8> {
+        let len = self.source.fetch(0, u64::MAX).data.len() as u64;
+        let cursor = self.cursor_stack.top();
+        if cursor.start() >= len {
+            *self.cursor_stack.top_mut() = Cursor::new(len.saturating_sub(1), len);
+        }
+        let cursor = self.cursor_stack.top();
+
+        let rows = 2 * area.height as u64;
+        self.display_columns = image.stride();
+        self.display_height = area.height;
+        self.panes = None;
+
+        let first = image.first(cursor.start(), rows);
+        let end = first.saturating_add(rows.saturating_mul(image.stride()));
+        let slice = self.source.fetch(first, end);
+        let view = ImageView {
+            image,
+            first,
+            data: slice.data.to_vec(),
+            cursor: cursor.start(),
+            endianness: self.endianness,
+        };
+        f.render_widget(view, area);
+
+        slice.fetch(cursor)
+    }
+
+    // Paints the code around the cursor as instructions instead of the panes,
+    // with the cursor on the whole of the instruction it's in.
+    fn paint_disassembly<B: Backend>(
+        &mut self,
+        f: &mut Frame<B>,
+        area: Rect,
+        disassembly: Disassembly,
+    ) -> Vec<u8> {
+        let len = self.source.fetch(0, u64::MAX).data.len() as u64;
+        let cursor = self.cursor_stack.top().start().min(len.saturating_sub(1));
+        let rows = DisassemblyView::rows(area.height) as u64;
+        self.display_height = area.height;
+        self.panes = None;
+
+        let window = disassembly.window(cursor, rows);
+        let data = self.source.read(window.start, window.end).to_vec();
+        let analysis = self.analysis.as_ref();
+        let lines = disassembly.lines(&data, window.start, cursor, rows, |offset| {
+            analysis
+                .and_then(|analysis| analysis.offset_to_address(offset))
+                .unwrap_or(offset)
+        });
+
+        let cursor = match lines.iter().find(|line| line.range().contains(&cursor)) {
+            Some(line) => Cursor::new(line.offset, line.range().end),
+            None => Cursor::new(cursor, cursor + 1),
+        };
+        *self.cursor_stack.top_mut() = cursor;
+        // Paging and scrolling go by the typical instruction length.
+        self.display_columns = match (lines.first(), lines.last()) {
+            (Some(first), Some(last)) => {
+                ((last.range().end - first.offset) / lines.len() as u64).max(1)
+            }
+            _ => disassembly.architecture.alignment(),
+        };
+
+        let view = DisassemblyView {
+            disassembly,
+            lines: lines
+                .iter()
+                .map(|line| {
+                    let label = line.instruction.target.and_then(|target| {
+                        let offset = App::code_offset(analysis, target, len)?;
+                        App::symbol_label(analysis?, offset)
+                    });
+                    (line.clone(), label)
+                })
+                .collect(),
+            cursor: cursor.start(),
+        };
+        f.render_widget(view, area);
+        self.listing = lines;
+
+        self.source.read(cursor.start(), cursor.end()).to_vec()
+    }
+
+    // The file offset of an address the code refers to. Files without a
+    // memory layout are taken to be loaded at zero.
+    fn code_offset(analysis: Option<&Analysis>, address: u64, len: u64) -> Option<u64> {
+        match analysis {
+            Some(analysis) if !analysis.mappings.is_empty() => analysis.address_to_offset(address),
+            _ => Some(address).filter(|&offset| offset < len),
+        }
+    }
+
+    // `focus` is where to keep in view if not the start of the cursor, which
+    // is then left as it is, such as the head of a selection.
     fn fetch_and_clamp_cursor<'a>(
         cursor_stack: &mut CursorStack,
         source: &'a mut dyn DataSource,
         rows: u16,
-        columns: u16,
+        columns: u64,
+        focus: Option<u64>,
     ) -> Slice<'a> {
         let ui_rows = rows as u64;
-        let ui_columns = columns as u64;
+        let ui_columns = columns;
 
         // We'll clamp the cursor to within the slice we managed to fetch from
         // the source further down, but for now let's not make any assumptions
         // about it. For example, it may have been set to u64::MAX to skip to
         // the end.
         let mut cursor = cursor_stack.top();
-        let pos = cursor.start().min(u64::MAX - ui_columns * ui_rows);
+        let pos = focus
+            .unwrap_or(cursor.start())
+            .min(u64::MAX - ui_columns * ui_rows);
         let column_zero_pos: u64 = pos.saturating_sub(pos % ui_columns);
 
         let pos_row = column_zero_pos / ui_columns;
@@ -155,61 +749,61 @@ impl App {
         let ui_view_end = ui_first_pos + ui_rows * ui_columns;
 
         let slice = source.fetch(ui_first_pos, ui_view_end);
-        let slice = slice.align_up(COLUMNS as u64);
+        let slice = slice.align_up(ui_columns);
 
-        cursor.clamp(slice.location_start..slice.location_end);
-        *cursor_stack.top_mut() = cursor;
+        if focus.is_none() {
+            cursor.clamp(slice.location_start..slice.location_end);
+            *cursor_stack.top_mut() = cursor;
+        }
 
         slice
     }
 
+    // `grid` is the hex or bit display, with its data already set.
     fn paint_display<B: Backend>(
         f: &mut Frame<B>,
-        area: Rect,
-        mut hex_display: HexDisplay,
+        panes: &Panes,
+        gutter: Gutter,
+        grid: impl Widget,
         mut unicode_display: UnicodeDisplay,
         cursor: Cursor,
         slice: Slice,
     ) {
-        let (hex_area, unicode_area) = Layout::default()
-            .direction(Direction::Horizontal)
-            .constraints([Constraint::Percentage(55), Constraint::Percentage(45)])
-            .split(area)
-            .into_iter()
-            .collect_tuple()
-            .unwrap();
-
-        hex_display.cursor = cursor;
-        hex_display.set_data(slice.data.to_vec(), slice.location_start);
+        for &area in &panes.gutters {
+            f.render_widget(gutter.clone(), area);
+        }
 
-        unicode_display.cursor = cursor;
-        unicode_display.set_data(slice.data.to_vec(), slice.location_start);
+        f.render_widget(grid, panes.hex);
 
-        f.render_widget(hex_display, hex_area);
-        f.render_widget(unicode_display, unicode_area);
+        if let Some(area) = panes.text {
+            unicode_display.cursor = cursor;
+            unicode_display.set_data(slice.data.to_vec(), slice.location_start);
+            f.render_widget(unicode_display, area);
+        }
     }
 
-    fn paint_info<B: Backend>(f: &mut Frame<B>, area: Rect, cursor: Cursor, slice: Slice) {
+    // `location` is the label and value describing where the cursor is, and
+    // `context` holds any further labelled values to show after the decoded
+    // bytes.
+    fn paint_info<B: Backend>(
+        f: &mut Frame<B>,
+        area: Rect,
+        data: &[u8],
+        reading: Reading,
+        location: (String, String),
+        context: Vec<(String, String)>,
+    ) {
         // TODO: We should handle > 16 bytes being selected better than just ignoring them
-        let data = slice.fetch(cursor);
-
-        let mut data_unsigned = data.clone();
-        data_unsigned.resize(16, 0);
-
-        let mut data_signed = data;
-        data_signed.resize(
-            16,
-            // TODO this assumes little-endian
-            if data_signed.last().copied().unwrap_or(0) & 0x80 != 0 {
-                0xff
-            } else {
-                0x00
-            },
-        );
-
-        // TODO select endianness
-        let as_unsigned = u128::from_le_bytes(data_unsigned.try_into().unwrap());
-        let as_signed = i128::from_le_bytes(data_signed.try_into().unwrap());
+        let ((as_signed, as_unsigned), order) = match reading {
+            Reading::Bits(bits, order) => (
+                decode_bits(data, bits.start() % 8, bits.width(), order),
+                format!("{}b {}", bits.width(), order.label()),
+            ),
+            Reading::Bytes(endianness) => (
+                decode_integer(data, endianness),
+                endianness.label().to_string(),
+            ),
+        };
 
         let bg_spacer = color(128, 128, 255);
         let shadow = color(32, 32, 128);
@@ -228,13 +822,13 @@ impl App {
         let style_field_angle = Style::default().bg(shadow).fg(bg_field);
         let style_field_shadow = Style::default().bg(bg_spacer).fg(shadow);
 
-        let line = vec![
+        let mut line = vec![
             Span::styled(" ", style_spacer),
             // cursor
             Span::styled("▟", style_label_angle),
-            Span::styled(" cursor ", style_label),
+            Span::styled(format!(" {} ", location.0), style_label),
             Span::styled("▟", style_separator),
-            Span::styled(format!(" {:#18x} ", cursor.start), style_field),
+            Span::styled(format!(" {:>18} ", location.1), style_field),
             Span::styled("▛", style_field_angle),
             Span::styled("▛", style_field_shadow),
             Span::styled("    ", style_spacer),
@@ -252,14 +846,144 @@ impl App {
             Span::styled(format!(" {:20} ", as_unsigned), style_field),
             Span::styled("▛", style_field_angle),
             Span::styled("▛", style_field_shadow),
+            // endianness
+            Span::styled("▟", style_label_angle),
+            Span::styled(format!(" {} ", order), style_label),
+            Span::styled("▛", style_field_shadow),
         ];
 
+        for (label, value) in context {
+            line.extend([
+                Span::styled(" ", style_spacer),
+                Span::styled("▟", style_label_angle),
+                Span::styled(format!(" {} ", label), style_label),
+                Span::styled("▟", style_separator),
+                Span::styled(format!(" {} ", value), style_field),
+                Span::styled("▛", style_field_angle),
+                Span::styled("▛", style_field_shadow),
+            ]);
+        }
+
         f.render_widget(Block::default().style(style_spacer), area);
 
         let label = Paragraph::new(Spans::from(line));
         f.render_widget(label, area);
     }
 
+    fn cursor_location(
+        analysis: Option<&Analysis>,
+        address_mode: AddressMode,
+        cursor: Cursor,
+    ) -> (String, String) {
+        match (address_mode, analysis) {
+            (AddressMode::Virtual, Some(analysis)) => (
+                "vaddr".to_string(),
+                analysis
+                    .offset_to_address(cursor.start())
+                    .map_or("unmapped".to_string(), |address| format!("{:#x}", address)),
+            ),
+            _ => ("cursor".to_string(), format!("{:#x}", cursor.start())),
+        }
+    }
+
+    // Describes which section and symbol the cursor is in, if we know.
+    fn cursor_context(analysis: Option<&Analysis>, cursor: Cursor) -> Vec<(String, String)> {
+        let Some(analysis) = analysis else {
+            return vec![];
+        };
+        let location = cursor.start();
+        let mut context = vec![];
+
+        if let Some(section) = analysis.section_at(location) {
+            context.push(("§".to_string(), section.name.clone()));
+        }
+        if let Some(name) = App::symbol_label(analysis, location) {
+            context.push(("ƒ".to_string(), name));
+        }
+
+        context
+    }
+
+    // The symbol `offset` is in, and how far into it, e.g. `main+0x10`.
+    fn symbol_label(analysis: &Analysis, offset: u64) -> Option<String> {
+        let symbol = analysis.symbol_at(offset)?;
+        let delta = offset - symbol.offset;
+        Some(if delta == 0 {
+            symbol.name.clone()
+        } else {
+            format!("{}+{:#x}", symbol.name, delta)
+        })
+    }
+
+    // The status line shows, in order of preference, the command line, the
+    // message left by the last command, or the annotation under the cursor.
+    fn paint_status<B: Backend>(&mut self, f: &mut Frame<B>, area: Rect) {
+        let style = Style::default()
+            .bg(color(16, 16, 48))
+            .fg(color(224, 224, 224));
+        let style_error = style.fg(color(255, 96, 96)).add_modifier(Modifier::BOLD);
+
+        let line = if let Some(prompt) = &self.prompt {
+            prompt.spans(style)
+        } else if let Some(message) = &self.message {
+            match message {
+                Message::Info(text) => Spans::from(Span::styled(text.clone(), style)),
+                Message::Error(text) => Spans::from(Span::styled(text.clone(), style_error)),
+            }
+        } else if self.selection.is_some() || self.count.is_some() || !self.keys.is_empty() {
+            let cursor = self.cursor();
+            let mode = match (self.selection, cursor.block()) {
+                (Some(_), Some(block)) => {
+                    let rows = (cursor.end() - cursor.start()).div_ceil(block.columns);
+                    format!("visual block: {}×{} ", block.right - block.left, rows)
+                }
+                (Some(_), None) => format!("visual: {} bytes ", cursor.end() - cursor.start()),
+                (None, _) => String::new(),
+            };
+            let count = self.count.map_or(String::new(), |count| count.to_string());
+            Spans::from(Span::styled(
+                format!("{}{}{}", mode, count, keymap::display(&self.keys)),
+                style,
+            ))
+        } else {
+            let location = self.cursor().start();
+            match self.annotations.at(location).cloned() {
+                Some(annotation) => {
+                    let data = self
+                        .source
+                        .read(annotation.range.start, annotation.range.end);
+                    let text = match annotation.value(data, self.endianness) {
+                        Some(value) => format!("{} = {}", annotation.label, value),
+                        None => annotation.label.clone(),
+                    };
+                    match annotation.error {
+                        Some(error) => Spans::from(vec![
+                            Span::styled(text, style),
+                            Span::styled(format!(" ⚠ {}", error), style_error),
+                        ]),
+                        None => Spans::from(Span::styled(text, style)),
+                    }
+                }
+                None => Spans::from(""),
+            }
+        };
+
+        // Progress of a background scan goes in front of the annotation.
+        let line = match self.scan_status {
+            Some((percent, found, false)) if self.prompt.is_none() && self.message.is_none() => {
+                let mut spans = vec![Span::styled(
+                    format!("scanning {}% ({} found) ", percent, found),
+                    style.add_modifier(Modifier::ITALIC),
+                )];
+                spans.extend(line.0);
+                Spans::from(spans)
+            }
+            _ => line,
+        };
+
+        f.render_widget(Paragraph::new(line).style(style), area);
+    }
+
     fn rainbow<'a>(location: f64, width: usize) -> Spans<'a> {
         let mut result: Vec<Span> = vec![];
 
@@ -289,95 +1013,1160 @@ impl App {
         Spans::from(result)
     }
 
-    fn push_cursor_if_key_changed_else_set<F>(&mut self, key: &KeyEvent, f: F)
+    fn push_cursor_if_key_changed_else_set<F>(&mut self, key: Option<KeyEvent>, f: F)
     where
-        F: FnOnce(&mut Cursor) -> (),
+        F: FnOnce(&mut Cursor),
     {
         let mut cursor = self.cursor_stack.top();
 
         f(&mut cursor);
 
-        if self.last_key == Some(*key) {
+        if key.is_some() && self.last_key == key {
             *self.cursor_stack.top_mut() = cursor;
         } else {
             self.cursor_stack.push(cursor);
         }
     }
 
-    fn on_key(&mut self, key: KeyEvent) {
-        match (key.modifiers, key.code) {
-            (KeyModifiers::NONE, KeyCode::Char('l')) | (KeyModifiers::NONE, KeyCode::Right) => {
-                self.cursor_stack.top_mut().increment(1)
-            }
+    // True while keys should go to the app rather than being interpreted as
+    // global shortcuts (such as `q` to quit).
+    pub fn capturing_input(&self) -> bool {
+        self.prompt.is_some()
+            || self.navigator.is_some()
+            || self.entropy_view.is_some()
+            || self.pending.is_some()
+            || !self.keys.is_empty()
+            || self.count.is_some()
+    }
 
-            (KeyModifiers::NONE, KeyCode::Char('h')) | (KeyModifiers::NONE, KeyCode::Left) => {
-                self.cursor_stack.top_mut().decrement(1);
+    fn run_command(&mut self, line: &str) {
+        if let Err(error) = self.execute(line) {
+            self.message = Some(Message::Error(error));
+        }
+    }
+
+    fn execute(&mut self, line: &str) -> Result<(), String> {
+        self.dispatch(line, None, None)
+    }
+
+    // Runs a command line, once `command` has checked it's a command that
+    // exists and has as many arguments as it takes. When `key` runs it, the
+    // count typed in front of the key goes to the command if it takes one the
+    // line doesn't give. In visual mode, motions move the head of the
+    // selection.
+    fn dispatch(
+        &mut self,
+        line: &str,
+        key: Option<KeyEvent>,
+        count: Option<u64>,
+    ) -> Result<(), String> {
+        let Some((command, args)) = command::parse(line)? else {
+            return Ok(());
+        };
+        let mut args = command.values(self, &args)?;
+        if let (Some(count), Some(Arg::Count)) = (count, command.args.get(args.len())) {
+            args.push(Value::Number(count));
+        }
+        let call = Call { command, args, key };
+
+        if self.selection.is_some() && command::MOTIONS.contains(&command.name) {
+            let mut result = Ok(());
+            self.move_head(|app| result = (command.run)(app, &call));
+            return result;
+        }
+        (command.run)(self, &call)
+    }
+
+    fn select(&mut self, start: u64, end: u64) -> Result<(), String> {
+        if end <= start {
+            return Err(format!("{:#x} is not after {:#x}", end, start));
+        }
+        self.cursor_stack.push(Cursor::new(start, end));
+        Ok(())
+    }
+
+    // Shows a fixed number of bytes per row, or as many as fit.
+    fn set_columns(&mut self, columns: Option<u64>) -> Result<(), String> {
+        self.columns = match columns {
+            None => Columns::Auto,
+            Some(columns @ 1..=MAX_COLUMNS) => Columns::Fixed(columns),
+            Some(columns) => return Err(format!("invalid number of columns: {}", columns)),
+        };
+        Ok(())
+    }
+
+    fn set_group(&mut self, size: u64) -> Result<(), String> {
+        if !GROUP_SIZES.contains(&size) {
+            return Err(format!("group size must be one of {:?}", GROUP_SIZES));
+        }
+        self.grid_display.group = size;
+        Ok(())
+    }
+
+    fn help(&mut self, command: Option<&Command>) {
+        let text = match command {
+            Some(command) => format!(
+                "{}: {}",
+                command.usage().trim_start_matches("usage: "),
+                command.help
+            ),
+            None => format!(
+                "commands: {}",
+                command::COMMANDS
+                    .iter()
+                    .map(|command| command.name)
+                    .join(", ")
+            ),
+        };
+        self.message = Some(Message::Info(text));
+    }
+
+    // Shows the array of `element`s at the cursor as a table.
+    fn show_table(&mut self, element: &str, stride: Option<u64>) -> Result<(), String> {
+        let element = Element::from_name(element).ok_or(format!(
+            "element type must be one of {}",
+            Element::names().join(", ")
+        ))?;
+        if stride.is_some_and(|stride| !(1..=MAX_STRIDE).contains(&stride)) {
+            return Err(format!("invalid stride: {}", stride.unwrap_or_default()));
+        }
+        let mut table = Table::new(element, self.cursor().start());
+        table.stride = stride.unwrap_or(table.stride);
+        self.image = None;
+        self.disassembly = None;
+        self.table = Some(table);
+        Ok(())
+    }
+
+    fn toggle_table(&mut self) {
+        self.image = None;
+        self.disassembly = None;
+        self.table = match self.table {
+            Some(_) => None,
+            None => Some(Table::new(Element::U32, self.cursor().start())),
+        }
+    }
+
+    // Shows the bytes from the cursor on as pixels in `format`.
+    fn show_image(
+        &mut self,
+        format: &str,
+        width: Option<u64>,
+        stride: Option<u64>,
+    ) -> Result<(), String> {
+        let format = PixelFormat::from_name(format).ok_or(format!(
+            "pixel format must be one of {}",
+            PixelFormat::names().join(", ")
+        ))?;
+        for size in [width, stride].into_iter().flatten() {
+            if !(1..=MAX_WIDTH).contains(&size) {
+                return Err(format!("invalid size: {}", size));
             }
-            (KeyModifiers::NONE, KeyCode::Char('j')) | (KeyModifiers::NONE, KeyCode::Down) => {
-                self.cursor_stack.top_mut().increment(COLUMNS.into());
+        }
+        let mut image = Image::new(format, self.cursor().start());
+        image.width = width.unwrap_or(image.width);
+        image.stride = stride;
+        self.table = None;
+        self.disassembly = None;
+        self.image = Some(image);
+        Ok(())
+    }
+
+    fn toggle_image(&mut self) {
+        self.table = None;
+        self.disassembly = None;
+        self.image = match self.image {
+            Some(_) => None,
+            None => Some(Image::new(PixelFormat::Gray8, self.cursor().start())),
+        }
+    }
+
+    fn toggle_disassembly(&mut self) {
+        match self.disassembly {
+            Some(_) => self.disassembly = None,
+            None => self.open_disassembly(None),
+        }
+    }
+
+    // Evaluates an offset expression, which mustn't go past the end.
+    fn evaluate(&mut self, expression: &str) -> Result<u64, String> {
+        let len = self.source.fetch(0, u64::MAX).data.len() as u64;
+        let context = Context {
+            cursor: self.cursor().start(),
+            len,
+            marks: &self.marks,
+        };
+        let offset = expr::evaluate(expression, &context)?;
+        if offset > len {
+            return Err(format!("{:#x} is past the end of the file", offset));
+        }
+        Ok(offset)
+    }
+
+    fn set_mark(&mut self, name: char, location: u64) {
+        self.marks.insert(name, location);
+        self.message = Some(Message::Info(format!("mark '{}' at {:#x}", name, location)));
+    }
+
+    // Writes the bytes under the cursor to a file.
+    fn export(&mut self, path: &str) -> Result<(), String> {
+        let cursor = self.cursor();
+        let data = self.source.read(cursor.start(), cursor.end());
+        fs::write(path, data).map_err(|error| format!("{}: {}", path, error))?;
+        self.message = Some(Message::Info(format!(
+            "wrote {} bytes to {}",
+            data.len(),
+            path
+        )));
+        Ok(())
+    }
+
+    // Puts the bytes under the cursor on the clipboard as text, by way of the
+    // terminal, or in a temporary file if there's too much for that. Visual
+    // mode ends, as there's nothing more to do with the selection.
+    fn copy(&mut self) -> Result<(), String> {
+        let cursor = self.cursor();
+        if cursor.end() - cursor.start() > MAX_COPY {
+            return Err(format!("can't copy more than {} bytes", MAX_COPY));
+        }
+        let mut data = vec![];
+        for range in cursor.ranges() {
+            data.extend_from_slice(self.source.read(range.start, range.end));
+        }
+        let text = copy::format(self.copy_format, &data, cursor.start());
+        self.end_selection();
+
+        let osc52 = copy::osc52(&text);
+        let mut stdout = io::stdout();
+        let copied = osc52.len() <= copy::MAX_OSC52
+            && stdout
+                .write_all(osc52.as_bytes())
+                .and_then(|_| stdout.flush())
+                .is_ok();
+        let destination = if copied {
+            String::new()
+        } else {
+            let path = copy::write_temp(&text).map_err(|error| error.to_string())?;
+            format!(" to {}", path.display())
+        };
+        self.message = Some(Message::Info(format!(
+            "copied {} bytes as {}{}",
+            data.len(),
+            self.copy_format.name(),
+            destination
+        )));
+        Ok(())
+    }
+
+    // Lays out the struct `name` from the C header at `path` at the cursor.
+    fn overlay_struct(&mut self, path: &str, name: &str) -> Result<(), String> {
+        let source = fs::read_to_string(path).map_err(|error| format!("{}: {}", path, error))?;
+        let header =
+            cstruct::Header::parse(&source).map_err(|error| format!("{}: {}", path, error))?;
+        let record = header
+            .record(name)
+            .map_err(|error| format!("{}: {}", path, error))?;
+
+        let size = record.size;
+        let base = self.cursor().start();
+        self.annotations
+            .set_group("struct", cstruct::annotate(record, name, base));
+        self.message = Some(Message::Info(format!(
+            "{} ({} bytes) at {:#x}",
+            name, size, base
+        )));
+
+        Ok(())
+    }
+
+    fn identify_at_cursor(&mut self) {
+        let start = self.cursor().start();
+        let data = self.source.read(start, start.saturating_add(MAGIC_WINDOW));
+        self.message = Some(Message::Info(format!(
+            "{:#x}: {}",
+            start,
+            self.magic.identify(data).as_deref().unwrap_or("data")
+        )));
+    }
+
+    // Moves the cursor to the file offset an RVA is loaded from.
+    fn goto_rva(&mut self, rva: u64) -> Result<(), String> {
+        let analysis = self
+            .analysis
+            .as_ref()
+            .filter(|analysis| analysis.image_base.is_some())
+            .ok_or("no RVAs in this file")?;
+        let offset = analysis
+            .rva_to_offset(rva)
+            .ok_or(format!("RVA {:#x} is not in the file", rva))?;
+        self.jump_to(offset);
+        Ok(())
+    }
+
+    // Shows offsets in the gutter relative to a mark.
+    fn relative_to_mark(&mut self, name: char) -> Result<(), String> {
+        if !self.marks.contains_key(&name) {
+            return Err(format!("mark '{}' isn't set", name));
+        }
+        self.relative_mark = Some(name);
+        Ok(())
+    }
+
+    // Handles the second key of `m<mark>` (set a mark at the cursor) and
+    // `'<mark>` (jump to it).
+    fn on_pending_key(&mut self, prefix: char, key: KeyEvent) {
+        let KeyCode::Char(name) = key.code else {
+            return;
+        };
+        let location = self.cursor().start();
+        match prefix {
+            'm' => self.set_mark(name, location),
+            '\'' => match self.marks.get(&name) {
+                Some(&offset) => self.jump_to(offset),
+                None => self.message = Some(Message::Error(format!("mark '{}' isn't set", name))),
+            },
+            _ => {}
+        }
+    }
+
+    // Starts scanning for embedded files, or lists what has been found so far.
+    fn scan(&mut self) -> Result<(), String> {
+        let Some(scan) = &self.scan else {
+            let data = self.source.shared().ok_or("can't scan this source")?;
+            self.scan = Some(Scan::start(data));
+            return Ok(());
+        };
+
+        let hits = scan.hits();
+        if hits.is_empty() {
+            return Err(if scan.is_done() {
+                "no signatures found".to_string()
+            } else {
+                "nothing found yet".to_string()
+            });
+        }
+        let title = if scan.is_done() {
+            format!("{} signatures", hits.len())
+        } else {
+            format!("{} signatures (scanning)", hits.len())
+        };
+        let entries = hits
+            .into_iter()
+            .map(|hit| NavigatorEntry {
+                label: hit.description,
+                detail: match hit.length {
+                    Some(length) => format!("{:#x} +{:#x}", hit.offset, length),
+                    None => format!("{:#x} +?", hit.offset),
+                },
+                offset: hit.offset,
+            })
+            .collect();
+        self.navigator = Some(Navigator::new(title, entries));
+        Ok(())
+    }
+
+    // Writes the scan hit under the cursor out to `path`, or to a file named
+    // after the source and the offset. Hits of unknown length run to the end
+    // of the data.
+    fn carve(&mut self, path: Option<&str>) -> Result<(), String> {
+        let scan = self
+            .scan
+            .as_ref()
+            .ok_or("no scan results; run :scan first")?;
+        let location = self.cursor().start();
+        let contains = |hit: &Hit| {
+            hit.offset <= location
+                && hit
+                    .length
+                    .is_none_or(|length| location < hit.offset + length)
+        };
+        let hit = scan
+            .hits()
+            .into_iter()
+            .rev()
+            .find(contains)
+            .ok_or(format!("no scan hit at {:#x}", location))?;
+
+        let path = match path {
+            Some(path) => PathBuf::from(path),
+            None => {
+                let name = Path::new(self.source.name())
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_else(|| "heks".to_string());
+                PathBuf::from(format!("{}-{:x}.{}", name, hit.offset, hit.extension))
             }
+        };
+        let end = hit.length.map_or(u64::MAX, |length| hit.offset + length);
+        let data = self.source.read(hit.offset, end);
+        fs::write(&path, data).map_err(|error| format!("{}: {}", path.display(), error))?;
+        self.message = Some(Message::Info(format!(
+            "wrote {} bytes of {} to {}",
+            data.len(),
+            hit.description,
+            path.display()
+        )));
+        Ok(())
+    }
+
+    // Picks up progress made in the background, returning whether there's
+    // anything new to show.
+    pub fn poll(&mut self) -> bool {
+        let mut changed = false;
+        if let Some(map) = &self.entropy {
+            let computed = map.computed();
+            changed = computed != self.entropy_computed;
+            self.entropy_computed = computed;
+        }
 
-            (KeyModifiers::NONE, KeyCode::Char('k')) | (KeyModifiers::NONE, KeyCode::Up) => {
-                if self.cursor_stack.top().start() >= COLUMNS.into() {
-                    self.cursor_stack.top_mut().decrement(COLUMNS.into());
+        let Some(scan) = &self.scan else {
+            return changed;
+        };
+        let status = (
+            (scan.progress() * 100.0) as u64,
+            scan.hit_count(),
+            scan.is_done(),
+        );
+        if self.scan_status == Some(status) {
+            return changed;
+        }
+        if status.2 {
+            self.message = Some(Message::Info(format!(
+                "scan finished, {} signatures found (:scan to list)",
+                status.1
+            )));
+        }
+        self.scan_status = Some(status);
+        true
+    }
+
+    fn on_prompt_key(&mut self, key: KeyEvent) {
+        let Some(prompt) = self.prompt.as_mut() else {
+            return;
+        };
+
+        match prompt.on_key(key) {
+            PromptEvent::Pending => {}
+            PromptEvent::Cancel => self.prompt = None,
+            PromptEvent::Submit(line) => {
+                self.prompt = None;
+                let history = self.history.entry(self.prompt_kind).or_default();
+                if !line.trim().is_empty() && history.last() != Some(&line) {
+                    history.push(line.clone());
+                }
+                match self.prompt_kind {
+                    PromptKind::Command => self.run_command(&line),
+                    PromptKind::Goto if line.trim().is_empty() => {}
+                    PromptKind::Goto => self.run_command(&format!("goto {}", line)),
                 }
             }
+        }
+    }
 
-            (KeyModifiers::SHIFT, KeyCode::Char('L')) => self.cursor_stack.top_mut().grow(),
-            (KeyModifiers::SHIFT, KeyCode::Char('H')) => self.cursor_stack.top_mut().shrink(),
-
-            (KeyModifiers::NONE, KeyCode::Tab)
-            | (
-                KeyModifiers::ALT,
-                KeyCode::Char('f'), // Should be KeyCode::Right, but that's what I get from crossterm..
-            ) => {
-                self.cursor_stack.top_mut().skip_right();
+    // Text pasted into the terminal goes into whichever prompt is open, be it
+    // the command line, the goto prompt or the navigator's filter.
+    pub fn on_paste(&mut self, text: &str) {
+        if let Some(prompt) = self.prompt.as_mut() {
+            match self.prompt_kind {
+                PromptKind::Goto => prompt.paste(&expr::pasted(text)),
+                PromptKind::Command => prompt.paste(text),
             }
+        } else if let Some(navigator) = self.navigator.as_mut() {
+            navigator.paste(text);
+        }
+    }
 
-            (KeyModifiers::SHIFT, KeyCode::BackTab)
-            | (
-                KeyModifiers::ALT,
-                KeyCode::Char('b'), // Should be KeyCode::Left, but that's what I get from crossterm..
-            ) => {
-                self.cursor_stack.top_mut().skip_left();
+    fn open_prompt(&mut self, kind: PromptKind) {
+        let history = self.history.get(&kind).cloned().unwrap_or_default();
+        let prompt = match kind {
+            PromptKind::Command => Prompt::new(":").with_completer(command::complete),
+            PromptKind::Goto => Prompt::new("goto: "),
+        };
+        self.prompt = Some(prompt.with_history(history));
+        self.prompt_kind = kind;
+    }
+
+    fn open_navigator(&mut self) {
+        let Some(analysis) = &self.analysis else {
+            self.message = Some(Message::Error("nothing to navigate".to_string()));
+            return;
+        };
+
+        let sections = analysis.sections.iter().map(|section| NavigatorEntry {
+            label: section.name.clone(),
+            detail: format!("section {:#x}", section.range.start),
+            offset: section.range.start,
+        });
+        let symbols = analysis.symbols.iter().map(|symbol| NavigatorEntry {
+            label: symbol.name.clone(),
+            detail: format!("symbol {:#x}", symbol.address),
+            offset: symbol.offset,
+        });
+
+        self.navigator = Some(Navigator::new(
+            analysis.description.clone(),
+            sections.chain(symbols).collect(),
+        ));
+    }
+
+    // Block entropies, which are computed in the background the first time
+    // they're asked for.
+    fn entropy_map(&mut self) -> Option<EntropyMap> {
+        if self.entropy.is_none() {
+            self.entropy = self.source.shared().map(EntropyMap::start);
+        }
+        self.entropy.clone()
+    }
+
+    fn cycle_footer(&mut self) {
+        let footer = match self.footer {
+            Footer::Rainbow => Footer::Minimap(Shading::Entropy),
+            Footer::Minimap(Shading::Entropy) => Footer::Minimap(Shading::ByteClass),
+            Footer::Minimap(Shading::ByteClass) => Footer::Rainbow,
+        };
+        if footer != Footer::Rainbow && self.entropy_map().is_none() {
+            self.message = Some(Message::Error("can't map this source".to_string()));
+            return;
+        }
+        self.footer = footer;
+    }
+
+    fn open_entropy_view(&mut self) {
+        let Some(map) = self.entropy_map() else {
+            self.message = Some(Message::Error("can't map this source".to_string()));
+            return;
+        };
+        self.entropy_view = Some(EntropyView::new(map, self.cursor().start()));
+    }
+
+    fn on_entropy_event(&mut self, event: EntropyEvent) {
+        match event {
+            EntropyEvent::Pending => {}
+            EntropyEvent::Cancel => self.entropy_view = None,
+            EntropyEvent::Jump(offset) => {
+                self.entropy_view = None;
+                self.jump_to(offset);
             }
+        }
+    }
 
-            (KeyModifiers::NONE, KeyCode::PageDown) => {
-                let page_size = COLUMNS as u64 * (self.display_height as u64 / 2);
-                self.push_cursor_if_key_changed_else_set(&key, |cursor| {
-                    cursor.increment(page_size)
-                });
+    pub fn on_mouse(&mut self, mouse: MouseEvent) {
+        let (column, row) = (mouse.column, mouse.row);
+        if let Some(view) = self.entropy_view.as_mut() {
+            if mouse.kind == MouseEventKind::Down(MouseButton::Left) {
+                let event = view.on_click(column, row);
+                self.on_entropy_event(event);
             }
+            return;
+        }
+        if self.capturing_input() {
+            return;
+        }
 
-            (KeyModifiers::NONE, KeyCode::PageUp) => {
-                let page_size = COLUMNS as u64 * (self.display_height as u64 / 2);
-                self.push_cursor_if_key_changed_else_set(&key, |cursor| {
-                    cursor.decrement(page_size)
-                });
+        let rows = SCROLL_ROWS * self.display_columns;
+        match mouse.kind {
+            MouseEventKind::ScrollDown => {
+                self.move_head(|app| app.cursor_stack.top_mut().increment(rows))
+            }
+            MouseEventKind::ScrollUp => {
+                self.move_head(|app| app.cursor_stack.top_mut().decrement(rows))
             }
 
-            (KeyModifiers::NONE, KeyCode::Home) => {
-                let mut cursor = self.cursor_stack.top().clone();
-                cursor.decrement(u64::MAX);
-                self.cursor_stack.push(cursor);
+            // Clicking leaves visual mode for selecting with the mouse.
+            MouseEventKind::Down(MouseButton::Left) => {
+                self.end_selection();
+                if let Some(offset) = self.minimap_offset_at(column, row) {
+                    self.jump_to(offset);
+                    return;
+                }
+                let Some(offset) = self.offset_at(column, row) else {
+                    return;
+                };
+
+                let now = Instant::now();
+                let double = matches!(self.last_click, Some((at, c, r))
+                    if (c, r) == (column, row) && now - at < DOUBLE_CLICK);
+                if double {
+                    self.last_click = None;
+                    self.drag = None;
+                    self.select_at(offset);
+                } else {
+                    self.last_click = Some((now, column, row));
+                    self.drag = Some(offset);
+                    self.cursor_stack.push(Cursor::new(offset, offset + 1));
+                }
             }
+            MouseEventKind::Drag(MouseButton::Left) => {
+                if let (Some(anchor), Some(offset)) = (self.drag, self.offset_at(column, row)) {
+                    *self.cursor_stack.top_mut() =
+                        Cursor::new(anchor.min(offset), anchor.max(offset) + 1);
+                }
+            }
+            MouseEventKind::Up(MouseButton::Left) => self.drag = None,
+            _ => {}
+        }
+    }
+
+    // The byte drawn at the given cell of either pane.
+    fn offset_at(&self, column: u16, row: u16) -> Option<u64> {
+        let (_, byte) = self.panes.as_ref()?.byte_at(column, row)?;
+        let offset = self.view.start + byte;
+        (offset < self.view.end).then_some(offset)
+    }
+
+    // The part of the file under the given cell of the footer minimap.
+    fn minimap_offset_at(&self, column: u16, row: u16) -> Option<u64> {
+        let (Footer::Minimap(_), Some(map)) = (self.footer, &self.entropy) else {
+            return None;
+        };
+        let area = self.footer_area;
+        if row != area.y || column < area.x || column >= area.right() {
+            return None;
+        }
+        let cell = (column - area.x) as usize;
+        Some(EntropyMap::cell(&(0..map.len()), cell, area.width as usize).start)
+    }
+
+    // Selects the string or, failing that, the annotation at `offset`, as
+    // part of the undo step of the click before.
+    fn select_at(&mut self, offset: u64) {
+        let data = self.source.fetch(0, u64::MAX).data;
+        let range = match string_at(data, offset as usize) {
+            Some(range) => range.start as u64..range.end as u64,
+            None => match self.annotations.at(offset) {
+                Some(annotation) => annotation.range.clone(),
+                None => return,
+            },
+        };
+        *self.cursor_stack.top_mut() = Cursor::new(range.start, range.end);
+    }
+
+    fn on_navigator_key(&mut self, key: KeyEvent) {
+        let Some(navigator) = self.navigator.as_mut() else {
+            return;
+        };
 
-            (KeyModifiers::NONE, KeyCode::End) => {
-                let mut cursor = self.cursor_stack.top().clone();
-                cursor.increment(u64::MAX);
-                self.cursor_stack.push(cursor);
+        match navigator.on_key(key) {
+            NavigatorEvent::Pending => {}
+            NavigatorEvent::Cancel => self.navigator = None,
+            NavigatorEvent::Jump(offset) => {
+                self.navigator = None;
+                self.jump_to(offset);
             }
+        }
+    }
+
+    // Keeps the bit cursor, if there is one, in the byte cursor, and the byte
+    // cursor on just the bytes the bit cursor covers.
+    fn sync_bit_cursor(&mut self) {
+        if let Some(bits) = self.bit_cursor.as_mut() {
+            bits.follow(self.cursor_stack.top());
+            *self.cursor_stack.top_mut() = bits.bytes();
+        }
+    }
+
+    // Moves the cursor to `offset`, keeping its size, as a new undo step.
+    fn jump_to(&mut self, offset: u64) {
+        if self.selection.is_some() {
+            self.move_head(|app| app.jump_to(offset));
+            return;
+        }
+        let cursor = self.cursor_stack.top();
+        let width = cursor.end() - cursor.start();
+        self.cursor_stack
+            .push(Cursor::new(offset, offset.saturating_add(width)));
+    }
 
-            (KeyModifiers::NONE, KeyCode::Char('z')) => self.cursor_stack.undo(),
-            (KeyModifiers::SHIFT, KeyCode::Char('Z')) => self.cursor_stack.redo(),
+    // The bytes things happen to: the selection in visual mode, otherwise the
+    // cursor.
+    fn cursor(&self) -> Cursor {
+        match self.selection {
+            Some(selection) => selection.cursor(self.display_columns),
+            None => self.cursor_stack.top(),
+        }
+    }
 
-            (_, _) => {
-                debug!("key event: {:?}", key);
+    // Starts selecting from the cursor, switches to `mode` if already
+    // selecting some other way, or stops.
+    fn toggle_selection(&mut self, mode: SelectionMode) {
+        match self.selection {
+            Some(selection) if selection.mode == mode => self.end_selection(),
+            Some(selection) => self.selection = Some(Selection { mode, ..selection }),
+            None => {
+                let at = self.cursor_stack.top().start();
+                self.selection = Some(Selection::new(mode, at));
             }
+        }
+    }
+
+    // Leaves visual mode with the selection as the cursor, as a new undo step,
+    // except for a block selection, which a cursor can't move as, so that goes
+    // to its head.
+    fn end_selection(&mut self) {
+        let Some(selection) = self.selection.take() else {
+            return;
         };
+        self.cursor_stack.push(match selection.mode {
+            SelectionMode::Linear => selection.cursor(self.display_columns),
+            SelectionMode::Block => Cursor::new(selection.head, selection.head + 1),
+        });
+    }
 
+    // Runs `f`, which moves the cursor, on just the head of the selection in
+    // visual mode, so that the selection then reaches from the anchor to
+    // wherever that ends up, in either direction. `f` gets a stack of its own
+    // to move the head on, so the moves leave no undo steps behind.
+    fn move_head<F>(&mut self, f: F)
+    where
+        F: FnOnce(&mut Self),
+    {
+        let Some(selection) = self.selection.take() else {
+            f(self);
+            return;
+        };
+        let head = Cursor::new(selection.head, selection.head + 1);
+        let stack = std::mem::replace(&mut self.cursor_stack, CursorStack::new(head));
+        f(self);
+        let head = std::mem::replace(&mut self.cursor_stack, stack).top();
+
+        let len = self.source.fetch(0, u64::MAX).data.len() as u64;
+        let head = head.start().min(len.saturating_sub(1));
+        self.selection = Some(Selection { head, ..selection });
+    }
+
+    // Shows the code at the cursor as instructions for `architecture`, or for
+    // the one the file is for, falling back to x86-64.
+    fn open_disassembly(&mut self, architecture: Option<Architecture>) {
+        let architecture = architecture
+            .or(self.analysis.as_ref().and_then(|a| a.architecture))
+            .unwrap_or(Architecture::X86_64);
+        self.table = None;
+        self.image = None;
+        self.disassembly = Some(Disassembly::new(architecture, self.cursor().start()));
+        self.message = Some(Message::Info(format!(
+            "instructions: {}",
+            architecture.name()
+        )));
+    }
+
+    // Jumps to where the instruction at the cursor branches to, or the data
+    // it refers to, as a new undo step.
+    fn follow_branch(&mut self) {
+        let start = self.cursor().start();
+        let target = self
+            .listing
+            .iter()
+            .find(|line| line.offset == start)
+            .and_then(|line| line.instruction.target);
+        let Some(target) = target else {
+            self.message = Some(Message::Error("nothing to follow".to_string()));
+            return;
+        };
+        let len = self.source.fetch(0, u64::MAX).data.len() as u64;
+        match App::code_offset(self.analysis.as_ref(), target, len) {
+            Some(offset) => self.cursor_stack.push(Cursor::new(offset, offset + 1)),
+            None => {
+                self.message = Some(Message::Error(format!("{:#x} is not in the file", target)))
+            }
+        }
+    }
+
+    // Jumps to where the value under the cursor points, as a new undo step. The
+    // value is read as wide as the cursor, which has to be wider than a byte.
+    fn follow_pointer(&mut self) {
+        let cursor = self.cursor();
+        let width = cursor.end() - cursor.start();
+        if width < 2 {
+            let size = self
+                .analysis
+                .as_ref()
+                .and_then(|analysis| analysis.architecture)
+                .map_or(4, |architecture| architecture.pointer_size());
+            self.message = Some(Message::Error(format!(
+                "a byte is too narrow for a pointer; widen the cursor to {} bytes",
+                size
+            )));
+            return;
+        }
+        let data = self
+            .source
+            .read(cursor.start(), cursor.start().saturating_add(width))
+            .to_vec();
+        if (data.len() as u64) < width {
+            self.message = Some(Message::Error("pointer runs past the end".to_string()));
+            return;
+        }
+        let target = pointer::target(
+            self.pointer_mode,
+            &data,
+            self.endianness,
+            cursor.start(),
+            self.gutter.base,
+            self.analysis.as_ref(),
+        );
+        let len = self.source.fetch(0, u64::MAX).data.len() as u64;
+        match target {
+            Ok(offset) if offset < len => self.jump_to(offset),
+            Ok(offset) => {
+                self.message = Some(Message::Error(format!(
+                    "{:#x} is past the end of the file",
+                    offset
+                )))
+            }
+            Err(error) => self.message = Some(Message::Error(error)),
+        }
+    }
+
+    fn on_key(&mut self, key: KeyEvent) {
+        self.message = None;
+        if self.prompt.is_some() {
+            self.on_prompt_key(key);
+            return;
+        }
+        if self.navigator.is_some() {
+            self.on_navigator_key(key);
+            return;
+        }
+        if let Some(view) = self.entropy_view.as_mut() {
+            let event = view.on_key(key);
+            self.on_entropy_event(event);
+            return;
+        }
+        if let Some(prefix) = self.pending.take() {
+            self.on_pending_key(prefix, key);
+            return;
+        }
+
+        if key.code == KeyCode::Esc && (self.count.is_some() || !self.keys.is_empty()) {
+            self.count = None;
+            self.keys.clear();
+        } else if key.code == KeyCode::Esc && self.selection.is_some() {
+            self.end_selection();
+        } else if let Some(digit) = self.count_digit(key) {
+            let count = self.count.unwrap_or(0);
+            self.count = Some(count.saturating_mul(10).saturating_add(digit));
+        } else {
+            self.on_sequence_key(key);
+        }
         self.last_key = Some(key);
     }
+
+    // The digit `key` adds to the count in front of a binding, as in `5j`. A
+    // count can't start with 0, and digits that are bound to something do
+    // that instead.
+    fn count_digit(&self, key: KeyEvent) -> Option<u64> {
+        let KeyCode::Char(c @ '0'..='9') = key.code else {
+            return None;
+        };
+        let lookup = self.keymap.lookup(&[keymap::normalize(key)]);
+        let bound = lookup.action.is_some() || lookup.longer;
+        if !key.modifiers.is_empty() || !self.keys.is_empty() || bound {
+            return None;
+        }
+        if c == '0' && self.count.is_none() {
+            return None;
+        }
+        c.to_digit(10).map(u64::from)
+    }
+
+    // Adds a key to the sequence being typed, running what it's bound to once
+    // no longer binding could still follow. If the key doesn't continue any
+    // binding, whatever the keys before it are bound to runs instead and the
+    // key starts a new sequence.
+    fn on_sequence_key(&mut self, key: KeyEvent) {
+        self.keys.push(keymap::normalize(key));
+        let lookup = self.keymap.lookup(&self.keys);
+        if lookup.longer {
+            return;
+        }
+        let action = lookup.action.map(String::from);
+        let keys = std::mem::take(&mut self.keys);
+        let count = self.count.take();
+        match action {
+            Some(action) => self.run_binding(&action, key, count),
+            None if keys.len() > 1 => {
+                let before = self.keymap.lookup(&keys[..keys.len() - 1]).action;
+                if let Some(action) = before.map(String::from) {
+                    self.run_binding(&action, key, count);
+                }
+                self.on_sequence_key(key);
+            }
+            None => debug!("key event: {:?}", key),
+        }
+    }
+
+    // Runs the command line a key is bound to, with or without a leading `:`.
+    // `count` is the count typed in front of the key, if any.
+    fn run_binding(&mut self, line: &str, key: KeyEvent, count: Option<u64>) {
+        let line = line.strip_prefix(':').unwrap_or(line);
+        if let Err(error) = self.dispatch(line, Some(key), count) {
+            self.message = Some(Message::Error(error));
+        }
+    }
+
+    // In the disassembly, the cursor moves an instruction at a time and a byte
+    // at a time, and in the latter case takes the instruction boundaries with
+    // it.
+    fn move_left(&mut self, count: u64) {
+        if self.disassembly.is_some() {
+            self.move_instruction_boundary(count, false);
+        } else {
+            self.cursor_stack.top_mut().decrement(count);
+        }
+    }
+
+    fn move_right(&mut self, count: u64) {
+        if self.disassembly.is_some() {
+            self.move_instruction_boundary(count, true);
+        } else {
+            self.cursor_stack.top_mut().increment(count);
+        }
+    }
+
+    fn move_instruction_boundary(&mut self, count: u64, forward: bool) {
+        let len = self.source.fetch(0, u64::MAX).data.len() as u64;
+        let start = self.cursor_stack.top().start();
+        if let Some(disassembly) = self.disassembly.as_mut() {
+            let start = disassembly.nudge(start, count, forward, len);
+            *self.cursor_stack.top_mut() = Cursor::new(start, start.saturating_add(1));
+        }
+    }
+
+    fn move_up(&mut self, count: u64) {
+        let start = self.cursor_stack.top().start();
+        if self.disassembly.is_some() {
+            let previous = self
+                .listing
+                .iter()
+                .rev()
+                .filter(|line| line.offset < start)
+                .nth(count as usize - 1)
+                .map_or(start.saturating_sub(count), |line| line.offset);
+            *self.cursor_stack.top_mut() = Cursor::new(previous, previous.saturating_add(1));
+            return;
+        }
+        let rows = self.display_columns.saturating_mul(count);
+        if start >= rows {
+            self.cursor_stack.top_mut().decrement(rows);
+        }
+    }
+
+    fn move_down(&mut self, count: u64) {
+        if self.disassembly.is_some() {
+            let end = self.cursor_stack.top().end();
+            let next = self
+                .listing
+                .iter()
+                .filter(|line| line.offset >= end)
+                .nth(count as usize - 1)
+                .map_or(end, |line| line.offset);
+            *self.cursor_stack.top_mut() = Cursor::new(next, next.saturating_add(1));
+            return;
+        }
+        let rows = self.display_columns.saturating_mul(count);
+        self.cursor_stack.top_mut().increment(rows);
+    }
+
+    fn repeat_on_cursor(&mut self, count: u64, f: fn(&mut Cursor)) {
+        let cursor = self.cursor_stack.top_mut();
+        (0..count).for_each(|_| f(cursor));
+    }
+
+    // The key typed before a count is a digit, so a counted page move is
+    // always an undo step of its own.
+    fn page_down(&mut self, key: Option<KeyEvent>, count: u64) {
+        let page_size = self.display_columns * (self.display_height as u64 / 2);
+        let page_size = page_size.saturating_mul(count);
+        self.push_cursor_if_key_changed_else_set(key, |cursor| cursor.increment(page_size));
+    }
+
+    fn page_up(&mut self, key: Option<KeyEvent>, count: u64) {
+        let page_size = self.display_columns * (self.display_height as u64 / 2);
+        let page_size = page_size.saturating_mul(count);
+        self.push_cursor_if_key_changed_else_set(key, |cursor| cursor.decrement(page_size));
+    }
+
+    fn move_to_start(&mut self) {
+        let mut cursor = self.cursor_stack.top();
+        cursor.decrement(u64::MAX);
+        self.cursor_stack.push(cursor);
+    }
+
+    fn move_to_end(&mut self) {
+        let mut cursor = self.cursor_stack.top();
+        cursor.increment(u64::MAX);
+        self.cursor_stack.push(cursor);
+    }
+
+    fn follow(&mut self) {
+        if self.disassembly.is_some() {
+            self.follow_branch();
+        } else {
+            self.follow_pointer();
+        }
+    }
+
+    fn cycle_copy_format(&mut self) {
+        self.copy_format = self.copy_format.next();
+        self.message = Some(Message::Info(format!(
+            "copy as: {}",
+            self.copy_format.name()
+        )));
+    }
+
+    fn toggle_address_mode(&mut self) {
+        self.address_mode = match self.address_mode {
+            AddressMode::Offset if self.analysis.is_some() => AddressMode::Virtual,
+            _ => AddressMode::Offset,
+        }
+    }
+
+    fn cycle_pointer_mode(&mut self) {
+        self.pointer_mode = self.pointer_mode.next();
+        self.message = Some(Message::Info(format!(
+            "pointers: {}",
+            self.pointer_mode.name()
+        )));
+    }
+
+    fn cycle_coloring(&mut self) {
+        self.coloring = self.coloring.next();
+        self.message = Some(Message::Info(format!("coloring: {}", self.coloring.name())));
+    }
+
+    fn toggle_bit_view(&mut self) {
+        self.bit_view = !self.bit_view;
+        self.bit_cursor = None;
+    }
+
+    fn toggle_bit_order(&mut self) {
+        self.bit_order = self.bit_order.toggle();
+        self.message = Some(Message::Info(format!(
+            "bit order: {} first",
+            self.bit_order.label()
+        )));
+    }
+
+    // Changes the width of the image a pixel at a time, or moves the cursor a
+    // bit at a time in the bit view.
+    fn fine_move(&mut self, count: u64, right: bool) {
+        if let Some(image) = self.image.as_mut() {
+            image.width = match right {
+                true => image.width.saturating_add(count),
+                false => image.width.saturating_sub(count),
+            }
+            .clamp(1, MAX_WIDTH);
+            self.message = Some(Message::Info(format!("width: {}", image.width)));
+        } else if self.bit_view {
+            let mut bits = self.bit_cursor();
+            match right {
+                true => bits.increment(count),
+                false => bits.decrement(count),
+            }
+            self.bit_cursor = Some(bits);
+            *self.cursor_stack.top_mut() = bits.bytes();
+        }
+    }
+
+    // Changes the width of the image 8 pixels at a time, or resizes the cursor
+    // a bit at a time in the bit view.
+    fn fine_resize(&mut self, count: u64, grow: bool) {
+        if let Some(image) = self.image.as_mut() {
+            let step = count.saturating_mul(8);
+            image.width = match grow {
+                true => image.width.saturating_add(step),
+                false => image.width.saturating_sub(step),
+            }
+            .clamp(1, MAX_WIDTH);
+            self.message = Some(Message::Info(format!("width: {}", image.width)));
+        } else if self.bit_view {
+            let mut bits = self.bit_cursor();
+            match grow {
+                true => (0..count).for_each(|_| bits.grow()),
+                false => (0..count).for_each(|_| bits.shrink()),
+            }
+            self.bit_cursor = Some(bits);
+            *self.cursor_stack.top_mut() = bits.bytes();
+        }
+    }
+
+    fn bit_cursor(&self) -> BitCursor {
+        self.bit_cursor
+            .unwrap_or_else(|| BitCursor::from_bytes(self.cursor_stack.top()))
+    }
+
+    // Switches to the next pixel format, architecture, table element or cell
+    // format, for whichever is showing.
+    fn cycle_format(&mut self) {
+        if let Some(image) = self.image.as_mut() {
+            image.format = image.format.next();
+            self.message = Some(Message::Info(format!("pixels: {}", image.format.name())));
+        } else if let Some(disassembly) = self.disassembly.as_mut() {
+            disassembly.architecture = disassembly.architecture.next();
+            self.message = Some(Message::Info(format!(
+                "instructions: {}",
+                disassembly.architecture.name()
+            )));
+        } else if let Some(table) = self.table.as_mut() {
+            let stride = table.stride.max(table.element.size());
+            *table = Table {
+                stride,
+                ..Table::new(table.element.next(), table.origin)
+            };
+        } else {
+            let format = &mut self.grid_display.format;
+            *format = format.next();
+            self.message = Some(Message::Info(format!("cells: {}", format.name())));
+        }
+    }
+
+    fn cycle_word(&mut self) {
+        let word = self.grid_display.word;
+        let index = WORD_SIZES.iter().position(|&size| size == word);
+        self.grid_display.word =
+            WORD_SIZES[index.map_or(0, |index| (index + 1) % WORD_SIZES.len())];
+        self.message = Some(Message::Info(format!(
+            "{} bytes per cell",
+            self.grid_display.word
+        )));
+    }
+
+    fn cycle_columns(&mut self) {
+        self.columns = match self.columns {
+            Columns::Fixed(columns) => COLUMN_PRESETS
+                .iter()
+                .find(|&&preset| preset > columns)
+                .map_or(Columns::Auto, |&preset| Columns::Fixed(preset)),
+            Columns::Auto => Columns::Fixed(COLUMN_PRESETS[0]),
+        };
+        self.message = Some(Message::Info(match self.columns {
+            Columns::Fixed(columns) => format!("{} bytes per row", columns),
+            Columns::Auto => "bytes per row to fit".to_string(),
+        }));
+    }
+
+    fn cycle_group(&mut self) {
+        let group = self.grid_display.group;
+        let index = GROUP_SIZES.iter().position(|&size| size == group);
+        self.grid_display.group =
+            GROUP_SIZES[index.map_or(0, |index| (index + 1) % GROUP_SIZES.len())];
+        self.message = Some(Message::Info(format!(
+            "bytes in groups of {}",
+            self.grid_display.group
+        )));
+    }
+
+    fn cycle_radix(&mut self) {
+        self.gutter.radix = self.gutter.radix.next();
+        self.message = Some(Message::Info(format!(
+            "offsets in {}",
+            self.gutter.radix.name()
+        )));
+    }
+
+    fn suspend(&mut self) {
+        signal::kill(getpid(), signal::SIGTSTP).ok();
+    }
 }
 
 pub struct EventLoop<B: Backend> {
@@ -420,7 +2209,7 @@ impl<B: Backend> EventLoop<B> {
     }
 
     pub fn tick(&mut self) -> io::Result<()> {
-        if self.handle_events()? {
+        if self.handle_events()? | self.app.poll() {
             self.dirty.store(true, std::sync::atomic::Ordering::Release);
         }
 
@@ -442,27 +2231,33 @@ impl<B: Backend> EventLoop<B> {
                 Event::FocusGained => {}
                 Event::FocusLost => {}
                 Event::Key(key) => match (key.modifiers, key.code) {
-                    (KeyModifiers::NONE, KeyCode::Esc)
-                    | (KeyModifiers::NONE, KeyCode::Char('q')) => {
-                        self.done.store(true, std::sync::atomic::Ordering::Release);
-                    }
-
                     (KeyModifiers::CONTROL, KeyCode::Char('c')) => {
                         signal::kill(getpid(), signal::SIGINT).ok();
                     }
 
-                    (KeyModifiers::CONTROL, KeyCode::Char('z')) => {
-                        signal::kill(getpid(), signal::SIGTSTP).ok();
+                    (_, _) => {
+                        self.app.on_key(key);
+                        if self.app.quitting {
+                            self.done.store(true, std::sync::atomic::Ordering::Release);
+                        }
                     }
-
-                    (_, _) => self.app.on_key(key),
                 },
-                Event::Mouse(_) => {}
-                Event::Paste(_) => {}
-                Event::Resize(_, _) => {}
+                Event::Mouse(mouse) => self.app.on_mouse(mouse),
+                Event::Paste(text) => self.app.on_paste(&text),
+                // Start from a clean slate, as some terminals leave junk
+                // behind; the cursor's row is recentred as part of painting.
+                Event::Resize(_, _) => self.terminal.lock().unwrap().clear()?,
             }
         }
 
         Ok(seen_event)
     }
 }
+
+// Parses a hex (0x-prefixed) or decimal address.
+fn parse_address(text: &str) -> Option<u64> {
+    match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => text.parse().ok(),
+    }
+}
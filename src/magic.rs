@@ -0,0 +1,757 @@
+// File-type identification from magic numbers, using (a subset of) the rule
+// syntax of libmagic's magic(5) files, e.g.
+//
+//   0       string      \x89PNG\r\n\x1a\n   PNG image data
+//   >16     belong      x                   \b, %d x
+//   >20     belong      x                   \b %d
+//
+// Each line has an offset, a type, a test and a message. Lines starting with
+// `>` are only checked if the line before them at the previous level
+// matched, and add to its message. Supported:
+//
+// - offsets: absolute (`16`), relative to the end of the parent match
+//   (`&4`), and indirect (`(0x3c.l)`, `(0x3c.l+4)`)
+// - types: byte, short, long, quad, with be/le prefixes, a u prefix for
+//   unsigned comparisons, and a `&mask` suffix; string and search/N
+// - tests: `x` (anything), numbers and strings with an optional `=`, `!`,
+//   `<`, `>`, `&` or `^` operator
+//
+// Anything else (regex, name/use, default, ...) is skipped, along with the
+// lines nested under it.
+
+use crate::decode::{decode_integer, Endianness};
+
+const BUILTIN: &str = r#"
+# Executables
+0       string      \x7fELF             ELF
+>4      byte        1                   32-bit
+>4      byte        2                   64-bit
+>5      byte        1                   LSB
+>>16    leshort     1                   relocatable
+>>16    leshort     2                   executable
+>>16    leshort     3                   shared object
+>>16    leshort     4                   core file
+>>18    leshort     3                   \b, Intel 80386
+>>18    leshort     40                  \b, ARM
+>>18    leshort     62                  \b, x86-64
+>>18    leshort     183                 \b, ARM aarch64
+>>18    leshort     243                 \b, RISC-V
+>5      byte        2                   MSB
+>>16    beshort     1                   relocatable
+>>16    beshort     2                   executable
+>>16    beshort     3                   shared object
+>>16    beshort     4                   core file
+0       string      MZ
+>(0x3c.l)   string  PE\0\0              PE
+>>&20   leshort     0x10b               \b32 executable
+>>&20   leshort     0x20b               \b32+ executable
+>>&0    leshort     0x14c               \b, Intel 80386
+>>&0    leshort     0x8664              \b, x86-64
+>>&0    leshort     0xaa64              \b, ARM64
+>(0x3c.l)   string  !PE\0\0             MS-DOS executable
+0       lelong      0xfeedface          Mach-O 32-bit
+0       lelong      0xfeedfacf          Mach-O 64-bit
+0       belong      0xcafebabe
+>4      belong      <20                 Mach-O universal binary with %d architectures
+>4      belong      >44                 compiled Java class data, version %d
+0       string      dex\n               Dalvik dex file
+0       string      \0asm               WebAssembly binary
+0       string      \!<arch>\n          current ar archive
+>8      string      debian-binary       \b, Debian binary package
+0       string      #!                  script text executable
+>2      string      x                   for %s
+
+# Archives and compression
+0       string      PK\x03\x04          Zip archive data
+0       string      PK\x05\x06          Zip archive data (empty)
+0       string      \x1f\x8b            gzip compressed data
+0       string      BZh                 bzip2 compressed data
+0       string      \xfd7zXZ\0          XZ compressed data
+0       lelong      0xfd2fb528          Zstandard compressed data
+0       lelong      0x184d2204          LZ4 compressed data
+0       string      7z\xbc\xaf\x27\x1c  7-zip archive data
+0       string      Rar!\x1a\x07        RAR archive data
+257     string      ustar               POSIX tar archive
+0       string      070701              ASCII cpio archive
+0       leshort     070707              cpio archive
+32769   string      CD001               ISO 9660 CD-ROM filesystem data
+0       string      SQLite\x20format\x203\0     SQLite 3.x database
+
+# Images
+0       string      \x89PNG\r\n\x1a\n   PNG image data
+>16     belong      x                   \b, %d x
+>20     belong      x                   \b %d
+0       string      GIF8                GIF image data
+>4      string      7a                  \b, version 87a
+>4      string      9a                  \b, version 89a
+>6      leshort     x                   \b, %d x
+>8      leshort     x                   \b %d
+0       string      \xff\xd8\xff        JPEG image data
+0       string      BM                  PC bitmap
+>18     lelong      x                   \b, %d x
+>22     lelong      x                   \b %d
+0       string      II*\0               TIFF image data, little-endian
+0       string      MM\0*               TIFF image data, big-endian
+
+# Audio, video and containers
+0       string      RIFF                RIFF (little-endian) data
+>8      string      WAVE                \b, WAVE audio
+>8      string      AVI\x20             \b, AVI
+>8      string      WEBP                \b, Web/P image
+4       string      ftyp                ISO Media
+>8      string      x                   \b, brand %.4s
+0       string      OggS                Ogg data
+0       string      fLaC                FLAC audio bitstream data
+0       string      ID3                 Audio file with ID3 version 2
+0       string      MThd                Standard MIDI data
+0       belong      0x1a45dfa3          EBML file (Matroska/WebM)
+
+# Documents
+0       string      %PDF-               PDF document
+>5      string      x                   \b, version %.3s
+0       string      \xd0\xcf\x11\xe0\xa1\xb1\x1a\xe1    Composite Document File V2 (MS Office)
+0       string      {\\rtf              Rich Text Format data
+0       string      \xef\xbb\xbf        UTF-8 Unicode (with BOM) text
+0       string      \<?xml              XML document text
+
+# Filesystems and boot sectors
+1080    leshort     0xef53              Linux ext2/3/4 filesystem data
+0       string      hsqs                Squashfs filesystem, little endian
+0       belong      0xd00dfeed          Device Tree Blob
+510     leshort     0xaa55              DOS/MBR boot sector
+"#;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Kind {
+    Integer {
+        size: u64,
+        endianness: Endianness,
+        signed: bool,
+        mask: Option<u64>,
+    },
+    String,
+    Search(u64),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    Any,
+    Integer(char, i128),
+    String(char, Vec<u8>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Indirect {
+    size: u64,
+    endianness: Endianness,
+    adjust: i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Offset {
+    // Relative to the end of the parent's match rather than the start of
+    // the data.
+    relative: bool,
+    value: i64,
+    // Read an integer at the offset and use that (plus an adjustment) as
+    // the offset instead.
+    indirect: Option<Indirect>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Rule {
+    offset: Offset,
+    kind: Kind,
+    value: Value,
+    message: String,
+    children: Vec<Rule>,
+}
+
+// What was matched, for formatting the message.
+enum Matched<'a> {
+    Integer(i128),
+    String(&'a [u8]),
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct Magic {
+    rules: Vec<Rule>,
+    // Number of rules we didn't understand and skipped.
+    pub skipped: usize,
+}
+
+enum LineError {
+    Unsupported,
+    Invalid(String),
+}
+
+impl Magic {
+    pub fn builtin() -> Magic {
+        Magic::parse(BUILTIN).expect("invalid built-in magic")
+    }
+
+    pub fn parse(source: &str) -> Result<Magic, String> {
+        let mut magic = Magic::default();
+        // The chain of rules the current line may be nested under, as
+        // (level, path of child indices from `magic.rules`).
+        let mut path: Vec<usize> = vec![];
+        // Set while skipping the lines nested under an unsupported one.
+        let mut skip_deeper_than: Option<usize> = None;
+
+        for (number, line) in source.lines().enumerate() {
+            let line = line.trim_end();
+            if line.trim_start().is_empty() || line.starts_with('#') || line.starts_with("!:") {
+                continue;
+            }
+            let level = line.chars().take_while(|&c| c == '>').count();
+            if let Some(skip) = skip_deeper_than {
+                if level > skip {
+                    continue;
+                }
+                skip_deeper_than = None;
+            }
+            if level > path.len() {
+                return Err(format!(
+                    "line {}: continuation without a parent",
+                    number + 1
+                ));
+            }
+
+            match parse_rule(&line[level..]) {
+                Ok(rule) => {
+                    path.truncate(level);
+                    let mut siblings = &mut magic.rules;
+                    for &index in path.iter() {
+                        siblings = &mut siblings[index].children;
+                    }
+                    siblings.push(rule);
+                    path.push(siblings.len() - 1);
+                }
+                Err(LineError::Unsupported) => {
+                    magic.skipped += 1;
+                    skip_deeper_than = Some(level);
+                    path.truncate(level);
+                }
+                Err(LineError::Invalid(error)) => {
+                    return Err(format!("line {}: {}", number + 1, error));
+                }
+            }
+        }
+
+        Ok(magic)
+    }
+
+    // Adds the rules from `other`, which take precedence over ours.
+    pub fn extend(&mut self, other: Magic) {
+        let rules = std::mem::take(&mut self.rules);
+        self.rules = other.rules;
+        self.rules.extend(rules);
+        self.skipped += other.skipped;
+    }
+
+    pub fn len(&self) -> usize {
+        self.rules.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    // Describes what `data` starts with, if we recognize it.
+    pub fn identify(&self, data: &[u8]) -> Option<String> {
+        self.rules.iter().find_map(|rule| {
+            let mut message = String::new();
+            evaluate(rule, data, 0, &mut message)?;
+            Some(message.trim().to_string()).filter(|message| !message.is_empty())
+        })
+    }
+}
+
+// Checks `rule` and, if it matches, appends its message and those of its
+// matching children to `out`. Returns None if the rule didn't match.
+fn evaluate(rule: &Rule, data: &[u8], parent_end: u64, out: &mut String) -> Option<()> {
+    let offset = resolve(rule.offset, data, parent_end)?;
+    let (matched, end) = test(rule, data, offset)?;
+
+    let message = format_message(&rule.message, &matched);
+    match message.strip_prefix("\\b") {
+        Some(message) => out.push_str(message),
+        None if message.is_empty() => {}
+        None => {
+            if !out.is_empty() {
+                out.push(' ');
+            }
+            out.push_str(&message);
+        }
+    }
+
+    for child in rule.children.iter() {
+        evaluate(child, data, end, out);
+    }
+    Some(())
+}
+
+fn resolve(offset: Offset, data: &[u8], parent_end: u64) -> Option<u64> {
+    let base = if offset.relative { parent_end } else { 0 };
+    let mut result = base.checked_add_signed(offset.value)?;
+    if let Some(indirect) = offset.indirect {
+        let start = usize::try_from(result).ok()?;
+        let bytes = data.get(start..start.checked_add(indirect.size as usize)?)?;
+        let (_, pointer) = decode_integer(bytes, indirect.endianness);
+        result = (pointer as u64).checked_add_signed(indirect.adjust)?;
+    }
+    Some(result)
+}
+
+// Returns what matched and where the match ended.
+fn test<'a>(rule: &Rule, data: &'a [u8], offset: u64) -> Option<(Matched<'a>, u64)> {
+    let start = usize::try_from(offset).ok()?;
+    match rule.kind {
+        Kind::Integer {
+            size,
+            endianness,
+            signed,
+            mask,
+        } => {
+            let bytes = data.get(start..start.checked_add(size as usize)?)?;
+            let (_, unsigned) = decode_integer(bytes, endianness);
+            let mut value = unsigned as u64;
+            if let Some(mask) = mask {
+                value &= mask;
+            }
+            // Sign-extend for comparisons.
+            let shift = 64 - size * 8;
+            let value = if signed {
+                (((value << shift) as i64) >> shift) as i128
+            } else {
+                value as i128
+            };
+            let ok = match &rule.value {
+                Value::Any => true,
+                Value::Integer(op, expected) => {
+                    let expected = if signed {
+                        ((((*expected as u64) << shift) as i64) >> shift) as i128
+                    } else {
+                        *expected & ((1i128 << (size * 8)) - 1)
+                    };
+                    match op {
+                        '=' => value == expected,
+                        '!' => value != expected,
+                        '<' => value < expected,
+                        '>' => value > expected,
+                        '&' => value & expected == expected,
+                        '^' => value & expected == 0,
+                        _ => false,
+                    }
+                }
+                Value::String(..) => false,
+            };
+            ok.then_some((Matched::Integer(value), offset + size))
+        }
+        Kind::String => {
+            let rest = data.get(start..)?;
+            match &rule.value {
+                Value::Any => {
+                    let len = rest
+                        .iter()
+                        .take(256)
+                        .position(|&b| b == 0 || b == b'\n')
+                        .unwrap_or(rest.len().min(256));
+                    Some((Matched::String(&rest[..len]), offset + len as u64))
+                }
+                Value::String(op, expected) => {
+                    let actual = &rest[..expected.len().min(rest.len())];
+                    let ok = match op {
+                        '=' => actual == expected.as_slice(),
+                        '!' => actual != expected.as_slice(),
+                        '<' => actual < expected.as_slice(),
+                        '>' => actual > expected.as_slice(),
+                        _ => false,
+                    };
+                    ok.then_some((Matched::String(actual), offset + expected.len() as u64))
+                }
+                Value::Integer(..) => None,
+            }
+        }
+        Kind::Search(range) => {
+            let Value::String(_, expected) = &rule.value else {
+                return None;
+            };
+            let rest = data.get(start..)?;
+            let window = &rest[..rest.len().min(range as usize + expected.len())];
+            let found = window
+                .windows(expected.len().max(1))
+                .position(|window| window == expected.as_slice())?;
+            let end = offset + (found + expected.len()) as u64;
+            Some((Matched::String(&window[found..found + expected.len()]), end))
+        }
+    }
+}
+
+// Expands a printf-style conversion (%d, %x, %s, ...) in `message`.
+fn format_message(message: &str, matched: &Matched) -> String {
+    let Some(percent) = message.find('%') else {
+        return message.to_string();
+    };
+    let spec: String = message[percent + 1..]
+        .chars()
+        .take_while(|c| !c.is_ascii_alphabetic() || matches!(c, 'l' | 'h'))
+        .collect();
+    let conversion = message[percent + 1 + spec.len()..].chars().next();
+    let Some(conversion) = conversion else {
+        return message.to_string();
+    };
+    let flags = spec.trim_end_matches(['l', 'h']);
+    let alternate = flags.contains('#');
+    let precision: Option<usize> = flags
+        .split_once('.')
+        .and_then(|(_, precision)| precision.parse().ok());
+
+    let text = match (conversion, matched) {
+        ('d' | 'i' | 'u', Matched::Integer(value)) => format!("{}", value),
+        ('x', Matched::Integer(value)) if alternate => format!("{:#x}", value),
+        ('x', Matched::Integer(value)) => format!("{:x}", value),
+        ('X', Matched::Integer(value)) => format!("{:X}", value),
+        ('o', Matched::Integer(value)) => format!("{:o}", value),
+        ('c', Matched::Integer(value)) => ((*value as u8) as char).to_string(),
+        ('s', Matched::String(bytes)) => {
+            let bytes = &bytes[..precision.unwrap_or(bytes.len()).min(bytes.len())];
+            String::from_utf8_lossy(bytes).trim_end().to_string()
+        }
+        _ => String::new(),
+    };
+    let rest = &message[percent + 1 + spec.len() + conversion.len_utf8()..];
+    format!("{}{}{}", &message[..percent], text, rest)
+}
+
+// Splits off the next whitespace-separated field, honouring backslash
+// escapes so that strings can contain spaces.
+fn next_field(line: &str) -> (&str, &str) {
+    let line = line.trim_start();
+    let mut escaped = false;
+    for (index, c) in line.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            c if c.is_whitespace() => return (&line[..index], &line[index..]),
+            _ => {}
+        }
+    }
+    (line, "")
+}
+
+fn parse_rule(line: &str) -> Result<Rule, LineError> {
+    let (offset, rest) = next_field(line);
+    let (kind, rest) = next_field(rest);
+    let (value, rest) = next_field(rest);
+    if value.is_empty() {
+        return Err(LineError::Invalid(
+            "expected offset, type and test".to_string(),
+        ));
+    }
+
+    let offset = parse_offset(offset)?;
+    let kind = parse_kind(kind)?;
+    let value = match kind {
+        _ if value == "x" => Value::Any,
+        Kind::Integer { .. } => {
+            let (op, number) = split_operator(value);
+            let number = parse_number(number)
+                .ok_or_else(|| LineError::Invalid(format!("invalid number {}", number)))?;
+            Value::Integer(op, number as i128)
+        }
+        Kind::String | Kind::Search(_) => {
+            let (op, text) = split_operator(value);
+            Value::String(op, unescape(text)?)
+        }
+    };
+
+    Ok(Rule {
+        offset,
+        kind,
+        value,
+        message: rest.trim().to_string(),
+        children: vec![],
+    })
+}
+
+fn split_operator(value: &str) -> (char, &str) {
+    match value.chars().next() {
+        Some(op @ ('=' | '!' | '<' | '>' | '&' | '^')) => (op, &value[1..]),
+        _ => ('=', value),
+    }
+}
+
+fn parse_offset(text: &str) -> Result<Offset, LineError> {
+    let (relative, text) = match text.strip_prefix('&') {
+        Some(text) => (true, text),
+        None => (false, text),
+    };
+
+    let Some(inner) = text.strip_prefix('(') else {
+        let value = parse_number(text)
+            .ok_or_else(|| LineError::Invalid(format!("invalid offset {}", text)))?;
+        return Ok(Offset {
+            relative,
+            value,
+            indirect: None,
+        });
+    };
+
+    // (base.type+adjust)
+    let inner = inner.strip_suffix(')').ok_or(LineError::Unsupported)?;
+    if inner.starts_with('&') {
+        return Err(LineError::Unsupported);
+    }
+    let split = inner.find(['+', '-']).unwrap_or(inner.len());
+    let (pointer, adjust) = inner.split_at(split);
+    let adjust = match adjust {
+        "" => 0,
+        _ => parse_number(adjust.trim_start_matches('+')).ok_or(LineError::Unsupported)?,
+    };
+    let (base, size) = pointer.split_once(['.', ',']).unwrap_or((pointer, "l"));
+    let value = parse_number(base).ok_or(LineError::Unsupported)?;
+    let (size, endianness) = match size {
+        "b" | "B" | "c" | "C" => (1, Endianness::Little),
+        "s" | "h" => (2, Endianness::Little),
+        "S" | "H" => (2, Endianness::Big),
+        "l" => (4, Endianness::Little),
+        "L" => (4, Endianness::Big),
+        "q" => (8, Endianness::Little),
+        "Q" => (8, Endianness::Big),
+        _ => return Err(LineError::Unsupported),
+    };
+
+    Ok(Offset {
+        relative,
+        value,
+        indirect: Some(Indirect {
+            size,
+            endianness,
+            adjust,
+        }),
+    })
+}
+
+fn parse_kind(text: &str) -> Result<Kind, LineError> {
+    let (name, mask) = match text.split_once('&') {
+        Some((name, mask)) => (
+            name,
+            Some(
+                parse_number(mask)
+                    .ok_or_else(|| LineError::Invalid(format!("invalid mask {}", mask)))?
+                    as u64,
+            ),
+        ),
+        None => (text, None),
+    };
+
+    if name == "string" || name.starts_with("string/") {
+        return Ok(Kind::String);
+    }
+    if let Some(range) = name.strip_prefix("search/") {
+        let range = range.split('/').find_map(|part| part.parse().ok());
+        return range.map(Kind::Search).ok_or(LineError::Unsupported);
+    }
+
+    let (signed, name) = match name.strip_prefix('u') {
+        Some(name) => (false, name),
+        None => (true, name),
+    };
+    let (endianness, name) = if let Some(name) = name.strip_prefix("le") {
+        (Endianness::Little, name)
+    } else if let Some(name) = name.strip_prefix("be") {
+        (Endianness::Big, name)
+    } else {
+        // "Native" byte order; we assume little-endian.
+        (Endianness::Little, name)
+    };
+    let size = match name {
+        "byte" => 1,
+        "short" => 2,
+        "long" => 4,
+        "quad" => 8,
+        _ => return Err(LineError::Unsupported),
+    };
+
+    Ok(Kind::Integer {
+        size,
+        endianness,
+        signed,
+        mask,
+    })
+}
+
+fn parse_number(text: &str) -> Option<i64> {
+    let (negative, text) = match text.strip_prefix('-') {
+        Some(text) => (true, text),
+        None => (false, text),
+    };
+    let text = text.trim_end_matches(['L', 'l']);
+    let value = if let Some(hex) = text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        u64::from_str_radix(hex, 16).ok()?
+    } else if text.len() > 1 && text.starts_with('0') {
+        u64::from_str_radix(&text[1..], 8).ok()?
+    } else {
+        text.parse().ok()?
+    } as i64;
+    Some(if negative {
+        value.wrapping_neg()
+    } else {
+        value
+    })
+}
+
+fn unescape(text: &str) -> Result<Vec<u8>, LineError> {
+    let invalid = || LineError::Invalid(format!("invalid escape in {}", text));
+    let bytes = text.as_bytes();
+    let mut result = vec![];
+    let mut index = 0;
+    while index < bytes.len() {
+        let b = bytes[index];
+        index += 1;
+        if b != b'\\' {
+            result.push(b);
+            continue;
+        }
+        let Some(&escape) = bytes.get(index) else {
+            return Err(invalid());
+        };
+        index += 1;
+        match escape {
+            b'n' => result.push(b'\n'),
+            b'r' => result.push(b'\r'),
+            b't' => result.push(b'\t'),
+            b'x' => {
+                let digits: Vec<u8> = bytes[index..]
+                    .iter()
+                    .take(2)
+                    .take_while(|b| b.is_ascii_hexdigit())
+                    .copied()
+                    .collect();
+                let digits = std::str::from_utf8(&digits).map_err(|_| invalid())?;
+                result.push(u8::from_str_radix(digits, 16).map_err(|_| invalid())?);
+                index += digits.len();
+            }
+            b'0'..=b'7' => {
+                let digits: Vec<u8> = bytes[index - 1..]
+                    .iter()
+                    .take(3)
+                    .take_while(|b| (b'0'..=b'7').contains(b))
+                    .copied()
+                    .collect();
+                let digits = std::str::from_utf8(&digits).map_err(|_| invalid())?;
+                result.push(u8::from_str_radix(digits, 8).map_err(|_| invalid())?);
+                index += digits.len() - 1;
+            }
+            other => result.push(other),
+        }
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod magic_tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin() {
+        let magic = Magic::builtin();
+        assert_eq!(magic.skipped, 0);
+
+        let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR".to_vec();
+        png.extend(700u32.to_be_bytes());
+        png.extend(433u32.to_be_bytes());
+        assert_eq!(magic.identify(&png).unwrap(), "PNG image data, 700 x 433");
+
+        let mut elf = b"\x7fELF\x02\x01\x01".to_vec();
+        elf.resize(20, 0);
+        elf[16] = 3;
+        elf[18] = 62;
+        assert_eq!(
+            magic.identify(&elf).unwrap(),
+            "ELF 64-bit LSB shared object, x86-64"
+        );
+
+        assert_eq!(
+            magic.identify(b"%PDF-1.7\n").unwrap(),
+            "PDF document, version 1.7"
+        );
+        assert_eq!(
+            magic.identify(b"\xff\xd8\xff\xe0").unwrap(),
+            "JPEG image data"
+        );
+        assert!(magic.identify(b"hello").is_none());
+        assert!(magic.identify(b"").is_none());
+    }
+
+    #[test]
+    fn test_indirect_and_relative() {
+        let magic = Magic::builtin();
+        let mut pe = vec![0u8; 0x60];
+        pe[..2].copy_from_slice(b"MZ");
+        pe[0x3c] = 0x40;
+        pe[0x40..0x44].copy_from_slice(b"PE\0\0");
+        pe[0x44..0x46].copy_from_slice(&0x8664u16.to_le_bytes());
+        pe[0x58..0x5a].copy_from_slice(&0x20bu16.to_le_bytes());
+        assert_eq!(magic.identify(&pe).unwrap(), "PE32+ executable, x86-64");
+
+        pe[0x40] = b'X';
+        assert_eq!(magic.identify(&pe).unwrap(), "MS-DOS executable");
+    }
+
+    #[test]
+    fn test_user_rules() {
+        let source = r"
+# a comment
+0       string      HEKS\ v         heks test file
+>6      byte        x               version %d
+>7      leshort&0xff00  >0x100      (big)
+0       regex       ^foo            skipped
+>0      byte        x               also skipped
+0       belong      0xcafebabe      overridden
+";
+        let mut magic = Magic::builtin();
+        let user = Magic::parse(source).unwrap();
+        assert_eq!(user.skipped, 1);
+        assert_eq!(user.len(), 2);
+        magic.extend(user);
+
+        assert_eq!(
+            magic.identify(b"HEKS v\x03\x00\x02").unwrap(),
+            "heks test file version 3 (big)"
+        );
+        assert_eq!(
+            magic.identify(b"HEKS v\x03\x00\x01").unwrap(),
+            "heks test file version 3"
+        );
+        assert_eq!(
+            magic.identify(b"\xca\xfe\xba\xbe\0\0\0\x34").unwrap(),
+            "overridden"
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            Magic::parse("0 string").unwrap_err(),
+            "line 1: expected offset, type and test"
+        );
+        assert_eq!(
+            Magic::parse("\n>0 byte 1 orphan").unwrap_err(),
+            "line 2: continuation without a parent"
+        );
+        assert_eq!(
+            Magic::parse("zero byte 1 bad").unwrap_err(),
+            "line 1: invalid offset zero"
+        );
+    }
+
+    #[test]
+    fn test_unescape() {
+        assert_eq!(
+            unescape(r"a\x41\0\101\ b\\").ok(),
+            Some(b"aA\0A b\\".to_vec())
+        );
+    }
+}
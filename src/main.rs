@@ -1,16 +1,27 @@
 use clap::Parser;
 use futures::{stream::FuturesUnordered, StreamExt};
-use heks::{source::FileSource, terminal::TerminalSetup, App, EventLoop};
+use heks::{config::Config, source::FileSource, terminal::TerminalSetup, App, EventLoop};
 use home::home_dir;
 use log::{error, info};
 use nix::unistd::getcwd;
-use std::{env, fs::OpenOptions, io, path::PathBuf, process::ExitCode, sync::Arc};
+use std::{
+    env,
+    fs::{self, OpenOptions},
+    io,
+    path::PathBuf,
+    process::ExitCode,
+    sync::Arc,
+};
 use tokio::signal::unix::{signal, SignalKind};
 use tui::{backend::CrosstermBackend, Terminal};
 
 #[derive(Parser, Debug)]
 struct Args {
     filename: PathBuf,
+
+    /// Additional magic(5) rule file for identifying file types
+    #[arg(long = "magic", value_name = "FILE")]
+    magic: Vec<PathBuf>,
 }
 
 fn install_exit_handler<F: FnMut() + Send + 'static>(mut handler: F) {
@@ -46,7 +57,6 @@ fn install_suspend_handler<F: FnMut() + Send + 'static>(mut handler: F) {
 }
 fn init_log_file(logger: &mut env_logger::Builder, path: PathBuf) {
     let log_file = OpenOptions::new()
-        .write(true)
         .append(true)
         .create(true)
         .open(path)
@@ -90,8 +100,36 @@ async fn main() -> ExitCode {
             panic!("{:?}", error);
         });
 
+    // The theme has to be picked before anything is drawn, so the config file
+    // is read up front; the rest of it is applied once there's an app.
+    let config_path = heks::config_dir()
+        .map(|dir| dir.join("config.toml"))
+        .filter(|path| path.exists());
+    let config = config_path.as_ref().map(|path| {
+        fs::read_to_string(path)
+            .map_err(|error| error.to_string())
+            .and_then(|source| Config::parse(&source))
+    });
+    if let Some(theme) = config
+        .as_ref()
+        .and_then(|config| config.as_ref().ok()?.theme)
+    {
+        heks::terminal::set_theme(theme);
+    }
+
     let _terminal_setup = TerminalSetup::new().unwrap();
-    let app = App::new(&mut terminal, source).unwrap();
+    let mut app = App::new(&mut terminal, source).unwrap();
+    if let (Some(path), Some(config)) = (&config_path, config) {
+        app.configure(path, config);
+    }
+    let user_magic = heks::config_dir().map(|dir| dir.join("magic"));
+    for path in user_magic
+        .iter()
+        .filter(|path| path.exists())
+        .chain(&args.magic)
+    {
+        app.load_magic(path);
+    }
     let mut event_loop = EventLoop::new(terminal, app);
 
     let done_clone = Arc::clone(&event_loop.done);
@@ -0,0 +1,236 @@
+use crossterm::event::{KeyCode, KeyEvent};
+use tui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Modifier, Style},
+    text::{Span, Spans},
+    widgets::{Block, Borders, Clear, Paragraph, Widget},
+};
+
+use crate::prompt::Prompt;
+use crate::terminal::color;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NavigatorEntry {
+    pub label: String,
+    pub detail: String,
+    pub offset: u64,
+}
+
+pub enum NavigatorEvent {
+    Pending,
+    Jump(u64),
+    Cancel,
+}
+
+// A popup list of named locations (sections, symbols, ...) that can be
+// filtered by typing and jumped to with enter.
+#[derive(Debug, Clone)]
+pub struct Navigator {
+    title: String,
+    entries: Vec<NavigatorEntry>,
+    filter: Prompt,
+    matches: Vec<usize>, // indices into `entries`
+    selected: usize,     // index into `matches`
+}
+
+impl Navigator {
+    pub fn new(title: impl Into<String>, entries: Vec<NavigatorEntry>) -> Self {
+        let mut navigator = Navigator {
+            title: title.into(),
+            entries,
+            filter: Prompt::new("/"),
+            matches: vec![],
+            selected: 0,
+        };
+        navigator.refilter();
+        navigator
+    }
+
+    fn refilter(&mut self) {
+        let filter = self.filter.text().to_lowercase();
+        self.matches = self
+            .entries
+            .iter()
+            .enumerate()
+            .filter(|(_, entry)| entry.label.to_lowercase().contains(&filter))
+            .map(|(index, _)| index)
+            .collect();
+        self.selected = self.selected.min(self.matches.len().saturating_sub(1));
+    }
+
+    pub fn selected(&self) -> Option<&NavigatorEntry> {
+        self.matches
+            .get(self.selected)
+            .map(|&index| &self.entries[index])
+    }
+
+    pub fn paste(&mut self, text: &str) {
+        self.filter.paste(text);
+        self.refilter();
+    }
+
+    pub fn on_key(&mut self, key: KeyEvent) -> NavigatorEvent {
+        const PAGE: usize = 10;
+        let last = self.matches.len().saturating_sub(1);
+
+        match key.code {
+            KeyCode::Esc => return NavigatorEvent::Cancel,
+            KeyCode::Enter => {
+                return match self.selected() {
+                    Some(entry) => NavigatorEvent::Jump(entry.offset),
+                    None => NavigatorEvent::Cancel,
+                }
+            }
+            KeyCode::Up => self.selected = self.selected.saturating_sub(1),
+            KeyCode::Down => self.selected = (self.selected + 1).min(last),
+            KeyCode::PageUp => self.selected = self.selected.saturating_sub(PAGE),
+            KeyCode::PageDown => self.selected = (self.selected + PAGE).min(last),
+            // Backspacing over an empty filter shouldn't close the navigator.
+            KeyCode::Backspace if self.filter.text().is_empty() => {}
+            _ => {
+                self.filter.on_key(key);
+                self.refilter();
+            }
+        }
+
+        NavigatorEvent::Pending
+    }
+}
+
+impl Widget for &Navigator {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let style = Style::default()
+            .bg(color(32, 32, 64))
+            .fg(color(224, 224, 224));
+        let style_detail = style.fg(color(128, 160, 255));
+        let style_selected = Style::default()
+            .bg(color(0, 96, 0))
+            .fg(color(96, 255, 96))
+            .add_modifier(Modifier::BOLD);
+
+        Clear.render(area, buf);
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .title(format!(
+                " {} ({}/{}) ",
+                self.title,
+                self.matches.len(),
+                self.entries.len()
+            ))
+            .style(style);
+        let inner = block.inner(area);
+        block.render(area, buf);
+        if inner.height < 2 {
+            return;
+        }
+
+        let filter_area = Rect { height: 1, ..inner };
+        Paragraph::new(self.filter.spans(style))
+            .style(style)
+            .render(filter_area, buf);
+
+        // Keep the selection in the middle of the list where possible.
+        let rows = (inner.height - 1) as usize;
+        let first = self
+            .selected
+            .saturating_sub(rows / 2)
+            .min(self.matches.len().saturating_sub(rows));
+
+        let lines: Vec<Spans> = self.matches[first..]
+            .iter()
+            .take(rows)
+            .enumerate()
+            .map(|(row, &index)| {
+                let entry = &self.entries[index];
+                let selected = first + row == self.selected;
+                let (style, style_detail) = if selected {
+                    (style_selected, style_selected)
+                } else {
+                    (style, style_detail)
+                };
+                let width = inner.width as usize;
+                let detail = format!(" {}", entry.detail);
+                let label_width = width.saturating_sub(detail.chars().count());
+                Spans::from(vec![
+                    Span::styled(
+                        format!("{:width$}", entry.label, width = label_width),
+                        style,
+                    ),
+                    Span::styled(detail, style_detail),
+                ])
+            })
+            .collect();
+
+        let list_area = Rect {
+            y: inner.y + 1,
+            height: inner.height - 1,
+            ..inner
+        };
+        Paragraph::new(lines).style(style).render(list_area, buf);
+    }
+}
+
+#[cfg(test)]
+mod navigator_tests {
+    use super::*;
+    use crossterm::event::KeyModifiers;
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    fn entries() -> Vec<NavigatorEntry> {
+        ["main", "_start", ".text", "maintenance"]
+            .iter()
+            .enumerate()
+            .map(|(index, label)| NavigatorEntry {
+                label: label.to_string(),
+                detail: String::new(),
+                offset: index as u64 * 0x10,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_select_and_jump() {
+        let mut navigator = Navigator::new("test", entries());
+        assert_eq!(navigator.selected().unwrap().label, "main");
+        navigator.on_key(key(KeyCode::Down));
+        navigator.on_key(key(KeyCode::Down));
+        assert_eq!(navigator.selected().unwrap().label, ".text");
+        navigator.on_key(key(KeyCode::PageDown));
+        assert_eq!(navigator.selected().unwrap().label, "maintenance");
+        assert!(matches!(
+            navigator.on_key(key(KeyCode::Enter)),
+            NavigatorEvent::Jump(0x30)
+        ));
+    }
+
+    #[test]
+    fn test_filter() {
+        let mut navigator = Navigator::new("test", entries());
+        navigator.on_key(key(KeyCode::Char('M')));
+        navigator.on_key(key(KeyCode::Char('a')));
+        navigator.on_key(key(KeyCode::Char('i')));
+        navigator.on_key(key(KeyCode::Down));
+        assert_eq!(navigator.selected().unwrap().label, "maintenance");
+        navigator.on_key(key(KeyCode::Char('x')));
+        assert!(navigator.selected().is_none());
+        assert!(matches!(
+            navigator.on_key(key(KeyCode::Enter)),
+            NavigatorEvent::Cancel
+        ));
+        navigator.on_key(key(KeyCode::Backspace));
+        assert_eq!(navigator.selected().unwrap().label, "main");
+    }
+
+    #[test]
+    fn test_paste() {
+        let mut navigator = Navigator::new("test", entries());
+        navigator.paste("tart\nmain");
+        assert_eq!(navigator.selected().unwrap().label, "_start");
+        navigator.on_key(key(KeyCode::Down));
+        assert_eq!(navigator.selected().unwrap().label, "_start");
+    }
+}
@@ -0,0 +1,123 @@
+// Following offsets and pointers stored in the file to where they point.
+
+use crate::decode::{decode_integer, Endianness};
+use crate::formats::Analysis;
+
+// What a value stored in the file is taken to be relative to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PointerMode {
+    Offset,  // the start of the file
+    Base,    // the base address offsets are shown relative to
+    Cursor,  // where the value itself is stored, as a signed distance
+    Virtual, // addresses as mapped by the file format, e.g. ELF segments
+}
+
+const MODES: &[PointerMode] = &[
+    PointerMode::Offset,
+    PointerMode::Base,
+    PointerMode::Cursor,
+    PointerMode::Virtual,
+];
+
+impl PointerMode {
+    pub fn next(self) -> PointerMode {
+        let index = MODES.iter().position(|&m| m == self).unwrap_or(0);
+        MODES[(index + 1) % MODES.len()]
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            PointerMode::Offset => "offset",
+            PointerMode::Base => "base",
+            PointerMode::Cursor => "cursor",
+            PointerMode::Virtual => "virtual",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<PointerMode> {
+        MODES.iter().copied().find(|m| m.name() == name)
+    }
+
+    pub fn names() -> Vec<&'static str> {
+        MODES.iter().map(|m| m.name()).collect()
+    }
+}
+
+// Where the value in `data`, stored at `location`, points to in the file.
+// `base` is the address the start of the file is shown at.
+pub fn target(
+    mode: PointerMode,
+    data: &[u8],
+    endianness: Endianness,
+    location: u64,
+    base: u64,
+    analysis: Option<&Analysis>,
+) -> Result<u64, String> {
+    if data.is_empty() || data.len() > 8 {
+        return Err(format!("can't follow a {}-byte pointer", data.len()));
+    }
+    let (signed, unsigned) = decode_integer(data, endianness);
+    let unsigned = unsigned as u64;
+    match mode {
+        PointerMode::Offset => Ok(unsigned),
+        PointerMode::Base => unsigned
+            .checked_sub(base)
+            .ok_or(format!("{:#x} is below the base {:#x}", unsigned, base)),
+        PointerMode::Cursor => u64::try_from(location as i128 + signed)
+            .map_err(|_| format!("{} bytes back is before the file", -signed)),
+        PointerMode::Virtual => analysis
+            .filter(|analysis| !analysis.mappings.is_empty())
+            .ok_or("no address mapping for this file".to_string())?
+            .address_to_offset(unsigned)
+            .ok_or(format!("{:#x} is not mapped", unsigned)),
+    }
+}
+
+#[cfg(test)]
+mod pointer_tests {
+    use super::*;
+    use crate::formats::Mapping;
+
+    #[test]
+    fn test_target() {
+        let data = [0x10, 0x20, 0, 0];
+        let little = Endianness::Little;
+        let target =
+            |mode, data: &[u8], analysis| target(mode, data, little, 0x100, 0x1000, analysis);
+
+        assert_eq!(target(PointerMode::Offset, &data, None), Ok(0x2010));
+        let big = super::target(PointerMode::Offset, &[0, 0x20], Endianness::Big, 0, 0, None);
+        assert_eq!(big, Ok(0x20));
+        assert_eq!(target(PointerMode::Base, &data, None), Ok(0x1010));
+        assert!(target(PointerMode::Base, &[0x10], None).is_err());
+        assert_eq!(target(PointerMode::Cursor, &data, None), Ok(0x2110));
+        assert_eq!(target(PointerMode::Cursor, &[0xf0], None), Ok(0xf0));
+        assert!(target(PointerMode::Cursor, &[0x00, 0xfe], None).is_err());
+
+        assert!(target(PointerMode::Virtual, &data, None).is_err());
+        let analysis = Analysis {
+            mappings: vec![Mapping {
+                offset: 0x400,
+                size: 0x100,
+                address: 0x2000,
+            }],
+            ..Default::default()
+        };
+        assert_eq!(
+            target(PointerMode::Virtual, &data, Some(&analysis)),
+            Ok(0x410)
+        );
+        assert!(target(PointerMode::Virtual, &[0x10], Some(&analysis)).is_err());
+
+        assert!(target(PointerMode::Offset, &[], None).is_err());
+        assert!(target(PointerMode::Offset, &[0; 9], None).is_err());
+    }
+
+    #[test]
+    fn test_names() {
+        for &mode in MODES {
+            assert_eq!(PointerMode::from_name(mode.name()), Some(mode));
+        }
+        assert_eq!(PointerMode::Virtual.next(), PointerMode::Offset);
+    }
+}
@@ -0,0 +1,310 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use tui::{
+    style::{Modifier, Style},
+    text::{Span, Spans},
+};
+
+pub enum PromptEvent {
+    Pending,
+    Submit(String),
+    Cancel,
+}
+
+// Ways to finish the word the text before the cursor ends with, and the
+// byte offset that word starts at.
+pub type Completer = fn(&str) -> (usize, Vec<String>);
+
+// A single line of text input, e.g. for the `:` command line.
+#[derive(Debug, Clone)]
+pub struct Prompt {
+    label: String,
+    text: Vec<char>,
+    position: usize, // in characters, not bytes
+    // Earlier lines to step through with up and down; stepping off the end
+    // brings back what was being typed.
+    history: Vec<String>,
+    history_index: usize,
+    draft: Vec<char>,
+    completer: Option<Completer>,
+    // While tab is pressed repeatedly: where the completed word starts, the
+    // candidates, and which one is showing.
+    completions: Option<(usize, Vec<String>, usize)>,
+}
+
+impl Prompt {
+    pub fn new(label: impl Into<String>) -> Self {
+        Prompt {
+            label: label.into(),
+            text: vec![],
+            position: 0,
+            history: vec![],
+            history_index: 0,
+            draft: vec![],
+            completer: None,
+            completions: None,
+        }
+    }
+
+    pub fn with_history(mut self, history: Vec<String>) -> Self {
+        self.history_index = history.len();
+        self.history = history;
+        self
+    }
+
+    pub fn with_completer(mut self, completer: Completer) -> Self {
+        self.completer = Some(completer);
+        self
+    }
+
+    pub fn text(&self) -> String {
+        self.text.iter().collect()
+    }
+
+    pub fn insert(&mut self, c: char) {
+        self.text.insert(self.position, c);
+        self.position += 1;
+    }
+
+    // Inserts pasted text, up to the end of its first line, as if typed.
+    pub fn paste(&mut self, text: &str) {
+        let line = text.lines().next().unwrap_or("");
+        line.chars()
+            .filter(|c| !c.is_control())
+            .for_each(|c| self.insert(c));
+    }
+
+    // Moves through the history, keeping what was typed to come back to.
+    fn recall(&mut self, index: usize) {
+        if self.history_index == self.history.len() {
+            self.draft = self.text.clone();
+        }
+        self.history_index = index;
+        self.text = match self.history.get(index) {
+            Some(line) => line.chars().collect(),
+            None => self.draft.clone(),
+        };
+        self.position = self.text.len();
+    }
+
+    // Replaces the word before the cursor with the first way to finish it,
+    // then with the next each time tab is pressed again. A single candidate
+    // is finished off with a space, unless it's a directory or a setting
+    // still missing its value.
+    fn complete(&mut self) {
+        let (start, candidates, index) = match self.completions.take() {
+            Some((start, candidates, index)) => {
+                let next = (index + 1) % candidates.len();
+                (start, candidates, next)
+            }
+            None => {
+                let Some(completer) = self.completer else {
+                    return;
+                };
+                let before: String = self.text[..self.position].iter().collect();
+                let (start, candidates) = completer(&before);
+                if candidates.is_empty() {
+                    return;
+                }
+                (before[..start].chars().count(), candidates, 0)
+            }
+        };
+
+        let mut replacement: Vec<char> = candidates[index].chars().collect();
+        if candidates.len() == 1 && !candidates[0].ends_with(['/', '=']) {
+            replacement.push(' ');
+        }
+        let len = replacement.len();
+        self.text.splice(start..self.position, replacement);
+        self.position = start + len;
+        if candidates.len() > 1 {
+            self.completions = Some((start, candidates, index));
+        }
+    }
+
+    pub fn on_key(&mut self, key: KeyEvent) -> PromptEvent {
+        if key.code == KeyCode::Tab {
+            self.complete();
+            return PromptEvent::Pending;
+        }
+        self.completions = None;
+
+        match (key.modifiers, key.code) {
+            (_, KeyCode::Enter) => return PromptEvent::Submit(self.text()),
+            (_, KeyCode::Esc) => return PromptEvent::Cancel,
+            (KeyModifiers::CONTROL, KeyCode::Char('u')) => {
+                self.text.drain(..self.position);
+                self.position = 0;
+            }
+            (KeyModifiers::CONTROL, KeyCode::Char('a')) | (_, KeyCode::Home) => self.position = 0,
+            (KeyModifiers::CONTROL, KeyCode::Char('e')) | (_, KeyCode::End) => {
+                self.position = self.text.len()
+            }
+            (KeyModifiers::NONE, KeyCode::Char(c)) | (KeyModifiers::SHIFT, KeyCode::Char(c)) => {
+                self.insert(c)
+            }
+            (_, KeyCode::Backspace) => {
+                // Like vim, backspacing over an empty prompt abandons it.
+                if self.text.is_empty() {
+                    return PromptEvent::Cancel;
+                }
+                if self.position > 0 {
+                    self.position -= 1;
+                    self.text.remove(self.position);
+                }
+            }
+            (_, KeyCode::Delete) => {
+                if self.position < self.text.len() {
+                    self.text.remove(self.position);
+                }
+            }
+            (_, KeyCode::Left) => self.position = self.position.saturating_sub(1),
+            (_, KeyCode::Right) => self.position = (self.position + 1).min(self.text.len()),
+            (_, KeyCode::Up) if self.history_index > 0 => self.recall(self.history_index - 1),
+            (_, KeyCode::Down) if self.history_index < self.history.len() => {
+                self.recall(self.history_index + 1)
+            }
+            (_, _) => {}
+        }
+
+        PromptEvent::Pending
+    }
+
+    pub fn spans<'a>(&self, style: Style) -> Spans<'a> {
+        let before: String = self.text[..self.position].iter().collect();
+        let under = self.text.get(self.position).copied().unwrap_or(' ');
+        let after: String = self.text[(self.position + 1).min(self.text.len())..]
+            .iter()
+            .collect();
+
+        Spans::from(vec![
+            Span::styled(self.label.clone(), style),
+            Span::styled(before, style),
+            Span::styled(under.to_string(), style.add_modifier(Modifier::REVERSED)),
+            Span::styled(after, style),
+        ])
+    }
+}
+
+#[cfg(test)]
+mod prompt_tests {
+    use super::*;
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    fn type_text(prompt: &mut Prompt, text: &str) {
+        text.chars().for_each(|c| {
+            prompt.on_key(key(KeyCode::Char(c)));
+        });
+    }
+
+    #[test]
+    fn test_typing_and_submit() {
+        let mut prompt = Prompt::new(":");
+        type_text(&mut prompt, "struct a.h");
+        assert_eq!(prompt.text(), "struct a.h");
+        match prompt.on_key(key(KeyCode::Enter)) {
+            PromptEvent::Submit(text) => assert_eq!(text, "struct a.h"),
+            _ => panic!("expected submit"),
+        }
+    }
+
+    #[test]
+    fn test_editing() {
+        let mut prompt = Prompt::new(":");
+        type_text(&mut prompt, "abd");
+        prompt.on_key(key(KeyCode::Left));
+        type_text(&mut prompt, "c");
+        assert_eq!(prompt.text(), "abcd");
+        prompt.on_key(key(KeyCode::Backspace));
+        assert_eq!(prompt.text(), "abd");
+        prompt.on_key(key(KeyCode::Home));
+        prompt.on_key(key(KeyCode::Delete));
+        assert_eq!(prompt.text(), "bd");
+        prompt.on_key(key(KeyCode::End));
+        type_text(&mut prompt, "e");
+        assert_eq!(prompt.text(), "bde");
+        prompt.on_key(KeyEvent::new(KeyCode::Char('u'), KeyModifiers::CONTROL));
+        assert_eq!(prompt.text(), "");
+    }
+
+    #[test]
+    fn test_paste() {
+        let mut prompt = Prompt::new(":");
+        type_text(&mut prompt, "go");
+        prompt.paste("to\t0x10\nset columns=8\n");
+        assert_eq!(prompt.text(), "goto0x10");
+        prompt.on_key(key(KeyCode::Home));
+        prompt.paste("");
+        prompt.paste("x");
+        assert_eq!(prompt.text(), "xgoto0x10");
+    }
+
+    #[test]
+    fn test_cancel() {
+        let mut prompt = Prompt::new(":");
+        assert!(matches!(
+            prompt.on_key(key(KeyCode::Esc)),
+            PromptEvent::Cancel
+        ));
+        assert!(matches!(
+            prompt.on_key(key(KeyCode::Backspace)),
+            PromptEvent::Cancel
+        ));
+        type_text(&mut prompt, "x");
+        assert!(matches!(
+            prompt.on_key(key(KeyCode::Backspace)),
+            PromptEvent::Pending
+        ));
+    }
+
+    #[test]
+    fn test_history() {
+        let history = vec!["first".to_string(), "second".to_string()];
+        let mut prompt = Prompt::new(":").with_history(history);
+        type_text(&mut prompt, "dra");
+        prompt.on_key(key(KeyCode::Up));
+        assert_eq!(prompt.text(), "second");
+        prompt.on_key(key(KeyCode::Up));
+        prompt.on_key(key(KeyCode::Up));
+        assert_eq!(prompt.text(), "first");
+        prompt.on_key(key(KeyCode::Down));
+        assert_eq!(prompt.text(), "second");
+        prompt.on_key(key(KeyCode::Down));
+        type_text(&mut prompt, "ft");
+        assert_eq!(prompt.text(), "draft");
+        prompt.on_key(key(KeyCode::Down));
+        assert_eq!(prompt.text(), "draft");
+    }
+
+    #[test]
+    fn test_completion() {
+        fn completer(line: &str) -> (usize, Vec<String>) {
+            let start = line.rfind(' ').map_or(0, |index| index + 1);
+            let words = ["table", "tab/", "image"];
+            let candidates = words
+                .iter()
+                .filter(|word| word.starts_with(&line[start..]))
+                .map(|word| word.to_string())
+                .collect();
+            (start, candidates)
+        }
+
+        let mut prompt = Prompt::new(":").with_completer(completer);
+        type_text(&mut prompt, "x i");
+        prompt.on_key(key(KeyCode::Tab));
+        assert_eq!(prompt.text(), "x image ");
+
+        type_text(&mut prompt, "ta");
+        prompt.on_key(key(KeyCode::Tab));
+        assert_eq!(prompt.text(), "x image table");
+        prompt.on_key(key(KeyCode::Tab));
+        assert_eq!(prompt.text(), "x image tab/");
+        prompt.on_key(key(KeyCode::Tab));
+        assert_eq!(prompt.text(), "x image table");
+        type_text(&mut prompt, "!");
+        prompt.on_key(key(KeyCode::Tab));
+        assert_eq!(prompt.text(), "x image table!");
+    }
+}
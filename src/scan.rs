@@ -0,0 +1,1002 @@
+// Scans data for things embedded in it (compressed streams, filesystems,
+// images, archives, certificates, executables), binwalk-style. Each
+// candidate signature is validated before being reported, and its length is
+// worked out where the format makes that possible, so that it can be carved
+// out into a file of its own.
+
+use std::io::{self, Read, Write};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use crate::decode::Endianness;
+use crate::formats::Reader;
+use crate::source::SharedData;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hit {
+    pub offset: u64,
+    // None if we can't tell where it ends.
+    pub length: Option<u64>,
+    pub description: String,
+    // File name extension to use when carving it out.
+    pub extension: &'static str,
+}
+
+type Validator = fn(&[u8]) -> Option<(String, Option<u64>)>;
+
+struct Signature {
+    magic: &'static [u8],
+    // Where the magic is relative to the start of what it identifies.
+    magic_offset: usize,
+    extension: &'static str,
+    // Whether to skip over the contents of a hit, e.g. the compressed files
+    // inside an archive, rather than reporting them too.
+    opaque: bool,
+    // Checks the candidate at the start of the data, returning a description
+    // and, if it can be worked out, its length.
+    validate: Validator,
+}
+
+const fn signature(
+    magic: &'static [u8],
+    extension: &'static str,
+    opaque: bool,
+    validate: Validator,
+) -> Signature {
+    Signature {
+        magic,
+        magic_offset: 0,
+        extension,
+        opaque,
+        validate,
+    }
+}
+
+const SIGNATURES: &[Signature] = &[
+    // Compressed streams
+    signature(b"\x1f\x8b\x08", "gz", true, gzip),
+    signature(b"\x78\x01", "zlib", true, zlib),
+    signature(b"\x78\x5e", "zlib", true, zlib),
+    signature(b"\x78\x9c", "zlib", true, zlib),
+    signature(b"\x78\xda", "zlib", true, zlib),
+    signature(b"BZh", "bz2", true, bzip2),
+    signature(b"\xfd7zXZ\x00", "xz", true, xz),
+    signature(b"\x28\xb5\x2f\xfd", "zst", true, zstd),
+    signature(b"\x04\x22\x4d\x18", "lz4", true, lz4),
+    // Filesystems and firmware images
+    signature(b"hsqs", "squashfs", true, squashfs),
+    signature(b"\x45\x3d\xcd\x28", "cramfs", true, cramfs),
+    signature(b"-rom1fs-", "romfs", true, romfs),
+    Signature {
+        magic: b"\x53\xef",
+        magic_offset: 1080,
+        extension: "ext",
+        opaque: true,
+        validate: ext,
+    },
+    Signature {
+        magic: b"\x01CD001\x01",
+        magic_offset: 0x8000,
+        extension: "iso",
+        opaque: true,
+        validate: iso9660,
+    },
+    signature(b"\x27\x05\x19\x56", "uimage", true, uimage),
+    signature(b"\xd0\x0d\xfe\xed", "dtb", true, device_tree),
+    // Images
+    signature(b"\x89PNG\r\n\x1a\n", "png", true, png),
+    signature(b"\xff\xd8\xff", "jpg", true, jpeg),
+    signature(b"GIF87a", "gif", true, gif),
+    signature(b"GIF89a", "gif", true, gif),
+    signature(b"BM", "bmp", true, bmp),
+    // Archives
+    signature(b"PK\x03\x04", "zip", true, zip),
+    Signature {
+        magic: b"ustar",
+        magic_offset: 257,
+        extension: "tar",
+        opaque: true,
+        validate: tar,
+    },
+    signature(b"7z\xbc\xaf\x27\x1c", "7z", true, seven_zip),
+    signature(b"Rar!\x1a\x07", "rar", true, rar),
+    signature(b"070701", "cpio", true, cpio),
+    // Certificates and keys
+    signature(b"-----BEGIN ", "pem", true, pem),
+    signature(b"\x30\x82", "der", true, der),
+    // Executables
+    signature(b"\x7fELF", "elf", false, elf),
+    signature(b"MZ", "exe", false, pe),
+];
+
+// Don't spend forever decompressing a stream just to find out where it ends.
+const MAX_INFLATE: u64 = 256 << 20;
+
+// How often to report progress, in bytes.
+const PROGRESS_INTERVAL: usize = 1 << 20;
+
+// Calls `on_hit` for each signature found in `data`, in order of the position
+// of their magic (which may be some way into what they identify). Stops
+// early if `cancel` is set.
+pub fn scan(data: &[u8], mut on_hit: impl FnMut(Hit), scanned: &AtomicU64, cancel: &AtomicBool) {
+    // Index the signatures by the first byte of their magic.
+    let mut by_byte: Vec<Vec<&Signature>> = vec![vec![]; 256];
+    for signature in SIGNATURES {
+        by_byte[signature.magic[0] as usize].push(signature);
+    }
+
+    let mut skip_until = 0;
+    for (position, &byte) in data.iter().enumerate() {
+        if position % PROGRESS_INTERVAL == 0 {
+            scanned.store(position as u64, Ordering::Relaxed);
+            if cancel.load(Ordering::Relaxed) {
+                return;
+            }
+        }
+
+        for signature in by_byte[byte as usize].iter() {
+            let Some(start) = position.checked_sub(signature.magic_offset) else {
+                continue;
+            };
+            if start < skip_until || !data[position..].starts_with(signature.magic) {
+                continue;
+            }
+            let Some((description, length)) = (signature.validate)(&data[start..]) else {
+                continue;
+            };
+            let length = length.filter(|&length| length <= (data.len() - start) as u64);
+            if let (true, Some(length)) = (signature.opaque, length) {
+                skip_until = start + length as usize;
+            }
+            on_hit(Hit {
+                offset: start as u64,
+                length,
+                description,
+                extension: signature.extension,
+            });
+            break;
+        }
+    }
+    scanned.store(data.len() as u64, Ordering::Relaxed);
+}
+
+// A scan running in a background thread.
+pub struct Scan {
+    hits: Arc<Mutex<Vec<Hit>>>,
+    scanned: Arc<AtomicU64>,
+    done: Arc<AtomicBool>,
+    cancel: Arc<AtomicBool>,
+    total: u64,
+}
+
+impl Scan {
+    pub fn start(data: SharedData) -> Scan {
+        let scan = Scan {
+            hits: Arc::new(Mutex::new(vec![])),
+            scanned: Arc::new(AtomicU64::new(0)),
+            done: Arc::new(AtomicBool::new(false)),
+            cancel: Arc::new(AtomicBool::new(false)),
+            total: (*data).as_ref().len() as u64,
+        };
+
+        let hits = scan.hits.clone();
+        let scanned = scan.scanned.clone();
+        let done = scan.done.clone();
+        let cancel = scan.cancel.clone();
+        thread::spawn(move || {
+            let on_hit = |hit: Hit| {
+                let mut hits = hits.lock().unwrap();
+                // Hits whose magic is some way in may start before earlier ones.
+                let index = hits.partition_point(|other| other.offset <= hit.offset);
+                hits.insert(index, hit);
+            };
+            self::scan((*data).as_ref(), on_hit, &scanned, &cancel);
+            done.store(true, Ordering::Release);
+        });
+
+        scan
+    }
+
+    pub fn hits(&self) -> Vec<Hit> {
+        self.hits.lock().unwrap().clone()
+    }
+
+    pub fn hit_count(&self) -> usize {
+        self.hits.lock().unwrap().len()
+    }
+
+    // How far along the scan is, from 0 to 1.
+    pub fn progress(&self) -> f64 {
+        if self.total == 0 {
+            1.0
+        } else {
+            self.scanned.load(Ordering::Relaxed) as f64 / self.total as f64
+        }
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.done.load(Ordering::Acquire)
+    }
+}
+
+impl Drop for Scan {
+    fn drop(&mut self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+}
+
+fn le(data: &[u8]) -> Reader<'_> {
+    Reader::new(data, Endianness::Little)
+}
+
+fn be(data: &[u8]) -> Reader<'_> {
+    Reader::new(data, Endianness::Big)
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+// Reads a NUL-terminated (or fixed-size, NUL-padded) string, lossily.
+fn text(bytes: &[u8]) -> String {
+    let len = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+    String::from_utf8_lossy(&bytes[..len]).trim().to_string()
+}
+
+// Checksums of decompressed data, computed as it's produced.
+#[derive(Default)]
+struct Checksums {
+    crc: crc32fast::Hasher,
+    adler_a: u32,
+    adler_b: u32,
+    produced: u64,
+}
+
+impl Write for Checksums {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.crc.update(buf);
+        for &b in buf {
+            self.adler_a = (self.adler_a + b as u32) % 65521;
+            self.adler_b = (self.adler_b + self.adler_a) % 65521;
+        }
+        self.produced += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+struct Inflated {
+    // Size of the compressed stream, if we got to the end of it.
+    consumed: Option<u64>,
+    produced: u64,
+    crc: u32,
+    adler: u32,
+}
+
+// Decompresses the raw deflate stream at the start of `data`.
+fn inflate(data: &[u8]) -> Option<Inflated> {
+    let mut decoder = flate2::bufread::DeflateDecoder::new(data);
+    let mut checksums = Checksums {
+        adler_a: 1,
+        ..Default::default()
+    };
+    io::copy(&mut (&mut decoder).take(MAX_INFLATE), &mut checksums).ok()?;
+    let complete = checksums.produced < MAX_INFLATE;
+    let remaining = decoder.into_inner().len();
+    Some(Inflated {
+        consumed: complete.then_some((data.len() - remaining) as u64),
+        produced: checksums.produced,
+        crc: checksums.crc.finalize(),
+        adler: (checksums.adler_b << 16) | checksums.adler_a,
+    })
+}
+
+fn gzip(data: &[u8]) -> Option<(String, Option<u64>)> {
+    let r = le(data);
+    let flags = r.u8(3)?;
+    if flags & 0xe0 != 0 {
+        return None;
+    }
+    let mut offset = 10;
+    if flags & 0x04 != 0 {
+        offset += 2 + r.u16(10)? as usize;
+    }
+    let mut name = String::new();
+    if flags & 0x08 != 0 {
+        let len = data.get(offset..)?.iter().position(|&b| b == 0)?;
+        name = text(&data[offset..offset + len]);
+        offset += len + 1;
+    }
+    if flags & 0x10 != 0 {
+        offset += data.get(offset..)?.iter().position(|&b| b == 0)? + 1;
+    }
+    if flags & 0x02 != 0 {
+        offset += 2;
+    }
+
+    let inflated = inflate(data.get(offset..)?)?;
+    let mut description = "gzip compressed data".to_string();
+    if !name.is_empty() {
+        description.push_str(&format!(", was \"{}\"", name));
+    }
+    let length = inflated
+        .consumed
+        .map(|consumed| offset as u64 + consumed + 8);
+    if let Some(length) = length {
+        if r.u32(length - 8) != Some(inflated.crc) {
+            description.push_str(", bad CRC");
+        }
+    }
+    Some((description, length))
+}
+
+fn zlib(data: &[u8]) -> Option<(String, Option<u64>)> {
+    let r = be(data);
+    // The header is a multiple of 31, and we don't support preset
+    // dictionaries.
+    if r.u16(0)? % 31 != 0 || data[1] & 0x20 != 0 {
+        return None;
+    }
+    let inflated = inflate(&data[2..])?;
+    // Short streams are too likely to be coincidences.
+    if inflated.produced < 32 {
+        return None;
+    }
+    let length = inflated.consumed.map(|consumed| 2 + consumed + 4);
+    if let Some(length) = length {
+        if r.u32(length - 4) != Some(inflated.adler) {
+            return None;
+        }
+    }
+    Some((
+        format!(
+            "zlib compressed data, {} bytes uncompressed",
+            inflated.produced
+        ),
+        length,
+    ))
+}
+
+fn bzip2(data: &[u8]) -> Option<(String, Option<u64>)> {
+    let level = *data.get(3)?;
+    if !(b'1'..=b'9').contains(&level) || data.get(4..10)? != b"\x31\x41\x59\x26\x53\x59" {
+        return None;
+    }
+    Some((
+        format!("bzip2 compressed data, block size {}00k", level as char),
+        None,
+    ))
+}
+
+fn xz(data: &[u8]) -> Option<(String, Option<u64>)> {
+    // The stream flags are followed by their CRC.
+    let flags = data.get(6..8)?;
+    if flags[0] != 0 || flags[1] > 0x0f || le(data).u32(8)? != crc32fast::hash(flags) {
+        return None;
+    }
+    Some(("XZ compressed data".to_string(), None))
+}
+
+fn zstd(data: &[u8]) -> Option<(String, Option<u64>)> {
+    let r = le(data);
+    let descriptor = r.u8(4)?;
+    if descriptor & 0x08 != 0 {
+        return None;
+    }
+    let single_segment = descriptor & 0x20 != 0;
+    let checksum = descriptor & 0x04 != 0;
+    let dictionary_size = [0, 1, 2, 4][(descriptor & 0x03) as usize];
+    let content_size_size = match descriptor >> 6 {
+        0 if single_segment => 1,
+        0 => 0,
+        1 => 2,
+        2 => 4,
+        _ => 8,
+    };
+    let mut offset = 5 + u64::from(!single_segment) + dictionary_size + content_size_size;
+
+    loop {
+        let header = r.uint(offset, 3)?;
+        let last = header & 1 != 0;
+        let size = match (header >> 1) & 3 {
+            0 | 2 => header >> 3,
+            1 => 1,
+            _ => return None,
+        };
+        offset += 3 + size;
+        if last {
+            break;
+        }
+    }
+    if checksum {
+        offset += 4;
+    }
+    Some(("Zstandard compressed data".to_string(), Some(offset)))
+}
+
+fn lz4(data: &[u8]) -> Option<(String, Option<u64>)> {
+    let r = le(data);
+    let flags = r.u8(4)?;
+    if flags >> 6 != 1 || flags & 0x02 != 0 {
+        return None;
+    }
+    let block_checksum = flags & 0x10 != 0;
+    let mut offset =
+        7 + if flags & 0x08 != 0 { 8 } else { 0 } + if flags & 0x01 != 0 { 4 } else { 0 };
+    loop {
+        let size = r.u32(offset)? as u64;
+        offset += 4;
+        if size == 0 {
+            break;
+        }
+        offset += (size & 0x7fffffff) + if block_checksum { 4 } else { 0 };
+    }
+    if flags & 0x04 != 0 {
+        offset += 4;
+    }
+    Some(("LZ4 compressed data".to_string(), Some(offset)))
+}
+
+fn squashfs(data: &[u8]) -> Option<(String, Option<u64>)> {
+    let r = le(data);
+    let (major, minor) = (r.u16(28)?, r.u16(30)?);
+    if !(2..=4).contains(&major) {
+        return None;
+    }
+    let inodes = r.u32(4)?;
+    let size = if major == 4 {
+        r.u64(40)?
+    } else {
+        r.u32(8)? as u64
+    };
+    Some((
+        format!(
+            "Squashfs filesystem, version {}.{}, {} inodes",
+            major, minor, inodes
+        ),
+        Some(size),
+    ))
+}
+
+fn cramfs(data: &[u8]) -> Option<(String, Option<u64>)> {
+    if data.get(16..32)? != b"Compressed ROMFS" {
+        return None;
+    }
+    let size = le(data).u32(4)? as u64;
+    Some((
+        format!("cramfs filesystem, \"{}\"", text(data.get(48..64)?)),
+        Some(size),
+    ))
+}
+
+fn romfs(data: &[u8]) -> Option<(String, Option<u64>)> {
+    let size = be(data).u32(8)? as u64;
+    let name = text(data.get(16..data.len().min(16 + 64))?);
+    Some((format!("romfs filesystem, \"{}\"", name), Some(size)))
+}
+
+fn ext(data: &[u8]) -> Option<(String, Option<u64>)> {
+    let r = le(data);
+    let blocks = r.u32(1024 + 4)? as u64;
+    let log_block_size = r.u32(1024 + 24)?;
+    if blocks == 0 || log_block_size > 6 {
+        return None;
+    }
+    let block_size = 1024u64 << log_block_size;
+    let journal = r.u32(1024 + 92)? & 0x4 != 0;
+    let name = text(data.get(1024 + 120..1024 + 136)?);
+    Some((
+        format!(
+            "Linux {} filesystem, {} blocks of {} bytes{}",
+            if journal { "ext3/4" } else { "ext2" },
+            blocks,
+            block_size,
+            if name.is_empty() {
+                String::new()
+            } else {
+                format!(", \"{}\"", name)
+            }
+        ),
+        Some(blocks * block_size),
+    ))
+}
+
+fn iso9660(data: &[u8]) -> Option<(String, Option<u64>)> {
+    let r = le(data);
+    let blocks = r.u32(0x8050)? as u64;
+    let block_size = r.u16(0x8080)? as u64;
+    if block_size == 0 {
+        return None;
+    }
+    Some((
+        format!(
+            "ISO 9660 filesystem, \"{}\"",
+            text(data.get(0x8028..0x8048)?)
+        ),
+        Some(blocks * block_size),
+    ))
+}
+
+fn uimage(data: &[u8]) -> Option<(String, Option<u64>)> {
+    let r = be(data);
+    // The header CRC is computed with the CRC field zeroed.
+    let mut header = data.get(..64)?.to_vec();
+    header[4..8].fill(0);
+    if r.u32(4)? != crc32fast::hash(&header) {
+        return None;
+    }
+    let size = r.u32(12)? as u64;
+    Some((
+        format!("uImage, \"{}\"", text(&data[32..64])),
+        Some(64 + size),
+    ))
+}
+
+fn device_tree(data: &[u8]) -> Option<(String, Option<u64>)> {
+    let r = be(data);
+    let (size, version) = (r.u32(4)? as u64, r.u32(20)?);
+    if !(16..=17).contains(&version) || size < 40 {
+        return None;
+    }
+    Some((
+        format!("Flattened device tree, version {}", version),
+        Some(size),
+    ))
+}
+
+fn png(data: &[u8]) -> Option<(String, Option<u64>)> {
+    let r = be(data);
+    if data.get(12..16)? != b"IHDR" {
+        return None;
+    }
+    let (width, height) = (r.u32(16)?, r.u32(20)?);
+    let mut offset = 8;
+    loop {
+        let (length, kind) = (r.u32(offset)? as u64, r.bytes(offset + 4, 4)?);
+        if !kind.iter().all(|b| b.is_ascii_alphabetic()) {
+            return None;
+        }
+        offset += 12 + length;
+        if kind == b"IEND" {
+            break;
+        }
+    }
+    Some((format!("PNG image, {} x {}", width, height), Some(offset)))
+}
+
+fn jpeg(data: &[u8]) -> Option<(String, Option<u64>)> {
+    let r = be(data);
+    let mut size = None;
+    let mut offset = 2;
+    loop {
+        if r.u8(offset)? != 0xff {
+            return None;
+        }
+        let marker = r.u8(offset + 1)?;
+        match marker {
+            0xd9 => {
+                offset += 2;
+                break;
+            }
+            0xd0..=0xd7 | 0x01 => offset += 2,
+            0xff => offset += 1,
+            _ => {
+                let length = r.u16(offset + 2)? as u64;
+                if length < 2 {
+                    return None;
+                }
+                if matches!(marker, 0xc0..=0xc3) {
+                    size = Some((r.u16(offset + 7)?, r.u16(offset + 5)?));
+                }
+                offset += 2 + length;
+                // Entropy-coded data runs until the next marker that isn't a
+                // stuffed zero or a restart.
+                if marker == 0xda {
+                    let start = offset as usize;
+                    let rest = data.get(start..)?;
+                    let end = rest.windows(2).position(|pair| {
+                        pair[0] == 0xff && pair[1] != 0 && !(0xd0..=0xd7).contains(&pair[1])
+                    })?;
+                    offset += end as u64;
+                }
+            }
+        }
+    }
+    let description = match size {
+        Some((width, height)) => format!("JPEG image, {} x {}", width, height),
+        None => "JPEG image".to_string(),
+    };
+    Some((description, Some(offset)))
+}
+
+fn gif(data: &[u8]) -> Option<(String, Option<u64>)> {
+    let r = le(data);
+    let (width, height, flags) = (r.u16(6)?, r.u16(8)?, r.u8(10)?);
+    let color_table = |flags: u8| {
+        if flags & 0x80 != 0 {
+            3 << ((flags & 7) + 1)
+        } else {
+            0
+        }
+    };
+    let sub_blocks = |mut offset: u64| -> Option<u64> {
+        loop {
+            let size = r.u8(offset)? as u64;
+            offset += 1 + size;
+            if size == 0 {
+                return Some(offset);
+            }
+        }
+    };
+
+    let mut offset = 13 + color_table(flags);
+    loop {
+        match r.u8(offset)? {
+            0x3b => break,
+            0x21 => offset = sub_blocks(offset + 2)?,
+            0x2c => {
+                let flags = r.u8(offset + 9)?;
+                offset = sub_blocks(offset + 10 + color_table(flags) + 1)?;
+            }
+            _ => return None,
+        }
+    }
+    Some((
+        format!("GIF image, {} x {}", width, height),
+        Some(offset + 1),
+    ))
+}
+
+fn bmp(data: &[u8]) -> Option<(String, Option<u64>)> {
+    let r = le(data);
+    let (size, reserved, pixels, header) = (r.u32(2)?, r.u32(6)?, r.u32(10)?, r.u32(14)?);
+    if reserved != 0 || pixels >= size || ![12, 40, 52, 56, 108, 124].contains(&header) {
+        return None;
+    }
+    let (width, height) = if header == 12 {
+        (r.u16(18)? as i32, r.u16(20)? as i16 as i32)
+    } else {
+        (r.u32(18)? as i32, r.u32(22)? as i32)
+    };
+    if width <= 0 || height == 0 {
+        return None;
+    }
+    Some((
+        format!("BMP image, {} x {}", width, height.abs()),
+        Some(size as u64),
+    ))
+}
+
+fn zip(data: &[u8]) -> Option<(String, Option<u64>)> {
+    let r = le(data);
+    let (version, name_length) = (r.u16(4)?, r.u16(26)? as usize);
+    if version > 63 || name_length == 0 || name_length > 1024 {
+        return None;
+    }
+    let name = text(data.get(30..30 + name_length)?);
+    // The archive ends with the end of central directory record.
+    let length = find(data, b"PK\x05\x06").and_then(|end| {
+        let comment = r.u16(end as u64 + 20)? as u64;
+        Some(end as u64 + 22 + comment)
+    });
+    Some((format!("Zip archive, first entry \"{}\"", name), length))
+}
+
+fn octal(bytes: &[u8]) -> Option<u64> {
+    let text = std::str::from_utf8(bytes).ok()?;
+    let text = text.trim_matches(|c: char| c == '\0' || c == ' ');
+    u64::from_str_radix(text, 8).ok()
+}
+
+fn tar(data: &[u8]) -> Option<(String, Option<u64>)> {
+    let header = |offset: usize| -> Option<&[u8]> {
+        let header = data.get(offset..offset + 512)?;
+        // The checksum treats its own field as spaces.
+        let sum: u64 = header
+            .iter()
+            .enumerate()
+            .map(|(index, &b)| if (148..156).contains(&index) { b' ' } else { b } as u64)
+            .sum();
+        (octal(&header[148..156])? == sum).then_some(header)
+    };
+
+    let name = text(&header(0)?[..100]);
+    let mut offset = 0;
+    let mut files = 0;
+    // The archive ends with zeroed blocks (or just the end of the data).
+    while let Some(entry) = header(offset) {
+        let size = octal(&entry[124..136])? as usize;
+        offset += 512 + size.div_ceil(512) * 512;
+        files += 1;
+    }
+    if data
+        .get(offset..offset + 1024)
+        .is_some_and(|end| end.iter().all(|&b| b == 0))
+    {
+        offset += 1024;
+    }
+    Some((
+        format!("POSIX tar archive, {} files, first \"{}\"", files, name),
+        Some(offset as u64),
+    ))
+}
+
+fn seven_zip(data: &[u8]) -> Option<(String, Option<u64>)> {
+    let r = le(data);
+    if r.u8(6)? != 0 || r.u32(8)? != crc32fast::hash(data.get(12..32)?) {
+        return None;
+    }
+    let (next_offset, next_size) = (r.u64(12)?, r.u64(20)?);
+    Some((
+        "7-zip archive".to_string(),
+        32u64
+            .checked_add(next_offset)
+            .and_then(|end| end.checked_add(next_size)),
+    ))
+}
+
+fn rar(data: &[u8]) -> Option<(String, Option<u64>)> {
+    match data.get(6..8)? {
+        [0x00, _] => Some(("RAR archive, version 4".to_string(), None)),
+        [0x01, 0x00] => Some(("RAR archive, version 5".to_string(), None)),
+        _ => None,
+    }
+}
+
+fn cpio(data: &[u8]) -> Option<(String, Option<u64>)> {
+    let hex = |offset: usize| {
+        let field = std::str::from_utf8(data.get(offset..offset + 8)?).ok()?;
+        u64::from_str_radix(field, 16).ok()
+    };
+
+    let mut offset = 0;
+    let mut files = 0;
+    loop {
+        if data.get(offset..offset + 6)? != b"070701" {
+            return None;
+        }
+        let (file_size, name_size) = (hex(offset + 54)? as usize, hex(offset + 94)? as usize);
+        let name = text(data.get(offset + 110..offset + 110 + name_size)?);
+        offset = (offset + 110 + name_size).next_multiple_of(4);
+        offset = (offset + file_size).next_multiple_of(4);
+        if name == "TRAILER!!!" {
+            break;
+        }
+        files += 1;
+    }
+    Some((
+        format!("ASCII cpio archive, {} files", files),
+        Some(offset as u64),
+    ))
+}
+
+fn pem(data: &[u8]) -> Option<(String, Option<u64>)> {
+    let header = data.get(11..data.len().min(11 + 64))?;
+    let label_length = find(header, b"-----")?;
+    let label = std::str::from_utf8(&header[..label_length]).ok()?;
+    if label.is_empty() || !label.bytes().all(|b| b.is_ascii_uppercase() || b == b' ') {
+        return None;
+    }
+    let footer = format!("-----END {}-----", label);
+    let window = &data[..data.len().min(1 << 20)];
+    let length = find(window, footer.as_bytes()).map(|end| (end + footer.len()) as u64);
+    Some((format!("PEM {}", label.to_lowercase()), length))
+}
+
+fn der(data: &[u8]) -> Option<(String, Option<u64>)> {
+    // A certificate is a sequence of the to-be-signed certificate, the
+    // signature algorithm and the signature.
+    let r = be(data);
+    let length = r.u16(2)? as u64 + 4;
+    if r.u16(4)? != 0x3082 {
+        return None;
+    }
+    let signed = r.u16(6)? as u64 + 4;
+    if 4 + signed >= length || r.u8(4 + signed)? != 0x30 || r.u8(8)? != 0xa0 {
+        return None;
+    }
+    Some(("DER certificate".to_string(), Some(length)))
+}
+
+fn elf(data: &[u8]) -> Option<(String, Option<u64>)> {
+    let (class, encoding, version) = (*data.get(4)?, *data.get(5)?, *data.get(6)?);
+    let endianness = match encoding {
+        1 => Endianness::Little,
+        2 => Endianness::Big,
+        _ => return None,
+    };
+    if !(1..=2).contains(&class) || version != 1 {
+        return None;
+    }
+    let r = Reader::new(data, endianness);
+    let w = if class == 2 { 8 } else { 4 };
+    let kind = r.u16(16)?;
+    let shoff = r.uint(24 + 2 * w, w)?;
+    let shentsize = r.u16(34 + 3 * w)? as u64;
+    let shnum = r.u16(36 + 3 * w)? as u64;
+
+    let kind = match kind {
+        1 => "relocatable",
+        2 => "executable",
+        3 => "shared object",
+        4 => "core file",
+        _ => return None,
+    };
+    // Section headers usually come last.
+    let length = (shoff > 0).then(|| shoff + shentsize * shnum);
+    Some((
+        format!(
+            "ELF {}-bit {} {}",
+            w * 8,
+            if encoding == 1 { "LSB" } else { "MSB" },
+            kind
+        ),
+        length,
+    ))
+}
+
+fn pe(data: &[u8]) -> Option<(String, Option<u64>)> {
+    let r = le(data);
+    let lfanew = r.u32(0x3c)? as u64;
+    if lfanew > 0x1000 || r.bytes(lfanew, 4)? != b"PE\0\0" {
+        return None;
+    }
+    let sections = r.u16(lfanew + 6)? as u64;
+    let optional_size = r.u16(lfanew + 20)? as u64;
+    let kind = match r.u16(lfanew + 24)? {
+        0x10b => "PE32",
+        0x20b => "PE32+",
+        _ => return None,
+    };
+    // The file ends with the last section's raw data.
+    let table = lfanew + 24 + optional_size;
+    let length = (0..sections.min(96))
+        .filter_map(|index| {
+            let section = table + index * 40;
+            Some(r.u32(section + 20)? as u64 + r.u32(section + 16)? as u64)
+        })
+        .max();
+    let dll = r.u16(lfanew + 22)? & 0x2000 != 0;
+    Some((
+        format!("{} {}", kind, if dll { "DLL" } else { "executable" }),
+        length,
+    ))
+}
+
+#[cfg(test)]
+mod scan_tests {
+    use super::*;
+    use flate2::write::{GzEncoder, ZlibEncoder};
+    use flate2::Compression;
+
+    fn scan_all(data: &[u8]) -> Vec<Hit> {
+        let mut hits = vec![];
+        scan(
+            data,
+            |hit| hits.push(hit),
+            &AtomicU64::new(0),
+            &AtomicBool::new(false),
+        );
+        hits
+    }
+
+    fn png_chunk(kind: &[u8], data: &[u8]) -> Vec<u8> {
+        let mut chunk = (data.len() as u32).to_be_bytes().to_vec();
+        chunk.extend(kind);
+        chunk.extend(data);
+        let crc = crc32fast::hash(&chunk[4..]);
+        chunk.extend(crc.to_be_bytes());
+        chunk
+    }
+
+    #[test]
+    fn test_embedded() {
+        let text = b"All work and no play makes Jack a dull boy. ".repeat(20);
+
+        let mut gzip = GzEncoder::new(vec![], Compression::default());
+        gzip.write_all(&text).unwrap();
+        let gzip = gzip.finish().unwrap();
+
+        let mut zlib = ZlibEncoder::new(vec![], Compression::default());
+        zlib.write_all(&text).unwrap();
+        let zlib = zlib.finish().unwrap();
+
+        let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+        png.extend(png_chunk(b"IHDR", &[0, 0, 0, 4, 0, 0, 0, 2, 8, 0, 0, 0, 0]));
+        png.extend(png_chunk(b"IEND", b""));
+
+        let mut data = vec![0u8; 100];
+        data.extend(&gzip);
+        data.extend([0u8; 37]);
+        data.extend(&png);
+        data.extend(b"junk");
+        data.extend(&zlib);
+        data.extend(b"x\x9c not really zlib");
+
+        let hits = scan_all(&data);
+        assert_eq!(
+            hits,
+            vec![
+                Hit {
+                    offset: 100,
+                    length: Some(gzip.len() as u64),
+                    description: "gzip compressed data".to_string(),
+                    extension: "gz",
+                },
+                Hit {
+                    offset: 137 + gzip.len() as u64,
+                    length: Some(png.len() as u64),
+                    description: "PNG image, 4 x 2".to_string(),
+                    extension: "png",
+                },
+                Hit {
+                    offset: 141 + (gzip.len() + png.len()) as u64,
+                    length: Some(zlib.len() as u64),
+                    description: "zlib compressed data, 880 bytes uncompressed".to_string(),
+                    extension: "zlib",
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_magic_offset() {
+        // A tar header has its magic at 257.
+        let mut header = vec![0u8; 512];
+        header[..5].copy_from_slice(b"hello");
+        header[124..135].copy_from_slice(b"00000000005");
+        header[257..263].copy_from_slice(b"ustar\0");
+        header[148..156].fill(b' ');
+        let sum: u64 = header.iter().map(|&b| b as u64).sum();
+        header[148..155].copy_from_slice(format!("{:06o}\0", sum).as_bytes());
+
+        let mut data = vec![0xaau8; 16];
+        data.extend(&header);
+        data.extend(b"world");
+        data.resize(16 + 1024 + 1024, 0);
+        data.extend([0xaa; 16]);
+
+        let hits = scan_all(&data);
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].offset, 16);
+        assert_eq!(hits[0].length, Some(2048));
+        assert_eq!(
+            hits[0].description,
+            "POSIX tar archive, 1 files, first \"hello\""
+        );
+    }
+
+    #[test]
+    fn test_validation() {
+        // Magic numbers alone aren't enough.
+        assert!(scan_all(b"\x1f\x8b\x08\x00 garbage garbage garbage").is_empty());
+        assert!(scan_all(b"BM and then some text that isn't a bitmap").is_empty());
+        assert!(scan_all(b"MZ, but no PE header").is_empty());
+        assert!(scan_all(b"-----BEGIN lowercase-----").is_empty());
+    }
+
+    #[test]
+    fn test_pem_and_cancel() {
+        let data = b"xx-----BEGIN CERTIFICATE-----\nMIIB\n-----END CERTIFICATE-----\n";
+        let hits = scan_all(data);
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].offset, 2);
+        assert_eq!(hits[0].length, Some(data.len() as u64 - 3));
+        assert_eq!(hits[0].description, "PEM certificate");
+
+        let mut hits = vec![];
+        scan(
+            data,
+            |hit| hits.push(hit),
+            &AtomicU64::new(0),
+            &AtomicBool::new(true),
+        );
+        assert!(hits.is_empty());
+    }
+}
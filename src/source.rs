@@ -1,5 +1,6 @@
 use memmap2::{Mmap, MmapOptions};
-use std::io::{self, ErrorKind};
+use std::io;
+use std::sync::Arc;
 use std::{cmp::min, fs::File, ops::Range, path::PathBuf};
 
 use crate::cursor::Cursor;
@@ -39,11 +40,30 @@ impl<'a> Slice<'a> {
     }
 }
 
+// The whole of a source's data, shareable with background threads.
+pub type SharedData = Arc<dyn AsRef<[u8]> + Send + Sync>;
+
 pub trait DataSource {
     fn name(&self) -> &str;
-    fn fetch(&mut self, start: u64, end: u64) -> Slice;
+    fn fetch(&mut self, start: u64, end: u64) -> Slice<'_>;
 
     fn fraction(&self, index: u64) -> f64;
+
+    // Unlike `fetch`, which may move the range to fit the source, returns
+    // exactly the bytes at `start..end`, cut short if the source ends first.
+    fn read(&mut self, start: u64, end: u64) -> &[u8] {
+        let slice = self.fetch(start, end);
+        if start < slice.location_start || start >= slice.location_end {
+            return &[];
+        }
+        let offset = (start - slice.location_start) as usize;
+        let len = (end.min(slice.location_end) - start) as usize;
+        &slice.data[offset..offset + len]
+    }
+
+    fn shared(&self) -> Option<SharedData> {
+        None
+    }
 }
 
 struct DebugSource {
@@ -69,7 +89,7 @@ impl DataSource for DebugSource {
         "debug"
     }
 
-    fn fetch(&mut self, _offset: u64, _end: u64) -> Slice {
+    fn fetch(&mut self, _offset: u64, _end: u64) -> Slice<'_> {
         Slice {
             data: self.buffer,
             location_start: 0,
@@ -81,26 +101,33 @@ impl DataSource for DebugSource {
         let max = (self.buffer.len() - 1) as u64;
         index.clamp(0, max) as f64 / max as f64
     }
+
+    fn shared(&self) -> Option<SharedData> {
+        Some(Arc::new(self.buffer))
+    }
 }
 
 pub struct FileSource {
     name: String,
-    mmap: Mmap,
+    mmap: Arc<Mmap>,
 }
 
 impl FileSource {
     pub fn new(filename: &PathBuf) -> Result<Self, io::Error> {
         let name = filename
             .to_str()
-            .ok_or(io::Error::new(
-                ErrorKind::Other,
-                format!("Unable to parse filename {:?}", filename),
-            ))?
+            .ok_or(io::Error::other(format!(
+                "Unable to parse filename {:?}",
+                filename
+            )))?
             .to_string();
         let file = File::open(filename)?;
         let mmap = unsafe { MmapOptions::new().map(&file)? };
 
-        Ok(FileSource { name, mmap })
+        Ok(FileSource {
+            name,
+            mmap: Arc::new(mmap),
+        })
     }
 }
 
@@ -126,7 +153,7 @@ impl DataSource for FileSource {
         self.name.as_str()
     }
 
-    fn fetch(&mut self, start: u64, end: u64) -> Slice {
+    fn fetch(&mut self, start: u64, end: u64) -> Slice<'_> {
         let range = clamp(start, end, self.mmap.len() as u64);
 
         if !range.is_empty() {
@@ -153,4 +180,8 @@ impl DataSource for FileSource {
             index.clamp(0, len - 1) as f64 / (len - 1) as f64
         }
     }
+
+    fn shared(&self) -> Option<SharedData> {
+        Some(self.mmap.clone())
+    }
 }
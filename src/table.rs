@@ -0,0 +1,375 @@
+// A table of the elements of an array at the cursor, such as f32 samples or
+// u32 offsets, with a plot of their values to make waveforms and lookup
+// tables stand out.
+
+use std::fmt;
+
+use tui::{
+    buffer::Buffer,
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Modifier, Style},
+    text::{Span, Spans},
+    widgets::{Paragraph, Sparkline, Widget},
+};
+
+use crate::{
+    decode::{decode_float, decode_integer, Endianness},
+    terminal::color,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Element {
+    U8,
+    I8,
+    U16,
+    I16,
+    U32,
+    I32,
+    U64,
+    I64,
+    F32,
+    F64,
+}
+
+const ELEMENTS: &[Element] = &[
+    Element::U8,
+    Element::I8,
+    Element::U16,
+    Element::I16,
+    Element::U32,
+    Element::I32,
+    Element::U64,
+    Element::I64,
+    Element::F32,
+    Element::F64,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Value {
+    Signed(i128),
+    Unsigned(u128),
+    Single(f32),
+    Double(f64),
+}
+
+impl Value {
+    fn as_f64(self) -> f64 {
+        match self {
+            Value::Signed(value) => value as f64,
+            Value::Unsigned(value) => value as f64,
+            Value::Single(value) => value as f64,
+            Value::Double(value) => value,
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Signed(value) => write!(f, "{}", value),
+            Value::Unsigned(value) => write!(f, "{}", value),
+            Value::Single(value) => write!(f, "{}", value),
+            Value::Double(value) => write!(f, "{}", value),
+        }
+    }
+}
+
+impl Element {
+    pub fn size(self) -> u64 {
+        match self {
+            Element::U8 | Element::I8 => 1,
+            Element::U16 | Element::I16 => 2,
+            Element::U32 | Element::I32 | Element::F32 => 4,
+            Element::U64 | Element::I64 | Element::F64 => 8,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Element::U8 => "u8",
+            Element::I8 => "i8",
+            Element::U16 => "u16",
+            Element::I16 => "i16",
+            Element::U32 => "u32",
+            Element::I32 => "i32",
+            Element::U64 => "u64",
+            Element::I64 => "i64",
+            Element::F32 => "f32",
+            Element::F64 => "f64",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Element> {
+        ELEMENTS
+            .iter()
+            .copied()
+            .find(|element| element.name() == name)
+    }
+
+    pub fn next(self) -> Element {
+        let index = ELEMENTS.iter().position(|&e| e == self).unwrap_or(0);
+        ELEMENTS[(index + 1) % ELEMENTS.len()]
+    }
+
+    pub fn names() -> Vec<&'static str> {
+        ELEMENTS.iter().map(|element| element.name()).collect()
+    }
+
+    // The value of the element in `bytes`, unless they're cut short.
+    pub fn decode(self, bytes: &[u8], endianness: Endianness) -> Option<Value> {
+        let bytes = bytes.get(..self.size() as usize)?;
+        let (signed, unsigned) = decode_integer(bytes, endianness);
+        Some(match self {
+            Element::U8 | Element::U16 | Element::U32 | Element::U64 => Value::Unsigned(unsigned),
+            Element::I8 | Element::I16 | Element::I32 | Element::I64 => Value::Signed(signed),
+            Element::F32 => Value::Single(decode_float(bytes, endianness)? as f32),
+            Element::F64 => Value::Double(decode_float(bytes, endianness)?),
+        })
+    }
+}
+
+// An array of elements `stride` bytes apart, whose first (or any) element is
+// at `origin`. Elements before it have negative indices.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Table {
+    pub element: Element,
+    pub stride: u64,
+    pub origin: u64,
+}
+
+impl Table {
+    pub fn new(element: Element, origin: u64) -> Self {
+        Table {
+            element,
+            stride: element.size(),
+            origin,
+        }
+    }
+
+    // Where the element `offset` falls in starts.
+    pub fn element_at(&self, offset: u64) -> u64 {
+        let phase = self.origin % self.stride;
+        if offset < phase {
+            return phase;
+        }
+        offset - (offset - phase) % self.stride
+    }
+
+    pub fn index(&self, element: u64) -> i128 {
+        (element as i128 - self.origin as i128).div_euclid(self.stride as i128)
+    }
+
+    // The first element to show in `rows` rows for the cursor to be in the
+    // middle, where possible.
+    pub fn first(&self, cursor: u64, rows: u16) -> u64 {
+        let element = self.element_at(cursor);
+        let before = (element - self.origin % self.stride) / self.stride;
+        element - before.min(rows as u64 / 2) * self.stride
+    }
+}
+
+// Rows of the table start after a header, and the plot goes below them if
+// there's room for it.
+const PLOT_HEIGHT: u16 = 4;
+const MIN_PLOT_ROWS: u16 = 8;
+
+pub struct TableView {
+    pub table: Table,
+    // The offset of the first element shown, and data from there.
+    pub first: u64,
+    pub data: Vec<u8>,
+    pub cursor: u64,
+    pub endianness: Endianness,
+}
+
+impl TableView {
+    fn has_plot(height: u16) -> bool {
+        height >= 1 + MIN_PLOT_ROWS + PLOT_HEIGHT
+    }
+
+    // How many elements there's room for in `height` rows.
+    pub fn rows(height: u16) -> u16 {
+        let plot = if TableView::has_plot(height) {
+            PLOT_HEIGHT
+        } else {
+            0
+        };
+        height.saturating_sub(1 + plot)
+    }
+
+    // The values of up to `count` elements from the first one shown.
+    fn values(&self, count: usize) -> Vec<(u64, Value)> {
+        let table = self.table;
+        (0..count as u64)
+            .map(|i| self.first + i * table.stride)
+            .map_while(|offset| {
+                let start = (offset - self.first) as usize;
+                let value = table
+                    .element
+                    .decode(self.data.get(start..)?, self.endianness)?;
+                Some((offset, value))
+            })
+            .collect()
+    }
+}
+
+// Where each value falls between the smallest and largest finite ones, from
+// 0 to 1.
+fn normalize(values: &[Value]) -> Vec<f64> {
+    let finite = values.iter().map(|v| v.as_f64()).filter(|v| v.is_finite());
+    let (min, max) = finite.fold((f64::MAX, f64::MIN), |(min, max), v| {
+        (min.min(v), max.max(v))
+    });
+    values
+        .iter()
+        .map(|value| match value.as_f64() {
+            v if !v.is_finite() => 0.0,
+            _ if max <= min => 0.5,
+            v => (v - min) / (max - min),
+        })
+        .collect()
+}
+
+// A horizontal bar `fraction` of `width` long, to an eighth of a cell.
+fn bar(fraction: f64, width: usize) -> String {
+    const EIGHTHS: [char; 8] = [' ', '▏', '▎', '▍', '▌', '▋', '▊', '▉'];
+    let eighths = (fraction * (width * 8) as f64).round() as usize;
+    let mut bar = "█".repeat(eighths / 8);
+    match eighths % 8 {
+        0 => {}
+        partial => bar.push(EIGHTHS[partial]),
+    }
+    bar
+}
+
+impl Widget for TableView {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let style = Style::default()
+            .bg(color(32, 32, 32))
+            .fg(color(192, 192, 192));
+        let style_header = style.fg(color(128, 160, 255)).add_modifier(Modifier::BOLD);
+        let style_cursor = Style::default().bg(color(0, 96, 0)).fg(color(96, 255, 96));
+        let style_bar = style.fg(color(96, 192, 255));
+
+        let rows = TableView::rows(area.height);
+        let plot = if TableView::has_plot(area.height) {
+            PLOT_HEIGHT
+        } else {
+            0
+        };
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(1),
+                Constraint::Length(rows),
+                Constraint::Length(plot),
+            ])
+            .split(area);
+
+        let value_width = 24;
+        let header = format!(
+            "{:>8}  {:>12}  {:>value_width$}  {} × {}, stride {}",
+            "index",
+            "offset",
+            "value",
+            self.table.element.name(),
+            self.endianness.label(),
+            self.table.stride
+        );
+        Paragraph::new(Span::styled(header, style_header))
+            .style(style)
+            .render(chunks[0], buf);
+
+        let values = self.values(rows as usize);
+        let fractions = normalize(&values.iter().map(|&(_, v)| v).collect::<Vec<_>>());
+        let bar_width = (area.width as usize).saturating_sub(8 + 2 + 12 + 2 + value_width + 2);
+        let lines: Vec<Spans> = values
+            .iter()
+            .zip(fractions)
+            .map(|(&(offset, value), fraction)| {
+                let text = format!(
+                    "{:>8}  {:#012x}  {:>value_width$}  ",
+                    self.table.index(offset),
+                    offset,
+                    value.to_string()
+                );
+                let element = offset..offset + self.table.element.size();
+                let row_style = if element.contains(&self.cursor) {
+                    style_cursor
+                } else {
+                    style
+                };
+                Spans::from(vec![
+                    Span::styled(text, row_style),
+                    Span::styled(bar(fraction, bar_width), style_bar),
+                ])
+            })
+            .collect();
+        Paragraph::new(lines).style(style).render(chunks[1], buf);
+
+        if plot > 0 {
+            let values = self.values(area.width as usize);
+            let heights: Vec<u64> = normalize(&values.iter().map(|&(_, v)| v).collect::<Vec<_>>())
+                .iter()
+                .map(|fraction| 1 + (fraction * 1000.0) as u64)
+                .collect();
+            Sparkline::default()
+                .data(&heights)
+                .max(1001)
+                .style(style_bar)
+                .render(chunks[2], buf);
+        }
+    }
+}
+
+#[cfg(test)]
+mod table_tests {
+    use super::*;
+
+    #[test]
+    fn test_decode() {
+        let le = Endianness::Little;
+        let bytes = 1.5f32.to_le_bytes();
+        assert_eq!(Element::F32.decode(&bytes, le), Some(Value::Single(1.5)));
+        assert_eq!(Value::Single(0.1).to_string(), "0.1");
+        assert_eq!(
+            Element::U16.decode(&[0xff, 0xff], le),
+            Some(Value::Unsigned(65535))
+        );
+        assert_eq!(
+            Element::I16.decode(&[0xff, 0xff], le),
+            Some(Value::Signed(-1))
+        );
+        assert_eq!(Element::U32.decode(&[0xff, 0xff], le), None);
+        assert_eq!(Element::from_name("f64"), Some(Element::F64));
+    }
+
+    #[test]
+    fn test_elements() {
+        let table = Table {
+            element: Element::U16,
+            stride: 6,
+            origin: 0x20,
+        };
+        assert_eq!(table.element_at(0x20), 0x20);
+        assert_eq!(table.element_at(0x25), 0x20);
+        assert_eq!(table.element_at(0x26), 0x26);
+        assert_eq!(table.element_at(0x1f), 0x1a);
+        assert_eq!(table.element_at(0), 2);
+        assert_eq!(table.index(0x26), 1);
+        assert_eq!(table.index(0x1a), -1);
+
+        // The cursor's element is in the middle, unless it's near the start.
+        assert_eq!(table.first(0x26 + 60, 10), 0x26 + 30);
+        assert_eq!(table.first(0x08, 10), 2);
+    }
+
+    #[test]
+    fn test_plot() {
+        let values = [1.0, 3.0, 2.0, f64::NAN].map(Value::Double);
+        assert_eq!(normalize(&values), vec![0.0, 1.0, 0.5, 0.0]);
+        assert_eq!(bar(0.5, 4), "██");
+        assert_eq!(bar(0.5, 3), "█▌");
+        assert_eq!(bar(0.0, 3), "");
+    }
+}
@@ -1,7 +1,7 @@
 use std::io;
 
 use crossterm::{
-    event::{DisableMouseCapture, EnableMouseCapture},
+    event::{DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
@@ -19,14 +19,24 @@ impl TerminalSetup {
     }
 
     pub fn show() -> Result<(), io::Error> {
-        execute!(io::stdout(), EnterAlternateScreen, EnableMouseCapture)?;
+        execute!(
+            io::stdout(),
+            EnterAlternateScreen,
+            EnableMouseCapture,
+            EnableBracketedPaste
+        )?;
         enable_raw_mode()?;
         Ok(())
     }
 
     pub fn hide() -> Result<(), io::Error> {
         disable_raw_mode().ok();
-        execute!(io::stdout(), LeaveAlternateScreen, DisableMouseCapture)?;
+        execute!(
+            io::stdout(),
+            LeaveAlternateScreen,
+            DisableMouseCapture,
+            DisableBracketedPaste
+        )?;
         Ok(())
     }
 }
@@ -50,7 +60,7 @@ fn query_depth() -> ColorDepth {
     //
     // where we query the terminal after attempting to set an RGB color. But
     // either way we should respect COLORTERM first.
-    match env::var("COLORTERM").unwrap_or(String::new()).as_str() {
+    match env::var("COLORTERM").unwrap_or_default().as_str() {
         "truecolor" => ColorDepth::Rgb888,
         _ => ColorDepth::Palette8,
     }
@@ -61,7 +71,50 @@ pub fn get_depth() -> ColorDepth {
     *DEPTH.get_or_init(query_depth)
 }
 
+// Colors are picked for a dark background; the light theme flips their
+// lightness, keeping the hue.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Theme {
+    Dark,
+    Light,
+}
+
+const THEMES: &[Theme] = &[Theme::Dark, Theme::Light];
+
+impl Theme {
+    pub fn name(self) -> &'static str {
+        match self {
+            Theme::Dark => "dark",
+            Theme::Light => "light",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Theme> {
+        THEMES.iter().copied().find(|t| t.name() == name)
+    }
+
+    pub fn names() -> Vec<&'static str> {
+        THEMES.iter().map(|t| t.name()).collect()
+    }
+}
+
+static THEME: OnceCell<Theme> = OnceCell::new();
+
+// Picks the theme for everything drawn from here on. This has to happen
+// before the first color is asked for, which settles on the dark theme.
+pub fn set_theme(theme: Theme) {
+    THEME.set(theme).ok();
+}
+
 pub fn color(r: u8, g: u8, b: u8) -> tui::style::Color {
+    let (r, g, b) = match THEME.get_or_init(|| Theme::Dark) {
+        Theme::Dark => (r, g, b),
+        Theme::Light => {
+            let hsla = pastel::Color::from_rgb(r, g, b).to_hsla();
+            let rgba = pastel::Color::from_hsla(hsla.h, hsla.s, 1.0 - hsla.l, 1.0).to_rgba();
+            (rgba.r, rgba.g, rgba.b)
+        }
+    };
     match get_depth() {
         ColorDepth::Palette8 => {
             let ansi = pastel::Color::from_rgb(r, g, b).to_ansi_8bit();